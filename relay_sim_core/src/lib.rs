@@ -0,0 +1,735 @@
+// The circuit solver of relay-sim without anything from bevy, so it can be tested and used without a window
+// The frontend fills a Circuit from its components every tick and lets the Solver step it
+// Which lights and coils are energized and which side every point is on can be read back afterwards
+
+use std::collections::HashMap;
+
+// Share of the supply a consumer needs to turn on, two equal consumers in series get half each and both stay off
+pub const PULL_IN_SHARE: f32 = 0.75;
+const MAX_SOLVER_PASSES: usize = 1000;
+const SOLVER_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+// The grid point between the two terminals of a component, the common terminal of a changeover contact
+pub fn middle(top: Point, bottom: Point) -> Point {
+    Point {
+        x: (top.x + bottom.x) / 2,
+        y: (top.y + bottom.y) / 2,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visited {
+    Positive,
+    Negative,
+    Unvisited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchType {
+    NormallyOpen,
+    NormallyClosed,
+    // Its middle grid point is the common terminal, switching over from the bottom (normally closed) to the top (normally open)
+    Changeover,
+}
+
+// A button or relay contact, actuated while its button is pressed or its relay pulled in
+#[derive(Debug, Clone, Copy)]
+pub struct Switch {
+    pub top: Point,
+    pub bottom: Point,
+    pub typ: SwitchType,
+    pub actuated: bool,
+}
+
+impl Switch {
+    // The connection the contact makes right now, None while it is open
+    pub fn closed_wire(&self) -> Option<(Point, Point)> {
+        match self.typ {
+            SwitchType::NormallyOpen => self.actuated.then_some((self.top, self.bottom)),
+            SwitchType::NormallyClosed => (!self.actuated).then_some((self.top, self.bottom)),
+            SwitchType::Changeover => Some((
+                middle(self.top, self.bottom),
+                if self.actuated { self.top } else { self.bottom },
+            )),
+        }
+    }
+}
+
+// Whether the point lies on the wire somewhere between its two ends, wires are always straight along x or y
+pub fn on_span(first: Point, second: Point, point: Point) -> bool {
+    let between = |a: usize, b: usize, value: usize| a.min(b) < value && value < a.max(b);
+    (first.x == second.x && point.x == first.x && between(first.y, second.y, point.y))
+        || (first.y == second.y && point.y == first.y && between(first.x, second.x, point.x))
+}
+
+// Everything that makes up the circuit for one tick, cleared and refilled so the allocations stay around
+// Lights and coils are both consumers, the solver does not care which is which
+#[derive(Debug, Default, Clone)]
+pub struct Circuit {
+    // The terminals of every supply, without at least one of each nothing gets powered
+    // All supplies share their negative side like a common ground
+    pub positive_sources: Vec<Point>,
+    pub negative_sources: Vec<Point>,
+    pub wires: Vec<(Point, Point)>,
+    // Points joined without a wire in between, like two net labels with the same name, nothing along them connects
+    pub links: Vec<(Point, Point)>,
+    pub switches: Vec<Switch>,
+    pub consumers: Vec<(Point, Point)>,
+    // Anode and cathode, a diode only conducts from the first to the second
+    pub diodes: Vec<(Point, Point)>,
+}
+
+impl Circuit {
+    pub fn has_sources(&self) -> bool {
+        !self.positive_sources.is_empty() && !self.negative_sources.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.wires.clear();
+        self.links.clear();
+        self.diodes.clear();
+        self.clear_keeping_wiring();
+    }
+
+    // Everything but the wires, links and diodes, for refilling it before Solver::step_keeping_wiring
+    pub fn clear_keeping_wiring(&mut self) {
+        self.positive_sources.clear();
+        self.negative_sources.clear();
+        self.switches.clear();
+        self.consumers.clear();
+    }
+}
+
+// Buffers used by every step, they only get cleared so the allocations stay around between steps
+// Points are merged into nets with a disjoint-set, the wiring once and the closed contacts on top of it every step
+#[derive(Debug, Default)]
+pub struct Solver {
+    points: Vec<Point>,
+    // Where every point is in points, so looking one up does not go through all of them
+    point_index: HashMap<Point, usize>,
+    // The parent of every point of the wiring with only the wires, links and T-connections merged, kept between steps
+    // None until the first step and after the wiring is invalidated
+    wiring_net_of: Option<Vec<usize>>,
+    // The same with the closed contacts of this step merged in as well, the points of contacts come after the wiring
+    net_of: Vec<usize>,
+    // Anode and cathode of every diode, by point index
+    diodes: Vec<(usize, usize)>,
+    // The net every point belongs to, in the same order as the points
+    nets: Vec<usize>,
+    // Which side every net is connected to, by the net
+    marks: Vec<Visited>,
+    net_count: usize,
+    // Set when the last step found the positive and negative side connected
+    short_circuit: bool,
+    // Every point connected to both sides then
+    shorted_points: Vec<Point>,
+    // One entry per consumer of the last stepped circuit, in the same order
+    energized: Vec<bool>,
+    // Share of the supply at every point, in the same order as the points
+    potentials: Vec<Option<f32>>,
+    buffers: StepBuffers,
+}
+
+// Only needed during a step, kept so stepping the same circuit again does not allocate
+#[derive(Debug, Default)]
+struct StepBuffers {
+    // Anode and cathode of every diode, by net
+    diode_nets: Vec<(usize, usize)>,
+    from_positive: Vec<bool>,
+    to_negative: Vec<bool>,
+    to_visit: Vec<usize>,
+    conducting: Vec<(usize, usize)>,
+    turned_on: Vec<(usize, usize)>,
+    // Parents of the nets with the conducting diodes merged in
+    joined: Vec<usize>,
+    // Potentials and the consumers at every joined net, by net
+    net_potentials: Vec<Option<f32>>,
+    fixed: Vec<bool>,
+    loads: Vec<Vec<usize>>,
+}
+
+impl Solver {
+    pub fn step(&mut self, circuit: &Circuit) {
+        self.invalidate_wiring();
+        self.step_keeping_wiring(circuit);
+    }
+
+    // Like step, but the wires, links and diodes are taken to be the ones of the last step, only the rest is looked at again
+    // Saves going through every pair of wires for T-connections, call invalidate_wiring whenever they did change
+    pub fn step_keeping_wiring(&mut self, circuit: &Circuit) {
+        self.clear_results();
+        if self.wiring_net_of.is_none() {
+            self.build_wiring(circuit);
+        }
+        let wiring_net_of = self.wiring_net_of.as_ref().unwrap();
+        for pos in self.points.drain(wiring_net_of.len()..) {
+            self.point_index.remove(&pos);
+        }
+        self.net_of.clear();
+        self.net_of.extend_from_slice(wiring_net_of);
+
+        // The contacts go on top of the wiring, they are the only connections that change from tick to tick
+        for (first, second) in circuit.switches.iter().filter_map(Switch::closed_wire) {
+            let first_index = self.index_or_insert(first);
+            let second_index = self.index_or_insert(second);
+            let new_points = self.net_of.len()..self.points.len();
+            self.net_of.extend(new_points);
+            merge(&mut self.net_of, first_index, second_index);
+        }
+
+        self.nets
+            .extend((0..self.points.len()).map(|index| net_root(&mut self.net_of, index)));
+        self.net_count = self
+            .nets
+            .iter()
+            .enumerate()
+            .filter(|(index, net)| index == *net)
+            .count();
+        self.marks.resize(self.points.len(), Visited::Unvisited);
+
+        if !circuit.has_sources() {
+            return;
+        }
+
+        // Without diodes both sides are just the nets of their sources, a short is a net with both a + and a - source in it
+        // Diodes lead further, the positive side only from anode to cathode and the negative side only the other way
+        let Solver {
+            point_index,
+            diodes,
+            nets,
+            marks,
+            buffers,
+            ..
+        } = self;
+        buffers.diode_nets.clear();
+        buffers.diode_nets.extend(
+            diodes
+                .iter()
+                .map(|(anode, cathode)| (nets[*anode], nets[*cathode])),
+        );
+        reachable_nets(
+            nets.len(),
+            source_nets(&circuit.positive_sources, point_index, nets),
+            &buffers.diode_nets,
+            true,
+            &mut buffers.from_positive,
+            &mut buffers.to_visit,
+        );
+        reachable_nets(
+            nets.len(),
+            source_nets(&circuit.negative_sources, point_index, nets),
+            &buffers.diode_nets,
+            false,
+            &mut buffers.to_negative,
+            &mut buffers.to_visit,
+        );
+        let (from_positive, to_negative) = (&buffers.from_positive, &buffers.to_negative);
+        for (net, mark) in marks.iter_mut().enumerate() {
+            *mark = if from_positive[net] {
+                Visited::Positive
+            } else if to_negative[net] {
+                Visited::Negative
+            } else {
+                Visited::Unvisited
+            };
+        }
+
+        if (0..nets.len()).any(|net| from_positive[net] && to_negative[net]) {
+            self.short_circuit = true;
+            // Every net on a way from a positive to a negative source is part of the short
+            for (index, net) in self.nets.iter().enumerate() {
+                if from_positive[*net] && to_negative[*net] {
+                    self.shorted_points.push(self.points[index]);
+                }
+            }
+            return;
+        }
+
+        // Consumers in series share the supply, so every consumer is looked at by the potential across it
+        // A diode between two of them conducts once its anode ends up above its cathode, until then it blocks
+        buffers.conducting.clear();
+        loop {
+            solve_potentials(
+                point_index,
+                nets,
+                marks,
+                &circuit.consumers,
+                buffers,
+                &mut self.potentials,
+            );
+            let potentials = &self.potentials;
+            let conducting = &buffers.conducting;
+            buffers.turned_on.clear();
+            buffers.turned_on.extend(
+                diodes
+                    .iter()
+                    .filter(|diode| !conducting.contains(*diode))
+                    .filter(
+                        |(anode, cathode)| match (potentials[*anode], potentials[*cathode]) {
+                            (Some(anode), Some(cathode)) => anode > cathode + SOLVER_TOLERANCE,
+                            _ => false,
+                        },
+                    ),
+            );
+            if buffers.turned_on.is_empty() {
+                break;
+            }
+            buffers.conducting.append(&mut buffers.turned_on);
+        }
+        for (top, bottom) in &circuit.consumers {
+            let energized = match (self.potential(*top), self.potential(*bottom)) {
+                (Some(top), Some(bottom)) => (top - bottom).abs() >= PULL_IN_SHARE,
+                _ => false,
+            };
+            self.energized.push(energized);
+        }
+    }
+
+    // Merges the points of every wire and link once, the diodes only get their points
+    fn build_wiring(&mut self, circuit: &Circuit) {
+        self.points.clear();
+        self.point_index.clear();
+        self.diodes.clear();
+        let mut net_of = Vec::new();
+        let mut connect = |solver: &mut Solver, first: Point, second: Point| {
+            let first_index = solver.index_or_insert(first);
+            let second_index = solver.index_or_insert(second);
+            let new_points = net_of.len()..solver.points.len();
+            net_of.extend(new_points);
+            merge(&mut net_of, first_index, second_index);
+        };
+        for (first, second) in circuit.wires.iter().chain(&circuit.links).copied() {
+            connect(self, first, second);
+        }
+        // A wire ending somewhere along another wire is connected to it there, a T-connection
+        // Wires only crossing each other with neither ending there stay apart
+        // Only the ends in the same column or row as a wire can lie along it
+        let mut ends_in_column = HashMap::<usize, Vec<Point>>::new();
+        let mut ends_in_row = HashMap::<usize, Vec<Point>>::new();
+        for end in circuit.wires.iter().flat_map(|(a, b)| [*a, *b]) {
+            ends_in_column.entry(end.x).or_default().push(end);
+            ends_in_row.entry(end.y).or_default().push(end);
+        }
+        for (first, second) in circuit.wires.iter().copied() {
+            let candidates = if first.x == second.x {
+                ends_in_column.get(&first.x)
+            } else {
+                ends_in_row.get(&first.y)
+            };
+            for end in candidates.into_iter().flatten().copied() {
+                if on_span(first, second, end) {
+                    connect(self, first, end);
+                }
+            }
+        }
+        for (anode, cathode) in &circuit.diodes {
+            let anode_index = self.index_or_insert(*anode);
+            let cathode_index = self.index_or_insert(*cathode);
+            self.diodes.push((anode_index, cathode_index));
+        }
+        net_of.extend(net_of.len()..self.points.len());
+        self.wiring_net_of = Some(net_of);
+    }
+
+    // The next step_keeping_wiring goes through the wires, links and diodes again
+    pub fn invalidate_wiring(&mut self) {
+        self.wiring_net_of = None;
+    }
+
+    // Forgets the last step, as if the circuit had never been powered
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.point_index.clear();
+        self.diodes.clear();
+        self.wiring_net_of = None;
+        self.clear_results();
+    }
+
+    fn clear_results(&mut self) {
+        self.nets.clear();
+        self.marks.clear();
+        self.net_count = 0;
+        self.short_circuit = false;
+        self.shorted_points.clear();
+        self.energized.clear();
+        self.potentials.clear();
+    }
+
+    pub fn net_count(&self) -> usize {
+        self.net_count
+    }
+
+    pub fn short_circuit(&self) -> bool {
+        self.short_circuit
+    }
+
+    pub fn is_shorted(&self, pos: impl Into<Point>) -> bool {
+        self.shorted_points.contains(&pos.into())
+    }
+
+    // Which side the point was connected to, Unvisited for points without a wire as well
+    pub fn mark(&self, pos: impl Into<Point>) -> Visited {
+        self.index(pos.into())
+            .map_or(Visited::Unvisited, |index| self.marks[self.nets[index]])
+    }
+
+    // Whether both points are connected through wires and closed contacts, consumers in between do not count
+    pub fn same_net(&self, first: impl Into<Point>, second: impl Into<Point>) -> bool {
+        let (first, second) = (first.into(), second.into());
+        if first == second {
+            return true;
+        }
+        match (self.index(first), self.index(second)) {
+            (Some(first), Some(second)) => self.nets[first] == self.nets[second],
+            _ => false,
+        }
+    }
+
+    // Share of the supply at the point, 1 on the positive side and 0 on the negative side
+    // None for points that are not connected to both sides somehow and for everything after a short circuit
+    pub fn potential(&self, pos: impl Into<Point>) -> Option<f32> {
+        self.index(pos.into())
+            .and_then(|index| self.potentials.get(index).copied().flatten())
+    }
+
+    // Whether the consumer at this index of the stepped circuit is on, always false after a short circuit
+    pub fn is_energized(&self, consumer: usize) -> bool {
+        self.energized.get(consumer).copied().unwrap_or(false)
+    }
+
+    fn index(&self, pos: Point) -> Option<usize> {
+        self.point_index.get(&pos).copied()
+    }
+
+    fn index_or_insert(&mut self, pos: Point) -> usize {
+        *self.point_index.entry(pos).or_insert_with(|| {
+            self.points.push(pos);
+            self.points.len() - 1
+        })
+    }
+}
+
+// Potential of every point, 1 on the positive side and 0 on the negative side, all consumers are taken to be the same load
+// Nets between consumers get the average of their neighbours, repeated until nothing changes anymore
+// Points that are not connected to both sides somehow get None
+fn solve_potentials(
+    point_index: &HashMap<Point, usize>,
+    net: &[usize],
+    marks: &[Visited],
+    consumers: &[(Point, Point)],
+    buffers: &mut StepBuffers,
+    potentials: &mut Vec<Option<f32>>,
+) {
+    let StepBuffers {
+        to_visit,
+        conducting,
+        joined,
+        net_potentials: potential,
+        fixed,
+        loads,
+        ..
+    } = buffers;
+
+    // Conducting diodes join the nets on both of their ends like a wire
+    joined.clear();
+    joined.extend(0..net.len());
+    for (anode, cathode) in conducting.iter() {
+        let anode_root = net_root(joined, net[*anode]);
+        let cathode_root = net_root(joined, net[*cathode]);
+        joined[anode_root] = cathode_root;
+    }
+    potential.clear();
+    potential.resize(net.len(), None);
+    for wired in net.iter().copied() {
+        let joined_net = net_root(joined, wired);
+        match marks[wired] {
+            Visited::Positive => potential[joined_net] = Some(1.),
+            Visited::Negative => potential[joined_net] = Some(0.),
+            Visited::Unvisited => {}
+        }
+    }
+    fixed.clear();
+    fixed.extend(potential.iter().map(Option::is_some));
+
+    // The nets on the other side of every consumer at a net, by net
+    for loads in loads.iter_mut() {
+        loads.clear();
+    }
+    loads.resize_with(net.len(), Vec::new);
+    for (top, bottom) in consumers.iter() {
+        let (Some(top), Some(bottom)) = (point_index.get(top), point_index.get(bottom)) else {
+            continue;
+        };
+        let (top, bottom) = (net_root(joined, net[*top]), net_root(joined, net[*bottom]));
+        if top != bottom {
+            loads[top].push(bottom);
+            loads[bottom].push(top);
+        }
+    }
+
+    // Only nets that reach a supplied net through consumers get a potential at all
+    to_visit.clear();
+    to_visit.extend((0..potential.len()).filter(|net| fixed[*net]));
+    while let Some(current) = to_visit.pop() {
+        for next in loads[current].iter().copied() {
+            if potential[next].is_none() {
+                potential[next] = Some(0.5);
+                to_visit.push(next);
+            }
+        }
+    }
+
+    for _ in 0..MAX_SOLVER_PASSES {
+        let mut largest_change: f32 = 0.;
+        for current in 0..potential.len() {
+            if fixed[current] || potential[current].is_none() {
+                continue;
+            }
+            let (sum, count) = loads[current]
+                .iter()
+                .filter_map(|next| potential[*next])
+                .fold((0., 0), |(sum, count), p| (sum + p, count + 1));
+            let average = sum / count as f32;
+            largest_change = largest_change.max((average - potential[current].unwrap()).abs());
+            potential[current] = Some(average);
+        }
+        if largest_change < SOLVER_TOLERANCE {
+            break;
+        }
+    }
+
+    potentials.clear();
+    potentials.extend(net.iter().map(|wired| potential[net_root(joined, *wired)]));
+}
+
+// The nets of the terminals of a supply
+fn source_nets<'a>(
+    sources: &'a [Point],
+    point_index: &'a HashMap<Point, usize>,
+    nets: &'a [usize],
+) -> impl Iterator<Item = usize> + 'a {
+    sources
+        .iter()
+        .filter_map(|source| point_index.get(source))
+        .map(|index| nets[*index])
+}
+
+// Puts both points into the same net
+fn merge(net_of: &mut [usize], first: usize, second: usize) {
+    let first_root = net_root(net_of, first);
+    let second_root = net_root(net_of, second);
+    if first_root != second_root {
+        net_of[first_root] = second_root;
+    }
+}
+
+fn net_root(net_of: &mut [usize], mut index: usize) -> usize {
+    while net_of[index] != index {
+        net_of[index] = net_of[net_of[index]];
+        index = net_of[index];
+    }
+    index
+}
+
+// Every net that can be reached from the starts through the diodes, forward from anode to cathode or the other way
+// Fills reached with one entry per net, true for the reached ones
+fn reachable_nets(
+    net_count: usize,
+    starts: impl Iterator<Item = usize>,
+    diode_nets: &[(usize, usize)],
+    forward: bool,
+    reached: &mut Vec<bool>,
+    to_visit: &mut Vec<usize>,
+) {
+    reached.clear();
+    reached.resize(net_count, false);
+    to_visit.clear();
+    to_visit.extend(starts);
+    while let Some(current) = to_visit.pop() {
+        if reached[current] {
+            continue;
+        }
+        reached[current] = true;
+        for (anode, cathode) in diode_nets.iter().copied() {
+            let (from, to) = if forward {
+                (anode, cathode)
+            } else {
+                (cathode, anode)
+            };
+            if from == current && !reached[to] {
+                to_visit.push(to);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: usize, y: usize) -> Point {
+        Point { x, y }
+    }
+
+    // A supply with its positive terminal at 0, 10 and its negative one at 0, 0
+    fn supplied() -> Circuit {
+        Circuit {
+            positive_sources: vec![p(0, 10)],
+            negative_sources: vec![p(0, 0)],
+            ..Default::default()
+        }
+    }
+
+    // A changeover contact with its common terminal on the supply, lamp 0 on the top terminal and lamp 1 on the bottom one
+    fn changeover(actuated: bool) -> Circuit {
+        let mut circuit = supplied();
+        circuit.wires = vec![
+            (p(0, 10), p(0, 6)),
+            (p(0, 6), p(2, 6)),
+            (p(2, 8), p(4, 8)),
+            (p(2, 4), p(6, 4)),
+            (p(0, 0), p(4, 0)),
+            (p(4, 0), p(6, 0)),
+        ];
+        circuit.switches = vec![Switch {
+            top: p(2, 8),
+            bottom: p(2, 4),
+            typ: SwitchType::Changeover,
+            actuated,
+        }];
+        circuit.consumers = vec![(p(4, 8), p(4, 0)), (p(6, 4), p(6, 0))];
+        circuit
+    }
+
+    fn energized(solver: &Solver, circuit: &Circuit) -> Vec<bool> {
+        (0..circuit.consumers.len())
+            .map(|consumer| solver.is_energized(consumer))
+            .collect()
+    }
+
+    #[test]
+    fn lamp_across_supply() {
+        let mut circuit = supplied();
+        circuit.wires = vec![(p(0, 10), p(2, 10)), (p(0, 0), p(2, 0))];
+        circuit.consumers = vec![(p(2, 10), p(2, 0))];
+        let mut solver = Solver::default();
+        solver.step(&circuit);
+
+        assert!(!solver.short_circuit());
+        assert!(solver.is_energized(0));
+        assert_eq!(solver.mark(p(2, 10)), Visited::Positive);
+        assert_eq!(solver.mark(p(2, 0)), Visited::Negative);
+        assert_eq!(solver.potential(p(2, 10)), Some(1.));
+        assert_eq!(solver.potential(p(2, 0)), Some(0.));
+    }
+
+    #[test]
+    fn consumers_in_series_stay_off() {
+        let mut circuit = supplied();
+        circuit.wires = vec![(p(0, 10), p(2, 10)), (p(2, 5), p(3, 5)), (p(0, 0), p(2, 0))];
+        circuit.consumers = vec![(p(2, 10), p(2, 5)), (p(2, 5), p(2, 0))];
+        let mut solver = Solver::default();
+        solver.step(&circuit);
+
+        assert_eq!(energized(&solver, &circuit), [false, false]);
+        let between = solver.potential(p(3, 5)).unwrap();
+        assert!((between - 0.5).abs() < 1e-3);
+        assert_eq!(solver.mark(p(3, 5)), Visited::Unvisited);
+    }
+
+    #[test]
+    fn short_circuit() {
+        let mut circuit = supplied();
+        circuit.wires = vec![
+            (p(0, 10), p(2, 10)),
+            (p(2, 10), p(2, 0)),
+            (p(2, 0), p(0, 0)),
+        ];
+        circuit.consumers = vec![(p(2, 10), p(2, 0))];
+        let mut solver = Solver::default();
+        solver.step(&circuit);
+
+        assert!(solver.short_circuit());
+        assert!(solver.is_shorted(p(0, 10)));
+        assert!(solver.is_shorted(p(2, 0)));
+        assert!(!solver.is_energized(0));
+        assert_eq!(solver.potential(p(2, 10)), None);
+    }
+
+    #[test]
+    fn changeover_contact() {
+        let mut solver = Solver::default();
+        let resting = changeover(false);
+        solver.step(&resting);
+        assert_eq!(energized(&solver, &resting), [false, true]);
+        assert!(solver.same_net(p(2, 6), p(2, 4)));
+        assert!(!solver.same_net(p(2, 6), p(2, 8)));
+
+        let actuated = changeover(true);
+        solver.step(&actuated);
+        assert_eq!(energized(&solver, &actuated), [true, false]);
+        assert!(solver.same_net(p(2, 6), p(2, 8)));
+        assert!(!solver.same_net(p(2, 6), p(2, 4)));
+    }
+
+    #[test]
+    fn diode_blocks_in_reverse() {
+        let mut circuit = supplied();
+        circuit.wires = vec![(p(0, 10), p(2, 10)), (p(0, 0), p(2, 0))];
+        circuit.consumers = vec![(p(2, 8), p(2, 0))];
+        let mut solver = Solver::default();
+
+        circuit.diodes = vec![(p(2, 10), p(2, 8))];
+        solver.step(&circuit);
+        assert!(solver.is_energized(0));
+        assert_eq!(solver.mark(p(2, 8)), Visited::Positive);
+
+        circuit.diodes = vec![(p(2, 8), p(2, 10))];
+        solver.step(&circuit);
+        assert!(!solver.short_circuit());
+        assert!(!solver.is_energized(0));
+        assert_eq!(solver.mark(p(2, 8)), Visited::Unvisited);
+    }
+
+    #[test]
+    fn keeping_wiring_matches_step() {
+        let mut kept = Solver::default();
+        let mut fresh = Solver::default();
+        let compare = |kept: &Solver, fresh: &Solver, circuit: &Circuit| {
+            assert_eq!(energized(kept, circuit), energized(fresh, circuit));
+            assert_eq!(kept.net_count(), fresh.net_count());
+            assert_eq!(kept.short_circuit(), fresh.short_circuit());
+            for (first, second) in &circuit.wires {
+                for pos in [*first, *second] {
+                    assert_eq!(kept.mark(pos), fresh.mark(pos));
+                    assert_eq!(kept.potential(pos), fresh.potential(pos));
+                }
+            }
+        };
+
+        // Only the contact changes, the wiring is kept
+        for actuated in [false, true, false] {
+            let circuit = changeover(actuated);
+            kept.step_keeping_wiring(&circuit);
+            fresh.step(&circuit);
+            compare(&kept, &fresh, &circuit);
+        }
+
+        // Lamp 1 loses its wire to the negative side, which only shows after the wiring is invalidated
+        let mut rewired = changeover(false);
+        rewired.wires.pop();
+        kept.step_keeping_wiring(&rewired);
+        assert!(kept.is_energized(1));
+        kept.invalidate_wiring();
+        kept.step_keeping_wiring(&rewired);
+        fresh.step(&rewired);
+        compare(&kept, &fresh, &rewired);
+        assert!(!kept.is_energized(1));
+    }
+}
@@ -0,0 +1,351 @@
+use bevy::{
+    a11y::{
+        accesskit::{Live, NodeBuilder, Role},
+        AccessibilityNode,
+    },
+    prelude::*,
+};
+
+use crate::{
+    hidden::HiddenRegion, history::SimulationHistory, ButtonSelect, ButtonSwitch, IsRunning, Light,
+    RelayCoil, RelayCoilSelect, RelaySwitch, RelaySwitchSelect, SwitchType, UIButton, UILight,
+};
+
+// Gives the buttons, the placed components and the simulation state names and states that screen readers can announce
+// Bevy names buttons after their text, which is only -P1 or NO for most of the left panel
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_status_node).add_systems(
+            Update,
+            (
+                name_toolbar_buttons,
+                name_panel_buttons,
+                add_component_nodes,
+                describe_components,
+                update_status_node,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component)]
+struct StatusNode;
+
+// Only touches the node when something differs, every change sends the whole tree to the screen reader again
+fn set_text(node: &mut Mut<AccessibilityNode>, name: &str, description: Option<&str>) {
+    if node.name() != Some(name) {
+        node.set_name(name);
+    }
+    if node.description() != description {
+        match description {
+            Some(description) => node.set_description(description),
+            None => node.clear_description(),
+        }
+    }
+}
+
+fn set_disabled(node: &mut Mut<AccessibilityNode>, disabled: bool) {
+    if node.is_disabled() == disabled {
+        return;
+    }
+    if disabled {
+        node.set_disabled();
+    } else {
+        node.clear_disabled();
+    }
+}
+
+fn contact_name(typ: SwitchType) -> &'static str {
+    match typ {
+        SwitchType::NormallyOpen => "normally open",
+        SwitchType::NormallyClosed => "normally closed",
+        SwitchType::Changeover => "changeover",
+    }
+}
+
+fn contact_state(typ: SwitchType, actuated: bool) -> &'static str {
+    match typ {
+        SwitchType::Changeover if actuated => "switched over",
+        SwitchType::Changeover => "at rest",
+        _ if actuated == (typ == SwitchType::NormallyOpen) => "closed",
+        _ => "open",
+    }
+}
+
+fn setup_status_node(mut cmd: Commands) {
+    let mut node = NodeBuilder::new(Role::StaticText);
+    node.set_live(Live::Polite);
+    cmd.spawn((
+        AccessibilityNode::from(node),
+        Name::new("Simulation Status"),
+        StatusNode,
+    ));
+}
+
+// Every toolbar button is named "<what it does> Button", which says more than its short label
+fn name_toolbar_buttons(
+    mut buttons: Query<(&Name, &mut AccessibilityNode), (With<Button>, Added<AccessibilityNode>)>,
+) {
+    for (name, mut node) in buttons.iter_mut() {
+        if let Some(label) = name.as_str().strip_suffix(" Button") {
+            set_text(&mut node, label, None);
+        }
+    }
+}
+
+fn name_panel_buttons(
+    mut ui_lights: Query<(&UILight, &mut AccessibilityNode)>,
+    mut ui_buttons: Query<(&UIButton, &Interaction, &mut AccessibilityNode), Without<UILight>>,
+    mut button_selects: Query<
+        (&ButtonSelect, &mut AccessibilityNode),
+        (Without<UILight>, Without<UIButton>),
+    >,
+    mut relay_coil_selects: Query<
+        (&RelayCoilSelect, &mut AccessibilityNode),
+        (Without<UILight>, Without<UIButton>, Without<ButtonSelect>),
+    >,
+    mut relay_switch_selects: Query<
+        (&RelaySwitchSelect, &mut AccessibilityNode),
+        (
+            Without<UILight>,
+            Without<UIButton>,
+            Without<ButtonSelect>,
+            Without<RelayCoilSelect>,
+        ),
+    >,
+    lights: Query<&Light>,
+    button_switches: Query<&ButtonSwitch>,
+    relay_coils: Query<&RelayCoil>,
+    relay_switches: Query<&RelaySwitch>,
+) {
+    for (ui_light, mut node) in ui_lights.iter_mut() {
+        let state = if ui_light.is_lit { "lit" } else { "off" };
+        set_text(
+            &mut node,
+            &format!("Place lamp -P{}", ui_light.id),
+            Some(state),
+        );
+        set_disabled(
+            &mut node,
+            lights.iter().any(|light| light.id == ui_light.id),
+        );
+    }
+
+    for (ui_button, interaction, mut node) in ui_buttons.iter_mut() {
+        let state = if ui_button.latched {
+            "latched"
+        } else if *interaction == Interaction::Pressed && !ui_button.maintained {
+            "pressed"
+        } else {
+            "released"
+        };
+        set_text(
+            &mut node,
+            &format!(
+                "{} button -S{}",
+                if ui_button.maintained {
+                    "Toggle"
+                } else {
+                    "Press"
+                },
+                ui_button.id
+            ),
+            Some(state),
+        );
+    }
+
+    for (select, mut node) in button_selects.iter_mut() {
+        set_text(
+            &mut node,
+            &format!(
+                "Place {} contact of button -S{}",
+                contact_name(select.typ),
+                select.id
+            ),
+            None,
+        );
+        set_disabled(
+            &mut node,
+            button_switches
+                .iter()
+                .any(|button| button.id == select.id && button.typ == select.typ),
+        );
+    }
+
+    for (select, mut node) in relay_coil_selects.iter_mut() {
+        let coil = relay_coils.iter().find(|coil| coil.id == select.id);
+        let state = if coil.is_some_and(|coil| coil.activated) {
+            "pulled in"
+        } else {
+            "released"
+        };
+        set_text(
+            &mut node,
+            &format!("Place coil of relay -K{}", select.id),
+            Some(state),
+        );
+        set_disabled(&mut node, coil.is_some());
+    }
+
+    for (select, mut node) in relay_switch_selects.iter_mut() {
+        set_text(
+            &mut node,
+            &format!(
+                "Place {} contact of relay -K{}",
+                contact_name(select.typ),
+                select.id
+            ),
+            None,
+        );
+        // The same limit of five contacts per kind as when placing
+        set_disabled(
+            &mut node,
+            relay_switches
+                .iter()
+                .filter(|relay_switch| {
+                    relay_switch.id == select.id && relay_switch.typ == select.typ
+                })
+                .count()
+                >= 5,
+        );
+    }
+}
+
+fn add_component_nodes(
+    mut cmd: Commands,
+    components: Query<
+        Entity,
+        (
+            Or<(
+                Added<Light>,
+                Added<ButtonSwitch>,
+                Added<RelayCoil>,
+                Added<RelaySwitch>,
+            )>,
+            Without<AccessibilityNode>,
+        ),
+    >,
+) {
+    for e in components.iter() {
+        cmd.entity(e)
+            .insert(AccessibilityNode::from(NodeBuilder::new(Role::Image)));
+    }
+}
+
+fn describe_components(
+    mut components: Query<(
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        &mut AccessibilityNode,
+    )>,
+    ui_lights: Query<&UILight>,
+    ui_buttons: Query<(&UIButton, &Interaction)>,
+    relay_coils: Query<&RelayCoil>,
+    hidden_regions: Query<&HiddenRegion>,
+) {
+    let relay_activated = |id: usize| {
+        relay_coils
+            .iter()
+            .any(|relay_coil| relay_coil.id == id && relay_coil.activated)
+    };
+
+    for ((light, button, relay_coil, relay_switch), mut node) in components.iter_mut() {
+        let (name, top, state) = if let Some(light) = light {
+            let lit = ui_lights
+                .iter()
+                .any(|ui_light| ui_light.id == light.id && ui_light.is_lit);
+            (
+                format!("Lamp -P{}", light.id),
+                light.top,
+                if lit { "lit" } else { "off" },
+            )
+        } else if let Some(button) = button {
+            let pressed = ui_buttons.iter().any(|(ui_button, interaction)| {
+                ui_button.id == button.id
+                    && (ui_button.latched
+                        || (*interaction == Interaction::Pressed && !ui_button.maintained))
+            });
+            (
+                format!(
+                    "{} contact of button -S{}",
+                    contact_name(button.typ),
+                    button.id
+                ),
+                button.top,
+                contact_state(button.typ, pressed),
+            )
+        } else if let Some(relay_coil) = relay_coil {
+            (
+                format!("Coil of relay -K{}", relay_coil.id),
+                relay_coil.top,
+                if relay_coil.activated {
+                    "pulled in"
+                } else {
+                    "released"
+                },
+            )
+        } else if let Some(relay_switch) = relay_switch {
+            (
+                format!(
+                    "{} contact of relay -K{}",
+                    contact_name(relay_switch.typ),
+                    relay_switch.id
+                ),
+                relay_switch.top,
+                contact_state(relay_switch.typ, relay_activated(relay_switch.id)),
+            )
+        } else {
+            continue;
+        };
+
+        // Whatever lies inside a black box must not be given away
+        if hidden_regions.iter().any(|region| region.hides(top)) {
+            if !node.is_hidden() {
+                node.set_hidden();
+            }
+            continue;
+        }
+        if node.is_hidden() {
+            node.clear_hidden();
+        }
+
+        set_text(
+            &mut node,
+            &format!("{name} at {}, {}", top.x, top.y),
+            Some(state),
+        );
+    }
+}
+
+fn update_status_node(
+    running: Res<IsRunning>,
+    history: Res<SimulationHistory>,
+    ui_lights: Query<&UILight>,
+    mut status: Query<&mut AccessibilityNode, With<StatusNode>>,
+) {
+    let mut lit = ui_lights
+        .iter()
+        .filter(|ui_light| ui_light.is_lit)
+        .map(|ui_light| format!("-P{}", ui_light.id))
+        .collect::<Vec<_>>();
+    lit.sort();
+
+    let running = if !running.0 {
+        "Simulation stopped"
+    } else if history.cursor.is_some() {
+        "Simulation paused"
+    } else {
+        "Simulation running"
+    };
+    let lamps = if lit.is_empty() {
+        "no lamps lit".to_string()
+    } else {
+        format!("lamps lit: {}", lit.join(", "))
+    };
+
+    for mut node in status.iter_mut() {
+        set_text(&mut node, &format!("{running}, {lamps}"), None);
+    }
+}
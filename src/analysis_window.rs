@@ -0,0 +1,324 @@
+use bevy::{
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    sprite::Anchor,
+    window::{WindowRef, WindowResolution},
+};
+
+use crate::{history::SimulationHistory, spawn_toolbar_button, Toolbar};
+
+const ANALYSIS_WINDOW_RESOLUTION: (f32, f32) = (900., 360.);
+// Nothing in the schematic window uses this layer, so the main camera never sees the diagram
+const ANALYSIS_LAYER: u8 = 1;
+const LABEL_WIDTH: f32 = 70.;
+
+// The analysis button opens a second os window with a timing diagram of the recorded history, so it can live on another monitor
+pub struct AnalysisWindowPlugin;
+
+impl Plugin for AnalysisWindowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AnalysisWindow>()
+            .add_systems(PostStartup, setup_analysis_button)
+            .add_systems(
+                Update,
+                (
+                    handle_analysis_button_press,
+                    close_analysis_window,
+                    draw_timing_diagram,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct AnalysisWindow {
+    // The window and its camera while the window is open
+    open: Option<(Entity, Entity)>,
+}
+
+#[derive(Component)]
+struct AnalysisButton;
+
+// Everything that is drawn in the analysis window
+#[derive(Component)]
+struct TimingDiagramPart;
+
+fn setup_analysis_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Timing", "Analysis Window", AnalysisButton);
+    });
+}
+
+fn handle_analysis_button_press(
+    mut cmd: Commands,
+    mut analysis: ResMut<AnalysisWindow>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<AnalysisButton>)>,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if let Some((window, camera)) = analysis.open.take() {
+        cmd.entity(window).despawn_recursive();
+        cmd.entity(camera).despawn_recursive();
+        return;
+    }
+
+    let window = cmd
+        .spawn((
+            Window {
+                title: "Timing Diagram".to_string(),
+                resolution: WindowResolution::from(ANALYSIS_WINDOW_RESOLUTION),
+                present_mode: bevy::window::PresentMode::AutoVsync,
+                ..Default::default()
+            },
+            Name::new("Analysis Window"),
+        ))
+        .id();
+
+    let camera = cmd
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            // The left section and overlays belong to the schematic window only
+            UiCameraConfig { show_ui: false },
+            RenderLayers::layer(ANALYSIS_LAYER),
+            Name::new("Analysis Camera"),
+        ))
+        .id();
+
+    analysis.open = Some((window, camera));
+}
+
+// Closing the os window only despawns the window, the camera and diagram have to go with it
+fn close_analysis_window(
+    mut cmd: Commands,
+    mut analysis: ResMut<AnalysisWindow>,
+    windows: Query<(), With<Window>>,
+) {
+    let Some((window, camera)) = analysis.open else {
+        return;
+    };
+
+    if !windows.contains(window) {
+        cmd.entity(camera).despawn_recursive();
+        analysis.open = None;
+    }
+}
+
+// One row per element that was active at some point, in the same order as the scrubber text, the dock draws them as well
+pub fn timing_rows(history: &SimulationHistory) -> Vec<(String, Vec<bool>)> {
+    let mut rows = Vec::new();
+    for (prefix, ids) in [
+        (
+            "S",
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| &snapshot.pressed_button_ids)
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "K",
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| &snapshot.activated_relay_ids)
+                .collect(),
+        ),
+        (
+            "P",
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| &snapshot.lit_light_ids)
+                .collect(),
+        ),
+    ] {
+        let mut seen = ids.iter().flat_map(|ids| ids.iter()).collect::<Vec<_>>();
+        seen.sort();
+        seen.dedup();
+        for id in seen {
+            rows.push((
+                format!("-{prefix}{id}"),
+                ids.iter().map(|ids| ids.contains(id)).collect(),
+            ));
+        }
+    }
+    rows
+}
+
+// The texts and sprites of the diagram are kept and moved around, new ones are only spawned when there are more rows or runs than before
+fn draw_timing_diagram(
+    mut cmd: Commands,
+    analysis: Res<AnalysisWindow>,
+    history: Res<SimulationHistory>,
+    mut drawn: Local<Option<(Vec<(String, Vec<bool>)>, Option<usize>)>>,
+    mut text_parts: Query<
+        (Entity, &mut Text, &mut Anchor, &mut Transform),
+        (With<TimingDiagramPart>, Without<Sprite>),
+    >,
+    mut sprite_parts: Query<(Entity, &mut Sprite, &mut Transform), With<TimingDiagramPart>>,
+) {
+    if !history.is_changed() && !analysis.is_changed() {
+        return;
+    }
+
+    if analysis.open.is_none() {
+        for (e, ..) in text_parts.iter() {
+            cmd.entity(e).despawn_recursive();
+        }
+        for (e, ..) in sprite_parts.iter() {
+            cmd.entity(e).despawn_recursive();
+        }
+        *drawn = None;
+        return;
+    }
+
+    // Other systems may touch the history without changing anything the diagram shows
+    let rows = timing_rows(&history);
+    if !analysis.is_changed() && drawn.as_ref() == Some(&(rows.clone(), history.cursor)) {
+        return;
+    }
+
+    let (width, height) = ANALYSIS_WINDOW_RESOLUTION;
+    let left = -width / 2. + LABEL_WIDTH;
+    let top = height / 2. - 10.;
+    let tick_width = (width - LABEL_WIDTH - 10.) / history.snapshots.len().max(1) as f32;
+    let row_height = ((height - 20.) / rows.len().max(1) as f32).min(30.);
+
+    let mut texts = Vec::new();
+    let mut sprites = Vec::new();
+    if rows.is_empty() {
+        texts.push((
+            "Nothing was active in the recorded history".to_string(),
+            Anchor::Center,
+            Vec3::ZERO,
+        ));
+    }
+    for (index, (label, states)) in rows.iter().enumerate() {
+        let middle = top - row_height * (index as f32 + 0.5);
+        texts.push((
+            label.clone(),
+            Anchor::CenterLeft,
+            Vec3::new(-width / 2. + 10., middle, 0.),
+        ));
+
+        // Low ticks are a thin line at the bottom of the row, runs of high ticks one tall block
+        let mut tick = 0;
+        while tick < states.len() {
+            let state = states[tick];
+            let run = states[tick..]
+                .iter()
+                .take_while(|other| **other == state)
+                .count();
+            let (block_height, color) = if state {
+                (row_height * 0.6, Color::rgb(0.2, 0.8, 0.2))
+            } else {
+                (2., Color::GRAY)
+            };
+            sprites.push((
+                color,
+                Vec2::new(run as f32 * tick_width, block_height),
+                Anchor::BottomLeft,
+                Vec3::new(
+                    left + tick as f32 * tick_width,
+                    middle - row_height * 0.3,
+                    0.,
+                ),
+            ));
+
+            tick += run;
+        }
+    }
+
+    // The tick that is shown in the schematic while paused
+    if let Some(cursor) = history.cursor.filter(|_| !rows.is_empty()) {
+        sprites.push((
+            Color::rgb(0.9, 0.9, 0.9),
+            Vec2::new(2., row_height * rows.len() as f32),
+            Anchor::TopCenter,
+            Vec3::new(left + (cursor as f32 + 0.5) * tick_width, top, 1.),
+        ));
+    }
+
+    let layer = RenderLayers::layer(ANALYSIS_LAYER);
+    let mut kept_texts = text_parts.iter_mut();
+    for (value, anchor, translation) in texts {
+        match kept_texts.next() {
+            Some((_, mut text, mut text_anchor, mut transform)) => {
+                if text.sections[0].value != value {
+                    text.sections[0].value = value;
+                }
+                *text_anchor = anchor;
+                transform.translation = translation;
+            }
+            None => {
+                cmd.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            value,
+                            TextStyle {
+                                font_size: 16.,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                                ..Default::default()
+                            },
+                        ),
+                        text_anchor: anchor,
+                        transform: Transform::from_translation(translation),
+                        ..Default::default()
+                    },
+                    layer,
+                    Name::new("Timing Diagram Text"),
+                    TimingDiagramPart,
+                ));
+            }
+        }
+    }
+    for (e, ..) in kept_texts {
+        cmd.entity(e).despawn_recursive();
+    }
+
+    let mut kept_sprites = sprite_parts.iter_mut();
+    for (color, size, anchor, translation) in sprites {
+        match kept_sprites.next() {
+            Some((_, mut sprite, mut transform)) => {
+                sprite.color = color;
+                sprite.custom_size = Some(size);
+                sprite.anchor = anchor;
+                transform.translation = translation;
+            }
+            None => {
+                cmd.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(size),
+                            anchor,
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(translation),
+                        ..Default::default()
+                    },
+                    layer,
+                    Name::new("Timing Diagram Block"),
+                    TimingDiagramPart,
+                ));
+            }
+        }
+    }
+    for (e, ..) in kept_sprites {
+        cmd.entity(e).despawn_recursive();
+    }
+
+    *drawn = Some((rows, history.cursor));
+}
@@ -0,0 +1,226 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, spawn_toolbar_button, CurrentlyPlacing,
+    GridPosition, MainCamera, Toolbar,
+};
+
+const ANNOTATION_COLOR: Color = Color::rgb(1., 0.6, 0.2);
+
+// Rectangles and arrows drawn on top of the schematic, they are only drawn with gizmos and never take part in placement or simulation
+pub struct AnnotationPlugin;
+
+impl Plugin for AnnotationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AnnotationsVisible>()
+            .add_systems(PostStartup, setup_annotation_buttons)
+            .add_systems(
+                Update,
+                (
+                    handle_annotation_button_press,
+                    handle_annotation_placement,
+                    draw_annotations,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AnnotationShape {
+    Rectangle,
+    Arrow,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub shape: AnnotationShape,
+    pub start: GridPosition,
+    pub end: GridPosition,
+}
+
+impl Annotation {
+    fn contains(&self, pos: GridPosition) -> bool {
+        let start = Vec2::new(self.start.x as f32, self.start.y as f32);
+        let end = Vec2::new(self.end.x as f32, self.end.y as f32);
+        let pos = Vec2::new(pos.x as f32, pos.y as f32);
+
+        match self.shape {
+            // Anywhere on the border
+            AnnotationShape::Rectangle => {
+                let min = start.min(end);
+                let max = start.max(end);
+                let inside = pos.cmpge(min).all() && pos.cmple(max).all();
+                inside && (pos.x == min.x || pos.x == max.x || pos.y == min.y || pos.y == max.y)
+            }
+            // Close to the line
+            AnnotationShape::Arrow => {
+                let line = end - start;
+                let t = ((pos - start).dot(line) / line.length_squared().max(f32::EPSILON))
+                    .clamp(0., 1.);
+                (start + line * t).distance(pos) <= 0.5
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct AnnotationsVisible(bool);
+
+impl Default for AnnotationsVisible {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+#[derive(Component)]
+struct AnnotationButton(AnnotationShape);
+
+#[derive(Component)]
+struct AnnotationToggleButton;
+
+pub fn spawn_annotation(cmd: &mut Commands, annotation: Annotation) -> Entity {
+    cmd.spawn((Name::new("Annotation"), annotation)).id()
+}
+
+fn setup_annotation_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(
+            root,
+            "Rect",
+            "Annotation Rectangle",
+            AnnotationButton(AnnotationShape::Rectangle),
+        );
+        spawn_toolbar_button(
+            root,
+            "Arrow",
+            "Annotation Arrow",
+            AnnotationButton(AnnotationShape::Arrow),
+        );
+        spawn_toolbar_button(root, "Notes", "Annotation Toggle", AnnotationToggleButton);
+    });
+}
+
+fn handle_annotation_button_press(
+    shape_buttons: Query<(&Interaction, &AnnotationButton), Changed<Interaction>>,
+    toggle_button: Query<&Interaction, (Changed<Interaction>, With<AnnotationToggleButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut visible: ResMut<AnnotationsVisible>,
+) {
+    for (interaction, button) in shape_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            *currently_placing = CurrentlyPlacing::Annotation(button.0);
+            visible.0 = true;
+        }
+    }
+
+    if toggle_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        visible.0 = !visible.0;
+    }
+}
+
+// Left drag draws a new annotation, right click removes the one under the cursor or leaves annotation mode
+fn handle_annotation_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    annotations: Query<(Entity, &Annotation)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut drag_start: Local<Option<GridPosition>>,
+    mut gizmos: Gizmos,
+) {
+    let CurrentlyPlacing::Annotation(shape) = *currently_placing else {
+        *drag_start = None;
+        return;
+    };
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        *drag_start = None;
+        match annotations
+            .iter()
+            .find(|(_, annotation)| annotation.contains(mouse_grid))
+        {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        *drag_start = Some(mouse_grid);
+    }
+
+    let Some(start) = *drag_start else {
+        return;
+    };
+
+    let annotation = Annotation {
+        shape,
+        start,
+        end: mouse_grid,
+    };
+
+    if mouse_button.just_released(MouseButton::Left) {
+        *drag_start = None;
+        if start != mouse_grid {
+            spawn_annotation(&mut cmd, annotation);
+        }
+    } else {
+        draw_annotation(&mut gizmos, &annotation, ANNOTATION_COLOR.with_a(0.5));
+    }
+}
+
+fn draw_annotation(gizmos: &mut Gizmos, annotation: &Annotation, color: Color) {
+    let start = grid_to_world(annotation.start);
+    let end = grid_to_world(annotation.end);
+
+    match annotation.shape {
+        AnnotationShape::Rectangle => {
+            gizmos.rect_2d((start + end) / 2., 0., (end - start).abs(), color);
+        }
+        AnnotationShape::Arrow => {
+            gizmos.line_2d(start, end, color);
+            let direction = (end - start).normalize_or_zero();
+            for side in [-1., 1.] {
+                let head = Vec2::from_angle(side * 2.6).rotate(direction) * 12.;
+                gizmos.line_2d(end, end + head, color);
+            }
+        }
+    }
+}
+
+fn draw_annotations(
+    visible: Res<AnnotationsVisible>,
+    annotations: Query<&Annotation>,
+    mut gizmos: Gizmos,
+) {
+    if !visible.0 {
+        return;
+    }
+
+    for annotation in annotations.iter() {
+        draw_annotation(&mut gizmos, annotation, ANNOTATION_COLOR);
+    }
+}
@@ -0,0 +1,557 @@
+use bevy::{input::InputSystem, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    component_middle, convert_mouse_to_grid,
+    diode::Diode,
+    fuse::Fuse,
+    grid::{GridSize, MAX_GRIDSIZE},
+    grid_to_world,
+    macros::{normalize, MacroStep},
+    measure::Measurement,
+    net_labels::NetLabel,
+    sheets::{SheetParts, Sheets},
+    spawn_toolbar_button,
+    time_switch::TimeSwitch,
+    wire_contains, ButtonSwitch, CurrentlyPlacing, GridPosition, Light, MainCamera, Power,
+    RelayCoil, RelaySwitch, SwitchType, Toolbar, Wire,
+};
+
+const BLOCK_COLOR: Color = Color::rgb(0.6, 0.5, 0.9);
+const MAX_NAME_LENGTH: usize = 24;
+// Relays inside a block get ids of their own per copy, far above what can be placed by hand
+const BLOCK_RELAY_IDS: usize = 10_000;
+const RELAY_IDS_PER_BLOCK: usize = 100;
+
+// With a selection made by the measure tool the block button collapses everything completely inside it into a block
+// Points where the rest of the circuit touches the block become its pins, a name can be typed right after collapsing
+// In block mode every click places another copy of the last collapsed or clicked block, right clicking a block removes it
+// The simulation flattens every copy into its own wires and components, lights and buttons keep their ids, relays get their own per copy
+pub struct BlocksPlugin;
+
+impl Plugin for BlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlockTemplate>()
+            .add_systems(PostStartup, setup_block_button)
+            .add_systems(PreUpdate, type_block_name.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    collapse_selection,
+                    handle_block_placement,
+                    add_block_visuals,
+                    update_block_names,
+                    draw_blocks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub name: String,
+    // No two blocks of a circuit share it, also over sheets, so every copy has relays of its own
+    pub instance: usize,
+    // Bottom left corner, the steps and pins are relative to it
+    pub origin: GridPosition,
+    pub steps: Vec<MacroStep>,
+    pub pins: Vec<GridPosition>,
+}
+
+impl Block {
+    fn size(&self) -> GridPosition {
+        let positions = self
+            .steps
+            .iter()
+            .flat_map(|step| <[GridPosition; 2]>::from(step.positions()));
+        GridPosition {
+            x: positions.clone().map(|pos| pos.x).max().unwrap_or(0),
+            y: positions.map(|pos| pos.y).max().unwrap_or(0),
+        }
+    }
+
+    fn at(&self, pos: GridPosition) -> GridPosition {
+        GridPosition {
+            x: self.origin.x + pos.x,
+            y: self.origin.y + pos.y,
+        }
+    }
+
+    pub fn contains(&self, pos: GridPosition) -> bool {
+        let end = self.at(self.size());
+        (self.origin.x..=end.x).contains(&pos.x) && (self.origin.y..=end.y).contains(&pos.y)
+    }
+
+    pub fn pin_positions(&self) -> impl Iterator<Item = GridPosition> + '_ {
+        self.pins.iter().map(|pin| self.at(*pin))
+    }
+
+    // The inside of every copy lies above the grid, each copy the height of the largest grid further up so no points meet
+    // The pins are joined to it and are a wire end themselves, so wires running through a pin connect to it
+    pub fn add_parts(&self, parts: &mut SheetParts) {
+        let inside = |pos: GridPosition| {
+            let pos = self.at(pos);
+            GridPosition {
+                x: pos.x,
+                y: pos.y + (self.instance + 1) * MAX_GRIDSIZE,
+            }
+        };
+        let relay_id = |id: usize| BLOCK_RELAY_IDS + self.instance * RELAY_IDS_PER_BLOCK + id;
+        // Contacts of relays whose coil is not part of the block keep switching with that relay
+        let coil_ids = self
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                MacroStep::RelayCoil(relay_coil) => Some(relay_coil.id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for step in self.steps.iter() {
+            let mut step = step.clone();
+            step.map_positions(inside);
+            match step {
+                MacroStep::Wire(wire) => parts.wires.push(wire),
+                MacroStep::Light(light) => parts.lights.push(light),
+                MacroStep::Button(button) => parts.buttons.push(button),
+                MacroStep::RelayCoil(mut relay_coil) => {
+                    relay_coil.id = relay_id(relay_coil.id);
+                    parts.relay_coils.push(relay_coil);
+                }
+                MacroStep::RelaySwitch(mut relay_switch) => {
+                    if coil_ids.contains(&relay_switch.id) {
+                        relay_switch.id = relay_id(relay_switch.id);
+                    }
+                    parts.relay_switches.push(relay_switch);
+                }
+                // Clocks only count time, they connect nothing
+                MacroStep::Clock(_) => {}
+                MacroStep::TimeSwitch(time_switch) => parts.time_switches.push(time_switch),
+                // Like on the sheet an intact fuse is a wire and a blown one connects nothing
+                MacroStep::Fuse(fuse) => {
+                    if !fuse.blown {
+                        parts.wires.push(Wire::from(&fuse));
+                    }
+                }
+                MacroStep::Diode(diode) => parts.diodes.push(diode),
+                MacroStep::NetLabel(label) => parts.net_labels.push(label),
+            }
+        }
+        for pin in self.pins.iter() {
+            let outside = self.at(*pin);
+            parts.wires.push(Wire {
+                first: outside,
+                second: outside,
+            });
+            parts.links.push((outside, inside(*pin)));
+        }
+    }
+}
+
+// What block mode places, also kept when the mode is left
+#[derive(Resource, Default)]
+struct BlockTemplate {
+    block: Option<Block>,
+    // The block that was just collapsed while its name is typed
+    naming: Option<Entity>,
+}
+
+#[derive(Component)]
+struct BlockButton;
+
+#[derive(Component)]
+struct BlockName;
+
+pub fn spawn_block(cmd: &mut Commands, block: Block) -> Entity {
+    cmd.spawn((SpatialBundle::default(), Name::new("Block"), block))
+        .id()
+}
+
+fn setup_block_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Block", "Block", BlockButton);
+    });
+}
+
+// Every point of a step, the common terminal of changeover contacts included
+fn step_points(step: &MacroStep) -> Vec<GridPosition> {
+    let (first, second) = step.positions();
+    let changeover = match step {
+        MacroStep::Button(button) => button.typ == SwitchType::Changeover,
+        MacroStep::RelaySwitch(relay_switch) => relay_switch.typ == SwitchType::Changeover,
+        _ => false,
+    };
+    [first, second]
+        .into_iter()
+        .chain(changeover.then(|| component_middle(first, second)))
+        .collect()
+}
+
+fn collapse_selection(
+    mut cmd: Commands,
+    block_button: Query<&Interaction, (Changed<Interaction>, With<BlockButton>)>,
+    mut measurement: ResMut<Measurement>,
+    mut template: ResMut<BlockTemplate>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    sheets: Res<Sheets>,
+    blocks: Query<&Block>,
+    (wires, lights, buttons, relay_coils, relay_switches): (
+        Query<(Entity, &Wire)>,
+        Query<(Entity, &Light)>,
+        Query<(Entity, &ButtonSwitch)>,
+        Query<(Entity, &RelayCoil)>,
+        Query<(Entity, &RelaySwitch)>,
+    ),
+    (power_sources, diodes, fuses, time_switches, net_labels): (
+        Query<&GridPosition, With<Power>>,
+        Query<&Diode>,
+        Query<&Fuse>,
+        Query<&TimeSwitch>,
+        Query<&NetLabel>,
+    ),
+) {
+    if !block_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let Some((a, b)) = measurement.selection() else {
+        if template.block.is_some() {
+            *currently_placing = CurrentlyPlacing::Block;
+        } else {
+            warn!("Select an area with the measure tool first, then make a block of it");
+        }
+        return;
+    };
+
+    let inside = |(first, second): (GridPosition, GridPosition)| {
+        [first, second].into_iter().all(|pos| {
+            (a.x.min(b.x)..=a.x.max(b.x)).contains(&pos.x)
+                && (a.y.min(b.y)..=a.y.max(b.y)).contains(&pos.y)
+        })
+    };
+    let steps = wires
+        .iter()
+        .map(|(e, wire)| (e, MacroStep::Wire(wire.clone())))
+        .chain(
+            lights
+                .iter()
+                .map(|(e, light)| (e, MacroStep::Light(light.clone()))),
+        )
+        .chain(
+            buttons
+                .iter()
+                .map(|(e, button)| (e, MacroStep::Button(button.clone()))),
+        )
+        .chain(
+            relay_coils
+                .iter()
+                .map(|(e, relay_coil)| (e, MacroStep::RelayCoil(relay_coil.clone()))),
+        )
+        .chain(
+            relay_switches
+                .iter()
+                .map(|(e, relay_switch)| (e, MacroStep::RelaySwitch(relay_switch.clone()))),
+        );
+    let (collapsed, rest): (Vec<_>, Vec<_>) = steps.partition(|(_, step)| inside(step.positions()));
+    if collapsed.is_empty() {
+        warn!("Nothing lies completely inside the selection, no block was made");
+        return;
+    }
+
+    // Relays inside a block are its own, so no contact outside of it could switch with them anymore
+    let coil_ids = collapsed
+        .iter()
+        .filter_map(|(_, step)| match step {
+            MacroStep::RelayCoil(relay_coil) => Some(relay_coil.id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let split_relay = rest.iter().find_map(|(_, step)| match step {
+        MacroStep::RelaySwitch(relay_switch) if coil_ids.contains(&relay_switch.id) => {
+            Some(relay_switch.id)
+        }
+        _ => None,
+    });
+    if let Some(id) = split_relay {
+        warn!("-K{id} has contacts outside the selection, a block has to hold every contact of its relays");
+        return;
+    }
+
+    // Every point of the collapsed steps that something outside of them touches becomes a pin
+    let touched = rest
+        .iter()
+        .flat_map(|(_, step)| step_points(step))
+        .chain(power_sources.iter().copied())
+        .chain(diodes.iter().flat_map(|diode| [diode.anode, diode.cathode]))
+        .chain(fuses.iter().flat_map(|fuse| [fuse.top, fuse.bottom]))
+        .chain(
+            time_switches
+                .iter()
+                .flat_map(|time_switch| [time_switch.top, time_switch.bottom]),
+        )
+        .chain(net_labels.iter().map(|label| label.pos))
+        .chain(blocks.iter().flat_map(|block| block.pin_positions()))
+        .collect::<Vec<_>>();
+    let outside_wires = rest
+        .iter()
+        .filter_map(|(_, step)| match step {
+            MacroStep::Wire(wire) => Some(wire),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut pins = collapsed
+        .iter()
+        .flat_map(|(_, step)| step_points(step))
+        .filter(|point| {
+            touched.contains(point) || outside_wires.iter().any(|wire| wire_contains(wire, point))
+        })
+        .collect::<Vec<_>>();
+    pins.sort_by_key(|pin| (pin.x, pin.y));
+    pins.dedup();
+
+    let steps = collapsed
+        .iter()
+        .map(|(_, step)| step.clone())
+        .collect::<Vec<_>>();
+    let positions = steps
+        .iter()
+        .flat_map(|step| <[GridPosition; 2]>::from(step.positions()));
+    let origin = GridPosition {
+        x: positions.clone().map(|pos| pos.x).min().unwrap_or(0),
+        y: positions.map(|pos| pos.y).min().unwrap_or(0),
+    };
+    let used = blocks
+        .iter()
+        .chain(sheets.sheets.iter().flat_map(|sheet| sheet.blocks()))
+        .map(|block| block.instance)
+        .collect::<Vec<_>>();
+    let instance = (1..).find(|i| !used.contains(i)).unwrap_or_default();
+    let block = Block {
+        name: format!("Block {instance}"),
+        instance,
+        origin,
+        steps: normalize(steps),
+        pins: pins
+            .into_iter()
+            .map(|pin| GridPosition {
+                x: pin.x - origin.x,
+                y: pin.y - origin.y,
+            })
+            .collect(),
+    };
+
+    for (e, _) in collapsed {
+        cmd.entity(e).despawn_recursive();
+    }
+    info!(
+        "Made a block of {} elements with {} pins",
+        block.steps.len(),
+        block.pins.len()
+    );
+    template.naming = Some(spawn_block(&mut cmd, block.clone()));
+    template.block = Some(block);
+    measurement.clear();
+    *currently_placing = CurrentlyPlacing::Block;
+}
+
+fn type_block_name(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut template: ResMut<BlockTemplate>,
+    mut blocks: Query<&mut Block>,
+) {
+    let Some(mut block) = template.naming.and_then(|e| blocks.get_mut(e).ok()) else {
+        if template.naming.is_some() {
+            template.naming = None;
+        }
+        characters.clear();
+        return;
+    };
+
+    let mut name = block.name.clone();
+    if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(KeyCode::Return) {
+        template.naming = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            name.pop();
+        }
+        for c in characters.read().map(|event| event.char) {
+            if name.chars().count() < MAX_NAME_LENGTH && !c.is_control() {
+                name.push(c);
+            }
+        }
+    }
+    if block.name != name {
+        block.name = name.clone();
+        if let Some(template) = template.block.as_mut() {
+            template.name = name;
+        }
+    }
+
+    characters.clear();
+    keys.reset_all();
+}
+
+fn handle_block_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut template: ResMut<BlockTemplate>,
+    sheets: Res<Sheets>,
+    blocks: Query<(Entity, &Block)>,
+    mut gizmos: Gizmos,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Block) {
+        return;
+    }
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+    let clicked = blocks.iter().find(|(_, block)| block.contains(mouse_grid));
+
+    if let Some(block) = template.block.as_ref().filter(|_| clicked.is_none()) {
+        let preview = Block {
+            origin: mouse_grid,
+            ..block.clone()
+        };
+        let (start, end) = (
+            grid_to_world(preview.origin),
+            grid_to_world(preview.at(preview.size())),
+        );
+        gizmos.rect_2d((start + end) / 2., 0., end - start, Color::GRAY);
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // Clicking a placed block makes copies of that one from then on
+    if let Some((_, block)) = clicked {
+        template.block = Some(block.clone());
+        return;
+    }
+    let Some(block) = template.block.as_ref() else {
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    };
+    let size = block.size();
+    if mouse_grid.x + size.x >= grid_size.width || mouse_grid.y + size.y >= grid_size.height {
+        warn!("The block {} does not fit here", block.name);
+        return;
+    }
+
+    let used = blocks
+        .iter()
+        .map(|(_, block)| block)
+        .chain(sheets.sheets.iter().flat_map(|sheet| sheet.blocks()))
+        .map(|block| block.instance)
+        .collect::<Vec<_>>();
+    let instance = (1..).find(|i| !used.contains(i)).unwrap_or_default();
+    spawn_block(
+        &mut cmd,
+        Block {
+            instance,
+            origin: mouse_grid,
+            ..block.clone()
+        },
+    );
+}
+
+// Made from the block itself, so loading only has to spawn the block
+fn add_block_visuals(mut cmd: Commands, blocks: Query<(Entity, &Block), Added<Block>>) {
+    for (e, block) in blocks.iter() {
+        let top_left = grid_to_world(GridPosition {
+            x: block.origin.x,
+            y: block.origin.y + block.size().y,
+        });
+        cmd.entity(e).with_children(|root| {
+            root.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        block.name.clone(),
+                        TextStyle {
+                            font_size: 14.,
+                            color: BLOCK_COLOR,
+                            ..Default::default()
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::BottomLeft,
+                    transform: Transform::from_translation(
+                        (top_left + Vec2::new(0., 12.)).extend(5.),
+                    ),
+                    ..Default::default()
+                },
+                Name::new("Block Name"),
+                BlockName,
+            ));
+        });
+    }
+}
+
+fn update_block_names(
+    template: Res<BlockTemplate>,
+    blocks: Query<(Entity, &Block, &Children)>,
+    mut texts: Query<&mut Text, With<BlockName>>,
+) {
+    for (e, block, children) in blocks.iter() {
+        let value = if template.naming == Some(e) {
+            format!("{}_   (Enter to keep)", block.name)
+        } else {
+            block.name.clone()
+        };
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            if text.sections[0].value != value {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
+
+// The outline and the pins are drawn every frame, what is inside is only drawn as grey lines
+fn draw_blocks(blocks: Query<&Block>, mut gizmos: Gizmos) {
+    for block in blocks.iter() {
+        let (start, end) = (
+            grid_to_world(block.origin),
+            grid_to_world(block.at(block.size())),
+        );
+        gizmos.rect_2d((start + end) / 2., 0., end - start, BLOCK_COLOR);
+        for step in block.steps.iter() {
+            let (first, second) = step.positions();
+            gizmos.line_2d(
+                grid_to_world(block.at(first)),
+                grid_to_world(block.at(second)),
+                Color::DARK_GRAY,
+            );
+        }
+        for pin in block.pin_positions() {
+            gizmos.circle_2d(grid_to_world(pin), 5., BLOCK_COLOR);
+        }
+    }
+}
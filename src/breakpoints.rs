@@ -0,0 +1,150 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_contains, component_middle, convert_mouse_to_grid,
+    grid::GridSize,
+    grid_to_world, is_running,
+    keybindings::{Action, KeyBindings},
+    oriented, simulate, IsRunning, Light, MainCamera, RelayCoil, UILight,
+};
+
+const BREAKPOINT_COLOR: Color = Color::rgb(0.9, 0.1, 0.1);
+const HIT_COLOR: Color = Color::rgb(1., 0.6, 0.);
+
+// The breakpoint key (F9) on a hovered relay coil or lamp pauses the simulation once that relay pulls in or that lamp lights up
+// The simulation is stopped right after the tick, the component that caused it stays outlined until running again
+pub struct BreakpointPlugin;
+
+impl Plugin for BreakpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BreakpointHit>()
+            .add_systems(
+                FixedUpdate,
+                check_breakpoints.after(simulate).run_if(is_running),
+            )
+            .add_systems(Update, (toggle_breakpoint, show_breakpoints).chain());
+    }
+}
+
+#[derive(Component)]
+struct Breakpoint {
+    // Only turning on breaks, something that is on already when running continues does not
+    was_on: bool,
+}
+
+fn is_on(
+    light: Option<&Light>,
+    relay_coil: Option<&RelayCoil>,
+    ui_lights: &Query<&UILight>,
+) -> bool {
+    match (light, relay_coil) {
+        (Some(light), _) => ui_lights
+            .iter()
+            .any(|ui_light| ui_light.id == light.id && ui_light.is_lit),
+        (_, Some(relay_coil)) => relay_coil.activated,
+        (None, None) => false,
+    }
+}
+
+// The component whose breakpoint stopped the simulation
+#[derive(Resource, Default)]
+struct BreakpointHit(Option<Entity>);
+
+fn toggle_breakpoint(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    components: Query<(Entity, AnyOf<(&Light, &RelayCoil)>, Has<Breakpoint>)>,
+    ui_lights: Query<&UILight>,
+) {
+    if !bindings.just_pressed(&keys, Action::ToggleBreakpoint) {
+        return;
+    }
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    let hovered = components.iter().find(|(_, (light, relay_coil), _)| {
+        light
+            .map(|light| (light.top, light.bottom))
+            .or(relay_coil.map(|relay_coil| (relay_coil.top, relay_coil.bottom)))
+            .is_some_and(|(top, bottom)| component_contains(top, bottom, mouse_grid))
+    });
+    match hovered {
+        Some((e, _, true)) => {
+            cmd.entity(e).remove::<Breakpoint>();
+        }
+        Some((e, (light, relay_coil), false)) => {
+            cmd.entity(e).insert(Breakpoint {
+                was_on: is_on(light, relay_coil, &ui_lights),
+            });
+        }
+        None => {}
+    }
+}
+
+fn check_breakpoints(
+    mut running: ResMut<IsRunning>,
+    mut hit: ResMut<BreakpointHit>,
+    mut breakpoints: Query<(Entity, &mut Breakpoint, AnyOf<(&Light, &RelayCoil)>)>,
+    ui_lights: Query<&UILight>,
+) {
+    for (e, mut breakpoint, (light, relay_coil)) in breakpoints.iter_mut() {
+        let on = is_on(light, relay_coil, &ui_lights);
+        let turned_on = on && !breakpoint.was_on;
+        breakpoint.was_on = on;
+        if turned_on && running.0 {
+            match (light, relay_coil) {
+                (Some(light), _) => info!("Breakpoint: -P{} lit up", light.id),
+                (_, Some(relay_coil)) => info!("Breakpoint: -K{} became energized", relay_coil.id),
+                (None, None) => {}
+            }
+            running.0 = false;
+            hit.0 = Some(e);
+        }
+    }
+}
+
+fn show_breakpoints(
+    running: Res<IsRunning>,
+    mut hit: ResMut<BreakpointHit>,
+    mut breakpoints: Query<(Entity, &mut Breakpoint, AnyOf<(&Light, &RelayCoil)>)>,
+    ui_lights: Query<&UILight>,
+    mut gizmos: Gizmos,
+) {
+    // Resuming continues from what is on now, so the breakpoint that just hit does not hit again right away
+    if running.is_changed() && running.0 {
+        hit.0 = None;
+        for (_, mut breakpoint, (light, relay_coil)) in breakpoints.iter_mut() {
+            breakpoint.was_on = is_on(light, relay_coil, &ui_lights);
+        }
+    }
+
+    for (e, _, (light, relay_coil)) in breakpoints.iter() {
+        let Some((top, bottom)) = light
+            .map(|light| (light.top, light.bottom))
+            .or(relay_coil.map(|relay_coil| (relay_coil.top, relay_coil.bottom)))
+        else {
+            continue;
+        };
+        let middle = grid_to_world(component_middle(top, bottom));
+        let size = oriented(top, bottom, Vec2::new(28., 64.));
+
+        // A dot in the corner like breakpoints in a code editor
+        gizmos.circle_2d(
+            middle + size / 2. * Vec2::new(-1., 1.),
+            4.,
+            BREAKPOINT_COLOR,
+        );
+        if hit.0 == Some(e) {
+            gizmos.rect_2d(middle, 0., size + Vec2::splat(8.), HIT_COLOR);
+        }
+    }
+}
@@ -0,0 +1,723 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    prelude::*, render::view::screenshot::ScreenshotManager, tasks::AsyncComputeTaskPool,
+    window::PrimaryWindow,
+};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+
+use crate::{
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    measure::Measurement,
+    platform::{self, timestamp},
+    spawn_toolbar_button, GridPosition, MainCamera, Toolbar,
+};
+
+const RECORDING_DURATIONS: [f32; 3] = [3., 5., 10.];
+const RECORDING_FPS: u32 = 10;
+
+// Print Screen enters capture mode, dragging a rectangle over the schematic then saves that region as a png at 2x or 4x
+// The region is rendered again with the camera zoomed in by that factor, one window sized tile per frame, so lines stay sharp
+// F12 saves everything that is visible of the schematic as a png right away, without the ui section on the left
+// The record button in the toolbar captures the schematic for a few seconds and writes an animated gif
+// Ctrl+Shift+C copies what is visible of the schematic, or only the measured selection, to the clipboard as an image
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RegionCapture>()
+            .init_resource::<GifRecording>()
+            .add_systems(Startup, setup_capture_overlay)
+            .add_systems(PostStartup, setup_record_buttons)
+            .add_systems(
+                Update,
+                (
+                    (
+                        toggle_region_capture,
+                        drag_region,
+                        render_region_tiles,
+                        update_capture_overlay,
+                    )
+                        .chain(),
+                    (handle_record_buttons, record_frames, update_record_buttons).chain(),
+                    save_screenshot,
+                ),
+            );
+
+        // The clipboard is only reached through arboard, which has no way into it from a browser page
+        #[cfg(not(target_arch = "wasm32"))]
+        app.init_resource::<ClipboardHandle>()
+            .add_systems(Update, copy_to_clipboard);
+    }
+}
+
+#[derive(Resource)]
+struct RegionCapture {
+    active: bool,
+    scale: u32,
+    // Where the drag started, in window coordinates
+    start: Option<Vec2>,
+    tiles: Option<TiledCapture>,
+}
+
+impl Default for RegionCapture {
+    fn default() -> Self {
+        Self {
+            active: false,
+            scale: 2,
+            start: None,
+            tiles: None,
+        }
+    }
+}
+
+// A region being rendered at a higher resolution, the camera and the ui are put back once every tile has arrived
+struct TiledCapture {
+    camera_view: (Transform, f32),
+    ui_visibilities: Vec<(Entity, Visibility)>,
+    // World position of the top left corner of the region and world units per pixel of the saved image
+    origin: Vec2,
+    unit: f32,
+    // Projection scale of the camera while rendering the tiles
+    zoom: f32,
+    // In physical pixels, every tile is a screenshot of the whole window
+    tile_size: UVec2,
+    tile_count: UVec2,
+    requested: u32,
+    // Screenshots arrive asynchronously, the tiles are pasted in as they come and counted
+    image: Arc<Mutex<(RgbaImage, u32)>>,
+    path: String,
+}
+
+#[derive(Component)]
+struct CaptureOverlay;
+
+#[derive(Component)]
+struct CaptureSelection;
+
+#[derive(Component)]
+struct CaptureHint;
+
+fn setup_capture_overlay(mut cmd: Commands) {
+    // Covers the whole window, so clicks while capturing never reach the grid
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..Default::default()
+            },
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(20),
+            ..Default::default()
+        },
+        Interaction::default(),
+        Name::new("Capture Overlay"),
+        CaptureOverlay,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.),
+                    left: Val::Px(290.),
+                    padding: UiRect::all(Val::Px(5.)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+                ..Default::default()
+            },
+            Name::new("Capture Hint"),
+            CaptureHint,
+        ));
+
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    border: UiRect::all(Val::Px(2.)),
+                    ..Default::default()
+                },
+                border_color: BorderColor(Color::rgb(0.9, 0.9, 0.9)),
+                background_color: BackgroundColor(Color::rgba(1., 1., 1., 0.1)),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            Name::new("Capture Selection"),
+            CaptureSelection,
+        ));
+    });
+}
+
+fn toggle_region_capture(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut capture: ResMut<RegionCapture>,
+) {
+    if capture.tiles.is_some() {
+        return;
+    }
+
+    if bindings.just_pressed(&keys, Action::RegionCapture) {
+        capture.active = !capture.active;
+        capture.start = None;
+    }
+
+    if !capture.active {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        capture.active = false;
+        capture.start = None;
+    } else if bindings.just_pressed(&keys, Action::CaptureScale2) {
+        capture.scale = 2;
+    } else if bindings.just_pressed(&keys, Action::CaptureScale4) {
+        capture.scale = 4;
+    }
+}
+
+// The selected rectangle, limited to the schematic to the right of the ui section
+fn selection_rect(start: Vec2, end: Vec2, window: &Window) -> Rect {
+    let bounds = Rect::new(280., 0., window.width(), window.height());
+    Rect::from_corners(start, end).intersect(bounds)
+}
+
+fn drag_region(
+    mut capture: ResMut<RegionCapture>,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut ui_roots: Query<(Entity, &mut Visibility), (With<Node>, Without<Parent>)>,
+) {
+    if !capture.active {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        capture.active = false;
+        capture.start = None;
+        return;
+    }
+
+    let window = windows.single();
+    let Some(mouse_position) = window.cursor_position() else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        capture.start = Some(mouse_position);
+        return;
+    }
+
+    if !mouse_button.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(start) = capture.start.take() else {
+        return;
+    };
+
+    let rect = selection_rect(start, mouse_position, window);
+    if rect.width() < 2. || rect.height() < 2. {
+        return;
+    }
+
+    // The camera is zoomed in by the scale, so every pixel of the window becomes a pixel of the saved image
+    let (camera_transform, projection) = cameras.single();
+    let window_scale = window.scale_factor() as f32;
+    let camera = camera_transform.translation.truncate();
+    let offset = (rect.min - Vec2::new(window.width(), window.height()) / 2.) * projection.scale;
+    let scale = capture.scale as f32;
+    let size = (rect.size() * window_scale * scale).round().as_uvec2();
+    let tile_size = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+
+    // The ui is hidden while the tiles are rendered, it would cover parts of them
+    let mut ui_visibilities = Vec::new();
+    for (e, mut visibility) in ui_roots.iter_mut() {
+        ui_visibilities.push((e, *visibility));
+        *visibility = Visibility::Hidden;
+    }
+
+    capture.active = false;
+    capture.tiles = Some(TiledCapture {
+        camera_view: (*camera_transform, projection.scale),
+        ui_visibilities,
+        origin: Vec2::new(camera.x + offset.x, camera.y - offset.y),
+        unit: projection.scale / (window_scale * scale),
+        zoom: projection.scale / scale,
+        tile_size,
+        tile_count: (size + tile_size - UVec2::ONE) / tile_size,
+        requested: 0,
+        image: Arc::new(Mutex::new((RgbaImage::new(size.x, size.y), 0))),
+        path: format!("capture_{}.png", timestamp()),
+    });
+}
+
+// Moves the camera over the next tile and asks for its screenshot, once all of them are in the image is saved
+fn render_region_tiles(
+    mut capture: ResMut<RegionCapture>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut visibilities: Query<&mut Visibility>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    let Some(tiles) = capture.tiles.as_mut() else {
+        return;
+    };
+    let (mut camera_transform, mut projection) = cameras.single_mut();
+    let total = tiles.tile_count.x * tiles.tile_count.y;
+
+    if tiles.requested < total {
+        let tile = UVec2::new(
+            tiles.requested % tiles.tile_count.x,
+            tiles.requested / tiles.tile_count.x,
+        );
+        let corner = tile * tiles.tile_size;
+        let center = (corner.as_vec2() + tiles.tile_size.as_vec2() / 2.) * tiles.unit;
+        camera_transform.translation = Vec3::new(
+            tiles.origin.x + center.x,
+            tiles.origin.y - center.y,
+            tiles.camera_view.0.translation.z,
+        );
+        projection.scale = tiles.zoom;
+
+        let image = tiles.image.clone();
+        let result = screenshot_manager.take_screenshot(windows.single(), move |screenshot| {
+            let mut image = image.lock().unwrap();
+            match screenshot.try_into_dynamic() {
+                Ok(screenshot) => image::imageops::replace(
+                    &mut image.0,
+                    &screenshot.to_rgba8(),
+                    corner.x as i64,
+                    corner.y as i64,
+                ),
+                Err(e) => error!("Cannot convert screenshot: {e}"),
+            }
+            // Still counts as arrived, so the capture does not wait for it forever
+            image.1 += 1;
+        });
+        // Another screenshot is being taken, this tile is tried again next frame
+        if result.is_ok() {
+            tiles.requested += 1;
+        }
+        return;
+    }
+
+    let image = {
+        let mut image = tiles.image.lock().unwrap();
+        if image.1 < total {
+            return;
+        }
+        std::mem::take(&mut image.0)
+    };
+
+    let Some(tiles) = capture.tiles.take() else {
+        return;
+    };
+    (*camera_transform, projection.scale) = tiles.camera_view;
+    for (e, visibility) in tiles.ui_visibilities {
+        if let Ok(mut current) = visibilities.get_mut(e) {
+            *current = visibility;
+        }
+    }
+
+    let path = tiles.path;
+    match platform::export_png(
+        Path::new(&path),
+        image::DynamicImage::from(image).to_rgb8().into(),
+    ) {
+        Ok(_) => info!("Region capture saved to {path}"),
+        Err(e) => error!("Cannot save region capture: {e}"),
+    }
+}
+
+fn save_screenshot(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !bindings.just_pressed(&keys, Action::Screenshot) {
+        return;
+    }
+
+    let (window_entity, window) = windows.single();
+    // The screenshot is in physical pixels
+    let left = (280. * window.scale_factor()) as u32;
+    let path = format!("screenshot_{}.png", timestamp());
+
+    let result = screenshot_manager.take_screenshot(window_entity, move |screenshot| {
+        let screenshot = match screenshot.try_into_dynamic() {
+            Ok(screenshot) => screenshot,
+            Err(e) => {
+                error!("Cannot convert screenshot: {e}");
+                return;
+            }
+        };
+
+        let schematic = screenshot.crop_imm(
+            left,
+            0,
+            screenshot.width().saturating_sub(left),
+            screenshot.height(),
+        );
+        match platform::export_png(Path::new(&path), schematic.to_rgb8().into()) {
+            Ok(_) => info!("Screenshot saved to {path}"),
+            Err(e) => error!("Cannot save screenshot: {e}"),
+        }
+    });
+
+    if result.is_err() {
+        warn!("A screenshot is already being taken");
+    }
+}
+
+// Kept for the whole session, on linux the copied image is gone as soon as the clipboard is dropped
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Resource, Default, Clone)]
+struct ClipboardHandle(Arc<Mutex<Option<arboard::Clipboard>>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_to_clipboard(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    clipboard: Res<ClipboardHandle>,
+    measurement: Res<Measurement>,
+    windows: Query<(Entity, &Window), With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !ctrl || !shift || !bindings.just_pressed(&keys, Action::CopyImage) {
+        return;
+    }
+
+    let (window_entity, window) = windows.single();
+    let (camera, camera_transform) = cameras.single();
+    let bounds = Rect::new(280., 0., window.width(), window.height());
+
+    // The outer edges of the selected grid cells, wherever the camera shows them
+    let rect = match measurement.selection() {
+        Some((start, end)) => {
+            let min = grid_to_world(GridPosition {
+                x: start.x.min(end.x),
+                y: start.y.min(end.y),
+            }) - Vec2::splat(10.);
+            let max = grid_to_world(GridPosition {
+                x: start.x.max(end.x),
+                y: start.y.max(end.y),
+            }) + Vec2::splat(10.);
+            match (
+                camera.world_to_viewport(camera_transform, min.extend(0.)),
+                camera.world_to_viewport(camera_transform, max.extend(0.)),
+            ) {
+                (Some(min), Some(max)) => Rect::from_corners(min, max).intersect(bounds),
+                _ => return,
+            }
+        }
+        None => bounds,
+    };
+    if rect.width() < 2. || rect.height() < 2. {
+        warn!("The selection is not on screen, nothing to copy");
+        return;
+    }
+
+    let window_scale = window.scale_factor() as f32;
+    let x = (rect.min.x * window_scale) as u32;
+    let y = (rect.min.y * window_scale) as u32;
+    let width = (rect.width() * window_scale) as u32;
+    let height = (rect.height() * window_scale) as u32;
+    let clipboard = clipboard.clone();
+
+    let result = screenshot_manager.take_screenshot(window_entity, move |screenshot| {
+        let image = match screenshot.try_into_dynamic() {
+            Ok(screenshot) => screenshot.crop_imm(x, y, width, height).to_rgba8(),
+            Err(e) => {
+                error!("Cannot convert screenshot: {e}");
+                return;
+            }
+        };
+
+        let Ok(mut clipboard) = clipboard.0.lock() else {
+            return;
+        };
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            None => match arboard::Clipboard::new() {
+                Ok(new_clipboard) => clipboard.insert(new_clipboard),
+                Err(e) => {
+                    error!("Cannot open the clipboard: {e}");
+                    return;
+                }
+            },
+        };
+
+        let result = clipboard.set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: std::borrow::Cow::Owned(image.into_raw()),
+        });
+        match result {
+            Ok(_) => info!("Copied {width}x{height} image to the clipboard"),
+            Err(e) => error!("Cannot copy image to the clipboard: {e}"),
+        }
+    });
+
+    if result.is_err() {
+        warn!("A screenshot is already being taken");
+    }
+}
+
+fn update_capture_overlay(
+    capture: Res<RegionCapture>,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut overlay: Query<&mut Visibility, With<CaptureOverlay>>,
+    mut selection: Query<
+        (&mut Style, &mut Visibility),
+        (With<CaptureSelection>, Without<CaptureOverlay>),
+    >,
+    mut hint: Query<&mut Text, With<CaptureHint>>,
+) {
+    for mut visibility in overlay.iter_mut() {
+        *visibility = if capture.active {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if !capture.active {
+        return;
+    }
+
+    for mut text in hint.iter_mut() {
+        text.sections[0].value = format!(
+            "Drag a rectangle to capture it at {}x (2 / 4 to change, Esc or right click to cancel)",
+            capture.scale
+        );
+    }
+
+    let window = windows.single();
+    let rect = match (capture.start, window.cursor_position()) {
+        (Some(start), Some(end)) if mouse_button.pressed(MouseButton::Left) => {
+            Some(selection_rect(start, end, window))
+        }
+        _ => None,
+    };
+
+    for (mut style, mut visibility) in selection.iter_mut() {
+        let Some(rect) = rect else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Inherited;
+        style.left = Val::Px(rect.min.x);
+        style.top = Val::Px(rect.min.y);
+        style.width = Val::Px(rect.width());
+        style.height = Val::Px(rect.height());
+    }
+}
+
+#[derive(Resource, Default)]
+struct GifRecording {
+    duration_index: usize,
+    active: Option<ActiveRecording>,
+}
+
+struct ActiveRecording {
+    // Real time, so pausing the simulation does not stop the recording
+    elapsed: f32,
+    requested_frames: usize,
+    // Screenshots arrive asynchronously, so every frame remembers its position
+    frames: Arc<Mutex<Vec<(usize, RgbaImage)>>>,
+}
+
+#[derive(Component)]
+struct RecordButton;
+
+#[derive(Component)]
+struct RecordDurationButton;
+
+fn setup_record_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "REC", "Record", RecordButton);
+        spawn_toolbar_button(
+            root,
+            &format!("{}s", RECORDING_DURATIONS[0]),
+            "Record Duration",
+            RecordDurationButton,
+        );
+    });
+}
+
+fn handle_record_buttons(
+    mut recording: ResMut<GifRecording>,
+    record_button: Query<&Interaction, (Changed<Interaction>, With<RecordButton>)>,
+    duration_button: Query<&Interaction, (Changed<Interaction>, With<RecordDurationButton>)>,
+) {
+    if recording.active.is_some() {
+        return;
+    }
+
+    if duration_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        recording.duration_index = (recording.duration_index + 1) % RECORDING_DURATIONS.len();
+    }
+
+    if record_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        recording.active = Some(ActiveRecording {
+            elapsed: 0.,
+            requested_frames: 0,
+            frames: Arc::default(),
+        });
+    }
+}
+
+fn record_frames(
+    mut recording: ResMut<GifRecording>,
+    time: Res<Time<Real>>,
+    windows: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    let duration = RECORDING_DURATIONS[recording.duration_index];
+    let Some(active) = recording.active.as_mut() else {
+        return;
+    };
+
+    if active.elapsed >= duration {
+        // Wait until every requested screenshot has arrived before writing the file
+        let frames = {
+            let mut frames = active.frames.lock().unwrap();
+            if frames.len() < active.requested_frames {
+                return;
+            }
+            std::mem::take(&mut *frames)
+        };
+        recording.active = None;
+        write_gif(frames, format!("recording_{}.gif", timestamp()));
+        return;
+    }
+
+    active.elapsed += time.delta_seconds();
+
+    let due_frames = (active.elapsed * RECORDING_FPS as f32) as usize + 1;
+    if active.requested_frames >= due_frames {
+        return;
+    }
+
+    // Only the schematic, the ui section is cut off
+    let (window_entity, window) = windows.single();
+    let x = (280. * window.scale_factor()) as u32;
+    let width = window.physical_width().saturating_sub(x);
+    let height = window.physical_height();
+
+    let index = active.requested_frames;
+    let frames = active.frames.clone();
+    let result = screenshot_manager.take_screenshot(window_entity, move |screenshot| {
+        match screenshot.try_into_dynamic() {
+            Ok(screenshot) => frames
+                .lock()
+                .unwrap()
+                .push((index, screenshot.crop_imm(x, 0, width, height).to_rgba8())),
+            Err(e) => {
+                error!("Cannot convert screenshot: {e}");
+                // Still counts as arrived, so the recording does not wait for it forever
+                frames
+                    .lock()
+                    .unwrap()
+                    .push((index, RgbaImage::new(width, height)));
+            }
+        }
+    });
+
+    if result.is_ok() {
+        active.requested_frames += 1;
+    }
+}
+
+fn write_gif(mut frames: Vec<(usize, RgbaImage)>, path: String) {
+    frames.sort_by_key(|(index, _)| *index);
+
+    AsyncComputeTaskPool::get()
+        .spawn(async move {
+            let mut gif = Vec::new();
+            let mut encoder = GifEncoder::new_with_speed(&mut gif, 10);
+            if let Err(e) = encoder.set_repeat(Repeat::Infinite) {
+                error!("Cannot write {path}: {e}");
+                return;
+            }
+
+            let delay = Delay::from_numer_denom_ms(1000, RECORDING_FPS);
+            let result = encoder
+                .encode_frames(
+                    frames
+                        .into_iter()
+                        .map(|(_, frame)| Frame::from_parts(frame, 0, 0, delay)),
+                )
+                .map_err(|e| e.to_string());
+            drop(encoder);
+            match result.and_then(|_| platform::export_file(Path::new(&path), &gif)) {
+                Ok(_) => info!("Recording saved to {path}"),
+                Err(e) => error!("Cannot write {path}: {e}"),
+            }
+        })
+        .detach();
+}
+
+fn update_record_buttons(
+    recording: Res<GifRecording>,
+    mut record_button: Query<(&Children, &mut BackgroundColor), With<RecordButton>>,
+    duration_button: Query<&Children, With<RecordDurationButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !recording.is_changed() {
+        return;
+    }
+
+    let duration = RECORDING_DURATIONS[recording.duration_index];
+    for (children, mut background_color) in record_button.iter_mut() {
+        let (label, color) = match &recording.active {
+            Some(active) if active.elapsed < duration => (
+                format!("REC {:.1}s", duration - active.elapsed),
+                Color::rgb(0.7, 0.1, 0.1),
+            ),
+            Some(_) => ("Saving".to_string(), Color::rgb(0.7, 0.1, 0.1)),
+            None => ("REC".to_string(), Color::rgb(0.25, 0.25, 0.25)),
+        };
+        background_color.0 = color;
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = label;
+        }
+    }
+
+    for children in duration_button.iter() {
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = format!("{duration}s");
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    blocks::Block,
+    diode::Diode,
+    fuse::Fuse,
+    headless::Simulation,
+    platform,
+    save::CircuitAccess,
+    scenario::{evaluate_scenario, Scenario},
+    spawn_toolbar_button,
+    time_switch::TimeSwitch,
+    ButtonSwitch, Light, RelayCoil, RelaySwitch, Toolbar,
+};
+
+const CHALLENGE_PATH: &str = "challenge.ron";
+const SOLVED_COLOR: Color = Color::rgb(0.4, 0.9, 0.4);
+const FAILED_COLOR: Color = Color::rgb(0.9, 0.4, 0.4);
+
+// The challenge button starts the task in challenge.ron from the working directory, for example
+// (task: "-P1 must light only while both -S1 and -S2 are pressed", lights: [1], buttons: [1, 2], tests: [(1, Press(1)), (2, ExpectOff(1)), ...])
+// Only the listed lights, buttons and relays can be placed, fuses, diodes, time switches and blocks not at all
+// Check runs the tests, written like a scenario, on a copy of the circuit and only tells how many of them failed
+pub struct ChallengePlugin;
+
+impl Plugin for ChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveChallenge>()
+            .add_systems(Startup, setup_challenge_panel)
+            .add_systems(PostStartup, setup_challenge_button)
+            .add_systems(
+                Update,
+                (
+                    toggle_challenge,
+                    enforce_available_components,
+                    check_solution,
+                    update_challenge_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    task: String,
+    #[serde(default)]
+    lights: Vec<usize>,
+    #[serde(default)]
+    buttons: Vec<usize>,
+    #[serde(default)]
+    relays: Vec<usize>,
+    tests: Scenario,
+}
+
+#[derive(Resource, Default)]
+struct ActiveChallenge {
+    challenge: Option<Challenge>,
+    // What the last check found, None until the first one
+    result: Option<(bool, String)>,
+}
+
+#[derive(Component)]
+struct ChallengeButton;
+
+#[derive(Component)]
+struct CheckButton;
+
+#[derive(Component)]
+struct ChallengePanel;
+
+#[derive(Component)]
+struct ChallengeText;
+
+fn setup_challenge_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Challenge", "Challenge", ChallengeButton);
+    });
+}
+
+fn setup_challenge_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                left: Val::Px(290.),
+                max_width: Val::Px(450.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Challenge Panel"),
+        ChallengePanel,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_sections([
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: SOLVED_COLOR,
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            Name::new("Challenge Text"),
+            ChallengeText,
+        ));
+        spawn_toolbar_button(root, "Check", "Check Challenge", CheckButton);
+    });
+}
+
+fn load_challenge() -> Result<Challenge, String> {
+    if !platform::file_exists(Path::new(CHALLENGE_PATH)) {
+        return Err(format!(
+            "there is no {CHALLENGE_PATH} in the working directory"
+        ));
+    }
+    platform::read_file(Path::new(CHALLENGE_PATH))
+        .and_then(|text| ron::from_str::<Challenge>(&text).map_err(|e| e.to_string()))
+}
+
+// Pressing the button again ends the challenge and unlocks every component
+fn toggle_challenge(
+    challenge_button: Query<&Interaction, (Changed<Interaction>, With<ChallengeButton>)>,
+    mut active: ResMut<ActiveChallenge>,
+) {
+    if !challenge_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if active.challenge.is_some() {
+        *active = ActiveChallenge::default();
+        return;
+    }
+    match load_challenge() {
+        Ok(challenge) => {
+            info!("Started the challenge: {}", challenge.task);
+            *active = ActiveChallenge {
+                challenge: Some(challenge),
+                result: None,
+            };
+        }
+        Err(e) => error!("Cannot start a challenge from {CHALLENGE_PATH}: {e}"),
+    }
+}
+
+// Whatever is placed or loaded that the challenge does not offer is taken away again right away
+fn enforce_available_components(
+    mut cmd: Commands,
+    active: Res<ActiveChallenge>,
+    lights: Query<(Entity, &Light), Added<Light>>,
+    buttons: Query<(Entity, &ButtonSwitch), Added<ButtonSwitch>>,
+    relay_coils: Query<(Entity, &RelayCoil), Added<RelayCoil>>,
+    relay_switches: Query<(Entity, &RelaySwitch), Added<RelaySwitch>>,
+    locked: Query<Entity, Or<(Added<Fuse>, Added<Diode>, Added<TimeSwitch>, Added<Block>)>>,
+) {
+    let Some(challenge) = &active.challenge else {
+        return;
+    };
+
+    let unavailable = lights
+        .iter()
+        .filter(|(_, light)| !challenge.lights.contains(&light.id))
+        .map(|(e, light)| (e, format!("-P{}", light.id)))
+        .chain(
+            buttons
+                .iter()
+                .filter(|(_, button)| !challenge.buttons.contains(&button.id))
+                .map(|(e, button)| (e, format!("-S{}", button.id))),
+        )
+        .chain(
+            relay_coils
+                .iter()
+                .filter(|(_, relay_coil)| !challenge.relays.contains(&relay_coil.id))
+                .map(|(e, relay_coil)| (e, format!("-K{}", relay_coil.id))),
+        )
+        .chain(
+            relay_switches
+                .iter()
+                .filter(|(_, relay_switch)| !challenge.relays.contains(&relay_switch.id))
+                .map(|(e, relay_switch)| (e, format!("-K{}", relay_switch.id))),
+        )
+        .chain(
+            locked
+                .iter()
+                .map(|e| (e, "A fuse, diode, time switch or block".to_string())),
+        );
+    for (e, name) in unavailable {
+        warn!("{name} is not available in this challenge");
+        cmd.entity(e).despawn_recursive();
+    }
+}
+
+fn check_solution(
+    check_button: Query<&Interaction, (Changed<Interaction>, With<CheckButton>)>,
+    mut active: ResMut<ActiveChallenge>,
+    circuit: CircuitAccess,
+) {
+    if !check_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let Some(challenge) = &active.challenge else {
+        return;
+    };
+
+    // A copy of the circuit runs on its own, like without a window, so the running simulation is left alone
+    let mut simulation = Simulation::new(circuit.collect(), &[]);
+    let outcomes = evaluate_scenario(&mut simulation, &challenge.tests);
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed).count();
+    active.result = Some(if failed == 0 {
+        (true, "Solved, every test passed".to_string())
+    } else {
+        (
+            false,
+            format!("{failed} of {} tests failed", outcomes.len()),
+        )
+    });
+}
+
+fn update_challenge_panel(
+    active: Res<ActiveChallenge>,
+    mut panel: Query<&mut Visibility, With<ChallengePanel>>,
+    mut texts: Query<&mut Text, With<ChallengeText>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if active.challenge.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let Some(challenge) = &active.challenge else {
+        return;
+    };
+    let available = challenge
+        .lights
+        .iter()
+        .map(|id| format!("-P{id}"))
+        .chain(challenge.buttons.iter().map(|id| format!("-S{id}")))
+        .chain(challenge.relays.iter().map(|id| format!("-K{id}")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!("{}\nAvailable: {available}", challenge.task);
+        match &active.result {
+            Some((solved, result)) => {
+                text.sections[1].value = format!("\n{result}");
+                text.sections[1].style.color = if *solved { SOLVED_COLOR } else { FAILED_COLOR };
+            }
+            None => text.sections[1].value.clear(),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, is_running, simulate,
+    spawn_toolbar_button, CircuitHandles, ComponentOrientation, CurrentlyPlacing, GridPosition,
+    IsRunning, MainCamera, SimulationScratch, Solver, Toolbar, Visited,
+};
+
+const CLOCK_COLOR: Color = Color::rgb(0.1, 0.2, 0.3);
+
+// Clocks on the grid count the simulated seconds, while their two terminals are powered they are held at zero
+// The readout in the top right corner counts the simulated seconds since run was pressed, time spent paused does not count
+// In clock mode (clock button) every click places a clock, right clicking a clock removes it again
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulatedTime>()
+            .add_systems(Startup, setup_time_readout)
+            .add_systems(PostStartup, setup_clock_button)
+            .add_systems(
+                FixedUpdate,
+                advance_clocks.after(simulate).run_if(is_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    restart_clocks,
+                    start_clock_placement,
+                    handle_clock_placement,
+                    add_clock_visuals,
+                    update_clock_texts,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SimulatedTime {
+    pub seconds: f32,
+}
+
+// Spans three grid points like a relay coil, the outer two are the reset input
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct SimulationClock {
+    pub top: GridPosition,
+    pub bottom: GridPosition,
+    // The time is part of the simulation state, not of the circuit
+    #[serde(skip)]
+    pub seconds: f32,
+}
+
+#[derive(Component)]
+struct ClockButton;
+
+#[derive(Component)]
+struct ClockText;
+
+#[derive(Component)]
+struct TimeReadout;
+
+pub fn spawn_clock(cmd: &mut Commands, clock: SimulationClock) -> Entity {
+    cmd.spawn((SpatialBundle::default(), Name::new("Clock"), clock))
+        .id()
+}
+
+fn setup_clock_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Clock", "Clock", ClockButton);
+    });
+}
+
+fn setup_time_readout(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                right: Val::Px(10.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Name::new("Simulated Time Readout"),
+        TimeReadout,
+    ));
+}
+
+// Pressing run starts every clock at zero again
+fn restart_clocks(
+    running: Res<IsRunning>,
+    mut simulated_time: ResMut<SimulatedTime>,
+    mut clocks: Query<&mut SimulationClock>,
+) {
+    if !running.is_changed() || !running.0 {
+        return;
+    }
+
+    simulated_time.seconds = 0.;
+    for mut clock in clocks.iter_mut() {
+        clock.seconds = 0.;
+    }
+}
+
+fn start_clock_placement(
+    clock_button: Query<&Interaction, (Changed<Interaction>, With<ClockButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if clock_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Clock;
+    }
+}
+
+fn handle_clock_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    clocks: Query<(Entity, &SimulationClock)>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Clock) {
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        let clicked = mouse_grid.and_then(|pos| {
+            clocks.iter().find(|(_, clock)| {
+                pos.x == clock.top.x && (clock.bottom.y..=clock.top.y).contains(&pos.y)
+            })
+        });
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(mouse_grid) = mouse_grid else {
+        return;
+    };
+    // Always upright, the same way as a component that was not turned
+    let Some((top, bottom)) = ComponentOrientation::Vertical.terminals(mouse_grid, &grid_size)
+    else {
+        warn!("A clock does not fit at the edge of the grid");
+        return;
+    };
+
+    spawn_clock(
+        &mut cmd,
+        SimulationClock {
+            top,
+            bottom,
+            seconds: 0.,
+        },
+    );
+}
+
+// Made from the clock itself, so loading only has to spawn the clock
+fn add_clock_visuals(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    clocks: Query<(Entity, &SimulationClock), Added<SimulationClock>>,
+) {
+    for (e, clock) in clocks.iter() {
+        let middle = (grid_to_world(clock.top) + grid_to_world(clock.bottom)) / 2.;
+
+        cmd.entity(e).with_children(|root| {
+            for (terminal, name) in [(clock.top, "Clock Point1"), (clock.bottom, "Clock Point2")] {
+                root.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: circuit_material.wire_point_mesh.clone(),
+                        material: circuit_material.wire_material.clone(),
+                        transform: Transform::from_translation(grid_to_world(terminal).extend(2.5)),
+                        ..Default::default()
+                    },
+                    Name::new(name),
+                ));
+            }
+            root.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: CLOCK_COLOR,
+                        custom_size: Some(Vec2::new(44., 24.)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(middle.extend(2.)),
+                    ..Default::default()
+                },
+                Name::new("Clock Face"),
+            ));
+            root.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::WHITE,
+                            ..Default::default()
+                        },
+                    ),
+                    transform: Transform::from_translation(middle.extend(5.)),
+                    ..Default::default()
+                },
+                Name::new("Clock Text"),
+                ClockText,
+            ));
+        });
+    }
+}
+
+fn is_powered(solver: &Solver, clock: &SimulationClock) -> bool {
+    matches!(
+        (solver.mark(clock.top), solver.mark(clock.bottom)),
+        (Visited::Positive, Visited::Negative) | (Visited::Negative, Visited::Positive)
+    )
+}
+
+// Runs on every simulation tick, so pausing stops the clocks as well
+fn advance_clocks(
+    time: Res<Time>,
+    scratch: Res<SimulationScratch>,
+    mut simulated_time: ResMut<SimulatedTime>,
+    mut clocks: Query<&mut SimulationClock>,
+) {
+    simulated_time.seconds += time.delta_seconds();
+
+    for mut clock in clocks.iter_mut() {
+        if is_powered(&scratch.solver, &clock) {
+            clock.seconds = 0.;
+        } else {
+            clock.seconds += time.delta_seconds();
+        }
+    }
+}
+
+fn update_clock_texts(
+    simulated_time: Res<SimulatedTime>,
+    clocks: Query<(&SimulationClock, &Children)>,
+    mut texts: Query<&mut Text, With<ClockText>>,
+    mut readout: Query<&mut Text, (With<TimeReadout>, Without<ClockText>)>,
+) {
+    for (clock, children) in clocks.iter() {
+        let value = format!("{:.1} s", clock.seconds);
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            if text.sections[0].value != value {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+
+    let value = format!("Simulated time: {:.1} s", simulated_time.seconds);
+    for mut text in readout.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use bevy::{input::InputSystem, prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_contains, component_terminals, convert_mouse_to_grid,
+    grid::GridSize,
+    hidden::HiddenRegion,
+    keybindings::{Action, KeyBindings},
+    ButtonSwitch, ComponentComment, CurrentlyPlacing, GridPosition, Light, MainCamera, RelayCoil,
+    RelaySwitch,
+};
+
+const MAX_COMMENT_LENGTH: usize = 200;
+
+// Pressing N while hovering a component opens an editor for its comment, hovering a commented component shows the comment next to the cursor
+pub struct CommentPlugin;
+
+impl Plugin for CommentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommentEditor>()
+            .add_systems(Startup, setup_comment_texts)
+            // Like the metadata fields, typing has to see the keys before any shortcut does
+            .add_systems(PreUpdate, type_comment.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    start_comment_edit,
+                    update_comment_editor,
+                    show_comment_tooltip,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct CommentEditor {
+    // The component whose comment is being edited, None when the editor is closed
+    component: Option<Entity>,
+    text: String,
+}
+
+#[derive(Component)]
+struct CommentEditorText;
+
+#[derive(Component)]
+struct CommentTooltip;
+
+fn setup_comment_texts(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                max_width: Val::Px(600.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Comment Editor"),
+        CommentEditorText,
+    ));
+
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                max_width: Val::Px(300.),
+                padding: UiRect::all(Val::Px(4.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.1, 0.1, 0.2, 0.9)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(15),
+            ..Default::default()
+        },
+        Name::new("Comment Tooltip"),
+        CommentTooltip,
+    ));
+}
+
+fn hovered_component<'a>(
+    mouse_grid: GridPosition,
+    components: impl Iterator<
+        Item = (
+            Entity,
+            Option<(GridPosition, GridPosition)>,
+            Option<&'a ComponentComment>,
+        ),
+    >,
+) -> Option<(Entity, Option<&'a ComponentComment>)> {
+    components
+        .filter_map(|(e, terminals, comment)| terminals.map(|terminals| (e, terminals, comment)))
+        .find(|(_, (top, bottom), _)| component_contains(*top, *bottom, mouse_grid))
+        .map(|(e, _, comment)| (e, comment))
+}
+
+fn start_comment_edit(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    currently_placing: Res<CurrentlyPlacing>,
+    components: Query<(
+        Entity,
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&ComponentComment>,
+    )>,
+    mut editor: ResMut<CommentEditor>,
+) {
+    if editor.component.is_some()
+        || !bindings.just_pressed(&keys, Action::ComponentComment)
+        || !matches!(*currently_placing, CurrentlyPlacing::Wire)
+    {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    if let Some((e, comment)) = hovered_component(
+        mouse_grid,
+        components
+            .iter()
+            .map(|(e, component, comment)| (e, component_terminals(component), comment)),
+    ) {
+        editor.component = Some(e);
+        editor.text = comment.map(|comment| comment.0.clone()).unwrap_or_default();
+    }
+}
+
+fn type_comment(
+    mut cmd: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut editor: ResMut<CommentEditor>,
+) {
+    let Some(component) = editor.component else {
+        characters.clear();
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.component = None;
+    } else if keys.just_pressed(KeyCode::Return) {
+        if let Some(mut entity) = cmd.get_entity(component) {
+            if editor.text.is_empty() {
+                entity.remove::<ComponentComment>();
+            } else {
+                entity.insert(ComponentComment(editor.text.clone()));
+            }
+        }
+        editor.component = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            editor.text.pop();
+        }
+
+        for c in characters.read().map(|event| event.char) {
+            if editor.text.chars().count() < MAX_COMMENT_LENGTH && !c.is_control() {
+                editor.text.push(c);
+            }
+        }
+    }
+
+    characters.clear();
+    keys.reset_all();
+}
+
+fn update_comment_editor(
+    editor: Res<CommentEditor>,
+    mut editor_text: Query<(&mut Text, &mut Visibility), With<CommentEditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in editor_text.iter_mut() {
+        if editor.component.is_none() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        *visibility = Visibility::Inherited;
+        text.sections[0].value = format!(
+            "Comment: {}_   (Enter to confirm, Esc to cancel)",
+            editor.text
+        );
+    }
+}
+
+fn show_comment_tooltip(
+    editor: Res<CommentEditor>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    components: Query<(
+        Entity,
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        &ComponentComment,
+    )>,
+    hidden_regions: Query<&HiddenRegion>,
+    mut tooltip: Query<(&mut Text, &mut Style, &mut Visibility), With<CommentTooltip>>,
+) {
+    let (mut text, mut style, mut visibility) = tooltip.single_mut();
+
+    let cursor = windows.single().cursor_position();
+    let hovered = cursor
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        // A comment would give away what is inside a black box
+        .filter(|mouse_grid| {
+            !hidden_regions
+                .iter()
+                .any(|region| region.hides(*mouse_grid))
+        })
+        .and_then(|mouse_grid| {
+            hovered_component(
+                mouse_grid,
+                components.iter().map(|(e, component, comment)| {
+                    (e, component_terminals(component), Some(comment))
+                }),
+            )
+        })
+        .and_then(|(_, comment)| comment);
+
+    let (Some(cursor), Some(comment), None) = (cursor, hovered, editor.component) else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    *visibility = Visibility::Inherited;
+    let (left, top) = (Val::Px(cursor.x + 16.), Val::Px(cursor.y + 16.));
+    if style.left != left || style.top != top {
+        style.left = left;
+        style.top = top;
+    }
+    if text.sections[0].value != comment.0 {
+        text.sections[0].value = comment.0.clone();
+    }
+}
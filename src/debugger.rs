@@ -0,0 +1,193 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_contains, convert_mouse_to_grid,
+    grid::GridSize,
+    hidden::HiddenRegion,
+    history::SimulationHistory,
+    keybindings::{Action, KeyBindings},
+    wire_contains, Faulty, GridPosition, IsRunning, MainCamera, RelayCoil, RelaySwitch,
+    SimulationScratch, Solver, UILight, Visited, Wire,
+};
+
+// While paused (space) the step key (F10) runs exactly one tick, the history jumps to it
+// Hovering a wire then shows which side it is on and hovering a coil whether it gets power in the next tick
+pub struct DebuggerPlugin;
+
+impl Plugin for DebuggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_inspection_tooltip)
+            .add_systems(Update, (step_tick, show_inspection).chain());
+    }
+}
+
+#[derive(Component)]
+struct InspectionTooltip;
+
+fn setup_inspection_tooltip(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(4.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.2, 0.1, 0.1, 0.9)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(15),
+            ..Default::default()
+        },
+        Name::new("Inspection Tooltip"),
+        InspectionTooltip,
+    ));
+}
+
+// Runs the fixed update schedule once by hand, like the fuzzer does
+fn step_tick(world: &mut World) {
+    if !world
+        .resource::<KeyBindings>()
+        .just_pressed(world.resource::<Input<KeyCode>>(), Action::StepTick)
+    {
+        return;
+    }
+    if !world.resource::<Time<Virtual>>().is_paused() || !world.resource::<IsRunning>().0 {
+        return;
+    }
+
+    // Scrubbing back shows an older state in the world, the step continues from the newest one
+    let newest = world
+        .resource::<SimulationHistory>()
+        .snapshots
+        .back()
+        .map(|snapshot| {
+            (
+                snapshot.activated_relay_ids.clone(),
+                snapshot.lit_light_ids.clone(),
+            )
+        });
+    if let Some((activated_relay_ids, lit_light_ids)) = newest {
+        for mut relay_coil in world.query::<&mut RelayCoil>().iter_mut(world) {
+            relay_coil.activated = activated_relay_ids.contains(&relay_coil.id);
+        }
+        for mut ui_light in world.query::<&mut UILight>().iter_mut(world) {
+            ui_light.is_lit = lit_light_ids.contains(&ui_light.id);
+        }
+    }
+
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    world.run_schedule(FixedUpdate);
+
+    let mut history = world.resource_mut::<SimulationHistory>();
+    history.cursor = history.snapshots.len().checked_sub(1);
+}
+
+fn describe_side(solver: &Solver, pos: GridPosition) -> &'static str {
+    if solver.is_shorted(pos) {
+        return "shorted";
+    }
+    match solver.mark(pos) {
+        Visited::Positive => "+ side",
+        Visited::Negative => "- side",
+        Visited::Unvisited => "no potential",
+    }
+}
+
+fn show_inspection(
+    time: Res<Time<Virtual>>,
+    running: Res<IsRunning>,
+    history: Res<SimulationHistory>,
+    scratch: Res<SimulationScratch>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    hidden_regions: Query<&HiddenRegion>,
+    wires: Query<&Wire>,
+    relay_coils: Query<(&RelayCoil, Has<Faulty>)>,
+    relay_switches: Query<&RelaySwitch, Without<Faulty>>,
+    mut preview: Local<Solver>,
+    mut tooltip: Query<(&mut Text, &mut Style, &mut Visibility), With<InspectionTooltip>>,
+) {
+    let (mut text, mut style, mut visibility) = tooltip.single_mut();
+
+    // The computed state belongs to the newest tick, older ones from the history have nothing to show
+    let at_newest =
+        history.cursor.is_none() || history.cursor == history.snapshots.len().checked_sub(1);
+    let cursor = windows.single().cursor_position();
+    let mouse_grid = cursor
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .filter(|mouse_grid| {
+            !hidden_regions
+                .iter()
+                .any(|region| region.hides(*mouse_grid))
+        })
+        .filter(|_| time.is_paused() && running.0 && at_newest);
+
+    let solver = &scratch.solver;
+    let value = mouse_grid.and_then(|mouse_grid| {
+        if let Some((relay_coil, faulty)) = relay_coils.iter().find(|(relay_coil, _)| {
+            component_contains(relay_coil.top, relay_coil.bottom, mouse_grid)
+        }) {
+            if faulty {
+                return Some(format!("-K{}: burned, never energized", relay_coil.id));
+            }
+
+            // The next tick sees the contacts the way the coils are now, so the last circuit is solved again with them
+            let mut circuit = scratch.circuit.clone();
+            for switch in circuit.switches.iter_mut() {
+                let relay_switch = relay_switches.iter().find(|relay_switch| {
+                    switch.top == relay_switch.top.into()
+                        && switch.bottom == relay_switch.bottom.into()
+                });
+                if let Some(relay_switch) = relay_switch {
+                    switch.actuated = relay_coils.iter().any(|(relay_coil, faulty)| {
+                        !faulty && relay_coil.id == relay_switch.id && relay_coil.activated
+                    });
+                }
+            }
+            preview.step(&circuit);
+
+            let consumer = circuit.consumers.iter().position(|(top, bottom)| {
+                *top == relay_coil.top.into() && *bottom == relay_coil.bottom.into()
+            });
+            let next = if preview.short_circuit() {
+                "short circuit in the next tick"
+            } else if consumer.is_some_and(|consumer| preview.is_energized(consumer)) {
+                "energized in the next tick"
+            } else {
+                "not energized in the next tick"
+            };
+            return Some(format!("-K{}: {next}", relay_coil.id));
+        }
+
+        wires
+            .iter()
+            .find(|wire| wire_contains(wire, &mouse_grid))
+            .map(|wire| format!("Wire: {}", describe_side(solver, wire.first)))
+    });
+
+    let (Some(cursor), Some(value)) = (cursor, value) else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    *visibility = Visibility::Inherited;
+    // Above the cursor, comments show up below it
+    let (left, top) = (Val::Px(cursor.x + 16.), Val::Px(cursor.y - 30.));
+    if style.left != left || style.top != top {
+        style.left = left;
+        style.top = top;
+    }
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_contains, component_middle, component_terminals, convert_mouse_to_grid,
+    grid::GridSize,
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    oriented, spawn_toolbar_button, wire_contains, ButtonSwitch, CurrentlyPlacing, Light,
+    MainCamera, RelayCoil, RelaySwitch, Toolbar, Wire,
+};
+
+const DELETE_COLOR: Color = Color::rgb(1., 0.2, 0.2);
+
+// In delete mode (delete button or key) only the one element under the cursor is outlined and removed on a left click
+// Components win over the wires that end on them, of several wires the shortest one is taken
+pub struct DeletePlugin;
+
+impl Plugin for DeletePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_delete_button)
+            .add_systems(Update, (start_delete_mode, delete_hovered).chain());
+    }
+}
+
+#[derive(Component)]
+struct DeleteButton;
+
+fn setup_delete_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Delete", "Delete", DeleteButton);
+    });
+}
+
+fn start_delete_mode(
+    delete_button: Query<&Interaction, (Changed<Interaction>, With<DeleteButton>)>,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if delete_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+        || bindings.just_pressed(&keys, Action::Delete)
+    {
+        *currently_placing = CurrentlyPlacing::Delete;
+    }
+}
+
+fn wire_length(wire: &Wire) -> usize {
+    wire.first.x.abs_diff(wire.second.x) + wire.first.y.abs_diff(wire.second.y)
+}
+
+fn delete_hovered(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    components: Query<(
+        Entity,
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+    )>,
+    wires: Query<(Entity, &Wire)>,
+    mut gizmos: Gizmos,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Delete) {
+        return;
+    }
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    let component = components.iter().find_map(|(e, component)| {
+        component_terminals(component)
+            .filter(|(top, bottom)| component_contains(*top, *bottom, mouse_grid))
+            .map(|terminals| (e, terminals))
+    });
+    let hovered = match component {
+        Some((e, (top, bottom))) => {
+            gizmos.rect_2d(
+                grid_to_world(component_middle(top, bottom)),
+                0.,
+                oriented(top, bottom, Vec2::new(28., 64.)),
+                DELETE_COLOR,
+            );
+            e
+        }
+        None => {
+            let Some((e, wire)) = wires
+                .iter()
+                .filter(|(_, wire)| wire_contains(wire, &mouse_grid))
+                .min_by_key(|(_, wire)| wire_length(wire))
+            else {
+                return;
+            };
+            let (first, second) = (grid_to_world(wire.first), grid_to_world(wire.second));
+            gizmos.line_2d(first, second, DELETE_COLOR);
+            gizmos.circle_2d(first, 6., DELETE_COLOR);
+            gizmos.circle_2d(second, 6., DELETE_COLOR);
+            e
+        }
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        cmd.entity(hovered).despawn_recursive();
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use serde::Deserialize;
+
+use crate::{dock::DOCK_HEIGHT, platform};
+
+const DEVICE_COUNTS_PATH: &str = "devices.ron";
+// More would only make the left section very long and the ids hard to tell apart
+const MAX_COUNT: usize = 99;
+// Pixels the left section moves per line of a mouse wheel notch
+const SCROLL_LINE_HEIGHT: f32 = 20.;
+
+// How many lights, buttons and relays the left section offers, read from devices.ron in the working directory, for example
+// (lights: 10, buttons: 8, relays: 12)
+// Missing counts stay at six, the dock shows the rows and when the toolbar below it does not fit the mouse wheel scrolls it
+pub struct DeviceCountsPlugin;
+
+impl Plugin for DeviceCountsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_device_counts())
+            .add_systems(Update, scroll_left_section);
+    }
+}
+
+#[derive(Resource, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct DeviceCounts {
+    pub lights: usize,
+    pub buttons: usize,
+    pub relays: usize,
+}
+
+impl Default for DeviceCounts {
+    fn default() -> Self {
+        Self {
+            lights: 6,
+            buttons: 6,
+            relays: 6,
+        }
+    }
+}
+
+// Holds the toolbar below the dock, moved up by how far it is scrolled
+#[derive(Component, Default)]
+pub struct LeftSectionContent {
+    pub scrolled: f32,
+}
+
+// Also used by the command line import, which runs without the app
+pub fn load_device_counts() -> DeviceCounts {
+    if !platform::file_exists(Path::new(DEVICE_COUNTS_PATH)) {
+        return DeviceCounts::default();
+    }
+
+    match platform::read_file(Path::new(DEVICE_COUNTS_PATH))
+        .and_then(|text| ron::from_str::<DeviceCounts>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(counts) => DeviceCounts {
+            lights: counts.lights.clamp(1, MAX_COUNT),
+            buttons: counts.buttons.clamp(1, MAX_COUNT),
+            relays: counts.relays.clamp(1, MAX_COUNT),
+        },
+        Err(e) => {
+            warn!("Cannot read device counts from {DEVICE_COUNTS_PATH}, using the defaults: {e}");
+            DeviceCounts::default()
+        }
+    }
+}
+
+fn scroll_left_section(
+    mut wheel_events: EventReader<MouseWheel>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut contents: Query<(&mut LeftSectionContent, &mut Style, &Node, &Parent)>,
+    nodes: Query<&Node>,
+) {
+    // The schematic zooms with the wheel everywhere else
+    if !windows
+        .single()
+        .cursor_position()
+        .is_some_and(|cursor| cursor.x < 280.)
+    {
+        wheel_events.clear();
+        return;
+    }
+
+    let delta = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum::<f32>();
+    if delta == 0. {
+        return;
+    }
+
+    for (mut content, mut style, node, parent) in contents.iter_mut() {
+        let Ok(section) = nodes.get(parent.get()) else {
+            continue;
+        };
+        // It goes up under the dock, which draws over it
+        let max_scrolled = (DOCK_HEIGHT + node.size().y - section.size().y).max(0.);
+        content.scrolled = (content.scrolled - delta).clamp(0., max_scrolled);
+        style.top = Val::Px(DOCK_HEIGHT - content.scrolled);
+    }
+}
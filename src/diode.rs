@@ -0,0 +1,178 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    component_contains, convert_mouse_to_grid, grid::GridSize, grid_to_world, spawn_toolbar_button,
+    CircuitHandles, ComponentOrientation, CurrentlyPlacing, GridPosition, MainCamera, Toolbar,
+};
+
+const DIODE_COLOR: Color = Color::rgb(0.85, 0.85, 0.85);
+
+// Diodes only conduct from their anode to their cathode, for steering and freewheel diodes
+// They are placed with the anode at the top terminal, turned with the rotate key, clicking a placed diode turns it around
+// Right clicking a diode while placing them removes it again
+pub struct DiodePlugin;
+
+impl Plugin for DiodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_diode_button)
+            .add_systems(
+                Update,
+                (
+                    start_placing_diodes,
+                    handle_diode_placement,
+                    add_diode_visuals,
+                    draw_diodes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Diode {
+    pub anode: GridPosition,
+    pub cathode: GridPosition,
+}
+
+#[derive(Component)]
+struct DiodeButton;
+
+pub fn spawn_diode(cmd: &mut Commands, diode: Diode) -> Entity {
+    cmd.spawn((SpatialBundle::default(), Name::new("Diode"), diode))
+        .id()
+}
+
+fn setup_diode_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Diode", "Diode", DiodeButton);
+    });
+}
+
+fn start_placing_diodes(
+    diode_button: Query<&Interaction, (Changed<Interaction>, With<DiodeButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if diode_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Diode;
+    }
+}
+
+fn handle_diode_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    orientation: Res<ComponentOrientation>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut diodes: Query<(Entity, &mut Diode)>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Diode) {
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+    let clicked = diodes
+        .iter()
+        .find(|(_, diode)| component_contains(diode.anode, diode.cathode, mouse_grid))
+        .map(|(e, _)| e);
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        match clicked {
+            Some(e) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if let Some(e) = clicked {
+        if let Ok((_, mut diode)) = diodes.get_mut(e) {
+            let Diode { anode, cathode } = *diode;
+            *diode = Diode {
+                anode: cathode,
+                cathode: anode,
+            };
+        }
+        return;
+    }
+
+    let Some((top, bottom)) = orientation.terminals(mouse_grid, &grid_size) else {
+        warn!("A diode does not fit at the edge of the grid");
+        return;
+    };
+    spawn_diode(
+        &mut cmd,
+        Diode {
+            anode: top,
+            cathode: bottom,
+        },
+    );
+}
+
+// Made from the diode itself, so loading only has to spawn the diode
+fn add_diode_visuals(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    diodes: Query<(Entity, &Diode), Added<Diode>>,
+) {
+    for (e, diode) in diodes.iter() {
+        cmd.entity(e).with_children(|root| {
+            for (terminal, name) in [
+                (diode.anode, "Diode Anode"),
+                (diode.cathode, "Diode Cathode"),
+            ] {
+                root.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: circuit_material.wire_point_mesh.clone(),
+                        material: circuit_material.wire_material.clone(),
+                        transform: Transform::from_translation(grid_to_world(terminal).extend(2.5)),
+                        ..Default::default()
+                    },
+                    Name::new(name),
+                ));
+            }
+        });
+    }
+}
+
+// The symbol is drawn every frame, turning a diode around only swaps its terminals
+fn draw_diodes(diodes: Query<&Diode>, mut gizmos: Gizmos) {
+    for diode in diodes.iter() {
+        let anode = grid_to_world(diode.anode);
+        let cathode = grid_to_world(diode.cathode);
+        let along = (cathode - anode).normalize_or_zero();
+        let across = along.perp() * 10.;
+        let middle = (anode + cathode) / 2.;
+        let (base, tip) = (middle - along * 8., middle + along * 8.);
+
+        gizmos.line_2d(anode, base, DIODE_COLOR);
+        gizmos.line_2d(tip, cathode, DIODE_COLOR);
+        // Triangle pointing in the direction it conducts, with the bar at the cathode
+        gizmos.linestrip_2d(
+            [base + across, tip, base - across, base + across],
+            DIODE_COLOR,
+        );
+        gizmos.line_2d(tip + across, tip - across, DIODE_COLOR);
+    }
+}
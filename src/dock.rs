@@ -0,0 +1,590 @@
+use bevy::{
+    input::{mouse::MouseWheel, InputSystem},
+    prelude::*,
+    ui::UiSystem,
+    window::PrimaryWindow,
+};
+use bevy_inspector_egui::{
+    bevy_egui::{EguiContext, EguiPlugin, EguiSet},
+    bevy_inspector, egui, DefaultInspectorConfigPlugin,
+};
+
+use crate::{
+    analysis_window::timing_rows, history::SimulationHistory, timer_relay::TimerRelaySelect,
+    ButtonModeSelect, ButtonSelect, ButtonSwitch, Light, RelayCoil, RelayCoilSelect, RelaySwitch,
+    RelaySwitchSelect, SwitchType, UIButton, UILight, Wire,
+};
+
+// The top of the left section, the toolbar scrolls below it
+pub const DOCK_HEIGHT: f32 = 400.;
+const DOCK_WIDTH: f32 = 280.;
+const PALETTE_BUTTON_SIZE: (f32, f32) = (44., 26.);
+const TIMING_ROW_HEIGHT: f32 = 18.;
+const TIMING_LABEL_WIDTH: f32 = 40.;
+
+// The left section is an egui dock with the palette, an inspector for placed elements, the event log and the timing diagram as tabs
+// Every tab can be popped out into a window of its own, closing that window or its Dock button puts it back
+// The palette buttons of the bevy ui stay around hidden, they keep the state other plugins read and get pressed from here
+pub struct DockPlugin;
+
+impl Plugin for DockPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        if !app.is_plugin_added::<DefaultInspectorConfigPlugin>() {
+            app.add_plugins(DefaultInspectorConfigPlugin);
+        }
+        app.init_resource::<Dock>()
+            .init_resource::<PaletteHighlight>()
+            .add_systems(
+                PreUpdate,
+                keep_input_from_egui
+                    .after(InputSystem)
+                    .after(EguiSet::BeginFrame)
+                    .before(UiSystem::Focus),
+            )
+            .add_systems(Update, show_dock);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DockTab {
+    Palette,
+    Inspector,
+    EventLog,
+    Timing,
+}
+
+impl DockTab {
+    fn title(self) -> &'static str {
+        match self {
+            DockTab::Palette => "Palette",
+            DockTab::Inspector => "Inspector",
+            DockTab::EventLog => "Events",
+            DockTab::Timing => "Timing",
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Dock {
+    // In the order of the tab bar, the popped out ones are not in here
+    docked: Vec<DockTab>,
+    active: DockTab,
+    floating: Vec<DockTab>,
+    // The placed element the inspector shows
+    inspected: Option<Entity>,
+}
+
+impl Default for Dock {
+    fn default() -> Self {
+        Self {
+            docked: vec![
+                DockTab::Palette,
+                DockTab::Inspector,
+                DockTab::EventLog,
+                DockTab::Timing,
+            ],
+            active: DockTab::Palette,
+            floating: Vec::new(),
+            inspected: None,
+        }
+    }
+}
+
+// Palette button that gets a frame, for the tutorial which cannot frame the hidden bevy ui one
+#[derive(Resource, Default)]
+pub struct PaletteHighlight(pub Option<Entity>);
+
+// Clicks, the mouse wheel and typing meant for egui should not reach the grid or the toolbar below a popped out tab
+fn keep_input_from_egui(
+    mut egui_context: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    mut wheel: ResMut<Events<MouseWheel>>,
+    mut keys: ResMut<Input<KeyCode>>,
+) {
+    let Ok(mut egui_context) = egui_context.get_single_mut() else {
+        return;
+    };
+    let ctx = egui_context.get_mut();
+    if ctx.wants_pointer_input() || ctx.is_pointer_over_area() {
+        mouse.reset_all();
+        wheel.clear();
+    }
+    if ctx.wants_keyboard_input() {
+        keys.reset_all();
+    }
+}
+
+fn show_dock(world: &mut World) {
+    let Ok(egui_context) = world
+        .query_filtered::<&EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+    let ctx = egui_context.get_mut();
+
+    world.resource_scope(|world, mut dock: Mut<Dock>| {
+        let mut palette = Palette::collect(world);
+
+        egui::Area::new("Dock")
+            .fixed_pos(egui::Pos2::ZERO)
+            .show(ctx, |ui| {
+                egui::Frame::side_top_panel(ui.style()).show(ui, |ui| {
+                    let margin = ui.style().spacing.window_margin;
+                    ui.set_width(DOCK_WIDTH - margin.left - margin.right);
+                    ui.set_height(DOCK_HEIGHT - margin.top - margin.bottom);
+
+                    let Dock { docked, active, .. } = &mut *dock;
+                    let mut pop_out = false;
+                    ui.horizontal(|ui| {
+                        for tab in docked.iter() {
+                            ui.selectable_value(active, *tab, tab.title());
+                        }
+                        if !docked.is_empty() {
+                            pop_out = ui
+                                .small_button("⏏")
+                                .on_hover_text("Pop out into a window")
+                                .clicked();
+                        }
+                    });
+                    ui.separator();
+
+                    if !dock.docked.contains(&dock.active) {
+                        match dock.docked.first() {
+                            Some(tab) => dock.active = *tab,
+                            None => {
+                                ui.label("Every tab is popped out");
+                                return;
+                            }
+                        }
+                    }
+                    let tab = dock.active;
+                    egui::ScrollArea::vertical()
+                        .id_source(tab.title())
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| show_tab(ui, tab, world, &mut dock, &mut palette));
+                    if pop_out {
+                        dock.docked.retain(|docked| *docked != tab);
+                        dock.floating.push(tab);
+                    }
+                });
+            });
+
+        for tab in dock.floating.clone() {
+            let mut open = true;
+            let mut dock_back = false;
+            egui::Window::new(tab.title())
+                .id(egui::Id::new(("Dock Window", tab.title())))
+                .open(&mut open)
+                .default_pos(egui::pos2(DOCK_WIDTH + 20., 40.))
+                .default_size(egui::vec2(DOCK_WIDTH, DOCK_HEIGHT))
+                .show(ctx, |ui| {
+                    dock_back = ui.small_button("Dock").clicked();
+                    egui::ScrollArea::vertical()
+                        .id_source(tab.title())
+                        .show(ui, |ui| show_tab(ui, tab, world, &mut dock, &mut palette));
+                });
+            if !open || dock_back {
+                dock.floating.retain(|floating| *floating != tab);
+                dock.docked.push(tab);
+                dock.active = tab;
+            }
+        }
+
+        palette.press(world);
+    });
+}
+
+fn show_tab(
+    ui: &mut egui::Ui,
+    tab: DockTab,
+    world: &mut World,
+    dock: &mut Dock,
+    palette: &mut Palette,
+) {
+    match tab {
+        DockTab::Palette => palette.show(ui, world.resource::<PaletteHighlight>().0),
+        DockTab::Inspector => show_inspector(ui, world, dock),
+        DockTab::EventLog => show_event_log(ui, world),
+        DockTab::Timing => show_timing_diagram(ui, world.resource::<SimulationHistory>()),
+    }
+}
+
+fn color32(color: Color) -> egui::Color32 {
+    let [r, g, b, a] = color.as_rgba_u8();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn switch_label(typ: SwitchType) -> &'static str {
+    match typ {
+        SwitchType::NormallyOpen => "NO",
+        SwitchType::NormallyClosed => "NC",
+        SwitchType::Changeover => "CO",
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PaletteSection {
+    Lamps,
+    Buttons,
+    Relays,
+}
+
+// One button of the palette, drawn in the colors of the hidden bevy ui button it stands for
+struct PaletteButton {
+    entity: Entity,
+    section: PaletteSection,
+    id: usize,
+    // Place in the row of its id
+    order: usize,
+    label: String,
+    fill: Color,
+    stroke: Color,
+    held: bool,
+}
+
+struct Palette(Vec<PaletteButton>);
+
+impl Palette {
+    fn collect(world: &mut World) -> Self {
+        let maintained = world
+            .query::<&UIButton>()
+            .iter(world)
+            .filter(|ui_button| ui_button.maintained)
+            .map(|ui_button| ui_button.id)
+            .collect::<Vec<_>>();
+
+        let mut buttons = Vec::new();
+        for (entity, background, border, kind) in world
+            .query::<(
+                Entity,
+                &BackgroundColor,
+                Option<&BorderColor>,
+                AnyOf<(
+                    &UILight,
+                    &UIButton,
+                    &ButtonSelect,
+                    &ButtonModeSelect,
+                    &RelayCoilSelect,
+                    &RelaySwitchSelect,
+                    &TimerRelaySelect,
+                )>,
+            )>()
+            .iter(world)
+        {
+            let (section, id, order, label) = match kind {
+                (Some(ui_light), ..) => (
+                    PaletteSection::Lamps,
+                    ui_light.id,
+                    0,
+                    format!("-P{}", ui_light.id),
+                ),
+                (_, Some(ui_button), ..) => (
+                    PaletteSection::Buttons,
+                    ui_button.id,
+                    0,
+                    format!("-S{}", ui_button.id),
+                ),
+                (_, _, Some(select), ..) => (
+                    PaletteSection::Buttons,
+                    select.id,
+                    1 + select.typ as usize,
+                    switch_label(select.typ).to_string(),
+                ),
+                (_, _, _, Some(select), ..) => {
+                    let mode = if maintained.contains(&select.id) {
+                        "Latch"
+                    } else {
+                        "Tap"
+                    };
+                    (PaletteSection::Buttons, select.id, 4, mode.to_string())
+                }
+                (.., Some(select), _, _) => (
+                    PaletteSection::Relays,
+                    select.id,
+                    0,
+                    format!("-K{}", select.id),
+                ),
+                (.., Some(select), _) => (
+                    PaletteSection::Relays,
+                    select.id,
+                    1 + select.typ as usize,
+                    switch_label(select.typ).to_string(),
+                ),
+                (.., Some(select)) => (PaletteSection::Relays, select.id, 4, "T".to_string()),
+                _ => continue,
+            };
+            buttons.push(PaletteButton {
+                entity,
+                section,
+                id,
+                order,
+                label,
+                fill: background.0,
+                stroke: border.map_or(Color::NONE, |border| border.0),
+                held: false,
+            });
+        }
+        buttons.sort_by_key(|button| (button.section, button.id, button.order));
+        Self(buttons)
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, highlight: Option<Entity>) {
+        let size = egui::vec2(PALETTE_BUTTON_SIZE.0, PALETTE_BUTTON_SIZE.1);
+        for (section, title) in [
+            (PaletteSection::Lamps, "Lamps"),
+            (PaletteSection::Buttons, "Buttons"),
+            (PaletteSection::Relays, "Relays"),
+        ] {
+            ui.label(title);
+            egui::Grid::new(title)
+                .spacing(egui::vec2(2., 2.))
+                .show(ui, |ui| {
+                    let mut previous = None;
+                    for button in self.0.iter_mut().filter(|button| button.section == section) {
+                        // Lamps have a button each, five to a row, the others have a row per id
+                        let new_row = match section {
+                            PaletteSection::Lamps => previous.is_some() && (button.id - 1) % 5 == 0,
+                            _ => previous.is_some_and(|id| id != button.id),
+                        };
+                        if new_row {
+                            ui.end_row();
+                        }
+                        previous = Some(button.id);
+
+                        let response = ui.add(
+                            egui::Button::new(
+                                egui::RichText::new(&button.label)
+                                    .color(egui::Color32::from_gray(230)),
+                            )
+                            .fill(color32(button.fill))
+                            .stroke(egui::Stroke::new(2., color32(button.stroke)))
+                            .min_size(size),
+                        );
+                        button.held = response.is_pointer_button_down_on();
+                        if highlight == Some(button.entity) {
+                            ui.painter().rect_stroke(
+                                response.rect.expand(2.),
+                                2.,
+                                egui::Stroke::new(3., egui::Color32::from_rgb(255, 204, 26)),
+                            );
+                        }
+                    }
+                });
+            ui.add_space(4.);
+        }
+    }
+
+    // A held egui button presses the bevy ui one like the mouse would, so the usual handlers place or press
+    fn press(self, world: &mut World) {
+        for button in self.0 {
+            if let Some(mut interaction) = world.get_mut::<Interaction>(button.entity) {
+                interaction.set_if_neq(if button.held {
+                    Interaction::Pressed
+                } else {
+                    Interaction::None
+                });
+            }
+        }
+    }
+}
+
+fn show_inspector(ui: &mut egui::Ui, world: &mut World, dock: &mut Dock) {
+    let mut elements = world
+        .query::<(
+            Entity,
+            AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch, &Wire)>,
+        )>()
+        .iter(world)
+        .map(|(e, element)| {
+            let label = match element {
+                (Some(light), ..) => format!("-P{}", light.id),
+                (_, Some(button), ..) => format!("-S{} {}", button.id, switch_label(button.typ)),
+                (_, _, Some(relay_coil), ..) => format!("-K{} coil", relay_coil.id),
+                (.., Some(relay_switch), _) => {
+                    format!("-K{} {}", relay_switch.id, switch_label(relay_switch.typ))
+                }
+                (.., Some(wire)) => format!(
+                    "Wire {}, {} to {}, {}",
+                    wire.first.x, wire.first.y, wire.second.x, wire.second.y
+                ),
+                _ => unreachable!(),
+            };
+            (label, e)
+        })
+        .collect::<Vec<_>>();
+    // Wires after the components
+    elements.sort_by_key(|(label, _)| (label.starts_with("Wire"), label.clone()));
+
+    if dock
+        .inspected
+        .is_some_and(|inspected| !elements.iter().any(|(_, e)| *e == inspected))
+    {
+        dock.inspected = None;
+    }
+    if elements.is_empty() {
+        ui.label("Nothing is placed yet");
+        return;
+    }
+
+    egui::ScrollArea::vertical()
+        .id_source("Placed Elements")
+        .max_height(ui.available_height() / 2.)
+        .show(ui, |ui| {
+            for (label, e) in elements {
+                let selected = dock.inspected == Some(e);
+                if ui.selectable_label(selected, label).clicked() {
+                    dock.inspected = if selected { None } else { Some(e) };
+                }
+            }
+        });
+    ui.separator();
+    match dock.inspected {
+        // Edits go through the live edit plugin, which checks them and rebuilds the visuals
+        Some(e) => bevy_inspector::ui_for_entity(world, e, ui),
+        None => {
+            ui.label("Pick an element to change it");
+        }
+    }
+}
+
+// What changed from one recorded tick to the next, the newest at the bottom
+fn show_event_log(ui: &mut egui::Ui, world: &World) {
+    let history = world.resource::<SimulationHistory>();
+    let tick = world.resource::<Time<Fixed>>().timestep().as_secs_f32();
+    let newest = history.snapshots.len().saturating_sub(1);
+
+    let mut events = Vec::new();
+    for (index, (before, after)) in history
+        .snapshots
+        .iter()
+        .zip(history.snapshots.iter().skip(1))
+        .enumerate()
+    {
+        let index = index + 1;
+        for (prefix, before, after, on, off) in [
+            (
+                "S",
+                &before.pressed_button_ids,
+                &after.pressed_button_ids,
+                "pressed",
+                "released",
+            ),
+            (
+                "K",
+                &before.activated_relay_ids,
+                &after.activated_relay_ids,
+                "pulled in",
+                "dropped out",
+            ),
+            (
+                "P",
+                &before.lit_light_ids,
+                &after.lit_light_ids,
+                "lit",
+                "off",
+            ),
+        ] {
+            let changed = after
+                .iter()
+                .filter(|id| !before.contains(id))
+                .map(|id| (id, on))
+                .chain(
+                    before
+                        .iter()
+                        .filter(|id| !after.contains(id))
+                        .map(|id| (id, off)),
+                );
+            for (id, what) in changed {
+                events.push((index, format!("-{prefix}{id} {what}")));
+            }
+        }
+    }
+
+    if events.is_empty() {
+        ui.label("Nothing changed in the recorded history");
+        return;
+    }
+    egui::Grid::new("Event Log").striped(true).show(ui, |ui| {
+        for (index, text) in events {
+            let seconds = (index as f32 - newest as f32) * tick;
+            let shown = history.cursor == Some(index);
+            ui.label(format!("{seconds:.2} s"));
+            if shown {
+                ui.strong(text);
+            } else {
+                ui.label(text);
+            }
+            ui.end_row();
+        }
+    });
+}
+
+// Like the analysis window, but small enough for the dock
+fn show_timing_diagram(ui: &mut egui::Ui, history: &SimulationHistory) {
+    let rows = timing_rows(history);
+    if rows.is_empty() {
+        ui.label("Nothing was active in the recorded history");
+        return;
+    }
+
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), TIMING_ROW_HEIGHT * rows.len() as f32),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    let left = rect.left() + TIMING_LABEL_WIDTH;
+    let tick_width = (rect.right() - left) / history.snapshots.len().max(1) as f32;
+    for (index, (label, states)) in rows.iter().enumerate() {
+        let top = rect.top() + TIMING_ROW_HEIGHT * index as f32;
+        let bottom = top + TIMING_ROW_HEIGHT - 3.;
+        painter.text(
+            egui::pos2(rect.left(), top + TIMING_ROW_HEIGHT / 2.),
+            egui::Align2::LEFT_CENTER,
+            label,
+            egui::FontId::proportional(12.),
+            egui::Color32::from_gray(230),
+        );
+
+        // Low ticks are a thin line at the bottom of the row, runs of high ticks one tall block
+        let mut tick = 0;
+        while tick < states.len() {
+            let state = states[tick];
+            let run = states[tick..]
+                .iter()
+                .take_while(|other| **other == state)
+                .count();
+            let (start, end) = (
+                left + tick as f32 * tick_width,
+                left + (tick + run) as f32 * tick_width,
+            );
+            if state {
+                painter.rect_filled(
+                    egui::Rect::from_min_max(egui::pos2(start, top + 3.), egui::pos2(end, bottom)),
+                    0.,
+                    egui::Color32::from_rgb(51, 204, 51),
+                );
+            } else {
+                painter.hline(
+                    start..=end,
+                    bottom,
+                    egui::Stroke::new(1., egui::Color32::GRAY),
+                );
+            }
+            tick += run;
+        }
+    }
+
+    // The tick that is shown in the schematic while paused
+    if let Some(cursor) = history.cursor {
+        painter.vline(
+            left + (cursor as f32 + 0.5) * tick_width,
+            rect.y_range(),
+            egui::Stroke::new(2., egui::Color32::from_gray(230)),
+        );
+    }
+}
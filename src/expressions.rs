@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use crate::{
+    blocks::Block,
+    diode::Diode,
+    fuse::Fuse,
+    net_labels::NetLabel,
+    plc_export::{rungs, Branch},
+    sheets::Sheets,
+    spawn_toolbar_button,
+    spice_export::ShownSheet,
+    time_switch::TimeSwitch,
+    ButtonSwitch, GridPosition, Light, Power, RelaySwitch, Toolbar, Wire,
+};
+
+// The logic button shows which buttons and contacts switch every lamp and coil, worked out from the wiring like the ladder export does
+// Every way the supply reaches a lamp or coil is one AND term, for example -K1 = -S1 AND NOT -S2 OR -K1 AND NOT -S2 for a self-holding relay
+// It is worked out again whenever the circuit is edited while the panel is open
+pub struct ExpressionsPlugin;
+
+impl Plugin for ExpressionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_expression_panel)
+            .add_systems(PostStartup, setup_expression_button)
+            .add_systems(
+                Update,
+                (toggle_expression_panel, update_expressions).chain(),
+            );
+    }
+}
+
+#[derive(Component)]
+struct ExpressionButton;
+
+#[derive(Component)]
+struct ExpressionPanel;
+
+// How many parts of each kind there are and where the coils are, the simulation changes coils every tick so they are compared instead
+#[derive(PartialEq)]
+struct CircuitShape {
+    counts: [usize; 9],
+    relay_coils: Vec<(usize, GridPosition, GridPosition)>,
+}
+
+fn setup_expression_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Logic", "Logic Expressions", ExpressionButton);
+    });
+}
+
+fn setup_expression_panel(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(80.),
+                right: Val::Px(10.),
+                max_width: Val::Px(500.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Logic Expression Panel"),
+        ExpressionPanel,
+    ));
+}
+
+fn toggle_expression_panel(
+    expression_button: Query<&Interaction, (Changed<Interaction>, With<ExpressionButton>)>,
+    mut panel: Query<&mut Visibility, With<ExpressionPanel>>,
+) {
+    if !expression_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn describe(branches: &[Branch]) -> String {
+    if branches.is_empty() {
+        return "0, never on".to_string();
+    }
+    // A way without any contacts makes every other one pointless, simplifying leaves only that one
+    if branches.iter().any(Vec::is_empty) {
+        return "1, always on".to_string();
+    }
+
+    branches
+        .iter()
+        .map(|branch| {
+            branch
+                .iter()
+                .map(|literal| {
+                    let not = if literal.negated { "NOT " } else { "" };
+                    format!("{not}-{}", literal.name)
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n      OR ")
+}
+
+fn update_expressions(
+    mut panel: Query<(&mut Text, &Visibility), With<ExpressionPanel>>,
+    shown: ShownSheet,
+    sheets: Res<Sheets>,
+    edited: Query<
+        (),
+        Or<(
+            Changed<Wire>,
+            Changed<Light>,
+            Changed<ButtonSwitch>,
+            Changed<RelaySwitch>,
+            Changed<Diode>,
+            Changed<NetLabel>,
+            Changed<TimeSwitch>,
+            Changed<Fuse>,
+            Changed<Block>,
+            (Changed<GridPosition>, With<Power>),
+        )>,
+    >,
+    mut last_shape: Local<Option<CircuitShape>>,
+) {
+    let Ok((mut text, visibility)) = panel.get_single_mut() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        *last_shape = None;
+        return;
+    }
+
+    let (shown_parts, block_parts) = (shown.parts(), shown.block_parts());
+    let shape = CircuitShape {
+        counts: [
+            shown_parts.wires.len(),
+            shown_parts.lights.len(),
+            shown_parts.buttons.len(),
+            shown_parts.relay_switches.len(),
+            shown_parts.diodes.len(),
+            shown_parts.net_labels.len(),
+            shown_parts.time_switches.len(),
+            shown_parts.sources.len(),
+            block_parts.links.len(),
+        ],
+        relay_coils: shown_parts
+            .relay_coils
+            .iter()
+            .map(|relay_coil| (relay_coil.id, relay_coil.top, relay_coil.bottom))
+            .collect(),
+    };
+    if edited.is_empty() && !sheets.is_changed() && last_shape.as_ref() == Some(&shape) {
+        return;
+    }
+    *last_shape = Some(shape);
+
+    let parts = [&shown_parts, &block_parts]
+        .into_iter()
+        .chain(sheets.others.iter())
+        .collect::<Vec<_>>();
+    let mut rungs = rungs(&parts).into_iter().collect::<Vec<_>>();
+    // Relays first and then lamps, by their number
+    rungs.sort_by_key(|(name, _)| (name.starts_with('P'), name[1..].parse::<usize>().ok()));
+
+    text.sections[0].value = if rungs.is_empty() {
+        "There are no lamps or coils".to_string()
+    } else {
+        rungs
+            .iter()
+            .map(|(name, branches)| format!("-{name} = {}", describe(branches)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+}
@@ -0,0 +1,337 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, is_running, simulate,
+    spawn_toolbar_button, CircuitHandles, ComponentOrientation, CurrentlyPlacing, GridPosition,
+    MainCamera, SimulationScratch, Toolbar, Wire,
+};
+
+const INTACT_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
+const BLOWN_COLOR: Color = Color::rgb(0.8, 0.1, 0.1);
+
+// Fuses conduct like a wire until they are part of a short circuit or carry current while the supply is overloaded, then they blow and stay open
+// They blow at the end of the tick that found the short or overload, from the next tick on the rest of the circuit runs without them
+// Blown fuses get a reset button in the fuse panel, right clicking a fuse while placing them removes it again
+pub struct FusePlugin;
+
+impl Plugin for FusePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_fuse_panel)
+            .add_systems(PostStartup, setup_fuse_button)
+            .add_systems(FixedUpdate, blow_fuses.after(simulate).run_if(is_running))
+            .add_systems(
+                Update,
+                (
+                    start_placing_fuses,
+                    handle_fuse_placement,
+                    handle_fuse_resets,
+                    add_fuse_visuals,
+                    update_fuse_visuals,
+                    update_fuse_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Label is -F{id}
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Fuse {
+    pub id: usize,
+    pub top: GridPosition,
+    pub bottom: GridPosition,
+    #[serde(default)]
+    pub blown: bool,
+}
+
+impl From<&Fuse> for Wire {
+    fn from(fuse: &Fuse) -> Self {
+        Self {
+            first: fuse.top,
+            second: fuse.bottom,
+        }
+    }
+}
+
+#[derive(Component)]
+struct FuseButton;
+
+#[derive(Component)]
+struct FusePanel;
+
+#[derive(Component)]
+struct FuseBody;
+
+#[derive(Component)]
+struct ResetFuseButton(Entity);
+
+pub fn spawn_fuse(cmd: &mut Commands, fuse: Fuse) -> Entity {
+    cmd.spawn((SpatialBundle::default(), Name::new("Fuse"), fuse))
+        .id()
+}
+
+fn setup_fuse_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Fuse", "Fuse", FuseButton);
+    });
+}
+
+fn setup_fuse_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(70.),
+                right: Val::Px(10.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Fuse Panel"),
+        FusePanel,
+    ));
+}
+
+fn start_placing_fuses(
+    fuse_button: Query<&Interaction, (Changed<Interaction>, With<FuseButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if fuse_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Fuse;
+    }
+}
+
+fn handle_fuse_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    fuses: Query<(Entity, &Fuse)>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Fuse) {
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        let clicked = mouse_grid.and_then(|pos| {
+            fuses.iter().find(|(_, fuse)| {
+                pos.x == fuse.top.x && (fuse.bottom.y..=fuse.top.y).contains(&pos.y)
+            })
+        });
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(mouse_grid) = mouse_grid else {
+        return;
+    };
+    // Always upright, the same way as a component that was not turned
+    let Some((top, bottom)) = ComponentOrientation::Vertical.terminals(mouse_grid, &grid_size)
+    else {
+        warn!("A fuse does not fit at the edge of the grid");
+        return;
+    };
+
+    // The lowest number that is not taken yet
+    let id = (1..)
+        .find(|id| fuses.iter().all(|(_, fuse)| fuse.id != *id))
+        .unwrap_or_default();
+    spawn_fuse(
+        &mut cmd,
+        Fuse {
+            id,
+            top,
+            bottom,
+            blown: false,
+        },
+    );
+}
+
+pub fn blow_fuses(scratch: Res<SimulationScratch>, mut fuses: Query<&mut Fuse>) {
+    if !scratch.solver.short_circuit() {
+        return;
+    }
+
+    for mut fuse in fuses.iter_mut() {
+        if !fuse.blown && scratch.solver.is_shorted(fuse.top) {
+            warn!("Fuse -F{} blew because of a short circuit", fuse.id);
+            fuse.blown = true;
+        }
+    }
+}
+
+// A fuse is a wire, so it carries current when its net reaches a lamp or coil that was on in the last tick
+pub fn carries_current(fuse: &Fuse, scratch: &SimulationScratch) -> bool {
+    let solver = &scratch.solver;
+    scratch
+        .circuit
+        .consumers
+        .iter()
+        .enumerate()
+        .filter(|(consumer, _)| solver.is_energized(*consumer))
+        .any(|(_, (top, bottom))| {
+            solver.same_net(fuse.top, *top) || solver.same_net(fuse.top, *bottom)
+        })
+}
+
+fn handle_fuse_resets(
+    reset_buttons: Query<(&Interaction, &ResetFuseButton), Changed<Interaction>>,
+    mut fuses: Query<&mut Fuse>,
+) {
+    for (interaction, reset) in reset_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Ok(mut fuse) = fuses.get_mut(reset.0) {
+            fuse.blown = false;
+        }
+    }
+}
+
+// Made from the fuse itself, so loading only has to spawn the fuse
+fn add_fuse_visuals(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    fuses: Query<(Entity, &Fuse), Added<Fuse>>,
+) {
+    for (e, fuse) in fuses.iter() {
+        let top = grid_to_world(fuse.top);
+        let bottom = grid_to_world(fuse.bottom);
+
+        cmd.entity(e).with_children(|root| {
+            for (terminal, name) in [(top, "Fuse Point1"), (bottom, "Fuse Point2")] {
+                root.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: circuit_material.wire_point_mesh.clone(),
+                        material: circuit_material.wire_material.clone(),
+                        transform: Transform::from_translation(terminal.extend(2.5)),
+                        ..Default::default()
+                    },
+                    Name::new(name),
+                ));
+            }
+            root.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: if fuse.blown {
+                            BLOWN_COLOR
+                        } else {
+                            INTACT_COLOR
+                        },
+                        custom_size: Some(Vec2::new(12., (top.y - bottom.y) * 0.6)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(((top + bottom) / 2.).extend(2.)),
+                    ..Default::default()
+                },
+                Name::new("Fuse Body"),
+                FuseBody,
+            ));
+            root.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        format!("-F{}", fuse.id),
+                        TextStyle {
+                            font_size: 14.,
+                            color: Color::WHITE,
+                            ..Default::default()
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::CenterLeft,
+                    transform: Transform::from_translation(
+                        ((top + bottom) / 2. + Vec2::new(10., 0.)).extend(5.),
+                    ),
+                    ..Default::default()
+                },
+                Name::new("Fuse Text"),
+            ));
+        });
+    }
+}
+
+fn update_fuse_visuals(
+    fuses: Query<(&Fuse, &Children), Changed<Fuse>>,
+    mut bodies: Query<&mut Sprite, With<FuseBody>>,
+) {
+    for (fuse, children) in fuses.iter() {
+        let color = if fuse.blown {
+            BLOWN_COLOR
+        } else {
+            INTACT_COLOR
+        };
+        let mut bodies = bodies.iter_many_mut(children);
+        while let Some(mut sprite) = bodies.fetch_next() {
+            sprite.color = color;
+        }
+    }
+}
+
+// One reset button per blown fuse, the panel is only there while a fuse is blown
+fn update_fuse_panel(
+    mut cmd: Commands,
+    changed: Query<(), Changed<Fuse>>,
+    mut removed: RemovedComponents<Fuse>,
+    fuses: Query<(Entity, &Fuse)>,
+    mut panel: Query<(Entity, &mut Visibility), With<FusePanel>>,
+) {
+    if changed.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    let mut blown = fuses
+        .iter()
+        .filter(|(_, fuse)| fuse.blown)
+        .collect::<Vec<_>>();
+    blown.sort_by_key(|(_, fuse)| fuse.id);
+
+    for (panel, mut visibility) in panel.iter_mut() {
+        *visibility = if blown.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        cmd.entity(panel)
+            .despawn_descendants()
+            .with_children(|root| {
+                for (e, fuse) in &blown {
+                    spawn_toolbar_button(
+                        root,
+                        &format!("Reset -F{}", fuse.id),
+                        "Reset Fuse",
+                        ResetFuseButton(*e),
+                    );
+                }
+            });
+    }
+}
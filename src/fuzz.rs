@@ -0,0 +1,405 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    spawn_toolbar_button, ButtonSwitch, IsRunning, RelayCoil, SimulationScratch, Toolbar, UIButton,
+    UILight,
+};
+
+const ASSERTIONS_PATH: &str = "assertions.ron";
+const MIN_TICKS: usize = 100;
+const MAX_TICKS: usize = 100_000;
+// Chance per tick that one of the placed buttons is pressed or let go
+const TOGGLE_CHANCE: f64 = 0.1;
+// Ticks the state may keep changing while the buttons stay the same, relay chains need a few to settle
+const OSCILLATION_TICKS: usize = 20;
+const MAX_REPORT_LINES: usize = 12;
+
+// Presses and releases random buttons for a number of simulation ticks in a single frame and reports what went wrong
+// Shorts, states that never settle and broken rules from assertions.ron in the working directory are reported, for example
+// [NeverTogether(["-K1", "-K2"]), Implies("-S1", "-P1")]
+pub struct FuzzPlugin;
+
+impl Plugin for FuzzPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Fuzzer>()
+            .add_systems(Startup, setup_fuzz_panel)
+            .add_systems(PostStartup, setup_fuzz_button)
+            .add_systems(
+                Update,
+                (
+                    toggle_fuzz_panel,
+                    handle_fuzz_rows,
+                    run_fuzzer,
+                    update_fuzz_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Names refer to lit lamps (-P1), pulled in relays (-K1) and pressed buttons (-S1)
+#[derive(Deserialize)]
+enum Assertion {
+    // Never all of them at once
+    NeverTogether(Vec<String>),
+    // Whenever the first one is on, the second one is as well
+    Implies(String, String),
+}
+
+impl Assertion {
+    fn holds(&self, on: &[String]) -> bool {
+        match self {
+            Assertion::NeverTogether(names) => !names.iter().all(|name| on.contains(name)),
+            Assertion::Implies(first, second) => !on.contains(first) || on.contains(second),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Assertion::NeverTogether(names) => format!("never {}", names.join(" and ")),
+            Assertion::Implies(first, second) => format!("{first} implies {second}"),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Fuzzer {
+    ticks: usize,
+    // Run on the next frame
+    pending: bool,
+    report: Vec<String>,
+}
+
+impl Default for Fuzzer {
+    fn default() -> Self {
+        Self {
+            ticks: 1000,
+            pending: false,
+            report: Vec::new(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct FuzzButton;
+
+#[derive(Component)]
+struct FuzzPanel;
+
+#[derive(Component)]
+struct FuzzReportText;
+
+#[derive(Component, Clone, Copy)]
+enum FuzzRow {
+    FewerTicks,
+    MoreTicks,
+    Run,
+}
+
+fn setup_fuzz_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Fuzz", "Fuzz Test", FuzzButton);
+    });
+}
+
+fn setup_fuzz_panel(mut cmd: Commands) {
+    let text_style = TextStyle {
+        font_size: 16.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                right: Val::Px(10.),
+                width: Val::Px(300.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Fuzz Panel"),
+        FuzzPanel,
+    ))
+    .with_children(|root| {
+        root.spawn((NodeBundle::default(), Name::new("Fuzz Controls")))
+            .with_children(|root| {
+                for row in [FuzzRow::FewerTicks, FuzzRow::MoreTicks, FuzzRow::Run] {
+                    root.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(3.)),
+                                margin: UiRect::all(Val::Px(1.)),
+                                ..Default::default()
+                            },
+                            background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                            ..Default::default()
+                        },
+                        Name::new("Fuzz Row"),
+                        row,
+                    ))
+                    .with_children(|root| {
+                        root.spawn((
+                            TextBundle::from_section("", text_style.clone()),
+                            Name::new("Fuzz Row Text"),
+                        ));
+                    });
+                }
+            });
+
+        root.spawn((
+            TextBundle::from_section("", text_style.clone()),
+            Name::new("Fuzz Report"),
+            FuzzReportText,
+        ));
+    });
+}
+
+fn toggle_fuzz_panel(
+    fuzz_button: Query<&Interaction, (Changed<Interaction>, With<FuzzButton>)>,
+    mut panel: Query<&mut Visibility, With<FuzzPanel>>,
+) {
+    if !fuzz_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_fuzz_rows(
+    rows: Query<(&Interaction, &FuzzRow), Changed<Interaction>>,
+    mut fuzzer: ResMut<Fuzzer>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match row {
+            FuzzRow::FewerTicks => fuzzer.ticks = (fuzzer.ticks / 10).max(MIN_TICKS),
+            FuzzRow::MoreTicks => fuzzer.ticks = (fuzzer.ticks * 10).min(MAX_TICKS),
+            FuzzRow::Run => fuzzer.pending = true,
+        }
+    }
+}
+
+fn load_assertions() -> Result<Vec<Assertion>, String> {
+    if !Path::new(ASSERTIONS_PATH).exists() {
+        return Ok(Vec::new());
+    }
+
+    fs::read_to_string(ASSERTIONS_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|text| ron::from_str::<Vec<Assertion>>(&text).map_err(|e| e.to_string()))
+}
+
+// Everything that is on after a tick, by the names the assertions use
+fn state_after_tick(world: &mut World) -> Vec<String> {
+    let mut on = world
+        .resource::<SimulationScratch>()
+        .active_button_ids
+        .iter()
+        .map(|id| format!("-S{id}"))
+        .collect::<Vec<_>>();
+    on.extend(
+        world
+            .query::<&RelayCoil>()
+            .iter(world)
+            .filter(|relay_coil| relay_coil.activated)
+            .map(|relay_coil| format!("-K{}", relay_coil.id)),
+    );
+    on.extend(
+        world
+            .query::<&UILight>()
+            .iter(world)
+            .filter(|ui_light| ui_light.is_lit)
+            .map(|ui_light| format!("-P{}", ui_light.id)),
+    );
+    on.sort();
+    on
+}
+
+// Counts how often a problem happened and remembers when it happened first
+#[derive(Default)]
+struct Finding {
+    count: usize,
+    first_tick: usize,
+}
+
+impl Finding {
+    fn record(&mut self, tick: usize) {
+        if self.count == 0 {
+            self.first_tick = tick;
+        }
+        self.count += 1;
+    }
+}
+
+// Runs the fixed update schedule directly, as fast as it goes, with the fixed time step like the normal loop
+fn run_fuzzer(world: &mut World) {
+    if !world.resource::<Fuzzer>().pending {
+        return;
+    }
+    world.resource_mut::<Fuzzer>().pending = false;
+
+    if world.resource::<Time<Virtual>>().is_paused() || !world.resource::<IsRunning>().0 {
+        world.resource_mut::<Fuzzer>().report =
+            vec!["Run or resume the simulation first".to_string()];
+        return;
+    }
+    let assertions = match load_assertions() {
+        Ok(assertions) => assertions,
+        Err(e) => {
+            world.resource_mut::<Fuzzer>().report =
+                vec![format!("Cannot read {ASSERTIONS_PATH}: {e}")];
+            return;
+        }
+    };
+
+    let mut button_ids = world
+        .query::<&ButtonSwitch>()
+        .iter(world)
+        .map(|button| button.id)
+        .collect::<Vec<_>>();
+    button_ids.sort();
+    button_ids.dedup();
+
+    let ticks = world.resource::<Fuzzer>().ticks;
+    let mut random = rand::thread_rng();
+    let mut held = Vec::new();
+    let mut previous = Vec::new();
+    let mut unsettled = 0;
+    let mut shorts = Finding::default();
+    let mut oscillations = Finding::default();
+    let mut broken = assertions
+        .iter()
+        .map(|_| Finding::default())
+        .collect::<Vec<_>>();
+
+    for tick in 0..ticks {
+        let mut inputs_changed = false;
+        if !button_ids.is_empty() && random.gen_bool(TOGGLE_CHANCE) {
+            let id = button_ids[random.gen_range(0..button_ids.len())];
+            match held.iter().position(|held| *held == id) {
+                Some(i) => {
+                    held.remove(i);
+                }
+                None => held.push(id),
+            }
+            inputs_changed = true;
+        }
+
+        for mut ui_button in world.query::<&mut UIButton>().iter_mut(world) {
+            ui_button.has_been_pressed = held.contains(&ui_button.id);
+        }
+
+        *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+        world.run_schedule(FixedUpdate);
+
+        if world.resource::<SimulationScratch>().solver.short_circuit() {
+            shorts.record(tick);
+        }
+
+        let on = state_after_tick(world);
+        if inputs_changed || on == previous {
+            unsettled = 0;
+        } else {
+            unsettled += 1;
+            if unsettled == OSCILLATION_TICKS {
+                oscillations.record(tick);
+            }
+        }
+
+        // Relays pull in a tick after their coil is powered, so only settled states are checked
+        if on == previous {
+            for (assertion, finding) in assertions.iter().zip(broken.iter_mut()) {
+                if !assertion.holds(&on) {
+                    finding.record(tick);
+                }
+            }
+        }
+        previous = on;
+    }
+
+    for mut ui_button in world.query::<&mut UIButton>().iter_mut(world) {
+        ui_button.has_been_pressed = false;
+    }
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+
+    let mut report = vec![format!(
+        "{ticks} ticks with {} buttons, {} assertions",
+        button_ids.len(),
+        assertions.len()
+    )];
+    let findings = [("Short circuit", &shorts), ("Never settles", &oscillations)]
+        .into_iter()
+        .map(|(what, finding)| (what.to_string(), finding))
+        .chain(
+            assertions
+                .iter()
+                .zip(broken.iter())
+                .map(|(assertion, finding)| (format!("Broken: {}", assertion.describe()), finding)),
+        );
+    for (what, finding) in findings {
+        if finding.count > 0 {
+            report.push(format!(
+                "{what}: {} times, first at tick {}",
+                finding.count, finding.first_tick
+            ));
+        }
+    }
+    if report.len() == 1 {
+        report.push("Nothing went wrong".to_string());
+    }
+    report.truncate(MAX_REPORT_LINES);
+
+    info!("Fuzz test finished: {}", report.join(", "));
+    world.resource_mut::<Fuzzer>().report = report;
+}
+
+fn update_fuzz_panel(
+    fuzzer: Res<Fuzzer>,
+    rows: Query<(&FuzzRow, &Children)>,
+    mut texts: Query<&mut Text, Without<FuzzReportText>>,
+    mut report_text: Query<&mut Text, With<FuzzReportText>>,
+) {
+    if !fuzzer.is_changed() {
+        return;
+    }
+
+    for (row, children) in rows.iter() {
+        let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) else {
+            continue;
+        };
+        text.sections[0].value = match row {
+            FuzzRow::FewerTicks => "-".to_string(),
+            FuzzRow::MoreTicks => format!("{} ticks +", fuzzer.ticks),
+            FuzzRow::Run => "Run".to_string(),
+        };
+    }
+
+    for mut text in report_text.iter_mut() {
+        text.sections[0].value = fuzzer.report.join("\n");
+    }
+}
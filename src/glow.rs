@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use bevy::{
+    core_pipeline::bloom::{BloomCompositeMode, BloomPrefilterSettings, BloomSettings},
+    prelude::*,
+};
+
+use crate::{
+    history::SimulationHistory, spawn_toolbar_button, CircuitHandles, IsRunning, Light, LightBulb,
+    SimulationScratch, Toolbar, UILight, Visited, Wire, NEGATIVE_WIRE_COLOR, POSITIVE_WIRE_COLOR,
+};
+
+// Ticks of history the brightness of a lamp is averaged over, a lamp switched every tick shines at half brightness
+const DUTY_WINDOW: usize = 10;
+
+// Wires connected to a power source and lit lights get their own materials, the glow button makes those bright enough to bloom
+// Every tick wires show the side they are connected to, red for positive and blue for negative, a short circuit is bright red
+// Lamps that are only lit part of the time, behind a flasher for example, shine as bright as the share of recent ticks they were lit in
+pub struct GlowPlugin;
+
+impl Plugin for GlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GlowEnabled>()
+            .init_resource::<LightBrightness>()
+            .add_systems(PostStartup, setup_glow_button)
+            .add_systems(
+                Update,
+                (
+                    (handle_glow_button_press, apply_glow).chain(),
+                    (update_light_brightness, show_energized_elements).chain(),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct GlowEnabled(bool);
+
+#[derive(Component)]
+struct GlowButton;
+
+// By the id of the lamp, from 0 for off to 1 for lit all the time
+#[derive(Resource, Default)]
+pub struct LightBrightness(HashMap<usize, f32>);
+
+impl LightBrightness {
+    pub fn of(&self, id: usize) -> f32 {
+        self.0.get(&id).copied().unwrap_or(0.)
+    }
+}
+
+// Only colors above 1.0 bloom, everything else keeps looking the same
+const GLOW_BLOOM: BloomSettings = BloomSettings {
+    intensity: 0.3,
+    low_frequency_boost: 0.7,
+    low_frequency_boost_curvature: 0.95,
+    high_pass_frequency: 1.0,
+    prefilter_settings: BloomPrefilterSettings {
+        threshold: 1.0,
+        threshold_softness: 0.2,
+    },
+    composite_mode: BloomCompositeMode::Additive,
+};
+
+fn setup_glow_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Glow", "Glow", GlowButton);
+    });
+}
+
+fn handle_glow_button_press(
+    mut glow: ResMut<GlowEnabled>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<GlowButton>)>,
+) {
+    if interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        glow.0 = !glow.0;
+    }
+}
+
+fn apply_glow(
+    mut cmd: Commands,
+    glow: Res<GlowEnabled>,
+    handles: Res<CircuitHandles>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut cameras: Query<(Entity, &mut Camera), With<Camera2d>>,
+    mut glow_button: Query<&mut BackgroundColor, With<GlowButton>>,
+) {
+    if !glow.is_changed() {
+        return;
+    }
+
+    // Bloom only works on hdr cameras
+    for (entity, mut camera) in cameras.iter_mut() {
+        camera.hdr = glow.0;
+        if glow.0 {
+            cmd.entity(entity).insert(GLOW_BLOOM);
+        } else {
+            cmd.entity(entity).remove::<BloomSettings>();
+        }
+    }
+
+    let (positive_color, negative_color, light_color, button_color) = if glow.0 {
+        (
+            Color::rgb(2.4, 0.8, 0.8),
+            Color::rgb(0.8, 1., 2.4),
+            Color::rgb(4., 4., 0.5),
+            Color::rgb(0.2, 0.5, 0.2),
+        )
+    } else {
+        (
+            POSITIVE_WIRE_COLOR,
+            NEGATIVE_WIRE_COLOR,
+            Color::YELLOW,
+            Color::rgb(0.25, 0.25, 0.25),
+        )
+    };
+
+    // Evenly spaced between an unlit and a lit light
+    let steps = handles.dimmed_light_materials.len() + 1;
+    for (i, handle) in handles.dimmed_light_materials.iter().enumerate() {
+        let share = (i + 1) as f32 / steps as f32;
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = Color::YELLOW * (1. - share) + light_color * share;
+        }
+    }
+
+    for (handle, color) in [
+        (&handles.positive_wire_material, positive_color),
+        (&handles.negative_wire_material, negative_color),
+    ] {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    }
+    if let Some(material) = materials.get_mut(&handles.lit_light_material) {
+        material.color = light_color;
+    }
+    for mut background_color in glow_button.iter_mut() {
+        background_color.0 = button_color;
+    }
+}
+
+// Taken from the recorded history, so scrubbing through it while paused shows the brightness of that moment
+// After stopping the history is kept for looking at, but the lights are off like everything else
+pub fn update_light_brightness(
+    history: Res<SimulationHistory>,
+    running: Res<IsRunning>,
+    ui_lights: Query<&UILight>,
+    mut brightness: ResMut<LightBrightness>,
+) {
+    let end = if running.0 {
+        history
+            .cursor
+            .map_or(history.snapshots.len(), |cursor| cursor + 1)
+    } else {
+        0
+    };
+    let start = end.saturating_sub(DUTY_WINDOW);
+
+    for ui_light in ui_lights.iter() {
+        let share = if start == end {
+            if ui_light.is_lit {
+                1.
+            } else {
+                0.
+            }
+        } else {
+            history
+                .snapshots
+                .range(start..end)
+                .filter(|snapshot| snapshot.lit_light_ids.contains(&ui_light.id))
+                .count() as f32
+                / (end - start) as f32
+        };
+
+        if brightness.0.get(&ui_light.id) != Some(&share) {
+            brightness.0.insert(ui_light.id, share);
+        }
+    }
+}
+
+fn show_energized_elements(
+    scratch: Res<SimulationScratch>,
+    handles: Res<CircuitHandles>,
+    wires: Query<(&Wire, &Children)>,
+    lights: Query<(&Light, &Children)>,
+    brightness: Res<LightBrightness>,
+    mut materials: Query<&mut Handle<ColorMaterial>>,
+    bulbs: Query<(), With<LightBulb>>,
+) {
+    for (wire, children) in wires.iter() {
+        let material = if scratch.solver.is_shorted(wire.first) {
+            &handles.shorted_wire_material
+        } else {
+            match scratch.solver.mark(wire.first) {
+                Visited::Positive => &handles.positive_wire_material,
+                Visited::Negative => &handles.negative_wire_material,
+                Visited::Unvisited => &handles.wire_material,
+            }
+        };
+
+        for child in children.iter() {
+            if let Ok(mut handle) = materials.get_mut(*child) {
+                if *handle != *material {
+                    *handle = material.clone();
+                }
+            }
+        }
+    }
+
+    let steps = handles.dimmed_light_materials.len() + 1;
+    for (light, children) in lights.iter() {
+        let step = (brightness.of(light.id) * steps as f32).round() as usize;
+        let material = match step {
+            0 => &handles.light_material,
+            step if step >= steps => &handles.lit_light_material,
+            step => &handles.dimmed_light_materials[step - 1],
+        };
+
+        for child in children.iter().filter(|child| bulbs.contains(**child)) {
+            if let Ok(mut handle) = materials.get_mut(*child) {
+                if *handle != *material {
+                    *handle = material.clone();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    is_running, platform, simulate, spawn_toolbar_button, RelayCoil, Toolbar, UIButton, UILight,
+};
+
+const GPIO_PATH: &str = "gpio.ron";
+const SYSFS_GPIO: &str = "/sys/class/gpio";
+// After exporting a pin it takes a moment until its files may be written by users in the gpio group
+const EXPORT_RETRIES: usize = 20;
+const EXPORT_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+// Only with cargo build --features gpio, for running on a Raspberry Pi with real buttons and lamps attached
+// The GPIO button claims the pins from gpio.ron in the working directory, inputs press buttons and outputs follow lights and relays, for example
+// (buttons: [(pin: 17, id: 1, active_low: true)], lights: [(pin: 27, id: 1)], relays: [(pin: 22, id: 1)])
+// Pins go through /sys/class/gpio, newer kernels number the pins of the Pi from 512 on, chip_base: 512 takes care of that
+pub struct GpioPlugin;
+
+impl Plugin for GpioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings())
+            .init_resource::<GpioPins>()
+            .add_systems(PostStartup, setup_gpio_button)
+            .add_systems(Update, (toggle_gpio, update_gpio_button).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    press_gpio_buttons.before(simulate),
+                    drive_gpio_outputs.after(simulate),
+                )
+                    .run_if(is_running),
+            );
+    }
+}
+
+#[derive(Clone, Deserialize)]
+struct GpioInput {
+    pin: u32,
+    id: usize,
+    // Buttons that connect the pin to ground with a pull up are pressed when the pin reads 0
+    #[serde(default)]
+    active_low: bool,
+}
+
+#[derive(Clone, Deserialize)]
+struct GpioOutput {
+    pin: u32,
+    id: usize,
+}
+
+#[derive(Resource, Clone, Default, Deserialize)]
+#[serde(default)]
+struct GpioSettings {
+    chip_base: u32,
+    buttons: Vec<GpioInput>,
+    lights: Vec<GpioOutput>,
+    relays: Vec<GpioOutput>,
+}
+
+fn load_settings() -> GpioSettings {
+    if !platform::file_exists(Path::new(GPIO_PATH)) {
+        return GpioSettings::default();
+    }
+
+    match platform::read_file(Path::new(GPIO_PATH))
+        .and_then(|text| ron::from_str::<GpioSettings>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Cannot read the GPIO pins from {GPIO_PATH}: {e}");
+            GpioSettings::default()
+        }
+    }
+}
+
+// The value file of an exported pin, kept open so reading and writing it every tick is cheap
+struct Pin {
+    number: u32,
+    value: File,
+}
+
+impl Pin {
+    fn export(number: u32, direction: &str) -> Result<Self, String> {
+        let dir = PathBuf::from(format!("{SYSFS_GPIO}/gpio{number}"));
+        if !dir.exists() {
+            fs::write(format!("{SYSFS_GPIO}/export"), number.to_string())
+                .map_err(|e| format!("cannot export pin {number}: {e}"))?;
+        }
+
+        let mut result = Err(String::new());
+        for _ in 0..EXPORT_RETRIES {
+            result = fs::write(dir.join("direction"), direction)
+                .and_then(|_| {
+                    OpenOptions::new()
+                        .read(true)
+                        .write(true)
+                        .open(dir.join("value"))
+                })
+                .map_err(|e| format!("cannot set up pin {number}: {e}"));
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(EXPORT_RETRY_DELAY);
+        }
+        result.map(|value| Self { number, value })
+    }
+
+    fn read(&mut self) -> Option<bool> {
+        let mut text = String::new();
+        self.value.seek(SeekFrom::Start(0)).ok()?;
+        self.value.read_to_string(&mut text).ok()?;
+        Some(text.trim() == "1")
+    }
+
+    fn write(&mut self, high: bool) -> bool {
+        self.value.seek(SeekFrom::Start(0)).is_ok()
+            && self.value.write_all(if high { b"1" } else { b"0" }).is_ok()
+    }
+}
+
+impl Drop for Pin {
+    // Outputs go low again when they are given back, writing to an input just fails
+    fn drop(&mut self) {
+        self.write(false);
+        let _ = fs::write(format!("{SYSFS_GPIO}/unexport"), self.number.to_string());
+    }
+}
+
+struct Claimed {
+    buttons: Vec<(Pin, GpioInput)>,
+    // The pin with the state it was last set to
+    lights: Vec<(Pin, usize, Option<bool>)>,
+    relays: Vec<(Pin, usize, Option<bool>)>,
+}
+
+#[derive(Resource, Default)]
+struct GpioPins(Option<Claimed>);
+
+fn claim_pins(settings: &GpioSettings) -> Result<Claimed, String> {
+    let number = |pin: u32| settings.chip_base + pin;
+    let outputs = |outputs: &[GpioOutput]| {
+        outputs
+            .iter()
+            .map(|output| Ok((Pin::export(number(output.pin), "low")?, output.id, None)))
+            .collect::<Result<Vec<_>, String>>()
+    };
+
+    Ok(Claimed {
+        buttons: settings
+            .buttons
+            .iter()
+            .map(|input| Ok((Pin::export(number(input.pin), "in")?, input.clone())))
+            .collect::<Result<_, String>>()?,
+        lights: outputs(&settings.lights)?,
+        relays: outputs(&settings.relays)?,
+    })
+}
+
+#[derive(Component)]
+struct GpioButton;
+
+fn setup_gpio_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "GPIO", "GPIO", GpioButton);
+    });
+}
+
+fn toggle_gpio(
+    gpio_button: Query<&Interaction, (Changed<Interaction>, With<GpioButton>)>,
+    settings: Res<GpioSettings>,
+    mut pins: ResMut<GpioPins>,
+) {
+    if !gpio_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if pins.0.take().is_some() {
+        info!("Gave back the GPIO pins");
+        return;
+    }
+
+    if settings.buttons.is_empty() && settings.lights.is_empty() && settings.relays.is_empty() {
+        warn!("There are no GPIO pins in {GPIO_PATH}");
+        return;
+    }
+    match claim_pins(&settings) {
+        Ok(claimed) => {
+            info!(
+                "Using {} GPIO inputs and {} outputs",
+                claimed.buttons.len(),
+                claimed.lights.len() + claimed.relays.len()
+            );
+            pins.0 = Some(claimed);
+        }
+        Err(e) => error!("Cannot use the GPIO pins: {e}"),
+    }
+}
+
+fn update_gpio_button(
+    pins: Res<GpioPins>,
+    mut gpio_button: Query<&mut BackgroundColor, With<GpioButton>>,
+) {
+    if !pins.is_changed() {
+        return;
+    }
+
+    for mut background_color in gpio_button.iter_mut() {
+        background_color.0 = match pins.0 {
+            Some(_) => Color::rgb(0.1, 0.5, 0.1),
+            None => Color::rgb(0.25, 0.25, 0.25),
+        };
+    }
+}
+
+// A pressed input holds its button down like the mouse does, the simulation lets go of it again every tick
+fn press_gpio_buttons(mut pins: ResMut<GpioPins>, mut ui_buttons: Query<&mut UIButton>) {
+    let Some(claimed) = pins.0.as_mut() else {
+        return;
+    };
+
+    for (pin, input) in claimed.buttons.iter_mut() {
+        let Some(high) = pin.read() else {
+            continue;
+        };
+        if high == input.active_low {
+            continue;
+        }
+        for mut ui_button in ui_buttons.iter_mut() {
+            if ui_button.id == input.id {
+                ui_button.has_been_pressed = true;
+            }
+        }
+    }
+}
+
+fn drive_gpio_outputs(
+    mut pins: ResMut<GpioPins>,
+    relay_coils: Query<&RelayCoil>,
+    ui_lights: Query<&UILight>,
+) {
+    let Some(claimed) = pins.0.as_mut() else {
+        return;
+    };
+
+    let lit = |id: usize| {
+        ui_lights
+            .iter()
+            .any(|ui_light| ui_light.id == id && ui_light.is_lit)
+    };
+    let pulled_in = |id: usize| {
+        relay_coils
+            .iter()
+            .any(|relay_coil| relay_coil.id == id && relay_coil.activated)
+    };
+    let outputs = claimed
+        .lights
+        .iter_mut()
+        .map(|(pin, id, last)| (pin, lit(*id), last))
+        .chain(
+            claimed
+                .relays
+                .iter_mut()
+                .map(|(pin, id, last)| (pin, pulled_in(*id), last)),
+        );
+    for (pin, on, last) in outputs {
+        if *last != Some(on) && pin.write(on) {
+            *last = Some(on);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    headless::Simulation,
+    save::read_circuit,
+    scenario::{evaluate_scenario, read_scenario, Outcome, Scenario},
+};
+
+const EXIT_OK: i32 = 0;
+const EXIT_USAGE: i32 = 1;
+// Thumbnails are a quarter of the exported svg
+const THUMBNAIL_SCALE: f32 = 0.25;
+
+const USAGE: &str =
+    "usage: relay-sim --grade <scenario file> --report <report.csv|report.json> <circuit file>...";
+
+// relay-sim --grade test.ron --report grades.csv students/*.ron runs the same scenario on every circuit, like --headless --scenario does for one
+// The report has a line per circuit with its title, the ticks it ran, how many expectations passed and each of them with PASS or FAIL
+// A .json report lists the expectations with what was actually there, anything else is written as csv
+// Each circuit gets a small svg of its schematic in a folder next to the report, grades-thumbnails for grades.csv
+// Circuits that cannot be loaded still get their line with the error, only bad arguments or an unwritable report exit with 1
+pub fn run(args: &[String]) -> i32 {
+    let (scenario_path, report_path, circuit_paths) = match parse_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+    let scenario = match read_scenario(Path::new(scenario_path)) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("Cannot load scenario from {scenario_path}: {e}");
+            return EXIT_USAGE;
+        }
+    };
+
+    let report_path = Path::new(report_path);
+    let thumbnail_dir = thumbnail_dir(report_path);
+    if let Err(e) = fs::create_dir_all(&thumbnail_dir) {
+        eprintln!("Cannot create {}: {e}", thumbnail_dir.display());
+        return EXIT_USAGE;
+    }
+
+    let grades = circuit_paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let grade = grade(path, &scenario, &thumbnail_dir, i + 1);
+            match &grade.result {
+                Ok(graded) => println!(
+                    "{path}: {} passed, {} failed",
+                    graded.passed(),
+                    graded.outcomes.len() - graded.passed()
+                ),
+                Err(e) => println!("{path}: {e}"),
+            }
+            grade
+        })
+        .collect::<Vec<_>>();
+
+    let report = if report_path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        json_report(&grades)
+    } else {
+        csv_report(&grades)
+    };
+    match fs::write(report_path, report) {
+        Ok(_) => {
+            println!("Wrote the report to {}", report_path.display());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("Cannot write the report to {}: {e}", report_path.display());
+            EXIT_USAGE
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(&str, &str, Vec<&str>), String> {
+    let mut args = args.iter();
+    let scenario = args.next().ok_or("no scenario file given")?;
+    let mut report = None;
+    let mut circuits = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--report" {
+            report = Some(args.next().ok_or("--report needs a value")?.as_str());
+        } else {
+            circuits.push(arg.as_str());
+        }
+    }
+
+    let report = report.ok_or("no report file given")?;
+    if circuits.is_empty() {
+        return Err("no circuit files given".to_string());
+    }
+    Ok((scenario, report, circuits))
+}
+
+fn thumbnail_dir(report_path: &Path) -> PathBuf {
+    let stem = report_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    report_path.with_file_name(format!("{stem}-thumbnails"))
+}
+
+struct Grade {
+    file: String,
+    result: Result<Graded, String>,
+}
+
+struct Graded {
+    title: String,
+    ticks: usize,
+    short_circuit: bool,
+    outcomes: Vec<Outcome>,
+    // None for empty circuits
+    thumbnail: Option<PathBuf>,
+}
+
+impl Graded {
+    fn passed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.passed)
+            .count()
+    }
+}
+
+// The number keeps thumbnails of circuits with the same file name in different folders apart
+fn grade(path: &str, scenario: &Scenario, thumbnail_dir: &Path, number: usize) -> Grade {
+    let result = read_circuit(Path::new(path)).and_then(|circuit| {
+        let title = circuit.title().to_string();
+        let mut simulation = Simulation::new(circuit, &[]);
+        let thumbnail = match simulation.schematic_svg(THUMBNAIL_SCALE) {
+            Some(svg) => {
+                let stem = Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy())
+                    .unwrap_or_default();
+                let thumbnail = thumbnail_dir.join(format!("{number}-{stem}.svg"));
+                fs::write(&thumbnail, svg).map_err(|e| {
+                    format!("cannot write the thumbnail {}: {e}", thumbnail.display())
+                })?;
+                Some(thumbnail)
+            }
+            None => None,
+        };
+        let outcomes = evaluate_scenario(&mut simulation, scenario);
+        Ok(Graded {
+            title,
+            ticks: simulation.ticks(),
+            short_circuit: simulation.short_circuit(),
+            outcomes,
+            thumbnail,
+        })
+    });
+    Grade {
+        file: path.to_string(),
+        result,
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// Every circuit runs the same scenario, so the expectations of any loaded one name the columns
+fn csv_report(grades: &[Grade]) -> String {
+    let expectations = grades
+        .iter()
+        .find_map(|grade| grade.result.as_ref().ok())
+        .map(|graded| {
+            graded
+                .outcomes
+                .iter()
+                .map(|outcome| format!("tick {}: {}", outcome.tick, outcome.expectation))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut report = String::new();
+    let header = [
+        "file",
+        "title",
+        "error",
+        "ticks",
+        "passed",
+        "failed",
+        "short circuit",
+        "thumbnail",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .chain(expectations.iter().cloned())
+    .map(|field| csv_field(&field))
+    .collect::<Vec<_>>();
+    let _ = writeln!(report, "{}", header.join(","));
+
+    for grade in grades {
+        let fields = match &grade.result {
+            Ok(graded) => [
+                grade.file.clone(),
+                graded.title.clone(),
+                String::new(),
+                graded.ticks.to_string(),
+                graded.passed().to_string(),
+                (graded.outcomes.len() - graded.passed()).to_string(),
+                graded.short_circuit.to_string(),
+                graded
+                    .thumbnail
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            ]
+            .into_iter()
+            .chain(
+                graded
+                    .outcomes
+                    .iter()
+                    .map(|outcome| if outcome.passed { "PASS" } else { "FAIL" }.to_string()),
+            )
+            .collect::<Vec<_>>(),
+            Err(e) => [grade.file.clone(), String::new(), e.clone()]
+                .into_iter()
+                .chain(std::iter::repeat_n(String::new(), 5 + expectations.len()))
+                .collect(),
+        };
+        let fields = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>();
+        let _ = writeln!(report, "{}", fields.join(","));
+    }
+    report
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped + "\""
+}
+
+fn json_report(grades: &[Grade]) -> String {
+    let mut report = String::from("[\n");
+    for (i, grade) in grades.iter().enumerate() {
+        let _ = write!(report, "  {{\n    \"file\": {}", json_string(&grade.file));
+        match &grade.result {
+            Ok(graded) => {
+                let thumbnail = graded
+                    .thumbnail
+                    .as_ref()
+                    .map(|path| json_string(&path.display().to_string()))
+                    .unwrap_or("null".to_string());
+                let _ = write!(
+                    report,
+                    ",\n    \"title\": {},\n    \"ticks\": {},\n    \"passed\": {},\n    \"failed\": {},\n    \"short_circuit\": {},\n    \"thumbnail\": {thumbnail},\n    \"expectations\": [",
+                    json_string(&graded.title),
+                    graded.ticks,
+                    graded.passed(),
+                    graded.outcomes.len() - graded.passed(),
+                    graded.short_circuit,
+                );
+                for (j, outcome) in graded.outcomes.iter().enumerate() {
+                    let _ = write!(
+                        report,
+                        "{}\n      {{\"tick\": {}, \"expectation\": {}, \"actual\": {}, \"passed\": {}}}",
+                        if j == 0 { "" } else { "," },
+                        outcome.tick,
+                        json_string(&outcome.expectation),
+                        json_string(&outcome.actual),
+                        outcome.passed
+                    );
+                }
+                if !graded.outcomes.is_empty() {
+                    report += "\n    ";
+                }
+                report += "]";
+            }
+            Err(e) => {
+                let _ = write!(report, ",\n    \"error\": {}", json_string(e));
+            }
+        }
+        report += if i + 1 == grades.len() {
+            "\n  }\n"
+        } else {
+            "\n  },\n"
+        };
+    }
+    report + "]\n"
+}
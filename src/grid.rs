@@ -0,0 +1,184 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{spawn_toolbar_button, BackgroundPoints, GridPosition, PlacedPositions, Toolbar};
+
+// Largest grid in either direction, sheets and blocks are this far apart when they are simulated together
+pub const MAX_GRIDSIZE: usize = 512;
+// Grid points per side of one piece of the background, pieces that are off screen are not drawn
+const CHUNK_SIZE: usize = 64;
+const PRESETS: [GridSize; 4] = [
+    GridSize {
+        width: 50,
+        height: 36,
+    },
+    GridSize {
+        width: 100,
+        height: 72,
+    },
+    GridSize {
+        width: 200,
+        height: 144,
+    },
+    GridSize {
+        width: MAX_GRIDSIZE,
+        height: MAX_GRIDSIZE,
+    },
+];
+
+// The grid button goes through the grid sizes, from the 50 by 36 points that fit the window up to 512 by 512 to scroll around in
+// Sizes that would leave something placed outside of the grid are skipped, the size is saved with the circuit
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridSize>()
+            .add_systems(PostStartup, setup_grid_button)
+            .add_systems(Update, (cycle_grid_size, rebuild_background_points).chain());
+    }
+}
+
+// Number of grid points in each direction
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct GridSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for GridSize {
+    fn default() -> Self {
+        PRESETS[0]
+    }
+}
+
+impl GridSize {
+    pub fn contains(self, pos: GridPosition) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+
+    // Whatever a file says, the grid stays within what can be simulated
+    pub fn clamped(self) -> Self {
+        Self {
+            width: self.width.clamp(1, MAX_GRIDSIZE),
+            height: self.height.clamp(1, MAX_GRIDSIZE),
+        }
+    }
+}
+
+#[derive(Component)]
+struct GridButton;
+
+fn setup_grid_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Grid", "Grid Size", GridButton);
+    });
+}
+
+fn cycle_grid_size(
+    grid_button: Query<&Interaction, (Changed<Interaction>, With<GridButton>)>,
+    mut grid_size: ResMut<GridSize>,
+    placed: PlacedPositions,
+) {
+    if !grid_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    // A size that is not one of the presets came from a file, going on from there starts at the smallest one
+    let start = PRESETS
+        .iter()
+        .position(|size| *size == *grid_size)
+        .map_or(0, |i| i + 1);
+    let next = (0..PRESETS.len())
+        .map(|i| PRESETS[(start + i) % PRESETS.len()])
+        .filter(|size| *size != *grid_size)
+        .find(|size| placed.iter().all(|pos| size.contains(pos)));
+    match next {
+        Some(size) => {
+            info!("The grid is now {} by {} points", size.width, size.height);
+            *grid_size = size;
+        }
+        None => warn!("The circuit does not fit on a smaller grid"),
+    }
+}
+
+// The background is made of square pieces of dots, pieces of the same size share their mesh
+fn rebuild_background_points(
+    mut cmd: Commands,
+    grid_size: Res<GridSize>,
+    background_points: Query<Entity, With<BackgroundPoints>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut chunk_meshes: Local<HashMap<(usize, usize), Mesh2dHandle>>,
+    mut material: Local<Option<Handle<ColorMaterial>>>,
+) {
+    if !grid_size.is_changed() {
+        return;
+    }
+
+    let material = material
+        .get_or_insert_with(|| materials.add(ColorMaterial::from(Color::GREEN)))
+        .clone();
+    let background_points = background_points.single();
+    cmd.entity(background_points).despawn_descendants();
+    for x in (0..grid_size.width).step_by(CHUNK_SIZE) {
+        for y in (0..grid_size.height).step_by(CHUNK_SIZE) {
+            let size = (
+                CHUNK_SIZE.min(grid_size.width - x),
+                CHUNK_SIZE.min(grid_size.height - y),
+            );
+            let mesh = chunk_meshes
+                .entry(size)
+                .or_insert_with(|| meshes.add(grid_points_mesh(size.0, size.1)).into())
+                .clone();
+            cmd.spawn((
+                MaterialMesh2dBundle {
+                    mesh,
+                    material: material.clone(),
+                    transform: Transform::from_xyz(20. * x as f32, 20. * y as f32, 0.),
+                    ..Default::default()
+                },
+                Name::new(format!("Grid Points {x}, {y}")),
+            ))
+            .set_parent(background_points);
+        }
+    }
+}
+
+// Every dot of a piece of the grid in one mesh, so the grid is drawn at once instead of as an entity per point
+fn grid_points_mesh(width: usize, height: usize) -> Mesh {
+    const RADIUS: f32 = 2.5;
+    const SIDES: u32 = 16;
+
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for x in 0..width {
+        for y in 0..height {
+            let center = Vec2::new(20. * x as f32 + 10., 20. * y as f32 + 10.);
+            let first = positions.len() as u32;
+            positions.push([center.x, center.y, 0.]);
+            for i in 0..SIDES {
+                let angle = i as f32 / SIDES as f32 * std::f32::consts::TAU;
+                let corner = center + RADIUS * Vec2::from_angle(angle);
+                positions.push([corner.x, corner.y, 0.]);
+                indices.extend([first, first + 1 + i, first + 1 + (i + 1) % SIDES]);
+            }
+        }
+    }
+
+    let count = positions.len();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; count]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; count]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
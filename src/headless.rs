@@ -0,0 +1,274 @@
+use std::{path::Path, time::Duration};
+
+use bevy::{diagnostic::DiagnosticsPlugin, ecs::system::SystemState, prelude::*};
+
+use crate::{
+    blocks::Block,
+    detect_wiring_changes,
+    fuse::blow_fuses,
+    macros::MacroStep,
+    save::{read_circuit, CircuitData},
+    scenario::{read_scenario, run_scenario},
+    settle::SettleRelays,
+    sheets::Sheets,
+    simulate,
+    svg_export::SchematicParts,
+    time_switch::TimeOfDay,
+    ButtonSwitch, Light, RelayCoil, SimulationScratch, UIButton, UILight,
+};
+
+const DEFAULT_TICKS: usize = 100;
+// The same rate as the fixed update of the windowed app, timer relays see the same seconds
+const TICK_SECONDS: f32 = 1. / 20.;
+
+const EXIT_OK: i32 = 0;
+const EXIT_USAGE: i32 = 1;
+const EXIT_SHORT_CIRCUIT: i32 = 2;
+const EXIT_FAILED: i32 = 3;
+
+const USAGE: &str = "usage: relay-sim --headless <circuit file> [--ticks <count>] [--press <button id>]... [--scenario <scenario file>]";
+
+struct Options<'a> {
+    path: &'a str,
+    ticks: usize,
+    pressed: Vec<usize>,
+    scenario: Option<&'a str>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options<'_>, String> {
+    let mut args = args.iter();
+    let path = args.next().ok_or("no circuit file given")?;
+    let mut options = Options {
+        path,
+        ticks: DEFAULT_TICKS,
+        pressed: Vec::new(),
+        scenario: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--ticks" => {
+                options.ticks = value
+                    .parse()
+                    .map_err(|_| format!("{value} is not a number of ticks"))?;
+            }
+            "--press" => options.pressed.push(
+                value
+                    .trim_start_matches("-S")
+                    .parse()
+                    .map_err(|_| format!("{value} is not a button id"))?,
+            ),
+            "--scenario" => options.scenario = Some(value),
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+// relay-sim --headless circuit.ron --ticks 200 --press 1 runs the circuit without a window while -S1 is held down
+// The state after the last tick is printed one line per lamp and relay, like "-P1 lit" or "-K2 released"
+// Exits with 1 for bad arguments or an unreadable circuit and with 2 if the circuit ends up shorted
+// With --scenario the scenario decides the ticks and presses instead and 3 means one of its expectations failed
+pub fn run(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+    let circuit = match read_circuit(Path::new(options.path)) {
+        Ok(circuit) => circuit,
+        Err(e) => {
+            eprintln!("Cannot load circuit from {}: {e}", options.path);
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut simulation = Simulation::new(circuit, &options.pressed);
+
+    if let Some(scenario_path) = options.scenario {
+        let scenario = match read_scenario(Path::new(scenario_path)) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                eprintln!("Cannot load scenario from {scenario_path}: {e}");
+                return EXIT_USAGE;
+            }
+        };
+        return if run_scenario(&mut simulation, &scenario) {
+            EXIT_OK
+        } else {
+            EXIT_FAILED
+        };
+    }
+
+    for _ in 0..options.ticks {
+        simulation.tick();
+    }
+
+    let world = &mut simulation.app.world;
+    let mut lines = world
+        .query::<&UILight>()
+        .iter(world)
+        .map(|ui_light| {
+            let state = if ui_light.is_lit { "lit" } else { "off" };
+            (format!("-P{}", ui_light.id), state)
+        })
+        .collect::<Vec<_>>();
+    lines.extend(world.query::<&RelayCoil>().iter(world).map(|relay_coil| {
+        let state = if relay_coil.activated {
+            "pulled in"
+        } else {
+            "released"
+        };
+        (format!("-K{}", relay_coil.id), state)
+    }));
+    lines.sort();
+    for (name, state) in lines {
+        println!("{name} {state}");
+    }
+
+    if simulation.short_circuit() {
+        println!("short circuit");
+        EXIT_SHORT_CIRCUIT
+    } else {
+        EXIT_OK
+    }
+}
+
+// The app without a window, ticked by hand at the fixed rate
+pub struct Simulation {
+    app: App,
+    schedule: Schedule,
+    time: Time,
+    ticks: usize,
+}
+
+impl Simulation {
+    pub fn new(circuit: CircuitData, pressed: &[usize]) -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, DiagnosticsPlugin))
+            .init_resource::<SimulationScratch>()
+            .init_resource::<TimeOfDay>()
+            .init_resource::<SettleRelays>()
+            .init_resource::<Sheets>();
+
+        let world = &mut app.world;
+        circuit.spawn_for_simulation(world);
+
+        // The simulation reports lamps through the ui lamps and reads buttons from the ui buttons, so there is one of each
+        // Lamps and buttons inside blocks included, they keep their ids
+        let block_steps = world
+            .query::<&Block>()
+            .iter(world)
+            .flat_map(|block| block.steps.clone())
+            .collect::<Vec<_>>();
+        let mut light_ids = world
+            .query::<&Light>()
+            .iter(world)
+            .map(|light| light.id)
+            .chain(block_steps.iter().filter_map(|step| match step {
+                MacroStep::Light(light) => Some(light.id),
+                _ => None,
+            }))
+            .collect::<Vec<_>>();
+        light_ids.sort();
+        light_ids.dedup();
+        for id in light_ids {
+            world.spawn(UILight { id, is_lit: false });
+        }
+        let mut button_ids = world
+            .query::<&ButtonSwitch>()
+            .iter(world)
+            .map(|button| button.id)
+            .chain(block_steps.iter().filter_map(|step| match step {
+                MacroStep::Button(button) => Some(button.id),
+                _ => None,
+            }))
+            .chain(pressed.iter().copied())
+            .collect::<Vec<_>>();
+        button_ids.sort();
+        button_ids.dedup();
+        for id in button_ids {
+            world.spawn(UIButton {
+                id,
+                has_been_pressed: false,
+                maintained: true,
+                latched: pressed.contains(&id),
+            });
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((detect_wiring_changes, simulate, blow_fuses).chain());
+        Self {
+            app,
+            schedule,
+            time: Time::default(),
+            ticks: 0,
+        }
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn tick(&mut self) {
+        self.time.advance_by(Duration::from_secs_f32(TICK_SECONDS));
+        self.app.world.insert_resource(self.time);
+        self.schedule.run(&mut self.app.world);
+        self.ticks += 1;
+    }
+
+    // Held buttons stay down until they are released again
+    pub fn hold_button(&mut self, id: usize, held: bool) {
+        let world = &mut self.app.world;
+        for mut button in world.query::<&mut UIButton>().iter_mut(world) {
+            if button.id == id {
+                button.latched = held;
+            }
+        }
+    }
+
+    // None if the circuit has no such lamp
+    pub fn is_lit(&mut self, id: usize) -> Option<bool> {
+        let world = &mut self.app.world;
+        world
+            .query::<&UILight>()
+            .iter(world)
+            .find(|ui_light| ui_light.id == id)
+            .map(|ui_light| ui_light.is_lit)
+    }
+
+    // None if the circuit has no coil of that relay
+    pub fn is_pulled_in(&mut self, id: usize) -> Option<bool> {
+        let world = &mut self.app.world;
+        world
+            .query::<&RelayCoil>()
+            .iter(world)
+            .find(|relay_coil| relay_coil.id == id)
+            .map(|relay_coil| relay_coil.activated)
+    }
+
+    // The schematic of every sheet side by side, without annotations and black boxes since those are not spawned here
+    pub fn schematic_svg(&mut self, scale: f32) -> Option<String> {
+        let mut state = SystemState::<SchematicParts>::new(&mut self.app.world);
+        state.get(&self.app.world).svg(scale)
+    }
+
+    pub fn net_count(&self) -> usize {
+        self.app
+            .world
+            .resource::<SimulationScratch>()
+            .solver
+            .net_count()
+    }
+
+    pub fn short_circuit(&self) -> bool {
+        self.app
+            .world
+            .resource::<SimulationScratch>()
+            .solver
+            .short_circuit()
+    }
+}
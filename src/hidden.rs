@@ -0,0 +1,193 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, spawn_toolbar_button, CurrentlyPlacing,
+    GridPosition, MainCamera, Toolbar,
+};
+
+const BOX_COLOR: Color = Color::rgb(0.12, 0.12, 0.12);
+
+// Black boxes for exercises, everything strictly inside a box is covered while it keeps working
+// The border of a box stays visible, those points are the terminal strips where the hidden part can be measured
+// In box mode (box button) two clicks place a box, right clicking a box removes it again
+pub struct HiddenRegionPlugin;
+
+impl Plugin for HiddenRegionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_box_button).add_systems(
+            Update,
+            (
+                start_box_placement,
+                handle_box_placement,
+                update_box_sprites,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct HiddenRegion {
+    min: GridPosition,
+    max: GridPosition,
+}
+
+impl HiddenRegion {
+    pub fn hides(&self, pos: GridPosition) -> bool {
+        pos.x > self.min.x && pos.x < self.max.x && pos.y > self.min.y && pos.y < self.max.y
+    }
+
+    pub fn corners(&self) -> (GridPosition, GridPosition) {
+        (self.min, self.max)
+    }
+
+    fn contains(&self, pos: GridPosition) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+}
+
+#[derive(Component)]
+struct BoxButton;
+
+pub fn spawn_hidden_region(cmd: &mut Commands, region: HiddenRegion) -> Entity {
+    cmd.spawn((SpatialBundle::default(), Name::new("Hidden Region"), region))
+        .id()
+}
+
+fn setup_box_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Box", "Hidden Region", BoxButton);
+    });
+}
+
+fn start_box_placement(
+    box_button: Query<&Interaction, (Changed<Interaction>, With<BoxButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if box_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::HiddenRegion;
+    }
+}
+
+fn handle_box_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut box_start: Local<Option<GridPosition>>,
+    regions: Query<(Entity, &HiddenRegion)>,
+    mut gizmos: Gizmos,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::HiddenRegion) {
+        *box_start = None;
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    if let (Some(start), Some(end)) = (*box_start, mouse_grid) {
+        let start = grid_to_world(start);
+        let end = grid_to_world(end);
+        gizmos.rect_2d((start + end) / 2., 0., (end - start).abs(), Color::GRAY);
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        if box_start.take().is_some() {
+            return;
+        }
+        match mouse_grid.and_then(|pos| regions.iter().find(|(_, region)| region.contains(pos))) {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(mouse_grid) = mouse_grid else {
+        return;
+    };
+
+    let Some(start) = box_start.take() else {
+        *box_start = Some(mouse_grid);
+        return;
+    };
+
+    let region = HiddenRegion {
+        min: GridPosition {
+            x: start.x.min(mouse_grid.x),
+            y: start.y.min(mouse_grid.y),
+        },
+        max: GridPosition {
+            x: start.x.max(mouse_grid.x),
+            y: start.y.max(mouse_grid.y),
+        },
+    };
+    // Without any point strictly inside there would be nothing to hide
+    if region.max.x - region.min.x < 2 || region.max.y - region.min.y < 2 {
+        warn!("A hidden region needs at least one grid point inside its border");
+        return;
+    }
+
+    spawn_hidden_region(&mut cmd, region);
+}
+
+// The covering sprite is made from the region, so loading only has to spawn the region itself
+fn update_box_sprites(
+    mut cmd: Commands,
+    regions: Query<(Entity, &HiddenRegion), Added<HiddenRegion>>,
+) {
+    for (e, region) in regions.iter() {
+        // Covers the inner points up to half a cell short of the border points
+        let min = grid_to_world(region.min) + Vec2::splat(10.);
+        let max = grid_to_world(region.max) - Vec2::splat(10.);
+
+        cmd.entity(e).with_children(|root| {
+            root.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: BOX_COLOR,
+                        custom_size: Some(max - min),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(((min + max) / 2.).extend(8.)),
+                    ..Default::default()
+                },
+                Name::new("Hidden Region Box"),
+            ));
+            root.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "?",
+                        TextStyle {
+                            font_size: 32.,
+                            color: Color::rgb(0.5, 0.5, 0.5),
+                            ..Default::default()
+                        },
+                    ),
+                    transform: Transform::from_translation(((min + max) / 2.).extend(9.)),
+                    ..Default::default()
+                },
+                Name::new("Hidden Region Text"),
+            ));
+        });
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use crate::{
+    is_running,
+    keybindings::{Action, KeyBindings},
+    simulate, RelayCoil, SimulationScratch, UILight,
+};
+
+// 10 seconds of ticks at 20 hz
+pub const HISTORY_LENGTH: usize = 200;
+
+// Records the last few seconds of simulation, while paused (space) the timeline at the bottom can be scrubbed with the mouse or the arrow keys
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationHistory>()
+            .add_systems(Startup, setup_scrubber)
+            .add_systems(
+                FixedUpdate,
+                record_history.after(simulate).run_if(is_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_pause,
+                    step_history,
+                    drag_scrubber,
+                    restore_history,
+                    update_scrubber,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Everything that is visible about a single tick
+pub struct SimulationSnapshot {
+    pub pressed_button_ids: Vec<usize>,
+    pub activated_relay_ids: Vec<usize>,
+    pub lit_light_ids: Vec<usize>,
+}
+
+#[derive(Resource, Default)]
+pub struct SimulationHistory {
+    pub snapshots: VecDeque<SimulationSnapshot>,
+    // Index into snapshots that is currently shown, None while the simulation is running
+    pub cursor: Option<usize>,
+}
+
+#[derive(Component)]
+struct Scrubber;
+
+#[derive(Component)]
+struct ScrubberBar;
+
+#[derive(Component)]
+struct ScrubberHandle;
+
+#[derive(Component)]
+struct ScrubberText;
+
+fn setup_scrubber(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(290.),
+                right: Val::Px(10.),
+                bottom: Val::Px(10.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(5),
+            ..Default::default()
+        },
+        Interaction::default(),
+        Name::new("History Scrubber"),
+        Scrubber,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("History Scrubber Text"),
+            ScrubberText,
+        ));
+
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Px(14.),
+                    margin: UiRect::top(Val::Px(5.)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.3, 0.3, 0.3)),
+                ..Default::default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            Name::new("History Scrubber Bar"),
+            ScrubberBar,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(6.),
+                        height: Val::Percent(100.),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.9, 0.9, 0.9)),
+                    ..Default::default()
+                },
+                Name::new("History Scrubber Handle"),
+                ScrubberHandle,
+            ));
+        });
+    });
+}
+
+fn record_history(
+    mut history: ResMut<SimulationHistory>,
+    scratch: Res<SimulationScratch>,
+    relay_coils: Query<&RelayCoil>,
+    ui_lights: Query<&UILight>,
+) {
+    if history.snapshots.len() == HISTORY_LENGTH {
+        history.snapshots.pop_front();
+    }
+
+    history.snapshots.push_back(SimulationSnapshot {
+        pressed_button_ids: scratch.active_button_ids.clone(),
+        activated_relay_ids: relay_coils
+            .iter()
+            .filter(|relay_coil| relay_coil.activated)
+            .map(|relay_coil| relay_coil.id)
+            .collect(),
+        lit_light_ids: ui_lights
+            .iter()
+            .filter(|ui_light| ui_light.is_lit)
+            .map(|ui_light| ui_light.id)
+            .collect(),
+    });
+}
+
+fn toggle_pause(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut time: ResMut<Time<Virtual>>,
+    mut history: ResMut<SimulationHistory>,
+) {
+    if !bindings.just_pressed(&keys, Action::PauseSimulation) {
+        return;
+    }
+
+    // Both ways start at the newest state, when unpausing this puts it back so the simulation continues from where it was paused
+    history.cursor = history.snapshots.len().checked_sub(1);
+    if time.is_paused() {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+fn step_history(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time<Virtual>>,
+    mut history: ResMut<SimulationHistory>,
+) {
+    if !time.is_paused() {
+        return;
+    }
+    let Some(cursor) = history.cursor else {
+        return;
+    };
+
+    if bindings.just_pressed(&keys, Action::StepBack) {
+        history.cursor = Some(cursor.saturating_sub(1));
+    } else if bindings.just_pressed(&keys, Action::StepForward) {
+        history.cursor = Some((cursor + 1).min(history.snapshots.len() - 1));
+    }
+}
+
+fn drag_scrubber(
+    mouse_button: Res<Input<MouseButton>>,
+    time: Res<Time<Virtual>>,
+    bar: Query<&RelativeCursorPosition, With<ScrubberBar>>,
+    mut history: ResMut<SimulationHistory>,
+    mut dragging: Local<bool>,
+) {
+    if !mouse_button.pressed(MouseButton::Left) || !time.is_paused() {
+        *dragging = false;
+        return;
+    }
+
+    let Ok(relative_position) = bar.get_single() else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        *dragging = relative_position.mouse_over();
+    }
+
+    if !*dragging || history.snapshots.is_empty() {
+        return;
+    }
+
+    let Some(position) = relative_position.normalized else {
+        return;
+    };
+    let last = history.snapshots.len() - 1;
+    history.cursor = Some(((position.x.clamp(0., 1.) * last as f32).round() as usize).min(last));
+}
+
+// Shows the selected snapshot in the world, this also puts the newest state back after unpausing
+fn restore_history(
+    mut history: ResMut<SimulationHistory>,
+    time: Res<Time<Virtual>>,
+    mut relay_coils: Query<&mut RelayCoil>,
+    mut ui_lights: Query<&mut UILight>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+    let Some(cursor) = history.cursor else {
+        return;
+    };
+
+    let snapshot = &history.snapshots[cursor];
+    for mut relay_coil in relay_coils.iter_mut() {
+        relay_coil.activated = snapshot.activated_relay_ids.contains(&relay_coil.id);
+    }
+    for mut ui_light in ui_lights.iter_mut() {
+        ui_light.is_lit = snapshot.lit_light_ids.contains(&ui_light.id);
+    }
+
+    if !time.is_paused() {
+        history.cursor = None;
+    }
+}
+
+fn update_scrubber(
+    time: Res<Time<Virtual>>,
+    history: Res<SimulationHistory>,
+    mut scrubber: Query<&mut Visibility, With<Scrubber>>,
+    mut handle: Query<&mut Style, With<ScrubberHandle>>,
+    mut text: Query<&mut Text, With<ScrubberText>>,
+) {
+    for mut visibility in scrubber.iter_mut() {
+        *visibility = if time.is_paused() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if !time.is_paused() || !history.is_changed() {
+        return;
+    }
+
+    let Some(cursor) = history.cursor else {
+        for mut text in text.iter_mut() {
+            text.sections[0].value = "Paused, no history yet".to_string();
+        }
+        return;
+    };
+    let last = history.snapshots.len() - 1;
+    let snapshot = &history.snapshots[cursor];
+
+    for mut style in handle.iter_mut() {
+        style.left = Val::Percent(if last == 0 {
+            100.
+        } else {
+            cursor as f32 / last as f32 * 100.
+        });
+    }
+
+    let ids = |prefix: &str, ids: &[usize]| {
+        if ids.is_empty() {
+            "none".to_string()
+        } else {
+            ids.iter()
+                .map(|id| format!("-{prefix}{id}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!(
+            "Paused, tick {} of {} (←/→ to step)   Pressed: {}   Energized: {}   Lit: {}",
+            cursor as isize - last as isize,
+            last,
+            ids("S", &snapshot.pressed_button_ids),
+            ids("K", &snapshot.activated_relay_ids),
+            ids("P", &snapshot.lit_light_ids),
+        );
+    }
+}
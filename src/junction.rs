@@ -0,0 +1,125 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use crate::{grid_to_world, wire_contains, CircuitHandles, GridPosition, Wire};
+
+// Wires ending somewhere along another wire are connected there, the solver splits the other wire at that point
+// Those points, and points where three or more wire ends meet, get a junction dot so the connection can be seen
+// Wires crossing each other without either ending there are not connected, the horizontal one gets a gap around the other
+pub struct JunctionPlugin;
+
+impl Plugin for JunctionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_junction_visuals);
+    }
+}
+
+#[derive(Component)]
+struct JunctionVisual;
+
+fn junctions(wires: &[&Wire]) -> Vec<GridPosition> {
+    let ends = wires
+        .iter()
+        .flat_map(|wire| [wire.first, wire.second])
+        .collect::<Vec<_>>();
+
+    let mut junctions = ends
+        .iter()
+        .copied()
+        .filter(|end| {
+            let on_span = wires
+                .iter()
+                .any(|wire| wire_contains(wire, end) && wire.first != *end && wire.second != *end);
+            on_span || ends.iter().filter(|other| *other == end).count() >= 3
+        })
+        .collect::<Vec<_>>();
+    junctions.sort_by_key(|pos| (pos.x, pos.y));
+    junctions.dedup();
+    junctions
+}
+
+// Where a horizontal and a vertical wire pass each other with no wire ending there
+fn crossings(wires: &[&Wire]) -> Vec<GridPosition> {
+    let is_end = |pos: &GridPosition| {
+        wires
+            .iter()
+            .any(|wire| wire.first == *pos || wire.second == *pos)
+    };
+    let horizontal = wires
+        .iter()
+        .filter(|wire| wire.first.y == wire.second.y && wire.first.x != wire.second.x);
+    let vertical = wires
+        .iter()
+        .filter(|wire| wire.first.x == wire.second.x && wire.first.y != wire.second.y);
+
+    horizontal
+        .flat_map(|horizontal| {
+            vertical.clone().filter_map(|vertical| {
+                let pos = GridPosition {
+                    x: vertical.first.x,
+                    y: horizontal.first.y,
+                };
+                (wire_contains(horizontal, &pos) && wire_contains(vertical, &pos)).then_some(pos)
+            })
+        })
+        .filter(|pos| !is_end(pos))
+        .collect()
+}
+
+// Made again from all wires whenever one is placed, moved or removed
+fn update_junction_visuals(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    changed: Query<(), Changed<Wire>>,
+    mut removed: RemovedComponents<Wire>,
+    wires: Query<&Wire>,
+    visuals: Query<Entity, With<JunctionVisual>>,
+    mut handles: Local<Option<(Handle<Mesh>, Handle<Mesh>, Handle<ColorMaterial>)>>,
+) {
+    if changed.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+
+    for visual in visuals.iter() {
+        cmd.entity(visual).despawn();
+    }
+    let (dot_mesh, gap_mesh, gap_material) = handles.get_or_insert_with(|| {
+        (
+            meshes.add(shape::Circle::new(6.).into()),
+            meshes.add(shape::Quad::new(Vec2::new(5., 6.)).into()),
+            materials.add(ColorMaterial::from(Color::BLACK)),
+        )
+    });
+
+    let wires = wires.iter().collect::<Vec<_>>();
+    for junction in junctions(&wires) {
+        cmd.spawn((
+            MaterialMesh2dBundle {
+                mesh: dot_mesh.clone().into(),
+                material: circuit_material.wire_material.clone(),
+                transform: Transform::from_translation(grid_to_world(junction).extend(2.6)),
+                ..Default::default()
+            },
+            Name::new("Junction Dot"),
+            JunctionVisual,
+        ));
+    }
+    // Covers the horizontal wire on both sides of the vertical one, which stays whole
+    for crossing in crossings(&wires) {
+        for offset in [-4.5, 4.5] {
+            cmd.spawn((
+                MaterialMesh2dBundle {
+                    mesh: gap_mesh.clone().into(),
+                    material: gap_material.clone(),
+                    transform: Transform::from_translation(
+                        (grid_to_world(crossing) + Vec2::new(offset, 0.)).extend(2.55),
+                    ),
+                    ..Default::default()
+                },
+                Name::new("Crossing Gap"),
+                JunctionVisual,
+            ));
+        }
+    }
+}
@@ -0,0 +1,530 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{platform, spawn_toolbar_button, Toolbar};
+
+const KEYBINDINGS_PATH: &str = "keybindings.ron";
+
+// Keys that can be bound to an action, the config file refers to them by these names
+const BINDABLE_KEYS: [KeyCode; 62] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Snapshot,
+    KeyCode::Pause,
+];
+
+// Every keyboard shortcut goes through these bindings, the keys button opens a screen to change them
+// Changes are written to keybindings.ron in the working directory and read again on the next start
+pub struct KeyBindingsPlugin;
+
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_keybindings())
+            .init_resource::<Rebinding>()
+            .add_systems(Startup, setup_keybindings_panel)
+            .add_systems(PostStartup, setup_keybindings_button)
+            // The new key must not also trigger whatever it was bound to before
+            .add_systems(PreUpdate, capture_rebind_key.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    toggle_keybindings_panel,
+                    handle_keybinding_rows,
+                    update_keybindings_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    TogglePerfOverlay,
+    PauseSimulation,
+    StepBack,
+    StepForward,
+    RegionCapture,
+    CaptureScale2,
+    CaptureScale4,
+    Save,
+    Load,
+    ZoomToFit,
+    AutoRoute,
+    Measure,
+    WireLabel,
+    ComponentComment,
+    CopyImage,
+    CommandPalette,
+    Copy,
+    Paste,
+    NextTab,
+    Undo,
+    Redo,
+    Rotate,
+    Delete,
+    Duplicate,
+    Screenshot,
+    StepTick,
+    ToggleBreakpoint,
+}
+
+impl Action {
+    pub const ALL: [Action; 27] = [
+        Action::TogglePerfOverlay,
+        Action::PauseSimulation,
+        Action::StepBack,
+        Action::StepForward,
+        Action::RegionCapture,
+        Action::CaptureScale2,
+        Action::CaptureScale4,
+        Action::Save,
+        Action::Load,
+        Action::ZoomToFit,
+        Action::AutoRoute,
+        Action::Measure,
+        Action::WireLabel,
+        Action::ComponentComment,
+        Action::CopyImage,
+        Action::CommandPalette,
+        Action::Copy,
+        Action::Paste,
+        Action::NextTab,
+        Action::Undo,
+        Action::Redo,
+        Action::Rotate,
+        Action::Delete,
+        Action::Duplicate,
+        Action::Screenshot,
+        Action::StepTick,
+        Action::ToggleBreakpoint,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::TogglePerfOverlay => "Performance overlay",
+            Action::PauseSimulation => "Pause / resume",
+            Action::StepBack => "Step back",
+            Action::StepForward => "Step forward",
+            Action::RegionCapture => "Region capture",
+            Action::CaptureScale2 => "Capture at 2x",
+            Action::CaptureScale4 => "Capture at 4x",
+            Action::Save => "Save (with Ctrl)",
+            Action::Load => "Load (with Ctrl)",
+            Action::ZoomToFit => "Zoom to fit",
+            Action::AutoRoute => "Auto route",
+            Action::Measure => "Measure",
+            Action::WireLabel => "Edit wire label",
+            Action::ComponentComment => "Edit component comment",
+            Action::CopyImage => "Copy image (with Ctrl+Shift)",
+            Action::CommandPalette => "Command palette (with Ctrl)",
+            Action::Copy => "Copy selection (with Ctrl)",
+            Action::Paste => "Paste (with Ctrl)",
+            Action::NextTab => "Next tab (with Ctrl)",
+            Action::Undo => "Undo (with Ctrl)",
+            Action::Redo => "Redo (with Ctrl)",
+            Action::Rotate => "Turn placed components",
+            Action::Delete => "Delete mode",
+            Action::Duplicate => "Duplicate selection (with Ctrl)",
+            Action::Screenshot => "Save the schematic as png",
+            Action::StepTick => "Run one tick while paused",
+            Action::ToggleBreakpoint => "Breakpoint on the hovered coil or lamp",
+        }
+    }
+
+    // Keys that have to be held together with the bound key, the same as in the names above
+    pub fn modifiers(self) -> &'static [KeyCode] {
+        match self {
+            Action::Save
+            | Action::Load
+            | Action::CommandPalette
+            | Action::Copy
+            | Action::Paste
+            | Action::NextTab
+            | Action::Undo
+            | Action::Redo
+            | Action::Duplicate => &[KeyCode::ControlLeft],
+            Action::CopyImage => &[KeyCode::ControlLeft, KeyCode::ShiftLeft],
+            _ => &[],
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::TogglePerfOverlay => KeyCode::F3,
+            Action::PauseSimulation => KeyCode::Space,
+            Action::StepBack => KeyCode::Left,
+            Action::StepForward => KeyCode::Right,
+            Action::RegionCapture => KeyCode::Snapshot,
+            Action::CaptureScale2 => KeyCode::Key2,
+            Action::CaptureScale4 => KeyCode::Key4,
+            Action::Save => KeyCode::S,
+            Action::Load => KeyCode::O,
+            Action::ZoomToFit => KeyCode::Home,
+            Action::AutoRoute => KeyCode::R,
+            Action::Measure => KeyCode::M,
+            Action::WireLabel => KeyCode::L,
+            Action::ComponentComment => KeyCode::N,
+            Action::CopyImage => KeyCode::C,
+            Action::CommandPalette => KeyCode::P,
+            Action::Copy => KeyCode::C,
+            Action::Paste => KeyCode::V,
+            Action::NextTab => KeyCode::Tab,
+            Action::Undo => KeyCode::Z,
+            Action::Redo => KeyCode::Y,
+            Action::Rotate => KeyCode::T,
+            Action::Delete => KeyCode::Delete,
+            Action::Duplicate => KeyCode::D,
+            Action::Screenshot => KeyCode::F12,
+            Action::StepTick => KeyCode::F10,
+            Action::ToggleBreakpoint => KeyCode::F9,
+        }
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct KeyBindings {
+    keys: HashMap<Action, KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys
+            .get(&action)
+            .copied()
+            .unwrap_or(action.default_key())
+    }
+
+    pub fn just_pressed(&self, keys: &Input<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.key(action))
+    }
+
+    // Ctrl+C and Ctrl+Shift+C can share a key, but a key without modifiers also fires while they are held
+    fn clashes(&self, action: Action) -> bool {
+        Action::ALL.into_iter().any(|other| {
+            other != action
+                && self.key(other) == self.key(action)
+                && (other.modifiers() == action.modifiers()
+                    || other.modifiers().is_empty()
+                    || action.modifiers().is_empty())
+        })
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|key| key_name(*key) == name)
+}
+
+// Actions missing from the file or bound to unknown keys keep their default
+fn load_keybindings() -> KeyBindings {
+    let mut bindings = KeyBindings::default();
+    if !platform::file_exists(Path::new(KEYBINDINGS_PATH)) {
+        return bindings;
+    }
+
+    let file = match platform::read_file(Path::new(KEYBINDINGS_PATH)).and_then(|text| {
+        ron::from_str::<BTreeMap<Action, String>>(&text).map_err(|e| e.to_string())
+    }) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Cannot read keybindings from {KEYBINDINGS_PATH}, using the defaults: {e}");
+            return bindings;
+        }
+    };
+
+    for (action, name) in file {
+        match key_from_name(&name) {
+            Some(key) => {
+                bindings.keys.insert(action, key);
+            }
+            None => warn!(
+                "Unknown key {name} for {}, using the default",
+                action.name()
+            ),
+        }
+    }
+
+    bindings
+}
+
+fn save_keybindings(bindings: &KeyBindings) {
+    let file = Action::ALL
+        .into_iter()
+        .map(|action| (action, key_name(bindings.key(action))))
+        .collect::<BTreeMap<_, _>>();
+
+    let result = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| platform::write_file(Path::new(KEYBINDINGS_PATH), &text));
+
+    if let Err(e) = result {
+        error!("Cannot save keybindings to {KEYBINDINGS_PATH}: {e}");
+    }
+}
+
+#[derive(Resource, Default)]
+struct Rebinding {
+    // The action that gets the next pressed key
+    action: Option<Action>,
+}
+
+#[derive(Component)]
+struct KeyBindingsButton;
+
+#[derive(Component)]
+struct KeyBindingsPanel;
+
+#[derive(Component)]
+enum KeyBindingsRow {
+    Action(Action),
+    ResetDefaults,
+}
+
+fn setup_keybindings_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Keys", "Key Bindings", KeyBindingsButton);
+    });
+}
+
+fn setup_keybindings_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                left: Val::Px(290.),
+                width: Val::Px(360.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Key Bindings Panel"),
+        KeyBindingsPanel,
+    ))
+    .with_children(|root| {
+        let rows = Action::ALL
+            .into_iter()
+            .map(KeyBindingsRow::Action)
+            .chain([KeyBindingsRow::ResetDefaults]);
+        for row in rows {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(3.)),
+                        margin: UiRect::all(Val::Px(1.)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                },
+                Name::new("Key Binding Row"),
+                row,
+            ))
+            .with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..Default::default()
+                        },
+                    ),
+                    Name::new("Key Binding Text"),
+                ));
+            });
+        }
+    });
+}
+
+fn toggle_keybindings_panel(
+    keybindings_button: Query<&Interaction, (Changed<Interaction>, With<KeyBindingsButton>)>,
+    mut panel: Query<&mut Visibility, With<KeyBindingsPanel>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if !keybindings_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            rebinding.action = None;
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_keybinding_rows(
+    rows: Query<(&Interaction, &KeyBindingsRow), Changed<Interaction>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match row {
+            KeyBindingsRow::Action(action) => rebinding.action = Some(*action),
+            KeyBindingsRow::ResetDefaults => {
+                *bindings = KeyBindings::default();
+                rebinding.action = None;
+                save_keybindings(&bindings);
+            }
+        }
+    }
+}
+
+fn capture_rebind_key(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        rebinding.action = None;
+    } else if let Some(key) = keys
+        .get_just_pressed()
+        .find(|key| BINDABLE_KEYS.contains(key))
+        .copied()
+    {
+        bindings.keys.insert(action, key);
+        rebinding.action = None;
+        save_keybindings(&bindings);
+    }
+
+    keys.reset_all();
+}
+
+fn update_keybindings_panel(
+    bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    rows: Query<(&KeyBindingsRow, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (row, children) in rows.iter() {
+        let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) else {
+            continue;
+        };
+
+        let (value, color) = match row {
+            KeyBindingsRow::Action(action) if rebinding.action == Some(*action) => (
+                format!("{}: press a key (Esc to cancel)", action.name()),
+                Color::rgb(0.6, 0.8, 1.),
+            ),
+            // Two actions on the same key both fire, so those are shown in red
+            KeyBindingsRow::Action(action) if bindings.clashes(*action) => (
+                format!(
+                    "{}: {} (clashes)",
+                    action.name(),
+                    key_name(bindings.key(*action))
+                ),
+                Color::rgb(1., 0.4, 0.4),
+            ),
+            KeyBindingsRow::Action(action) => (
+                format!("{}: {}", action.name(), key_name(bindings.key(*action))),
+                Color::rgb(0.9, 0.9, 0.9),
+            ),
+            KeyBindingsRow::ResetDefaults => {
+                ("Reset to defaults".to_string(), Color::rgb(0.9, 0.9, 0.9))
+            }
+        };
+        text.sections[0].value = value;
+        text.sections[0].style.color = color;
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    grid::GridSize, spawn_button_visuals, spawn_light_visuals, spawn_relay_coil_visuals,
+    spawn_relay_switch_visuals, spawn_wire_visuals, ButtonSwitch, CircuitHandles, ComponentComment,
+    GridPosition, Light, Power, PowerType, RelayCoil, RelaySwitch, SwitchType, Wire, WireLabel,
+};
+
+// Registers the circuit components for reflection, so the inspector can change them while the circuit runs
+// Edited ids, switch types and terminal positions are checked and the visuals are made again to match
+pub struct LiveEditPlugin;
+
+impl Plugin for LiveEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GridPosition>()
+            .register_type::<Wire>()
+            .register_type::<WireLabel>()
+            .register_type::<Light>()
+            .register_type::<ButtonSwitch>()
+            .register_type::<RelayCoil>()
+            .register_type::<RelaySwitch>()
+            .register_type::<SwitchType>()
+            .register_type::<ComponentComment>()
+            .register_type::<Power>()
+            .register_type::<PowerType>()
+            .add_systems(
+                PostUpdate,
+                (
+                    rebuild_edited::<Wire>,
+                    rebuild_edited::<Light>,
+                    rebuild_edited::<ButtonSwitch>,
+                    rebuild_edited::<RelayCoil>,
+                    rebuild_edited::<RelaySwitch>,
+                    move_edited_power,
+                ),
+            );
+    }
+}
+
+// Everything about an element that its visuals are made from
+#[derive(Clone, Copy, PartialEq)]
+struct Shape {
+    id: usize,
+    typ: Option<SwitchType>,
+    first: GridPosition,
+    second: GridPosition,
+}
+
+trait Editable: Component {
+    // Components always span three grid points in a column, wires only have to stay straight
+    const IS_COMPONENT: bool = true;
+
+    fn shape(&self) -> Shape;
+    fn set_shape(&mut self, shape: Shape);
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity);
+    fn label(&self) -> String;
+}
+
+impl Editable for Wire {
+    const IS_COMPONENT: bool = false;
+
+    fn shape(&self) -> Shape {
+        Shape {
+            id: 0,
+            typ: None,
+            first: self.first,
+            second: self.second,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        self.first = shape.first;
+        self.second = shape.second;
+    }
+
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity) {
+        spawn_wire_visuals(cmd, circuit_material, parent, self);
+    }
+
+    fn label(&self) -> String {
+        format!(
+            "Wire {}, {} to {}, {}",
+            self.first.x, self.first.y, self.second.x, self.second.y
+        )
+    }
+}
+
+impl Editable for Light {
+    fn shape(&self) -> Shape {
+        Shape {
+            id: self.id,
+            typ: None,
+            first: self.top,
+            second: self.bottom,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        self.id = shape.id;
+        self.top = shape.first;
+        self.bottom = shape.second;
+    }
+
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity) {
+        spawn_light_visuals(cmd, circuit_material, parent, self, self.label());
+    }
+
+    fn label(&self) -> String {
+        format!("-P{}", self.id)
+    }
+}
+
+impl Editable for ButtonSwitch {
+    fn shape(&self) -> Shape {
+        Shape {
+            id: self.id,
+            typ: Some(self.typ),
+            first: self.top,
+            second: self.bottom,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        self.id = shape.id;
+        self.typ = shape.typ.unwrap_or(self.typ);
+        self.top = shape.first;
+        self.bottom = shape.second;
+    }
+
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity) {
+        spawn_button_visuals(cmd, circuit_material, parent, self, self.label());
+    }
+
+    fn label(&self) -> String {
+        format!("-S{}", self.id)
+    }
+}
+
+impl Editable for RelayCoil {
+    fn shape(&self) -> Shape {
+        Shape {
+            id: self.id,
+            typ: None,
+            first: self.top,
+            second: self.bottom,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        self.id = shape.id;
+        self.top = shape.first;
+        self.bottom = shape.second;
+    }
+
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity) {
+        spawn_relay_coil_visuals(cmd, circuit_material, parent, self, self.label());
+    }
+
+    fn label(&self) -> String {
+        format!("-K{}", self.id)
+    }
+}
+
+impl Editable for RelaySwitch {
+    fn shape(&self) -> Shape {
+        Shape {
+            id: self.id,
+            typ: Some(self.typ),
+            first: self.top,
+            second: self.bottom,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        self.id = shape.id;
+        self.typ = shape.typ.unwrap_or(self.typ);
+        self.top = shape.first;
+        self.bottom = shape.second;
+    }
+
+    fn spawn_visuals(&self, cmd: &mut Commands, circuit_material: &CircuitHandles, parent: Entity) {
+        spawn_relay_switch_visuals(cmd, circuit_material, parent, self, self.label());
+    }
+
+    fn label(&self) -> String {
+        format!("-K{}", self.id)
+    }
+}
+
+// Makes an edited shape consistent again, moving one terminal of a component drags the other one along
+// The component stays upright or turned like it was before
+fn settle_shape(
+    previous: Shape,
+    mut shape: Shape,
+    is_component: bool,
+    grid_size: &GridSize,
+) -> Result<Shape, String> {
+    if is_component {
+        let upright = previous.first.x == previous.second.x;
+        if shape.first != previous.first {
+            shape.second = if upright {
+                GridPosition {
+                    x: shape.first.x,
+                    y: shape.first.y.checked_sub(2).ok_or("the top is too low")?,
+                }
+            } else {
+                GridPosition {
+                    x: shape
+                        .first
+                        .x
+                        .checked_sub(2)
+                        .ok_or("the top is too far left")?,
+                    y: shape.first.y,
+                }
+            };
+        } else if shape.second != previous.second {
+            shape.first = if upright {
+                GridPosition {
+                    x: shape.second.x,
+                    y: shape.second.y + 2,
+                }
+            } else {
+                GridPosition {
+                    x: shape.second.x + 2,
+                    y: shape.second.y,
+                }
+            };
+        }
+    } else if shape.first.x != shape.second.x && shape.first.y != shape.second.y {
+        return Err("wires can only run horizontally or vertically".to_string());
+    }
+
+    if !grid_size.contains(shape.first) || !grid_size.contains(shape.second) {
+        return Err("it would leave the grid".to_string());
+    }
+    Ok(shape)
+}
+
+fn rebuild_edited<T: Editable>(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    grid_size: Res<GridSize>,
+    mut elements: Query<(Entity, &mut T, Option<&WireLabel>), Changed<T>>,
+    mut shapes: Local<HashMap<Entity, Shape>>,
+) {
+    for (e, mut element, wire_label) in elements.iter_mut() {
+        let shape = element.shape();
+        // Newly placed elements already have fitting visuals, so do elements the simulation touched
+        let previous = match shapes.insert(e, shape) {
+            Some(previous) if !element.is_added() && previous != shape => previous,
+            _ => continue,
+        };
+
+        let settled = match settle_shape(previous, shape, T::IS_COMPONENT, &grid_size) {
+            Ok(settled) => settled,
+            Err(reason) => {
+                warn!("Cannot apply the edit to {}, {reason}", element.label());
+                previous
+            }
+        };
+        if settled != shape {
+            element.set_shape(settled);
+            shapes.insert(e, settled);
+        }
+        if settled == previous {
+            continue;
+        }
+
+        cmd.entity(e)
+            .despawn_descendants()
+            .insert(Name::new(element.label()));
+        element.spawn_visuals(&mut cmd, &circuit_material, e);
+        // The label text is one of the children, inserting it again makes it anew
+        if let Some(wire_label) = wire_label {
+            cmd.entity(e).insert(wire_label.clone());
+        }
+    }
+}
+
+fn move_edited_power(
+    mut powers: Query<(&GridPosition, &mut Transform), (With<Power>, Changed<GridPosition>)>,
+) {
+    for (pos, mut transform) in powers.iter_mut() {
+        transform.translation.x = 20. * pos.x as f32 + 10.;
+        transform.translation.y = 20. * pos.y as f32 + 10.;
+    }
+}
@@ -0,0 +1,396 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fuse::{carries_current, Fuse},
+    is_running, simulate, spawn_toolbar_button, RelayCoil, SimulationScratch, Toolbar, UILight,
+};
+
+const CURRENT_STEP: u32 = 5;
+const LIMIT_STEP: u32 = 50;
+const GAUGE_WIDTH: f32 = 200.;
+
+// The supply button shows a gauge of how much current all lit lamps and pulled in coils draw together
+// Every lamp and coil draws the nominal current set for its kind, going over the limit is reported and shown in red
+// A tick that draws more than the limit blows the fuses carrying current, without any the breaker of the supply trips
+// Nothing is powered then until the breaker is reset in the supply panel
+pub struct LoadMeterPlugin;
+
+impl Plugin for LoadMeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SupplySettings>()
+            .init_resource::<SupplyLoad>()
+            .init_resource::<BreakerTripped>()
+            .add_systems(Startup, setup_load_panel)
+            .add_systems(PostStartup, setup_supply_button)
+            .add_systems(FixedUpdate, trip_breaker.after(simulate).run_if(is_running))
+            .add_systems(
+                Update,
+                (
+                    toggle_load_panel,
+                    handle_setting_buttons,
+                    handle_breaker_reset,
+                    measure_supply_load,
+                    update_load_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Saved with the circuit, all values in milliamperes
+#[derive(Resource, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SupplySettings {
+    pub lamp_current: u32,
+    pub coil_current: u32,
+    pub limit: u32,
+}
+
+impl Default for SupplySettings {
+    fn default() -> Self {
+        Self {
+            lamp_current: 40,
+            coil_current: 25,
+            limit: 500,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SupplyLoad {
+    pub current: u32,
+    pub overloaded: bool,
+}
+
+// Set by an overload, the simulation leaves the supply out until it is reset
+#[derive(Resource, Default)]
+pub struct BreakerTripped(pub bool);
+
+#[derive(Clone, Copy)]
+enum Setting {
+    LampCurrent,
+    CoilCurrent,
+    Limit,
+}
+
+impl Setting {
+    fn name(self) -> &'static str {
+        match self {
+            Setting::LampCurrent => "Lamp",
+            Setting::CoilCurrent => "Coil",
+            Setting::Limit => "Limit",
+        }
+    }
+
+    fn step(self) -> u32 {
+        match self {
+            Setting::Limit => LIMIT_STEP,
+            _ => CURRENT_STEP,
+        }
+    }
+
+    fn value(self, settings: &SupplySettings) -> u32 {
+        match self {
+            Setting::LampCurrent => settings.lamp_current,
+            Setting::CoilCurrent => settings.coil_current,
+            Setting::Limit => settings.limit,
+        }
+    }
+
+    fn value_mut(self, settings: &mut SupplySettings) -> &mut u32 {
+        match self {
+            Setting::LampCurrent => &mut settings.lamp_current,
+            Setting::CoilCurrent => &mut settings.coil_current,
+            Setting::Limit => &mut settings.limit,
+        }
+    }
+}
+
+#[derive(Component)]
+struct SupplyButton;
+
+#[derive(Component)]
+struct LoadPanel;
+
+#[derive(Component)]
+struct LoadText;
+
+#[derive(Component)]
+struct GaugeFill;
+
+#[derive(Component)]
+struct SettingText(Setting);
+
+// Increases the setting by its step when positive, decreases it when negative
+#[derive(Component)]
+struct SettingButton(Setting, i32);
+
+#[derive(Component)]
+struct ResetBreakerButton;
+
+fn setup_supply_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Supply", "Supply Load", SupplyButton);
+    });
+}
+
+fn setup_load_panel(mut cmd: Commands) {
+    let text_style = TextStyle {
+        font_size: 16.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                bottom: Val::Px(220.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Supply Load Panel"),
+        LoadPanel,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section("", text_style.clone()),
+            Name::new("Supply Load Text"),
+            LoadText,
+        ));
+
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(GAUGE_WIDTH),
+                    height: Val::Px(14.),
+                    margin: UiRect::vertical(Val::Px(5.)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.2, 0.2, 0.2)),
+                ..Default::default()
+            },
+            Name::new("Supply Load Gauge"),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.),
+                        height: Val::Percent(100.),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.2, 0.8, 0.2)),
+                    ..Default::default()
+                },
+                Name::new("Supply Load Gauge Fill"),
+                GaugeFill,
+            ));
+        });
+
+        for setting in [Setting::LampCurrent, Setting::CoilCurrent, Setting::Limit] {
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                Name::new(format!("Supply {} Setting", setting.name())),
+            ))
+            .with_children(|root| {
+                for (label, direction) in [("-", -1), ("+", 1)] {
+                    spawn_toolbar_button(
+                        root,
+                        label,
+                        &format!("Supply {} {label}", setting.name()),
+                        SettingButton(setting, direction),
+                    );
+                }
+                root.spawn((
+                    TextBundle::from_section("", text_style.clone()),
+                    Name::new(format!("Supply {} Text", setting.name())),
+                    SettingText(setting),
+                ));
+            });
+        }
+
+        // Only shown while the breaker is tripped
+        spawn_toolbar_button(root, "Reset breaker", "Reset Breaker", ResetBreakerButton);
+    });
+}
+
+fn toggle_load_panel(
+    supply_button: Query<&Interaction, (Changed<Interaction>, With<SupplyButton>)>,
+    mut panel: Query<&mut Visibility, With<LoadPanel>>,
+) {
+    if !supply_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_setting_buttons(
+    setting_buttons: Query<(&Interaction, &SettingButton), Changed<Interaction>>,
+    mut settings: ResMut<SupplySettings>,
+) {
+    for (interaction, SettingButton(setting, direction)) in setting_buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let value = setting.value_mut(&mut settings);
+        *value = value
+            .saturating_add_signed(direction * setting.step() as i32)
+            .max(setting.step());
+    }
+}
+
+fn handle_breaker_reset(
+    reset_buttons: Query<&Interaction, (Changed<Interaction>, With<ResetBreakerButton>)>,
+    mut tripped: ResMut<BreakerTripped>,
+) {
+    if reset_buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        tripped.0 = false;
+    }
+}
+
+// In milliamperes, from the lamps and coils the last tick turned on
+fn drawn_current(
+    settings: &SupplySettings,
+    ui_lights: &Query<&UILight>,
+    relay_coils: &Query<&RelayCoil>,
+) -> u32 {
+    let lit_lamps = ui_lights.iter().filter(|light| light.is_lit).count() as u32;
+    let active_coils = relay_coils.iter().filter(|coil| coil.activated).count() as u32;
+    lit_lamps * settings.lamp_current + active_coils * settings.coil_current
+}
+
+// Right after the tick, so the next tick already runs without the blown fuses or the supply
+fn trip_breaker(
+    settings: Res<SupplySettings>,
+    ui_lights: Query<&UILight>,
+    relay_coils: Query<&RelayCoil>,
+    scratch: Res<SimulationScratch>,
+    mut fuses: Query<&mut Fuse>,
+    mut tripped: ResMut<BreakerTripped>,
+    mut panel: Query<&mut Visibility, With<LoadPanel>>,
+) {
+    let current = drawn_current(&settings, &ui_lights, &relay_coils);
+    if tripped.0 || current <= settings.limit {
+        return;
+    }
+
+    let mut blown_fuses = 0;
+    for mut fuse in fuses.iter_mut() {
+        if !fuse.blown && carries_current(&fuse, &scratch) {
+            warn!(
+                "Fuse -F{} blew, {current} mA drawn with a limit of {} mA",
+                fuse.id, settings.limit
+            );
+            fuse.blown = true;
+            blown_fuses += 1;
+        }
+    }
+    if blown_fuses > 0 {
+        return;
+    }
+
+    warn!(
+        "The breaker of the supply tripped, {current} mA drawn with a limit of {} mA",
+        settings.limit
+    );
+    tripped.0 = true;
+    for mut visibility in panel.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn measure_supply_load(
+    settings: Res<SupplySettings>,
+    ui_lights: Query<&UILight>,
+    relay_coils: Query<&RelayCoil>,
+    mut load: ResMut<SupplyLoad>,
+) {
+    let current = drawn_current(&settings, &ui_lights, &relay_coils);
+    let overloaded = current > settings.limit;
+
+    if overloaded && !load.overloaded {
+        warn!(
+            "Supply overloaded, {current} mA drawn with a limit of {} mA",
+            settings.limit
+        );
+    }
+
+    if load.current != current || load.overloaded != overloaded {
+        *load = SupplyLoad {
+            current,
+            overloaded,
+        };
+    }
+}
+
+fn update_load_panel(
+    settings: Res<SupplySettings>,
+    load: Res<SupplyLoad>,
+    tripped: Res<BreakerTripped>,
+    mut load_text: Query<&mut Text, (With<LoadText>, Without<SettingText>)>,
+    mut setting_texts: Query<(&mut Text, &SettingText)>,
+    mut gauge_fill: Query<(&mut Style, &mut BackgroundColor), With<GaugeFill>>,
+    mut reset_button: Query<&mut Style, (With<ResetBreakerButton>, Without<GaugeFill>)>,
+) {
+    if !settings.is_changed() && !load.is_changed() && !tripped.is_changed() {
+        return;
+    }
+
+    for mut text in load_text.iter_mut() {
+        text.sections[0].value = format!("Supply load: {} / {} mA", load.current, settings.limit);
+        if tripped.0 {
+            text.sections[0].value += "\nThe breaker tripped, the supply is off";
+        }
+    }
+
+    for mut style in reset_button.iter_mut() {
+        style.display = if tripped.0 {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+
+    for (mut text, SettingText(setting)) in setting_texts.iter_mut() {
+        text.sections[0].value = format!(" {}: {} mA", setting.name(), setting.value(&settings));
+    }
+
+    for (mut style, mut color) in gauge_fill.iter_mut() {
+        style.width = Val::Percent((load.current as f32 / settings.limit as f32 * 100.).min(100.));
+        *color = if load.overloaded {
+            BackgroundColor(Color::rgb(0.9, 0.2, 0.2))
+        } else {
+            BackgroundColor(Color::rgb(0.2, 0.8, 0.2))
+        };
+    }
+}
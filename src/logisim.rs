@@ -0,0 +1,416 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    device_counts::{load_device_counts, DeviceCounts},
+    grid::{GridSize, MAX_GRIDSIZE},
+    net_labels::NetLabel,
+    platform,
+    save::{write_circuit, CircuitAccess, CircuitData},
+    spawn_toolbar_button, ButtonSwitch, GridPosition, Light, RelayCoil, RelaySwitch, SwitchType,
+    Toolbar, Wire, NEGATIVE_SOURCE, POSITIVE_SOURCE,
+};
+
+const EXIT_OK: i32 = 0;
+const EXIT_USAGE: i32 = 1;
+const EXIT_FAILED: i32 = 2;
+const USAGE: &str = "usage: relay-sim --import-logisim <logisim file> <circuit file>";
+const IMPORT_PATH: &str = "circuit.circ";
+
+// Logisim draws on a 10 pixel grid, one of its grid points becomes one of ours
+const LOGISIM_GRID: i64 = 10;
+// Room around the imported parts, components reach two points up or down from where they connect
+const MARGIN: usize = 3;
+// Net labels that stand in for the supply, logic high becomes the positive rail and low the negative one
+const POSITIVE_LABEL: &str = "L+";
+const NEGATIVE_LABEL: &str = "M";
+
+// Imports the main circuit of a Logisim-evolution project, so material from a logic class can be opened here
+// Wires stay wires, input pins and buttons become buttons switching L+ and output pins and LEDs become lamps to M
+// Tunnels become net labels, ground and power connect to M and L+, subcircuits with relay in their name become relays
+// Everything else is left out and listed, in the window with the Logisim button reading circuit.circ
+// or from the command line with relay-sim --import-logisim circuit.circ circuit.ron
+pub struct LogisimPlugin;
+
+impl Plugin for LogisimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_import_button)
+            .add_systems(Update, import_logisim_file);
+    }
+}
+
+#[derive(Default)]
+struct LogisimComponent {
+    lib: Option<String>,
+    name: String,
+    loc: (i64, i64),
+    attributes: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct LogisimCircuit {
+    name: String,
+    wires: Vec<((i64, i64), (i64, i64))>,
+    components: Vec<LogisimComponent>,
+}
+
+#[derive(Default)]
+struct Project {
+    // Library number to its description, like "#Wiring"
+    libraries: HashMap<String, String>,
+    main: Option<String>,
+    circuits: Vec<LogisimCircuit>,
+}
+
+fn attribute<'a>(attributes: &'a [xml::attribute::OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.as_str())
+}
+
+// Points are written like (170,130)
+fn parse_point(text: &str) -> Result<(i64, i64), String> {
+    text.trim()
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+        .and_then(|text| text.split_once(','))
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("{text} is not a point"))
+}
+
+fn parse_project(text: &str) -> Result<Project, String> {
+    let mut project = Project::default();
+    let mut component: Option<LogisimComponent> = None;
+
+    for event in EventReader::from_str(text) {
+        match event.map_err(|e| e.to_string())? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "lib" => {
+                    if let (Some(name), Some(desc)) = (
+                        attribute(&attributes, "name"),
+                        attribute(&attributes, "desc"),
+                    ) {
+                        project.libraries.insert(name.to_string(), desc.to_string());
+                    }
+                }
+                "main" => project.main = attribute(&attributes, "name").map(str::to_string),
+                "circuit" => project.circuits.push(LogisimCircuit {
+                    name: attribute(&attributes, "name")
+                        .unwrap_or_default()
+                        .to_string(),
+                    ..Default::default()
+                }),
+                "wire" => {
+                    let (Some(from), Some(to), Some(circuit)) = (
+                        attribute(&attributes, "from"),
+                        attribute(&attributes, "to"),
+                        project.circuits.last_mut(),
+                    ) else {
+                        continue;
+                    };
+                    circuit.wires.push((parse_point(from)?, parse_point(to)?));
+                }
+                "comp" if !project.circuits.is_empty() => {
+                    component = Some(LogisimComponent {
+                        lib: attribute(&attributes, "lib").map(str::to_string),
+                        name: attribute(&attributes, "name")
+                            .unwrap_or_default()
+                            .to_string(),
+                        loc: parse_point(attribute(&attributes, "loc").unwrap_or_default())?,
+                        attributes: HashMap::new(),
+                    });
+                }
+                // Attributes of the component they are in, like the label of a tunnel
+                "a" => {
+                    if let (Some(component), Some(name), Some(value)) = (
+                        component.as_mut(),
+                        attribute(&attributes, "name"),
+                        attribute(&attributes, "val"),
+                    ) {
+                        component
+                            .attributes
+                            .insert(name.to_string(), value.to_string());
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::EndElement { name } if name.local_name == "comp" => {
+                if let (Some(component), Some(circuit)) =
+                    (component.take(), project.circuits.last_mut())
+                {
+                    circuit.components.push(component);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(project)
+}
+
+// What a Logisim component turns into
+enum Part {
+    Button,
+    Lamp,
+    Label(String),
+    Relay,
+    Unsupported(String),
+}
+
+fn classify(project: &Project, component: &LogisimComponent) -> Part {
+    let library = component
+        .lib
+        .as_ref()
+        .and_then(|lib| project.libraries.get(lib))
+        .map(String::as_str);
+    match (library, component.name.as_str()) {
+        // Subcircuits have no library, they name a circuit of the project instead
+        (None, name) if name.to_lowercase().contains("relay") => Part::Relay,
+        (Some("#Wiring"), "Pin") => {
+            if component.attributes.get("output").map(String::as_str) == Some("true") {
+                Part::Lamp
+            } else {
+                Part::Button
+            }
+        }
+        (Some("#Wiring"), "Tunnel") => match component.attributes.get("label") {
+            Some(label) if !label.is_empty() => Part::Label(label.clone()),
+            _ => Part::Unsupported("Tunnel without a label".to_string()),
+        },
+        (Some("#Wiring"), "Power") => Part::Label(POSITIVE_LABEL.to_string()),
+        (Some("#Wiring"), "Ground") => Part::Label(NEGATIVE_LABEL.to_string()),
+        (Some("#I/O" | "#Input/Output"), "Button") => Part::Button,
+        (Some("#I/O" | "#Input/Output"), "LED") => Part::Lamp,
+        (None, name) => Part::Unsupported(format!("subcircuit {name}")),
+        (Some(_), name) => Part::Unsupported(name.to_string()),
+    }
+}
+
+// The circuit with a list of everything that could not be imported
+// Lights, buttons and relays beyond the configured device counts are left out, the left section has no row for them
+pub fn import(text: &str, counts: &DeviceCounts) -> Result<(CircuitData, Vec<String>), String> {
+    let project = parse_project(text)?;
+    let circuit = match &project.main {
+        Some(main) => project
+            .circuits
+            .iter()
+            .find(|circuit| circuit.name == *main),
+        None => project.circuits.first(),
+    }
+    .ok_or_else(|| "the file has no circuit".to_string())?;
+
+    // Logisim counts y downwards, ours goes up, so the lowest point ends up at the bottom margin
+    let points = circuit
+        .wires
+        .iter()
+        .flat_map(|(from, to)| [*from, *to])
+        .chain(circuit.components.iter().map(|component| component.loc))
+        .collect::<Vec<_>>();
+    let min_x = points.iter().map(|point| point.0).min().unwrap_or(0);
+    let max_y = points.iter().map(|point| point.1).max().unwrap_or(0);
+    let to_grid = |(x, y): (i64, i64)| GridPosition {
+        x: ((x - min_x) as f32 / LOGISIM_GRID as f32).round() as usize + MARGIN,
+        y: ((max_y - y) as f32 / LOGISIM_GRID as f32).round() as usize + MARGIN,
+    };
+    let up = |pos: GridPosition| GridPosition {
+        y: pos.y + 2,
+        ..pos
+    };
+    let down = |pos: GridPosition| GridPosition {
+        y: pos.y - 2,
+        ..pos
+    };
+    let right = |pos: GridPosition| GridPosition {
+        x: pos.x + 2,
+        ..pos
+    };
+
+    let mut report = Vec::new();
+    let mut unsupported = BTreeMap::<String, usize>::new();
+    let mut wires = Vec::new();
+    let mut lights = Vec::new();
+    let mut buttons = Vec::new();
+    let mut relay_coils = Vec::new();
+    let mut relay_switches = Vec::new();
+    let mut net_labels = vec![
+        NetLabel {
+            pos: POSITIVE_SOURCE,
+            name: POSITIVE_LABEL.to_string(),
+        },
+        NetLabel {
+            pos: NEGATIVE_SOURCE,
+            name: NEGATIVE_LABEL.to_string(),
+        },
+    ];
+    let label = |pos: GridPosition, name: &str| NetLabel {
+        pos,
+        name: name.to_string(),
+    };
+
+    for (from, to) in circuit.wires.iter() {
+        let (first, second) = (to_grid(*from), to_grid(*to));
+        if first == second {
+            continue;
+        }
+        if first.x != second.x && first.y != second.y {
+            *unsupported.entry("diagonal wire".to_string()).or_default() += 1;
+            continue;
+        }
+        wires.push(Wire { first, second });
+    }
+
+    for component in circuit.components.iter() {
+        let pos = to_grid(component.loc);
+        match classify(&project, component) {
+            Part::Button if buttons.len() < counts.buttons => {
+                buttons.push(ButtonSwitch {
+                    id: buttons.len() + 1,
+                    typ: SwitchType::NormallyOpen,
+                    top: up(pos),
+                    bottom: pos,
+                });
+                net_labels.push(label(up(pos), POSITIVE_LABEL));
+            }
+            Part::Lamp if lights.len() < counts.lights => {
+                lights.push(Light {
+                    id: lights.len() + 1,
+                    top: pos,
+                    bottom: down(pos),
+                });
+                net_labels.push(label(down(pos), NEGATIVE_LABEL));
+            }
+            Part::Relay if relay_coils.len() < counts.relays => {
+                let id = relay_coils.len() + 1;
+                relay_coils.push(RelayCoil {
+                    id,
+                    top: pos,
+                    bottom: down(pos),
+                    ..Default::default()
+                });
+                net_labels.push(label(down(pos), NEGATIVE_LABEL));
+                relay_switches.push(RelaySwitch {
+                    id,
+                    typ: SwitchType::NormallyOpen,
+                    top: right(pos),
+                    bottom: right(down(pos)),
+                });
+                report.push(format!(
+                    "The contact of -K{id} ({}) is placed next to its coil and still has to be wired",
+                    component.name
+                ));
+            }
+            Part::Button | Part::Lamp | Part::Relay => {
+                *unsupported
+                    .entry(format!("{} beyond the device counts", component.name))
+                    .or_default() += 1;
+            }
+            Part::Label(name) => net_labels.push(label(pos, &name)),
+            Part::Unsupported(name) => *unsupported.entry(name).or_default() += 1,
+        }
+    }
+
+    report.extend(
+        unsupported
+            .into_iter()
+            .map(|(name, count)| format!("Left out {count} x {name}")),
+    );
+
+    let used = wires
+        .iter()
+        .flat_map(|wire| [wire.first, wire.second])
+        .chain(net_labels.iter().map(|label| label.pos))
+        .chain(relay_switches.iter().map(|relay_switch| relay_switch.top));
+    let default = GridSize::default();
+    let grid_size = used.fold(default, |size, pos| GridSize {
+        width: size.width.max(pos.x + MARGIN),
+        height: size.height.max(pos.y + MARGIN),
+    });
+    if grid_size.width > MAX_GRIDSIZE || grid_size.height > MAX_GRIDSIZE {
+        return Err("the circuit is larger than the largest grid".to_string());
+    }
+
+    let circuit = CircuitData::from_parts(
+        grid_size,
+        wires,
+        lights,
+        buttons,
+        relay_coils,
+        relay_switches,
+    )
+    .with_net_labels(net_labels);
+    Ok((circuit, report))
+}
+
+// relay-sim --import-logisim circuit.circ circuit.ron writes the imported circuit and prints what was left out
+pub fn run(args: &[String]) -> i32 {
+    let [input, output] = args else {
+        eprintln!("{USAGE}");
+        return EXIT_USAGE;
+    };
+
+    let result = platform::read_file(Path::new(input))
+        .and_then(|text| import(&text, &load_device_counts()))
+        .and_then(|(circuit, report)| {
+            write_circuit(Path::new(output), &circuit)?;
+            Ok(report)
+        });
+    match result {
+        Ok(report) => {
+            for line in report {
+                println!("{line}");
+            }
+            println!("Wrote the circuit to {output}");
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("Cannot import {input}: {e}");
+            EXIT_FAILED
+        }
+    }
+}
+
+#[derive(Component)]
+struct ImportButton;
+
+fn setup_import_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Logisim", "Import Logisim", ImportButton);
+    });
+}
+
+// In the browser the file is picked and uploaded first, like loading a circuit
+fn import_logisim_file(
+    import_button: Query<&Interaction, (Changed<Interaction>, With<ImportButton>)>,
+    counts: Res<DeviceCounts>,
+    mut circuit: CircuitAccess,
+) {
+    let path = PathBuf::from(IMPORT_PATH);
+    if import_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        platform::open_file(&path);
+    }
+    let Some(result) = platform::take_opened_file(&path) else {
+        return;
+    };
+
+    match result.and_then(|text| import(&text, &counts)) {
+        Ok((data, report)) => {
+            circuit.replace(data);
+            info!("Imported {IMPORT_PATH}");
+            for line in report {
+                warn!("{line}");
+            }
+        }
+        Err(e) => error!("Cannot import {IMPORT_PATH}: {e}"),
+    }
+}
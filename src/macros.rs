@@ -0,0 +1,972 @@
+use std::{collections::HashMap, path::Path};
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{spawn_clock, SimulationClock},
+    convert_mouse_to_grid,
+    device_counts::DeviceCounts,
+    diode::{spawn_diode, Diode},
+    fuse::{spawn_fuse, Fuse},
+    grid::GridSize,
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    measure::Measurement,
+    net_labels::{spawn_net_label, NetLabel},
+    platform, spawn_button, spawn_light, spawn_relay_coil, spawn_relay_switch,
+    spawn_toolbar_button, spawn_wire,
+    templates::Templates,
+    time_switch::{spawn_time_switch, TimeSwitch},
+    ButtonSwitch, CircuitHandles, CurrentlyPlacing, GridOrigin, GridPosition, Light, MainCamera,
+    RelayCoil, RelaySwitch, SwitchType, Toolbar, Wire,
+};
+
+const MACROS_PATH: &str = "macros.ron";
+const MAX_NAME_LENGTH: usize = 24;
+// Like when placing by hand, a relay has at most five contacts of each kind
+const MAX_CONTACTS: usize = 5;
+
+// Records placed wires and components as a named macro, the macro button lists them
+// Playing a macro places a copy wherever is clicked, lights, buttons and relays whose coil is part of the macro get ids that are still free
+// Macros are kept in macros.ron in the working directory, like the keybindings
+// Ctrl+C copies everything inside the measured selection as an unnamed macro that Ctrl+V places, also in another tab
+// Ctrl+D duplicates the selection right below it, what has no free id left is skipped instead of refusing the whole copy
+pub struct MacroPlugin;
+
+impl Plugin for MacroPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_macros())
+            .init_resource::<MacroRecorder>()
+            .init_resource::<CopiedSelection>()
+            .add_systems(Startup, setup_macro_panel)
+            .add_systems(PostStartup, setup_macro_button)
+            .add_systems(PreUpdate, type_macro_name.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    toggle_macro_panel,
+                    handle_macro_rows,
+                    record_edits,
+                    copy_and_paste,
+                    handle_macro_placement,
+                    update_macro_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Also what the undo history remembers about placed and deleted elements
+#[derive(Clone, Serialize, Deserialize)]
+pub enum MacroStep {
+    Wire(Wire),
+    Light(Light),
+    Button(ButtonSwitch),
+    RelayCoil(RelayCoil),
+    RelaySwitch(RelaySwitch),
+    Clock(SimulationClock),
+    TimeSwitch(TimeSwitch),
+    Fuse(Fuse),
+    Diode(Diode),
+    NetLabel(NetLabel),
+}
+
+impl MacroStep {
+    pub fn positions(&self) -> (GridPosition, GridPosition) {
+        match self {
+            MacroStep::Wire(wire) => (wire.first, wire.second),
+            MacroStep::Light(light) => (light.top, light.bottom),
+            MacroStep::Button(button) => (button.top, button.bottom),
+            MacroStep::RelayCoil(relay_coil) => (relay_coil.top, relay_coil.bottom),
+            MacroStep::RelaySwitch(relay_switch) => (relay_switch.top, relay_switch.bottom),
+            MacroStep::Clock(clock) => (clock.top, clock.bottom),
+            MacroStep::TimeSwitch(time_switch) => (time_switch.top, time_switch.bottom),
+            MacroStep::Fuse(fuse) => (fuse.top, fuse.bottom),
+            MacroStep::Diode(diode) => (diode.anode, diode.cathode),
+            MacroStep::NetLabel(label) => (label.pos, label.pos),
+        }
+    }
+
+    // Every position of the step put through move_to, a net label only has the one
+    pub fn map_positions(&mut self, mut move_to: impl FnMut(GridPosition) -> GridPosition) {
+        let mut both = |first: &mut GridPosition, second: &mut GridPosition| {
+            (*first, *second) = (move_to(*first), move_to(*second));
+        };
+        match self {
+            MacroStep::Wire(wire) => both(&mut wire.first, &mut wire.second),
+            MacroStep::Light(light) => both(&mut light.top, &mut light.bottom),
+            MacroStep::Button(button) => both(&mut button.top, &mut button.bottom),
+            MacroStep::RelayCoil(relay_coil) => both(&mut relay_coil.top, &mut relay_coil.bottom),
+            MacroStep::RelaySwitch(relay_switch) => {
+                both(&mut relay_switch.top, &mut relay_switch.bottom)
+            }
+            MacroStep::Clock(clock) => both(&mut clock.top, &mut clock.bottom),
+            MacroStep::TimeSwitch(time_switch) => {
+                both(&mut time_switch.top, &mut time_switch.bottom)
+            }
+            MacroStep::Fuse(fuse) => both(&mut fuse.top, &mut fuse.bottom),
+            MacroStep::Diode(diode) => both(&mut diode.anode, &mut diode.cathode),
+            MacroStep::NetLabel(label) => label.pos = move_to(label.pos),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EditMacro {
+    pub name: String,
+    // Positions are relative to the bottom left corner of everything in the macro
+    pub steps: Vec<MacroStep>,
+}
+
+impl EditMacro {
+    fn size(&self) -> GridPosition {
+        let positions = self
+            .steps
+            .iter()
+            .flat_map(|step| <[GridPosition; 2]>::from(step.positions()));
+        GridPosition {
+            x: positions.clone().map(|pos| pos.x).max().unwrap_or(0),
+            y: positions.map(|pos| pos.y).max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct Macros(Vec<EditMacro>);
+
+// Not saved, but kept when switching tabs
+#[derive(Resource, Default)]
+struct CopiedSelection(Option<EditMacro>);
+
+#[derive(Resource, Default)]
+struct MacroRecorder {
+    recording: bool,
+    // The entity of every recorded step, so deleting it again also takes it out of the macro
+    steps: Vec<(Entity, MacroStep)>,
+    // Some while the finished recording waits for a name
+    name: Option<String>,
+}
+
+#[derive(Component)]
+struct MacroButton;
+
+#[derive(Component)]
+struct MacroPanel;
+
+#[derive(Component)]
+enum MacroRow {
+    Record,
+    Play(usize),
+    Delete(usize),
+}
+
+fn setup_macro_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Macro", "Macros", MacroButton);
+    });
+}
+
+fn setup_macro_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                right: Val::Px(10.),
+                width: Val::Px(300.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Macro Panel"),
+        MacroPanel,
+    ));
+}
+
+fn spawn_macro_row(root: &mut ChildBuilder, row: MacroRow, grow: bool) {
+    root.spawn((
+        ButtonBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(3.)),
+                margin: UiRect::all(Val::Px(1.)),
+                flex_grow: if grow { 1. } else { 0. },
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+            ..Default::default()
+        },
+        Name::new("Macro Row"),
+        row,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("Macro Row Text"),
+        ));
+    });
+}
+
+fn load_macros() -> Macros {
+    if !platform::file_exists(Path::new(MACROS_PATH)) {
+        return Macros::default();
+    }
+
+    match platform::read_file(Path::new(MACROS_PATH))
+        .and_then(|text| ron::from_str::<Vec<EditMacro>>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(macros) => Macros(macros),
+        Err(e) => {
+            warn!("Cannot read macros from {MACROS_PATH}: {e}");
+            Macros::default()
+        }
+    }
+}
+
+fn save_macros(macros: &Macros) {
+    let result = ron::ser::to_string_pretty(&macros.0, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| platform::write_file(Path::new(MACROS_PATH), &text));
+
+    if let Err(e) = result {
+        error!("Cannot save macros to {MACROS_PATH}: {e}");
+    }
+}
+
+fn toggle_macro_panel(
+    macro_button: Query<&Interaction, (Changed<Interaction>, With<MacroButton>)>,
+    mut panel: Query<&mut Visibility, With<MacroPanel>>,
+) {
+    if !macro_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_macro_rows(
+    rows: Query<(&Interaction, &MacroRow), Changed<Interaction>>,
+    mut macros: ResMut<Macros>,
+    mut recorder: ResMut<MacroRecorder>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match row {
+            MacroRow::Record if recorder.name.is_some() => {}
+            MacroRow::Record if recorder.recording => {
+                recorder.recording = false;
+                if recorder.steps.is_empty() {
+                    warn!("Nothing was placed while recording, no macro was made");
+                } else {
+                    recorder.name = Some(String::new());
+                }
+            }
+            MacroRow::Record => {
+                recorder.recording = true;
+                recorder.steps.clear();
+            }
+            MacroRow::Play(i) => *currently_placing = CurrentlyPlacing::Macro(*i),
+            MacroRow::Delete(i) => {
+                if matches!(*currently_placing, CurrentlyPlacing::Macro(_)) {
+                    *currently_placing = CurrentlyPlacing::Wire;
+                }
+                macros.0.remove(*i);
+                save_macros(&macros);
+            }
+        }
+    }
+}
+
+fn type_macro_name(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut recorder: ResMut<MacroRecorder>,
+    mut macros: ResMut<Macros>,
+) {
+    let Some(mut name) = recorder.name.clone() else {
+        characters.clear();
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        recorder.name = None;
+        recorder.steps.clear();
+    } else if keys.just_pressed(KeyCode::Return) {
+        if name.is_empty() {
+            name = format!("Macro {}", macros.0.len() + 1);
+        }
+        let steps = std::mem::take(&mut recorder.steps)
+            .into_iter()
+            .map(|(_, step)| step)
+            .collect();
+        macros.0.push(EditMacro {
+            name,
+            steps: normalize(steps),
+        });
+        save_macros(&macros);
+        recorder.name = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            name.pop();
+        }
+
+        for c in characters.read().map(|event| event.char) {
+            if name.chars().count() < MAX_NAME_LENGTH && !c.is_control() {
+                name.push(c);
+            }
+        }
+        recorder.name = Some(name);
+    }
+
+    characters.clear();
+    keys.reset_all();
+}
+
+// Moves the steps so the bottom left corner of all of them is at 0, 0
+pub fn normalize(mut steps: Vec<MacroStep>) -> Vec<MacroStep> {
+    let positions = steps
+        .iter()
+        .flat_map(|step| <[GridPosition; 2]>::from(step.positions()));
+    let min_x = positions.clone().map(|pos| pos.x).min().unwrap_or(0);
+    let min_y = positions.map(|pos| pos.y).min().unwrap_or(0);
+
+    for step in steps.iter_mut() {
+        step.map_positions(|pos| GridPosition {
+            x: pos.x - min_x,
+            y: pos.y - min_y,
+        });
+    }
+    steps
+}
+
+// Every wire and component placed, deleted or edited in place since the last time, for the macro recorder and the undo history
+#[derive(SystemParam)]
+pub struct CircuitEdits<'w, 's> {
+    wires: Query<'w, 's, (Entity, Ref<'static, Wire>), Changed<Wire>>,
+    lights: Query<'w, 's, (Entity, Ref<'static, Light>), Changed<Light>>,
+    buttons: Query<'w, 's, (Entity, Ref<'static, ButtonSwitch>), Changed<ButtonSwitch>>,
+    relay_coils: Query<'w, 's, (Entity, Ref<'static, RelayCoil>), Changed<RelayCoil>>,
+    relay_switches: Query<'w, 's, (Entity, Ref<'static, RelaySwitch>), Changed<RelaySwitch>>,
+    clocks: Query<'w, 's, (Entity, Ref<'static, SimulationClock>), Changed<SimulationClock>>,
+    time_switches: Query<'w, 's, (Entity, Ref<'static, TimeSwitch>), Changed<TimeSwitch>>,
+    fuses: Query<'w, 's, (Entity, Ref<'static, Fuse>), Changed<Fuse>>,
+    diodes: Query<'w, 's, (Entity, Ref<'static, Diode>), Changed<Diode>>,
+    net_labels: Query<'w, 's, (Entity, Ref<'static, NetLabel>), Changed<NetLabel>>,
+    removed_wires: RemovedComponents<'w, 's, Wire>,
+    removed_lights: RemovedComponents<'w, 's, Light>,
+    removed_buttons: RemovedComponents<'w, 's, ButtonSwitch>,
+    removed_relay_coils: RemovedComponents<'w, 's, RelayCoil>,
+    removed_relay_switches: RemovedComponents<'w, 's, RelaySwitch>,
+    removed_clocks: RemovedComponents<'w, 's, SimulationClock>,
+    removed_time_switches: RemovedComponents<'w, 's, TimeSwitch>,
+    removed_fuses: RemovedComponents<'w, 's, Fuse>,
+    removed_diodes: RemovedComponents<'w, 's, Diode>,
+    removed_net_labels: RemovedComponents<'w, 's, NetLabel>,
+}
+
+impl CircuitEdits<'_, '_> {
+    // Whether it was just placed comes with every step
+    fn changed(&self) -> impl Iterator<Item = (Entity, MacroStep, bool)> + '_ {
+        self.wires
+            .iter()
+            .map(|(e, wire)| (e, MacroStep::Wire(wire.clone()), wire.is_added()))
+            .chain(
+                self.lights
+                    .iter()
+                    .map(|(e, light)| (e, MacroStep::Light(light.clone()), light.is_added())),
+            )
+            .chain(
+                self.buttons
+                    .iter()
+                    .map(|(e, button)| (e, MacroStep::Button(button.clone()), button.is_added())),
+            )
+            .chain(self.relay_coils.iter().map(|(e, relay_coil)| {
+                // Timer relays brought back by undo or placed by a macro start out let go, not halfway through their delay
+                let mut at_rest = relay_coil.clone();
+                at_rest.reset();
+                (e, MacroStep::RelayCoil(at_rest), relay_coil.is_added())
+            }))
+            .chain(self.relay_switches.iter().map(|(e, relay_switch)| {
+                let added = relay_switch.is_added();
+                (e, MacroStep::RelaySwitch(relay_switch.clone()), added)
+            }))
+            .chain(
+                self.clocks
+                    .iter()
+                    .map(|(e, clock)| (e, MacroStep::Clock(clock.clone()), clock.is_added())),
+            )
+            .chain(self.time_switches.iter().map(|(e, time_switch)| {
+                let added = time_switch.is_added();
+                (e, MacroStep::TimeSwitch(time_switch.clone()), added)
+            }))
+            .chain(
+                self.fuses
+                    .iter()
+                    .map(|(e, fuse)| (e, MacroStep::Fuse(fuse.clone()), fuse.is_added())),
+            )
+            .chain(
+                self.diodes
+                    .iter()
+                    .map(|(e, diode)| (e, MacroStep::Diode(diode.clone()), diode.is_added())),
+            )
+            .chain(
+                self.net_labels
+                    .iter()
+                    .map(|(e, label)| (e, MacroStep::NetLabel(label.clone()), label.is_added())),
+            )
+    }
+
+    pub fn added(&self) -> impl Iterator<Item = (Entity, MacroStep)> + '_ {
+        self.changed()
+            .filter(|(_, _, added)| *added)
+            .map(|(e, step, _)| (e, step))
+    }
+
+    // Elements that were there already and got moved, the simulation changes coils every tick so the positions are compared
+    pub fn moved<'a>(
+        &'a self,
+        before: impl Fn(Entity) -> Option<&'a MacroStep> + 'a,
+    ) -> impl Iterator<Item = (Entity, MacroStep)> + 'a {
+        self.changed()
+            .filter(move |(e, step, added)| {
+                !added && before(*e).is_some_and(|old| old.positions() != step.positions())
+            })
+            .map(|(e, step, _)| (e, step))
+    }
+
+    pub fn removed(&mut self) -> Vec<Entity> {
+        self.removed_wires
+            .read()
+            .chain(self.removed_lights.read())
+            .chain(self.removed_buttons.read())
+            .chain(self.removed_relay_coils.read())
+            .chain(self.removed_relay_switches.read())
+            .chain(self.removed_clocks.read())
+            .chain(self.removed_time_switches.read())
+            .chain(self.removed_fuses.read())
+            .chain(self.removed_diodes.read())
+            .chain(self.removed_net_labels.read())
+            .collect()
+    }
+}
+
+fn record_edits(mut recorder: ResMut<MacroRecorder>, mut edits: CircuitEdits) {
+    let removed = edits.removed();
+
+    if !recorder.recording {
+        return;
+    }
+
+    recorder.steps.retain(|(e, _)| !removed.contains(e));
+    // Moving something while recording records where it ended up
+    let moved = edits
+        .moved(|e| {
+            recorder
+                .steps
+                .iter()
+                .find(|(recorded, _)| *recorded == e)
+                .map(|(_, step)| step)
+        })
+        .collect::<Vec<_>>();
+    for (e, step) in moved {
+        if let Some(recorded) = recorder
+            .steps
+            .iter_mut()
+            .find(|(recorded, _)| *recorded == e)
+        {
+            recorded.1 = step;
+        }
+    }
+    recorder.steps.extend(edits.added());
+}
+
+fn copy_and_paste(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut measurement: ResMut<Measurement>,
+    mut copied: ResMut<CopiedSelection>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+    counts: Res<DeviceCounts>,
+    wires: Query<&Wire>,
+    placed: PlacedIds,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl {
+        return;
+    }
+
+    if bindings.just_pressed(&keys, Action::Paste) {
+        if copied.0.is_some() {
+            *currently_placing = CurrentlyPlacing::Paste;
+        } else {
+            warn!("Nothing was copied yet");
+        }
+    }
+
+    // Ctrl+Shift+C copies an image of the circuit instead
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let copy = !shift && bindings.just_pressed(&keys, Action::Copy);
+    let duplicate = bindings.just_pressed(&keys, Action::Duplicate);
+    if !copy && !duplicate {
+        return;
+    }
+    let Some((a, b)) = measurement.selection() else {
+        warn!("Select an area with the measure tool first, then copy it");
+        return;
+    };
+
+    // Only what lies completely inside the selection is copied
+    let inside = |(first, second): (GridPosition, GridPosition)| {
+        [first, second].into_iter().all(|pos| {
+            (a.x.min(b.x)..=a.x.max(b.x)).contains(&pos.x)
+                && (a.y.min(b.y)..=a.y.max(b.y)).contains(&pos.y)
+        })
+    };
+    let steps = wires
+        .iter()
+        .map(|wire| MacroStep::Wire(wire.clone()))
+        .chain(
+            placed
+                .lights
+                .iter()
+                .map(|light| MacroStep::Light(light.clone())),
+        )
+        .chain(
+            placed
+                .buttons
+                .iter()
+                .map(|button| MacroStep::Button(button.clone())),
+        )
+        .chain(
+            placed
+                .relay_coils
+                .iter()
+                .map(|relay_coil| MacroStep::RelayCoil(relay_coil.clone())),
+        )
+        .chain(
+            placed
+                .relay_switches
+                .iter()
+                .map(|relay_switch| MacroStep::RelaySwitch(relay_switch.clone())),
+        )
+        .filter(|step| inside(step.positions()))
+        .collect::<Vec<_>>();
+
+    if steps.is_empty() {
+        warn!("Nothing lies completely inside the selection, nothing was copied");
+        return;
+    }
+    let positions = steps
+        .iter()
+        .flat_map(|step| <[GridPosition; 2]>::from(step.positions()));
+    let bottom_left = GridPosition {
+        x: positions.clone().map(|pos| pos.x).min().unwrap_or(0),
+        y: positions.map(|pos| pos.y).min().unwrap_or(0),
+    };
+    let selection = EditMacro {
+        name: "copied selection".to_string(),
+        steps: normalize(steps),
+    };
+
+    if copy {
+        info!("Copied {} elements", selection.steps.len());
+        copied.0 = Some(selection);
+        return;
+    }
+
+    // The copy goes right below the original with one free row in between and becomes the selection
+    // so pressing it again keeps stacking rungs downwards
+    let offset = selection.size().y + 2;
+    let Some(y) = bottom_left.y.checked_sub(offset) else {
+        warn!("There is no room below the selection to duplicate it");
+        return;
+    };
+    let used = placed.used();
+    let (placed, skipped) = place_steps(
+        &selection,
+        GridPosition {
+            x: bottom_left.x,
+            y,
+        },
+        used,
+        &counts,
+    );
+    if skipped > 0 {
+        warn!("{skipped} components were left out, there are no free ids left for them");
+    }
+    let grid_origin = grid_origin.single();
+    for step in placed {
+        spawn_step(&mut cmd, &circuit_material, grid_origin, step);
+    }
+    let down = |pos: GridPosition| GridPosition {
+        x: pos.x,
+        y: pos.y.saturating_sub(offset),
+    };
+    measurement.select(down(a), down(b));
+}
+
+// Ids of what is already placed, placed copies get other ones
+struct UsedIds {
+    lights: Vec<usize>,
+    buttons: Vec<usize>,
+    relays: Vec<usize>,
+    contacts: Vec<(usize, SwitchType)>,
+    fuses: Vec<usize>,
+}
+
+#[derive(SystemParam)]
+struct PlacedIds<'w, 's> {
+    lights: Query<'w, 's, &'static Light>,
+    buttons: Query<'w, 's, &'static ButtonSwitch>,
+    relay_coils: Query<'w, 's, &'static RelayCoil>,
+    relay_switches: Query<'w, 's, &'static RelaySwitch>,
+    fuses: Query<'w, 's, &'static Fuse>,
+}
+
+impl PlacedIds<'_, '_> {
+    fn used(&self) -> UsedIds {
+        UsedIds {
+            lights: self.lights.iter().map(|light| light.id).collect(),
+            buttons: self.buttons.iter().map(|button| button.id).collect(),
+            relays: self
+                .relay_coils
+                .iter()
+                .map(|relay_coil| relay_coil.id)
+                .chain(
+                    self.relay_switches
+                        .iter()
+                        .map(|relay_switch| relay_switch.id),
+                )
+                .collect(),
+            contacts: self
+                .relay_switches
+                .iter()
+                .map(|relay_switch| (relay_switch.id, relay_switch.typ))
+                .collect(),
+            fuses: self.fuses.iter().map(|fuse| fuse.id).collect(),
+        }
+    }
+}
+
+// Maps every id in recorded to the lowest id up to max_id that is neither used nor handed out already
+// Ids that find no free one are left out of the map
+fn remap_ids(
+    recorded: impl Iterator<Item = usize>,
+    used: &[usize],
+    max_id: usize,
+    map: &mut HashMap<usize, usize>,
+) {
+    let mut taken = used.to_vec();
+    for id in recorded {
+        if map.contains_key(&id) {
+            continue;
+        }
+        let Some(free) = (1..=max_id).find(|free| !taken.contains(free)) else {
+            continue;
+        };
+        taken.push(free);
+        map.insert(id, free);
+    }
+}
+
+// The steps moved to the anchor with free ids, lights, buttons and relays that get no free id are left out
+// So are contacts of a relay that already has five of that kind, the second value counts what was left out
+fn place_steps(
+    edit_macro: &EditMacro,
+    anchor: GridPosition,
+    mut used: UsedIds,
+    counts: &DeviceCounts,
+) -> (Vec<MacroStep>, usize) {
+    // Contacts of relays whose coil is not part of the macro keep switching with that relay
+    let coil_ids = edit_macro
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            MacroStep::RelayCoil(relay_coil) => Some(relay_coil.id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut light_ids = HashMap::new();
+    let mut button_ids = HashMap::new();
+    let mut relay_ids = HashMap::new();
+    remap_ids(
+        edit_macro.steps.iter().filter_map(|step| match step {
+            MacroStep::Light(light) => Some(light.id),
+            _ => None,
+        }),
+        &used.lights,
+        counts.lights,
+        &mut light_ids,
+    );
+    remap_ids(
+        edit_macro.steps.iter().filter_map(|step| match step {
+            MacroStep::Button(button) => Some(button.id),
+            _ => None,
+        }),
+        &used.buttons,
+        counts.buttons,
+        &mut button_ids,
+    );
+    remap_ids(
+        coil_ids.iter().copied(),
+        &used.relays,
+        counts.relays,
+        &mut relay_ids,
+    );
+    // Fuses are numbered without a limit
+    let mut fuse_ids = HashMap::new();
+    remap_ids(
+        edit_macro.steps.iter().filter_map(|step| match step {
+            MacroStep::Fuse(fuse) => Some(fuse.id),
+            _ => None,
+        }),
+        &used.fuses,
+        usize::MAX,
+        &mut fuse_ids,
+    );
+
+    let shift = |pos: GridPosition| GridPosition {
+        x: pos.x + anchor.x,
+        y: pos.y + anchor.y,
+    };
+    let mut placed = Vec::new();
+    for step in edit_macro.steps.iter() {
+        let mut step = step.clone();
+        step.map_positions(shift);
+
+        let remapped = |ids: &HashMap<usize, usize>, id: &mut usize| match ids.get(id) {
+            Some(new_id) => {
+                *id = *new_id;
+                true
+            }
+            None => false,
+        };
+        let keep = match &mut step {
+            MacroStep::Wire(_) => true,
+            MacroStep::Light(light) => remapped(&light_ids, &mut light.id),
+            MacroStep::Button(button) => remapped(&button_ids, &mut button.id),
+            MacroStep::RelayCoil(relay_coil) => remapped(&relay_ids, &mut relay_coil.id),
+            MacroStep::RelaySwitch(relay_switch) => {
+                let has_relay = !coil_ids.contains(&relay_switch.id)
+                    || remapped(&relay_ids, &mut relay_switch.id);
+                let contact = (relay_switch.id, relay_switch.typ);
+                let has_room = used
+                    .contacts
+                    .iter()
+                    .filter(|used| **used == contact)
+                    .count()
+                    < MAX_CONTACTS;
+                if has_relay && has_room {
+                    used.contacts.push(contact);
+                }
+                has_relay && has_room
+            }
+            MacroStep::Clock(_)
+            | MacroStep::TimeSwitch(_)
+            | MacroStep::Diode(_)
+            | MacroStep::NetLabel(_) => true,
+            MacroStep::Fuse(fuse) => remapped(&fuse_ids, &mut fuse.id),
+        };
+        if keep {
+            placed.push(step);
+        }
+    }
+
+    let skipped = edit_macro.steps.len() - placed.len();
+    (placed, skipped)
+}
+
+fn handle_macro_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    (macros, copied, templates): (Res<Macros>, Res<CopiedSelection>, Res<Templates>),
+    counts: Res<DeviceCounts>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+    placed: PlacedIds,
+    mut gizmos: Gizmos,
+) {
+    let edit_macro = match *currently_placing {
+        CurrentlyPlacing::Macro(index) => macros.0.get(index),
+        CurrentlyPlacing::Paste => copied.0.as_ref(),
+        CurrentlyPlacing::Template(index) => templates.0.get(index),
+        _ => return,
+    };
+    let Some(edit_macro) = edit_macro else {
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    };
+
+    let Some(anchor) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    let shift = |pos: GridPosition| GridPosition {
+        x: pos.x + anchor.x,
+        y: pos.y + anchor.y,
+    };
+    for step in edit_macro.steps.iter() {
+        let (first, second) = step.positions();
+        gizmos.line_2d(
+            grid_to_world(shift(first)),
+            grid_to_world(shift(second)),
+            Color::GRAY,
+        );
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    }
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let size = edit_macro.size();
+    if anchor.x + size.x >= grid_size.width || anchor.y + size.y >= grid_size.height {
+        warn!("The macro {} does not fit here", edit_macro.name);
+        return;
+    }
+
+    let used = placed.used();
+    let (placed, skipped) = place_steps(edit_macro, anchor, used, &counts);
+    if skipped > 0 {
+        warn!(
+            "{skipped} components of the macro {} were left out, there are no free ids left for them",
+            edit_macro.name
+        );
+    }
+
+    let grid_origin = grid_origin.single();
+    for step in placed {
+        spawn_step(&mut cmd, &circuit_material, grid_origin, step);
+    }
+}
+
+pub fn spawn_step(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    step: MacroStep,
+) -> Entity {
+    match step {
+        MacroStep::Wire(wire) => spawn_wire(cmd, circuit_material, grid_origin, wire),
+        MacroStep::Light(light) => {
+            let label = format!("-P{}", light.id);
+            spawn_light(cmd, circuit_material, grid_origin, light, label)
+        }
+        MacroStep::Button(button) => {
+            let label = format!("-S{}", button.id);
+            spawn_button(cmd, circuit_material, grid_origin, button, label)
+        }
+        MacroStep::RelayCoil(relay_coil) => {
+            let label = format!("-K{}", relay_coil.id);
+            spawn_relay_coil(cmd, circuit_material, grid_origin, relay_coil, label)
+        }
+        MacroStep::RelaySwitch(relay_switch) => {
+            let label = format!("-K{}", relay_switch.id);
+            spawn_relay_switch(cmd, circuit_material, grid_origin, relay_switch, label)
+        }
+        MacroStep::Clock(clock) => spawn_clock(cmd, clock),
+        MacroStep::TimeSwitch(time_switch) => spawn_time_switch(cmd, time_switch),
+        MacroStep::Fuse(fuse) => spawn_fuse(cmd, fuse),
+        MacroStep::Diode(diode) => spawn_diode(cmd, diode),
+        MacroStep::NetLabel(label) => spawn_net_label(cmd, label),
+    }
+}
+
+fn update_macro_panel(
+    mut cmd: Commands,
+    macros: Res<Macros>,
+    recorder: Res<MacroRecorder>,
+    panel: Query<Entity, With<MacroPanel>>,
+    rows: Query<(&MacroRow, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    // The rows are made again whenever a macro is added or deleted
+    if macros.is_changed() {
+        for e in panel.iter() {
+            cmd.entity(e).despawn_descendants().with_children(|root| {
+                spawn_macro_row(root, MacroRow::Record, true);
+                for (i, edit_macro) in macros.0.iter().enumerate() {
+                    root.spawn((NodeBundle::default(), Name::new(edit_macro.name.clone())))
+                        .with_children(|root| {
+                            spawn_macro_row(root, MacroRow::Play(i), true);
+                            spawn_macro_row(root, MacroRow::Delete(i), false);
+                        });
+                }
+            });
+        }
+        // The texts are filled in on the next frame, once the rows exist
+        return;
+    }
+
+    for (row, children) in rows.iter() {
+        let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) else {
+            continue;
+        };
+
+        let value = match row {
+            MacroRow::Record => match &recorder.name {
+                Some(name) => format!("Name: {name}_   (Enter to keep, Esc to discard)"),
+                None if recorder.recording => {
+                    format!("Stop recording ({} steps)", recorder.steps.len())
+                }
+                None => "Record".to_string(),
+            },
+            MacroRow::Play(i) => macros.0.get(*i).map_or(String::new(), |edit_macro| {
+                format!("{} ({} steps)", edit_macro.name, edit_macro.steps.len())
+            }),
+            MacroRow::Delete(_) => "x".to_string(),
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
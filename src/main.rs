@@ -1,6 +1,8 @@
-#![allow(clippy::too_many_arguments)]
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
 use bevy::{
+    diagnostic::Diagnostics,
+    ecs::system::SystemParam,
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     window::PrimaryWindow,
@@ -9,9 +11,102 @@ use bevy::{
 #[cfg(debug_assertions)]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+use grid::GridSize;
+use keybindings::{Action, KeyBindings};
 use rand::Rng;
+use relay_sim_core::{Circuit, Point, Solver, Switch, Visited};
+use serde::{Deserialize, Serialize};
+
+mod accessibility;
+mod analysis_window;
+mod annotations;
+mod blocks;
+mod breakpoints;
+mod capture;
+mod challenge;
+mod clock;
+mod comments;
+mod debugger;
+mod delete;
+mod device_counts;
+mod diode;
+mod dock;
+mod expressions;
+mod fuse;
+mod fuzz;
+mod glow;
+#[cfg(feature = "gpio")]
+mod gpio;
+mod grading;
+mod grid;
+mod headless;
+mod hidden;
+mod history;
+mod junction;
+mod keybindings;
+mod live_edit;
+mod load_meter;
+mod logisim;
+mod macros;
+mod measure;
+mod metadata;
+#[cfg(not(target_arch = "wasm32"))]
+mod modbus;
+mod moving;
+mod multimeter;
+mod net_highlight;
+mod net_labels;
+mod oscillation;
+mod palette;
+mod perf_overlay;
+mod placement_status;
+mod platform;
+mod plc_export;
+mod print;
+mod routing;
+mod save;
+mod scenario;
+mod scope;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(not(target_arch = "wasm32"))]
+mod serial;
+mod settle;
+mod share;
+mod sheets;
+mod short_circuit;
+mod spice_export;
+mod stress;
+mod supply;
+mod svg_export;
+mod synthesis;
+mod tabs;
+mod templates;
+mod tidy;
+mod time_switch;
+mod timer_relay;
+mod troubleshoot;
+mod tutorial;
+mod undo;
+mod vcd_export;
+mod view;
+mod wire_labels;
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|arg| arg == "--headless") {
+        std::process::exit(headless::run(&args[1..]));
+    }
+    if args.first().is_some_and(|arg| arg == "--grade") {
+        std::process::exit(grading::run(&args[1..]));
+    }
+    if args.first().is_some_and(|arg| arg == "--stress") {
+        std::process::exit(stress::run(&args[1..]));
+    }
+    if args.first().is_some_and(|arg| arg == "--import-logisim") {
+        std::process::exit(logisim::run(&args[1..]));
+    }
+
     let mut app = App::new();
     app.insert_resource(ClearColor(Color::BLACK)).add_plugins((
         DefaultPlugins.set(WindowPlugin {
@@ -19,9 +114,16 @@ fn main() {
                 title: "Circuit Simulator".to_string(),
                 resolution: WINDOWRESOULTION.into(),
                 present_mode: bevy::window::PresentMode::AutoVsync,
-                resizable: false,
+                // In the browser the app draws into the canvas of web/index.html and takes the size of the page,
+                // keys like Ctrl+S go to the app instead of the browser
+                resizable: cfg!(target_arch = "wasm32"),
+                canvas: Some("#relay-sim".to_string()),
+                fit_canvas_to_parent: true,
+                prevent_default_event_handling: true,
                 ..Default::default()
             }),
+            // The analysis window should not keep the app alive on its own
+            exit_condition: bevy::window::ExitCondition::OnPrimaryClosed,
             ..Default::default()
         }),
         SimPlugin,
@@ -39,12 +141,21 @@ struct SimPlugin;
 const GRIDORIGIN: (f32, f32) = (-360., -360.);
 const WINDOWRESOULTION: (f32, f32) = (1280., 720.);
 
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[derive(
+    Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[reflect(Component)]
 struct GridPosition {
     x: usize,
     y: usize,
 }
 
+impl From<GridPosition> for Point {
+    fn from(pos: GridPosition) -> Self {
+        Point { x: pos.x, y: pos.y }
+    }
+}
+
 impl From<Vec2> for GridPosition {
     fn from(vec: Vec2) -> Self {
         Self {
@@ -55,16 +166,32 @@ impl From<Vec2> for GridPosition {
 }
 
 // Label for power source is -K{id}
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 struct RelayCoil {
     id: usize,
     top: GridPosition,
     bottom: GridPosition,
+    #[serde(skip)]
     activated: bool,
+    // Only set for timer relays
+    #[serde(default)]
+    timer: Option<timer_relay::RelayTimer>,
+}
+
+impl RelayCoil {
+    // Let go, with a timer relay counting its delay from the beginning again
+    fn reset(&mut self) {
+        self.activated = false;
+        if let Some(timer) = &mut self.timer {
+            timer.elapsed = None;
+        }
+    }
 }
 
 // Label for relays is -K{id}
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 struct RelaySwitch {
     id: usize,
     typ: SwitchType,
@@ -72,15 +199,6 @@ struct RelaySwitch {
     bottom: GridPosition,
 }
 
-impl From<&RelaySwitch> for Wire {
-    fn from(relay: &RelaySwitch) -> Self {
-        Self {
-            first: relay.top,
-            second: relay.bottom,
-        }
-    }
-}
-
 #[derive(Component)]
 struct RelayCoilSelect {
     id: usize,
@@ -98,6 +216,15 @@ struct RelaySwitchSelect {
 struct UIButton {
     id: usize,
     has_been_pressed: bool,
+    // Maintained buttons stay latched after a click until they are clicked again, like a selector switch
+    maintained: bool,
+    latched: bool,
+}
+
+// Switches the button with the same id between momentary and maintained
+#[derive(Component)]
+struct ButtonModeSelect {
+    id: usize,
 }
 
 #[derive(Component)]
@@ -107,7 +234,8 @@ struct ButtonSelect {
 }
 
 // This is the actual switch of the button
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 struct ButtonSwitch {
     id: usize,
     typ: SwitchType,
@@ -115,36 +243,75 @@ struct ButtonSwitch {
     bottom: GridPosition,
 }
 
-impl From<&ButtonSwitch> for Wire {
-    fn from(button: &ButtonSwitch) -> Self {
-        Self {
-            first: button.top,
-            second: button.bottom,
-        }
-    }
-}
-
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Reflect, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum SwitchType {
+    #[default]
     NormallyOpen,
     NormallyClosed,
+    // Its middle grid point is the common terminal, switching over from the bottom (normally closed) to the top (normally open)
+    Changeover,
+}
+
+impl From<SwitchType> for relay_sim_core::SwitchType {
+    fn from(typ: SwitchType) -> Self {
+        match typ {
+            SwitchType::NormallyOpen => relay_sim_core::SwitchType::NormallyOpen,
+            SwitchType::NormallyClosed => relay_sim_core::SwitchType::NormallyClosed,
+            SwitchType::Changeover => relay_sim_core::SwitchType::Changeover,
+        }
+    }
 }
 
 // A Wire represented as 2 points with a line between, can only go horizontally or vertically
-#[derive(Component, Clone)]
+#[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 struct Wire {
     first: GridPosition,
     second: GridPosition,
 }
 
+// Optional short text next to a wire, like the wire numbers in real schematics
+#[derive(Component, Reflect, Clone, Default)]
+#[reflect(Component)]
+struct WireLabel(String);
+
+// Optional free text on a placed component, shown as a tooltip when hovering it
+#[derive(Component, Reflect, Clone, Default)]
+#[reflect(Component)]
+struct ComponentComment(String);
+
+// Hidden fault put in by the troubleshooting mode, a broken wire, contact or coil never conducts
+// A welded contact never moves, a changeover one stays at rest
+#[derive(Component, Clone, Copy, PartialEq)]
+enum Faulty {
+    Broken,
+    Welded,
+}
+
+impl Faulty {
+    // Where a contact is instead of where its button or relay wants it, None for a broken one that is left out
+    fn actuated(faulty: Option<&Faulty>, typ: SwitchType, actuated: bool) -> Option<bool> {
+        match faulty {
+            None => Some(actuated),
+            Some(Faulty::Broken) => None,
+            Some(Faulty::Welded) => Some(typ == SwitchType::NormallyOpen),
+        }
+    }
+}
+
 // Label for lights is -P{id}
-#[derive(Component)]
+#[derive(Component, Reflect, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
 struct Light {
     id: usize,
     top: GridPosition,
     bottom: GridPosition,
 }
 
+// The middle point of a placed light, it shows whether the light is lit
+#[derive(Component)]
+struct LightBulb;
+
 #[derive(Component)]
 struct UILight {
     id: usize,
@@ -154,28 +321,145 @@ struct UILight {
 #[derive(Component)]
 struct GridOrigin;
 
-#[derive(Component, PartialEq)]
+// The camera looking at the schematic, as opposed to cameras of other windows
+#[derive(Component)]
+struct MainCamera;
+
+// Parent of the green dots that mark the grid points
+#[derive(Component)]
+struct BackgroundPoints;
+
+// Text on top of a component body, unlike labels it has to contrast with the body instead of the background
+#[derive(Component)]
+struct BodyText;
+
+// Row of small buttons at the bottom of the left section
+#[derive(Component)]
+struct Toolbar;
+
+#[derive(Component, Reflect, Default, PartialEq)]
+#[reflect(Component)]
 struct Power(PowerType);
 
-#[derive(PartialEq)]
+// The supply that is always there, it starts out here and can be dragged elsewhere in move mode
+#[derive(Component)]
+struct MainSupply;
+
+// Where the power sources of the main supply start out on the grid
+const POSITIVE_SOURCE: GridPosition = GridPosition { x: 0, y: 19 };
+const NEGATIVE_SOURCE: GridPosition = GridPosition { x: 0, y: 16 };
+
+#[derive(Reflect, Default, Clone, Copy, PartialEq)]
 enum PowerType {
+    #[default]
     Positive,
     Negative,
 }
 
+// Every grid position that is covered by something placed, including the power sources and annotations
+#[derive(SystemParam)]
+struct PlacedPositions<'w, 's> {
+    wires: Query<'w, 's, &'static Wire>,
+    lights: Query<'w, 's, &'static Light>,
+    buttons: Query<'w, 's, &'static ButtonSwitch>,
+    relay_coils: Query<'w, 's, &'static RelayCoil>,
+    relay_switches: Query<'w, 's, &'static RelaySwitch>,
+    power_sources: Query<'w, 's, &'static GridPosition, With<Power>>,
+    annotations: Query<'w, 's, &'static annotations::Annotation>,
+}
+
+impl PlacedPositions<'_, '_> {
+    fn iter(&self) -> impl Iterator<Item = GridPosition> + '_ {
+        self.wires
+            .iter()
+            .flat_map(|wire| [wire.first, wire.second])
+            .chain(
+                self.lights
+                    .iter()
+                    .flat_map(|light| [light.top, light.bottom]),
+            )
+            .chain(
+                self.buttons
+                    .iter()
+                    .flat_map(|button| [button.top, button.bottom]),
+            )
+            .chain(
+                self.relay_coils
+                    .iter()
+                    .flat_map(|relay_coil| [relay_coil.top, relay_coil.bottom]),
+            )
+            .chain(
+                self.relay_switches
+                    .iter()
+                    .flat_map(|relay_switch| [relay_switch.top, relay_switch.bottom]),
+            )
+            .chain(self.power_sources.iter().copied())
+            .chain(
+                self.annotations
+                    .iter()
+                    .flat_map(|annotation| [annotation.start, annotation.end]),
+            )
+    }
+}
+
+const POSITIVE_WIRE_COLOR: Color = Color::rgb(0.8, 0.3, 0.3);
+const NEGATIVE_WIRE_COLOR: Color = Color::rgb(0.3, 0.4, 0.9);
+
 #[derive(Resource, Default)]
 struct CircuitHandles {
     wire_point_mesh: Mesh2dHandle,
+    // Shared by every component and wire, so placing and removing them does not leave meshes behind
+    component_body_mesh: Mesh2dHandle,
+    component_wire_mesh: OrientedMesh,
+    relay_coil_mesh: OrientedMesh,
+    // A unit square, wire lines are scaled to their size
+    wire_line_mesh: Mesh2dHandle,
     wire_material: Handle<ColorMaterial>,
     light_material: Handle<ColorMaterial>,
+    // Swapped in for wires connected to a power source, by the side they are on, and lit lights
+    positive_wire_material: Handle<ColorMaterial>,
+    negative_wire_material: Handle<ColorMaterial>,
+    lit_light_material: Handle<ColorMaterial>,
+    // For wires that are part of a short circuit
+    shorted_wire_material: Handle<ColorMaterial>,
+    // In between the two above, for lights that are only lit some of the time
+    dimmed_light_materials: Vec<Handle<ColorMaterial>>,
+}
+
+// A quad standing upright and the same one turned on its side, for both ways a component can be placed
+#[derive(Default)]
+struct OrientedMesh {
+    upright: Mesh2dHandle,
+    turned: Mesh2dHandle,
+}
+
+impl OrientedMesh {
+    fn new(meshes: &mut Assets<Mesh>, upright: Vec2) -> Self {
+        Self {
+            upright: meshes.add(shape::Quad::new(upright).into()).into(),
+            turned: meshes
+                .add(shape::Quad::new(Vec2::new(upright.y, upright.x)).into())
+                .into(),
+        }
+    }
+
+    fn get(&self, top: GridPosition, bottom: GridPosition) -> Mesh2dHandle {
+        if top.x == bottom.x {
+            self.upright.clone()
+        } else {
+            self.turned.clone()
+        }
+    }
 }
 
-#[derive(Resource, Clone)]
+#[derive(Resource, Clone, Default)]
 enum CurrentlyPlacing {
+    #[default]
     Wire,
     RelayCoil {
         id: usize,
         label: String,
+        timer: Option<timer_relay::RelayTimer>,
     },
     RelaySwitch {
         id: usize,
@@ -191,36 +475,191 @@ enum CurrentlyPlacing {
         label: String,
         typ: SwitchType,
     },
+    // Handled by the annotations plugin, annotations are not part of the circuit
+    Annotation(annotations::AnnotationShape),
+    // Handled by the routing plugin, picks two terminals and places the wires between them
+    Route,
+    // Handled by the measure plugin, clicks only pick the points to measure between
+    Measure,
+    // Handled by the troubleshooting plugin, clicks name the element that is believed to be faulty
+    Troubleshoot,
+    // Handled by the hidden region plugin, places and removes black boxes for exercises
+    HiddenRegion,
+    // Handled by the macro plugin, every click places another copy of the macro with this index
+    Macro(usize),
+    // Handled by the macro plugin like a macro, places what was copied with Ctrl+C
+    Paste,
+    // Handled by the clock plugin, every click places a clock that counts simulated seconds
+    Clock,
+    // Handled by the time switch plugin, every click places a time switch with the windows set in its panel
+    TimeSwitch,
+    // Handled by the move plugin, components are dragged to a new place
+    Move,
+    // Handled by the delete plugin, clicks remove the single element under the cursor
+    Delete,
+    // Handled by the troubleshooting plugin, clicks break elements for an exercise someone else solves
+    InjectFault,
+    // Handled by the multimeter plugin, clicks place the two probes
+    Multimeter,
+    // Handled by the fuse plugin, every click places a fuse
+    Fuse,
+    // Handled by the diode plugin, every click places a diode or turns a placed one around
+    Diode,
+    // Handled by the supply plugin, every click places another supply
+    Supply,
+    // Handled by the net label plugin, a click opens the editor for the label on that grid point
+    NetLabel,
+    // Handled by the blocks plugin, every click places another copy of the last made or picked block
+    Block,
+    // Handled by the macro plugin like a macro, stamps the built-in template with this index
+    Template(usize),
+}
+
+// Components are placed upright unless turned with the rotate key, turned ones have their top terminal on the right
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+enum ComponentOrientation {
+    #[default]
+    Vertical,
+    Horizontal,
 }
 
-impl Default for CurrentlyPlacing {
-    fn default() -> Self {
-        Self::Wire
+impl ComponentOrientation {
+    // The top and bottom terminal of a component around the given grid point, None when it would not fit on the grid
+    fn terminals(
+        self,
+        middle: GridPosition,
+        grid_size: &GridSize,
+    ) -> Option<(GridPosition, GridPosition)> {
+        let GridPosition { x, y } = middle;
+        match self {
+            ComponentOrientation::Vertical => (y >= 1 && y + 1 < grid_size.height)
+                .then_some((GridPosition { x, y: y + 1 }, GridPosition { x, y: y - 1 })),
+            ComponentOrientation::Horizontal => (x >= 1 && x + 1 < grid_size.width)
+                .then_some((GridPosition { x: x + 1, y }, GridPosition { x: x - 1, y })),
+        }
     }
 }
 
-#[derive(Resource, Default)]
+// The simulation only evaluates the circuit while running, the run button in the toolbar switches it
+#[derive(Resource)]
 struct IsRunning(bool);
 
+#[derive(Component)]
+struct RunButton;
+
+// For every system that moves the simulation forward
+fn is_running(running: Res<IsRunning>) -> bool {
+    running.0
+}
+
 impl Plugin for SimPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Time::<Fixed>::from_hz(20.))
             .init_resource::<CircuitHandles>()
             .init_resource::<CurrentlyPlacing>()
-            .init_resource::<IsRunning>()
+            .insert_resource(IsRunning(true))
+            .init_resource::<ComponentOrientation>()
+            .init_resource::<SimulationScratch>()
+            .init_resource::<PlacementConflict>()
+            .add_plugins((
+                perf_overlay::PerfOverlayPlugin,
+                history::HistoryPlugin,
+                capture::CapturePlugin,
+                glow::GlowPlugin,
+                save::SavePlugin,
+                wire_labels::WireLabelPlugin,
+                annotations::AnnotationPlugin,
+                print::PrintPlugin,
+                analysis_window::AnalysisWindowPlugin,
+                view::ViewPlugin,
+                routing::RoutingPlugin,
+                tidy::TidyPlugin,
+                measure::MeasurePlugin,
+                metadata::MetadataPlugin,
+                comments::CommentPlugin,
+            ))
+            .add_plugins((
+                keybindings::KeyBindingsPlugin,
+                load_meter::LoadMeterPlugin,
+                troubleshoot::TroubleshootPlugin,
+                hidden::HiddenRegionPlugin,
+                palette::CommandPalettePlugin,
+                macros::MacroPlugin,
+                live_edit::LiveEditPlugin,
+                accessibility::AccessibilityPlugin,
+                tabs::TabsPlugin,
+                clock::ClockPlugin,
+                time_switch::TimeSwitchPlugin,
+                fuzz::FuzzPlugin,
+                undo::UndoPlugin,
+                short_circuit::ShortCircuitPlugin,
+                moving::MovePlugin,
+            ))
+            .add_plugins((
+                timer_relay::TimerRelayPlugin,
+                device_counts::DeviceCountsPlugin,
+                placement_status::PlacementStatusPlugin,
+                delete::DeletePlugin,
+                svg_export::SvgExportPlugin,
+                scope::ScopePlugin,
+                vcd_export::VcdExportPlugin,
+                oscillation::OscillationPlugin,
+                settle::SettlePlugin,
+                debugger::DebuggerPlugin,
+                breakpoints::BreakpointPlugin,
+                multimeter::MultimeterPlugin,
+                net_highlight::NetHighlightPlugin,
+                fuse::FusePlugin,
+                diode::DiodePlugin,
+            ))
+            .add_plugins((
+                supply::SupplyPlugin,
+                junction::JunctionPlugin,
+                net_labels::NetLabelPlugin,
+                sheets::SheetsPlugin,
+                blocks::BlocksPlugin,
+                templates::TemplatesPlugin,
+                challenge::ChallengePlugin,
+                tutorial::TutorialPlugin,
+                grid::GridPlugin,
+                share::SharePlugin,
+                logisim::LogisimPlugin,
+                spice_export::SpiceExportPlugin,
+                plc_export::PlcExportPlugin,
+                expressions::ExpressionsPlugin,
+                synthesis::SynthesisPlugin,
+            ))
+            .add_plugins(dock::DockPlugin)
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
                 (
-                    accept_input,
-                    change_light_opacity,
+                    (
+                        rotate_placement,
+                        reject_overlapping_placement,
+                        accept_input,
+                        preview_placed_component,
+                    )
+                        .chain(),
+                    flash_placement_conflict,
+                    change_light_opacity.after(glow::update_light_brightness),
                     handle_light_button_press,
-                    handle_button_button_press,
+                    (handle_button_button_press, show_button_modes).chain(),
                     handle_relay_switch_button_press,
                     handle_relay_coil_button_press,
+                    (handle_run_button_press, update_run_button).chain(),
                 ),
             )
-            .add_systems(FixedUpdate, simulate);
+            .add_systems(PostUpdate, detect_wiring_changes)
+            .add_systems(FixedUpdate, simulate.run_if(is_running));
+
+        // Browser pages cannot listen for connections or open serial ports
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugins((modbus::ModbusPlugin, serial::SerialPlugin));
+        #[cfg(feature = "gpio")]
+        app.add_plugins(gpio::GpioPlugin);
+        #[cfg(feature = "scripting")]
+        app.add_plugins(scripting::ScriptingPlugin);
     }
 }
 
@@ -229,8 +668,9 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut handles: ResMut<CircuitHandles>,
+    counts: Res<device_counts::DeviceCounts>,
 ) {
-    cmd.spawn(Camera2dBundle::default());
+    cmd.spawn((Camera2dBundle::default(), MainCamera));
 
     let circle_mesh: Mesh2dHandle = meshes
         .add(
@@ -244,8 +684,21 @@ fn setup(
     let wire_material = materials.add(ColorMaterial::from(Color::GRAY));
     let light_material = materials.add(ColorMaterial::from(Color::YELLOW));
     handles.wire_point_mesh = circle_mesh;
+    handles.component_body_mesh = meshes
+        .add(shape::Quad::new(Vec2 { x: 20., y: 20. }).into())
+        .into();
+    handles.component_wire_mesh = OrientedMesh::new(&mut meshes, Vec2 { x: 4., y: 40. });
+    handles.relay_coil_mesh = OrientedMesh::new(&mut meshes, Vec2 { x: 30., y: 20. });
+    handles.wire_line_mesh = meshes.add(shape::Quad::new(Vec2::ONE).into()).into();
     handles.wire_material = wire_material;
     handles.light_material = light_material;
+    handles.positive_wire_material = materials.add(ColorMaterial::from(POSITIVE_WIRE_COLOR));
+    handles.negative_wire_material = materials.add(ColorMaterial::from(NEGATIVE_WIRE_COLOR));
+    handles.lit_light_material = materials.add(ColorMaterial::from(Color::YELLOW));
+    handles.shorted_wire_material = materials.add(ColorMaterial::from(Color::RED));
+    handles.dimmed_light_materials = (0..3)
+        .map(|_| materials.add(ColorMaterial::from(Color::YELLOW)))
+        .collect();
 
     // UI
     cmd.spawn(
@@ -263,14 +716,13 @@ fn setup(
         ),
     )
     .with_children(|root| {
-        // Left section
+        // Left section, the dock draws itself over the top of it and the toolbar below scrolls when it does not fit
         root.spawn((
             NodeBundle {
                 style: Style {
                     width: Val::Px(280.),
-                    display: Display::Flex,
-                    flex_direction: FlexDirection::Row,
-                    flex_wrap: FlexWrap::Wrap,
+                    height: Val::Percent(100.),
+                    overflow: Overflow::clip_y(),
                     ..Default::default()
                 },
                 background_color: BackgroundColor(Color::rgb(0.1, 0.1, 0.1)),
@@ -279,135 +731,41 @@ fn setup(
             Name::new("Left Section"),
         ))
         .with_children(|root| {
-            let mut random = rand::thread_rng();
-
             root.spawn((
                 NodeBundle {
                     style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(dock::DOCK_HEIGHT),
+                        width: Val::Px(280.),
+                        padding: UiRect::bottom(Val::Px(placement_status::STATUS_STRIP_HEIGHT)),
                         display: Display::Flex,
-                        flex_direction: FlexDirection::Column,
-                        width: Val::Px(100.),
+                        flex_direction: FlexDirection::Row,
+                        flex_wrap: FlexWrap::Wrap,
                         ..Default::default()
                     },
                     ..Default::default()
                 },
-                Name::from("Light container"),
+                Name::new("Left Section Content"),
+                device_counts::LeftSectionContent::default(),
             ))
             .with_children(|root| {
-                for i in 1..=6 {
-                    root.spawn((
-                        ButtonBundle {
-                            style: Style {
-                                width: Val::Px(50.),
-                                height: Val::Px(50.),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                border: UiRect::all(Val::Px(7.)),
-                                ..Default::default()
-                            },
-                            border_color: BorderColor(Color::Rgba {
-                                red: 0.9,
-                                green: 0.9,
-                                blue: 0.9,
-                                alpha: 0.,
-                            }),
-                            background_color: BackgroundColor(Color::Rgba {
-                                red: random.gen_range(0.0..1.0),
-                                green: random.gen_range(0.0..1.0),
-                                blue: random.gen_range(0.0..1.0),
-                                alpha: 1.,
-                            }),
-
+                let mut random = rand::thread_rng();
+
+                // The palette rows are drawn by the dock, these only hold their state and colors
+                root.spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_direction: FlexDirection::Column,
+                            width: Val::Px(100.),
                             ..Default::default()
                         },
-                        Name::new(format!("Light {} Button", i)),
-                        UILight {
-                            id: i,
-                            is_lit: false,
-                        },
-                    ))
-                    .with_children(|root| {
-                        root.spawn((
-                            TextBundle::from_section(
-                                format!("-P{i}"),
-                                TextStyle {
-                                    font_size: 20.,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                    ..Default::default()
-                                },
-                            ),
-                            Name::new(format!("Light {} Button Text", i)),
-                        ));
-                    });
-                }
-            });
-            root.spawn((
-                NodeBundle {
-                    style: Style {
-                        display: Display::Flex,
-                        flex_direction: FlexDirection::Column,
                         ..Default::default()
                     },
-                    ..Default::default()
-                },
-                Name::new("Button Container"),
-            ))
-            .with_children(|root| {
-                for i in 1..=6 {
-                    let color = Color::Rgba {
-                        red: random.gen_range(0.0..1.0),
-                        green: random.gen_range(0.0..1.0),
-                        blue: random.gen_range(0.0..1.0),
-                        alpha: 1.,
-                    };
-                    root.spawn((
-                        NodeBundle {
-                            style: Style {
-                                display: Display::Flex,
-                                flex_direction: FlexDirection::Row,
-                                height: Val::Px(50.),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
-                        Name::new(format!("Button {} Container", i)),
-                    ))
-                    .with_children(|root| {
-                        // Button for pressing
-                        root.spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(50.),
-                                    height: Val::Px(50.),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..Default::default()
-                                },
-                                background_color: BackgroundColor(color),
-
-                                ..Default::default()
-                            },
-                            Name::new(format!("Button {} Button", i)),
-                            UIButton {
-                                id: i,
-                                has_been_pressed: false,
-                            },
-                        ))
-                        .with_children(|root| {
-                            root.spawn((
-                                TextBundle::from_section(
-                                    format!("-S{i}"),
-                                    TextStyle {
-                                        font_size: 20.,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
-                                        ..Default::default()
-                                    },
-                                ),
-                                Name::new(format!("Button {} Button Text", i)),
-                            ));
-                        });
-                        // The two buttons for placing the normally open and normally closed switch
-
+                    Name::from("Light container"),
+                ))
+                .with_children(|root| {
+                    for i in 1..=counts.lights {
                         root.spawn((
                             ButtonBundle {
                                 style: Style {
@@ -422,244 +780,360 @@ fn setup(
                                     red: 0.9,
                                     green: 0.9,
                                     blue: 0.9,
-                                    alpha: 0.4,
+                                    alpha: 0.,
                                 }),
-                                background_color: BackgroundColor(color),
-                                ..Default::default()
-                            },
-                            Name::new(format!("Button {} NO Button", i)),
-                            ButtonSelect {
-                                id: i,
-                                typ: SwitchType::NormallyOpen,
-                            },
-                        ))
-                        .with_children(|root| {
-                            root.spawn((
-                                TextBundle::from_section(
-                                    "NO",
-                                    TextStyle {
-                                        font_size: 20.,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
-                                        ..Default::default()
-                                    },
-                                ),
-                                Name::new(format!("Button {} NO Button Text", i)),
-                            ));
-                        });
-
-                        root.spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(50.),
-                                    height: Val::Px(50.),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    border: UiRect::all(Val::Px(7.)),
-                                    ..Default::default()
-                                },
-                                border_color: BorderColor(Color::Rgba {
-                                    red: 0.9,
-                                    green: 0.9,
-                                    blue: 0.9,
-                                    alpha: 0.4,
+                                background_color: BackgroundColor(Color::Rgba {
+                                    red: random.gen_range(0.0..1.0),
+                                    green: random.gen_range(0.0..1.0),
+                                    blue: random.gen_range(0.0..1.0),
+                                    alpha: 1.,
                                 }),
-                                background_color: BackgroundColor(color),
 
                                 ..Default::default()
                             },
-                            Name::new(format!("Button {} NC Button", i)),
-                            ButtonSelect {
+                            Name::new(format!("Light {} Button", i)),
+                            UILight {
                                 id: i,
-                                typ: SwitchType::NormallyClosed,
+                                is_lit: false,
                             },
                         ))
                         .with_children(|root| {
                             root.spawn((
                                 TextBundle::from_section(
-                                    "NC",
+                                    format!("-P{i}"),
                                     TextStyle {
                                         font_size: 20.,
                                         color: Color::rgb(0.9, 0.9, 0.9),
                                         ..Default::default()
                                     },
                                 ),
-                                Name::new(format!("Button {} NC Button Text", i)),
+                                Name::new(format!("Light {} Button Text", i)),
                             ));
                         });
-                    });
-                }
-            });
-            root.spawn((
-                NodeBundle {
-                    style: Style {
-                        display: Display::Flex,
-                        flex_direction: FlexDirection::Column,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-                Name::new("Relay Container"),
-            ))
-            .with_children(|root| {
-                for i in 1..=6 {
-                    root.spawn((
-                        NodeBundle {
-                            style: Style {
-                                display: Display::Flex,
-                                flex_direction: FlexDirection::Row,
-                                height: Val::Px(50.),
-                                ..Default::default()
-                            },
+                    }
+                });
+                root.spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_direction: FlexDirection::Column,
                             ..Default::default()
                         },
-                        Name::new(format!("Relay {} Container", i)),
-                    ))
-                    .with_children(|root| {
-                        // Like the button with three buttons, one with label -K{id} for the coil, one for NO and one for NC for the switches
+                        ..Default::default()
+                    },
+                    Name::new("Button Container"),
+                ))
+                .with_children(|root| {
+                    for i in 1..=counts.buttons {
                         let color = Color::Rgba {
                             red: random.gen_range(0.0..1.0),
                             green: random.gen_range(0.0..1.0),
                             blue: random.gen_range(0.0..1.0),
                             alpha: 1.,
                         };
-
                         root.spawn((
-                            ButtonBundle {
+                            NodeBundle {
                                 style: Style {
-                                    width: Val::Px(50.),
+                                    display: Display::Flex,
+                                    flex_direction: FlexDirection::Row,
                                     height: Val::Px(50.),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    border: UiRect::all(Val::Px(7.)),
                                     ..Default::default()
                                 },
-                                border_color: BorderColor(Color::Rgba {
-                                    red: 0.9,
-                                    green: 0.9,
-                                    blue: 0.9,
-                                    alpha: 0.4,
-                                }),
-                                background_color: BackgroundColor(color),
-
                                 ..Default::default()
                             },
-                            Name::new(format!("Relay {} Coil Button", i)),
-                            RelayCoilSelect { id: i },
+                            Name::new(format!("Button {} Container", i)),
                         ))
                         .with_children(|root| {
+                            // Button for pressing, the border shows whether a maintained button is latched
                             root.spawn((
-                                TextBundle::from_section(
-                                    format!("-K{i}"),
-                                    TextStyle {
-                                        font_size: 20.,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(50.),
+                                        height: Val::Px(50.),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        border: UiRect::all(Val::Px(5.)),
                                         ..Default::default()
                                     },
-                                ),
-                                Name::new(format!("Relay {} Coil Button Text", i)),
-                            ));
-                        });
+                                    border_color: BorderColor(Color::NONE),
+                                    background_color: BackgroundColor(color),
+
+                                    ..Default::default()
+                                },
+                                Name::new(format!("Button {} Button", i)),
+                                UIButton {
+                                    id: i,
+                                    has_been_pressed: false,
+                                    maintained: false,
+                                    latched: false,
+                                },
+                            ))
+                            .with_children(|root| {
+                                root.spawn((
+                                    TextBundle::from_section(
+                                        format!("-S{i}"),
+                                        TextStyle {
+                                            font_size: 20.,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                            ..Default::default()
+                                        },
+                                    ),
+                                    Name::new(format!("Button {} Button Text", i)),
+                                ));
+                            });
+                            // The buttons for placing the normally open, normally closed and changeover switch
+                            for (typ, text) in [
+                                (SwitchType::NormallyOpen, "NO"),
+                                (SwitchType::NormallyClosed, "NC"),
+                                (SwitchType::Changeover, "CO"),
+                            ] {
+                                root.spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(50.),
+                                            height: Val::Px(50.),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            border: UiRect::all(Val::Px(7.)),
+                                            ..Default::default()
+                                        },
+                                        border_color: BorderColor(Color::Rgba {
+                                            red: 0.9,
+                                            green: 0.9,
+                                            blue: 0.9,
+                                            alpha: 0.4,
+                                        }),
+                                        background_color: BackgroundColor(color),
+                                        ..Default::default()
+                                    },
+                                    Name::new(format!("Button {} {} Button", i, text)),
+                                    ButtonSelect { id: i, typ },
+                                ))
+                                .with_children(|root| {
+                                    root.spawn((
+                                        TextBundle::from_section(
+                                            text,
+                                            TextStyle {
+                                                font_size: 20.,
+                                                color: Color::rgb(0.9, 0.9, 0.9),
+                                                ..Default::default()
+                                            },
+                                        ),
+                                        Name::new(format!("Button {} {} Button Text", i, text)),
+                                    ));
+                                });
+                            }
 
+                            root.spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(50.),
+                                        height: Val::Px(50.),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..Default::default()
+                                    },
+                                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                                    ..Default::default()
+                                },
+                                Name::new(format!("Button {} Mode Button", i)),
+                                ButtonModeSelect { id: i },
+                            ))
+                            .with_children(|root| {
+                                root.spawn((
+                                    TextBundle::from_section(
+                                        "Tap",
+                                        TextStyle {
+                                            font_size: 16.,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                            ..Default::default()
+                                        },
+                                    ),
+                                    Name::new(format!("Button {} Mode Button Text", i)),
+                                ));
+                            });
+                        });
+                    }
+                });
+                root.spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            flex_direction: FlexDirection::Column,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    Name::new("Relay Container"),
+                ))
+                .with_children(|root| {
+                    for i in 1..=counts.relays {
                         root.spawn((
-                            ButtonBundle {
+                            NodeBundle {
                                 style: Style {
-                                    width: Val::Px(50.),
+                                    display: Display::Flex,
+                                    flex_direction: FlexDirection::Row,
                                     height: Val::Px(50.),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    border: UiRect::all(Val::Px(7.)),
                                     ..Default::default()
                                 },
-                                border_color: BorderColor(Color::Rgba {
-                                    red: 0.9,
-                                    green: 0.9,
-                                    blue: 0.9,
-                                    alpha: 0.4,
-                                }),
-                                background_color: BackgroundColor(color),
-
                                 ..Default::default()
                             },
-                            Name::new(format!("Relay {} NO Button", i)),
-                            RelaySwitchSelect {
-                                id: i,
-                                typ: SwitchType::NormallyOpen,
-                            },
+                            Name::new(format!("Relay {} Container", i)),
                         ))
                         .with_children(|root| {
+                            // Like the button with three buttons, one with label -K{id} for the coil, one for NO and one for NC for the switches
+                            let color = Color::Rgba {
+                                red: random.gen_range(0.0..1.0),
+                                green: random.gen_range(0.0..1.0),
+                                blue: random.gen_range(0.0..1.0),
+                                alpha: 1.,
+                            };
+
                             root.spawn((
-                                TextBundle::from_section(
-                                    "NO",
-                                    TextStyle {
-                                        font_size: 20.,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(50.),
+                                        height: Val::Px(50.),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        border: UiRect::all(Val::Px(7.)),
                                         ..Default::default()
                                     },
-                                ),
-                                Name::new(format!("Relay {} NO Button Text", i)),
-                            ));
-                        });
+                                    border_color: BorderColor(Color::Rgba {
+                                        red: 0.9,
+                                        green: 0.9,
+                                        blue: 0.9,
+                                        alpha: 0.4,
+                                    }),
+                                    background_color: BackgroundColor(color),
 
-                        root.spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(50.),
-                                    height: Val::Px(50.),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    border: UiRect::all(Val::Px(7.)),
                                     ..Default::default()
                                 },
-                                border_color: BorderColor(Color::Rgba {
-                                    red: 0.9,
-                                    green: 0.9,
-                                    blue: 0.9,
-                                    alpha: 0.4,
-                                }),
-                                background_color: BackgroundColor(color),
+                                Name::new(format!("Relay {} Coil Button", i)),
+                                RelayCoilSelect { id: i },
+                            ))
+                            .with_children(|root| {
+                                root.spawn((
+                                    TextBundle::from_section(
+                                        format!("-K{i}"),
+                                        TextStyle {
+                                            font_size: 20.,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                            ..Default::default()
+                                        },
+                                    ),
+                                    Name::new(format!("Relay {} Coil Button Text", i)),
+                                ));
+                            });
+
+                            for (typ, text) in [
+                                (SwitchType::NormallyOpen, "NO"),
+                                (SwitchType::NormallyClosed, "NC"),
+                                (SwitchType::Changeover, "CO"),
+                            ] {
+                                root.spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(50.),
+                                            height: Val::Px(50.),
+                                            justify_content: JustifyContent::Center,
+                                            align_items: AlignItems::Center,
+                                            border: UiRect::all(Val::Px(7.)),
+                                            ..Default::default()
+                                        },
+                                        border_color: BorderColor(Color::Rgba {
+                                            red: 0.9,
+                                            green: 0.9,
+                                            blue: 0.9,
+                                            alpha: 0.4,
+                                        }),
+                                        background_color: BackgroundColor(color),
 
-                                ..Default::default()
-                            },
-                            Name::new(format!("Relay {} NC Button", i)),
-                            RelaySwitchSelect {
-                                id: i,
-                                typ: SwitchType::NormallyClosed,
-                            },
-                        ))
-                        .with_children(|root| {
+                                        ..Default::default()
+                                    },
+                                    Name::new(format!("Relay {} {} Button", i, text)),
+                                    RelaySwitchSelect { id: i, typ },
+                                ))
+                                .with_children(|root| {
+                                    root.spawn((
+                                        TextBundle::from_section(
+                                            text,
+                                            TextStyle {
+                                                font_size: 20.,
+                                                color: Color::rgb(0.9, 0.9, 0.9),
+                                                ..Default::default()
+                                            },
+                                        ),
+                                        Name::new(format!("Relay {} {} Button Text", i, text)),
+                                    ));
+                                });
+                            }
+
+                            // Places the coil as a timer relay instead
                             root.spawn((
-                                TextBundle::from_section(
-                                    "NC",
-                                    TextStyle {
-                                        font_size: 20.,
-                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(50.),
+                                        height: Val::Px(50.),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        border: UiRect::all(Val::Px(7.)),
                                         ..Default::default()
                                     },
-                                ),
-                                Name::new(format!("Relay {} NC Button Text", i)),
-                            ));
+                                    border_color: BorderColor(Color::Rgba {
+                                        red: 0.9,
+                                        green: 0.9,
+                                        blue: 0.9,
+                                        alpha: 0.4,
+                                    }),
+                                    background_color: BackgroundColor(color),
+
+                                    ..Default::default()
+                                },
+                                Name::new(format!("Relay {} Timer Button", i)),
+                                timer_relay::TimerRelaySelect { id: i },
+                            ))
+                            .with_children(|root| {
+                                root.spawn((
+                                    TextBundle::from_section(
+                                        "T",
+                                        TextStyle {
+                                            font_size: 20.,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                            ..Default::default()
+                                        },
+                                    ),
+                                    Name::new(format!("Relay {} Timer Button Text", i)),
+                                ));
+                            });
                         });
-                    });
-                }
+                    }
+                });
+                // Filled by the plugins in PostStartup
+                root.spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::Flex,
+                            flex_direction: FlexDirection::Row,
+                            flex_wrap: FlexWrap::Wrap,
+                            width: Val::Percent(100.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    Name::new("Toolbar"),
+                    Toolbar,
+                ))
+                .with_children(|root| {
+                    spawn_toolbar_button(root, "Stop", "Run/Stop", RunButton);
+                });
             });
         });
     });
 
     // Point Grid, the ui section stretches out 280 pixels, meaning there is 1000 pixels left for the grid
 
-    // 48 * 48 grid with origin at the bottom left, 20 pixels of distance between each point, also that distance to the border
-
-    let circle_mesh: Mesh2dHandle = meshes
-        .add(
-            shape::Circle {
-                radius: 2.5,
-                ..Default::default()
-            }
-            .into(),
-        )
-        .into();
-
-    let circle_material = materials.add(ColorMaterial::from(Color::GREEN));
+    // Grid with origin at the bottom left, 20 pixels of distance between each point, how many points there are is up to the grid size
 
     let grid_origin = cmd
         .spawn((
@@ -672,41 +1146,23 @@ fn setup(
         ))
         .id();
 
-    let background_points = cmd
-        .spawn((SpatialBundle::default(), Name::new("Background Points")))
-        .set_parent(grid_origin)
-        .id();
-
-    for x in 0..50 {
-        for y in 0..36 {
-            cmd.spawn((
-                MaterialMesh2dBundle {
-                    mesh: circle_mesh.clone(),
-                    material: circle_material.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        20. * x as f32 + 10.,
-                        20. * y as f32 + 10.,
-                        0.,
-                    )),
-                    ..Default::default()
-                },
-                GridPosition { x, y },
-                Name::new(format!("GridMarker {}, {}", x, y)),
-            ))
-            .set_parent(background_points);
-        }
-    }
+    // The grid plugin fills this with the dots for the size of the grid
+    cmd.spawn((
+        SpatialBundle::default(),
+        Name::new("Background Points"),
+        BackgroundPoints,
+    ))
+    .set_parent(grid_origin);
 
     // The default power source
     cmd.spawn((
         Name::new("Power Source Positive"),
         Power(PowerType::Positive),
-        GridPosition { x: 0, y: 19 },
+        MainSupply,
+        POSITIVE_SOURCE,
         MaterialMesh2dBundle {
             material: materials.add(ColorMaterial::from(Color::RED)),
-            mesh: meshes
-                .add(shape::Quad::new(Vec2 { x: 20., y: 20. }).into())
-                .into(),
+            mesh: handles.component_body_mesh.clone(),
             transform: Transform::from_translation(Vec3::new(10., 20. * 19. + 10., 5.)),
             ..Default::default()
         },
@@ -716,12 +1172,11 @@ fn setup(
     cmd.spawn((
         Name::new("Power Source Negative"),
         Power(PowerType::Negative),
-        GridPosition { x: 0, y: 16 },
+        MainSupply,
+        NEGATIVE_SOURCE,
         MaterialMesh2dBundle {
             material: materials.add(ColorMaterial::from(Color::BLUE)),
-            mesh: meshes
-                .add(shape::Quad::new(Vec2 { x: 20., y: 20. }).into())
-                .into(),
+            mesh: handles.component_body_mesh.clone(),
             transform: Transform::from_translation(Vec3::new(10., 20. * 16. + 10., 5.)),
             ..Default::default()
         },
@@ -729,35 +1184,340 @@ fn setup(
     .set_parent(grid_origin);
 }
 
-fn convert_mouse_to_grid(pos: Vec2) -> Option<GridPosition> {
+fn spawn_toolbar_button(
+    root: &mut ChildBuilder,
+    label: &str,
+    name: &str,
+    marker: impl Bundle,
+) -> Entity {
+    root.spawn((
+        ButtonBundle {
+            style: Style {
+                height: Val::Px(30.),
+                padding: UiRect::horizontal(Val::Px(8.)),
+                margin: UiRect::all(Val::Px(2.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.25, 0.25, 0.25)),
+            ..Default::default()
+        },
+        Name::new(format!("{name} Button")),
+        marker,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new(format!("{name} Button Text")),
+        ));
+    })
+    .id()
+}
+
+// Center of a grid point in world space, for drawing things that are not parented to the grid origin
+fn grid_to_world(pos: GridPosition) -> Vec2 {
+    Vec2::new(
+        GRIDORIGIN.0 + 20. * pos.x as f32 + 10.,
+        GRIDORIGIN.1 + 20. * pos.y as f32 + 10.,
+    )
+}
+
+fn convert_mouse_to_grid(
+    pos: Vec2,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    grid_size: &GridSize,
+) -> Option<GridPosition> {
     // the 280 comes from the ui section width
-    if pos.x < GRIDORIGIN.0 || pos.y < GRIDORIGIN.1 || pos.x < 280. {
+    if pos.x < 280. {
         return None;
     }
 
-    // 0, 0 in mouse space is the top left cornor
-    let x = ((pos.x - 280.) / 20.) as usize;
-    let y = (-(pos.y - WINDOWRESOULTION.1) / 20.) as usize;
+    // The camera can be moved and zoomed, so this has to go through world space
+    let world = camera.viewport_to_world_2d(camera_transform, pos)?;
+    let grid = (world - Vec2::new(GRIDORIGIN.0, GRIDORIGIN.1)) / 20.;
+    if grid.x < 0. || grid.y < 0. {
+        return None;
+    }
+
+    Some(GridPosition::from(grid)).filter(|pos| grid_size.contains(*pos))
+}
+
+// Stopping puts everything back to rest, so the circuit can be edited without relays holding on
+fn handle_run_button_press(
+    run_button: Query<&Interaction, (Changed<Interaction>, With<RunButton>)>,
+    mut running: ResMut<IsRunning>,
+    mut relay_coils: Query<&mut RelayCoil>,
+    mut ui_lights: Query<&mut UILight>,
+    mut ui_buttons: Query<&mut UIButton>,
+    mut scratch: ResMut<SimulationScratch>,
+) {
+    if !run_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    running.0 = !running.0;
+    if running.0 {
+        return;
+    }
+
+    for mut relay_coil in relay_coils.iter_mut() {
+        relay_coil.reset();
+    }
+    for mut ui_light in ui_lights.iter_mut() {
+        ui_light.is_lit = false;
+    }
+    for mut ui_button in ui_buttons.iter_mut() {
+        ui_button.has_been_pressed = false;
+        ui_button.latched = false;
+    }
+    scratch.solver.clear();
+    scratch.active_button_ids.clear();
+    scratch.other_sheet_relays.clear();
+}
+
+fn update_run_button(
+    running: Res<IsRunning>,
+    run_button: Query<&Children, With<RunButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !running.is_changed() {
+        return;
+    }
 
-    Some(GridPosition { x, y })
+    for children in run_button.iter() {
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = if running.0 { "Stop" } else { "Run" }.to_string();
+        }
+    }
 }
 
-fn change_light_opacity(mut ui_button: Query<(&UILight, &mut BackgroundColor, &mut BorderColor)>) {
+fn show_button_modes(
+    mut ui_buttons: Query<(&UIButton, &mut BorderColor), Changed<UIButton>>,
+    mode_selects: Query<(&ButtonModeSelect, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (ui_button, mut border_color) in ui_buttons.iter_mut() {
+        let border = match (ui_button.maintained, ui_button.latched) {
+            (false, _) => Color::NONE,
+            (true, false) => Color::rgba(0.9, 0.9, 0.9, 0.3),
+            (true, true) => Color::rgb(1., 0.9, 0.3),
+        };
+        if border_color.0 != border {
+            border_color.0 = border;
+        }
+
+        for (mode_select, children) in mode_selects.iter() {
+            if mode_select.id != ui_button.id {
+                continue;
+            }
+            let mut texts = texts.iter_many_mut(children);
+            while let Some(mut text) = texts.fetch_next() {
+                text.sections[0].value =
+                    if ui_button.maintained { "Latch" } else { "Tap" }.to_string();
+            }
+        }
+    }
+}
+
+fn change_light_opacity(
+    mut ui_button: Query<(&UILight, &mut BackgroundColor, &mut BorderColor)>,
+    brightness: Res<glow::LightBrightness>,
+) {
     for (ui_light, mut background_color, mut border_color) in ui_button.iter_mut() {
-        if ui_light.is_lit {
-            background_color.0.set_a(0.95);
-            border_color.0.set_a(0.95);
-        } else {
-            background_color.0.set_a(0.4);
-            border_color.0.set_a(0.1);
+        let brightness = brightness.of(ui_light.id);
+        background_color.0.set_a(0.4 + 0.55 * brightness);
+        border_color.0.set_a(0.1 + 0.85 * brightness);
+    }
+}
+
+fn rotate_placement(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut orientation: ResMut<ComponentOrientation>,
+) {
+    if !bindings.just_pressed(&keys, Action::Rotate) {
+        return;
+    }
+
+    *orientation = match *orientation {
+        ComponentOrientation::Vertical => ComponentOrientation::Horizontal,
+        ComponentOrientation::Horizontal => ComponentOrientation::Vertical,
+    };
+    info!(
+        "Placing components {}",
+        match *orientation {
+            ComponentOrientation::Vertical => "upright",
+            ComponentOrientation::Horizontal => "turned",
         }
+    );
+}
+
+// Outlines where the component that is being placed would go, with its terminals marked
+// Red at the edge of the grid, where a terminal would lie outside of it and clicks place nothing
+fn preview_placed_component(
+    currently_placing: Res<CurrentlyPlacing>,
+    orientation: Res<ComponentOrientation>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut gizmos: Gizmos,
+) {
+    let common = match *currently_placing {
+        CurrentlyPlacing::Light { .. } | CurrentlyPlacing::RelayCoil { .. } => false,
+        CurrentlyPlacing::Button { typ, .. } | CurrentlyPlacing::RelaySwitch { typ, .. } => {
+            typ == SwitchType::Changeover
+        }
+        _ => return,
+    };
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(middle) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+    let Some((top, bottom)) = orientation.terminals(middle, &grid_size) else {
+        let size = match *orientation {
+            ComponentOrientation::Vertical => Vec2::new(24., 60.),
+            ComponentOrientation::Horizontal => Vec2::new(60., 24.),
+        };
+        gizmos.rect_2d(
+            grid_to_world(middle),
+            0.,
+            size,
+            Color::rgba(1., 0.2, 0.2, 0.6),
+        );
+        return;
+    };
+
+    let color = Color::rgba(1., 1., 1., 0.4);
+    gizmos.rect_2d(
+        grid_to_world(middle),
+        0.,
+        oriented(top, bottom, Vec2::new(24., 60.)),
+        color,
+    );
+    let terminals = [Some(top), Some(bottom), common.then_some(middle)];
+    for terminal in terminals.into_iter().flatten() {
+        gizmos.circle_2d(grid_to_world(terminal), 5., color);
+    }
+}
+
+// Grid points of components that were in the way of the last rejected placement, flashed until the given elapsed time
+#[derive(Resource, Default)]
+struct PlacementConflict {
+    points: Vec<GridPosition>,
+    until: f32,
+}
+
+const CONFLICT_FLASH_SECONDS: f32 = 0.6;
+
+// Components may share a terminal, but neither may have its middle on the other one
+// The click is taken away before accept_input sees it, so nothing is placed
+fn reject_overlapping_placement(
+    mut mouse_button: ResMut<Input<MouseButton>>,
+    currently_placing: Res<CurrentlyPlacing>,
+    orientation: Res<ComponentOrientation>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    components: Query<AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>>,
+    time: Res<Time>,
+    mut conflict: ResMut<PlacementConflict>,
+) {
+    if !matches!(
+        *currently_placing,
+        CurrentlyPlacing::Light { .. }
+            | CurrentlyPlacing::Button { .. }
+            | CurrentlyPlacing::RelayCoil { .. }
+            | CurrentlyPlacing::RelaySwitch { .. }
+    ) || !mouse_button.just_pressed(MouseButton::Left)
+    {
+        return;
+    }
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some((top, bottom)) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .and_then(|middle| orientation.terminals(middle, &grid_size))
+    else {
+        return;
+    };
+    let middle = component_middle(top, bottom);
+
+    let in_the_way = components
+        .iter()
+        .filter_map(component_terminals)
+        .filter(|(other_top, other_bottom)| {
+            component_contains(*other_top, *other_bottom, middle)
+                || component_contains(top, bottom, component_middle(*other_top, *other_bottom))
+        })
+        .collect::<Vec<_>>();
+    if in_the_way.is_empty() {
+        return;
+    }
+
+    warn!("Components cannot be placed on top of each other");
+    mouse_button.clear_just_pressed(MouseButton::Left);
+    conflict.points = in_the_way
+        .into_iter()
+        .flat_map(|(other_top, other_bottom)| {
+            [
+                other_top,
+                component_middle(other_top, other_bottom),
+                other_bottom,
+            ]
+        })
+        .collect();
+    conflict.until = time.elapsed_seconds() + CONFLICT_FLASH_SECONDS;
+}
+
+fn flash_placement_conflict(time: Res<Time>, conflict: Res<PlacementConflict>, mut gizmos: Gizmos) {
+    let left = conflict.until - time.elapsed_seconds();
+    if left <= 0. {
+        return;
+    }
+
+    let color = Color::rgba(1., 0.2, 0.2, left / CONFLICT_FLASH_SECONDS);
+    for point in conflict.points.iter() {
+        gizmos.circle_2d(grid_to_world(*point), 8., color);
     }
 }
 
 fn accept_input(
     cmd: Commands,
     mouse_button: Res<Input<MouseButton>>,
-    windows: Query<&Window, With<PrimaryWindow>>,
+    (windows, cameras, grid_size): (
+        Query<&Window, With<PrimaryWindow>>,
+        Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+        Res<GridSize>,
+    ),
     wire_origin: Local<Option<GridPosition>>,
     wires: Query<(Entity, &Wire)>,
     lights: Query<(Entity, &Light)>,
@@ -765,37 +1525,50 @@ fn accept_input(
     relay_switches: Query<(Entity, &RelaySwitch)>,
     relay_coils: Query<(Entity, &RelayCoil)>,
     circuit_material: Res<CircuitHandles>,
-    meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     currently_placing: ResMut<CurrentlyPlacing>,
+    ui_interactions: Query<&Interaction>,
+    orientation: Res<ComponentOrientation>,
+    gizmos: Gizmos,
 ) {
     let Some(mouse_position) = windows.single().cursor_position() else {
         return;
     };
+    let mouse_grid_pos = convert_mouse_to_grid(mouse_position, cameras.single(), &grid_size);
+    let orientation = *orientation;
+
+    // Clicks on ui elements that lie above the grid should not reach it
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
 
     match currently_placing.as_ref().clone() {
         CurrentlyPlacing::Wire => handle_wire_placement(
             cmd,
-            mouse_position,
+            mouse_grid_pos,
             mouse_button,
             wires,
             circuit_material,
-            meshes,
             grid_origin,
             wire_origin,
             lights,
             buttons,
             relay_switches,
             relay_coils,
+            gizmos,
         ),
         CurrentlyPlacing::Light { id, label } => handle_light_placement(
             cmd,
             id,
             label,
-            mouse_position,
+            mouse_grid_pos,
+            orientation,
+            *grid_size,
             mouse_button,
             circuit_material,
-            meshes,
             grid_origin,
             currently_placing,
         ),
@@ -804,21 +1577,24 @@ fn accept_input(
             id,
             label,
             typ,
-            mouse_position,
+            mouse_grid_pos,
+            orientation,
+            *grid_size,
             mouse_button,
             circuit_material,
-            meshes,
             grid_origin,
             currently_placing,
         ),
-        CurrentlyPlacing::RelayCoil { id, label } => handle_relay_coil_placement(
+        CurrentlyPlacing::RelayCoil { id, label, timer } => handle_relay_coil_placement(
             cmd,
             id,
             label,
-            mouse_position,
+            timer,
+            mouse_grid_pos,
+            orientation,
+            *grid_size,
             mouse_button,
             circuit_material,
-            meshes,
             grid_origin,
             currently_placing,
         ),
@@ -827,13 +1603,33 @@ fn accept_input(
             id,
             label,
             typ,
-            mouse_position,
+            mouse_grid_pos,
+            orientation,
+            *grid_size,
             mouse_button,
             circuit_material,
-            meshes,
             grid_origin,
             currently_placing,
         ),
+        CurrentlyPlacing::Annotation(_)
+        | CurrentlyPlacing::Route
+        | CurrentlyPlacing::Measure
+        | CurrentlyPlacing::Troubleshoot
+        | CurrentlyPlacing::HiddenRegion
+        | CurrentlyPlacing::Macro(_)
+        | CurrentlyPlacing::Paste
+        | CurrentlyPlacing::Clock
+        | CurrentlyPlacing::TimeSwitch
+        | CurrentlyPlacing::Move
+        | CurrentlyPlacing::Delete
+        | CurrentlyPlacing::InjectFault
+        | CurrentlyPlacing::Multimeter
+        | CurrentlyPlacing::Fuse
+        | CurrentlyPlacing::Diode
+        | CurrentlyPlacing::Supply
+        | CurrentlyPlacing::NetLabel
+        | CurrentlyPlacing::Block
+        | CurrentlyPlacing::Template(_) => {}
     }
 }
 // Exactly the same as buttons, but with a rectangle instead of a square
@@ -841,10 +1637,12 @@ fn handle_relay_coil_placement(
     mut cmd: Commands,
     id: usize,
     label: String,
-    mouse_position: Vec2,
+    timer: Option<timer_relay::RelayTimer>,
+    mouse_grid_pos: Option<GridPosition>,
+    orientation: ComponentOrientation,
+    grid_size: GridSize,
     mouse_button: Res<Input<MouseButton>>,
     circuit_material: Res<CircuitHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     mut currently_placing: ResMut<CurrentlyPlacing>,
 ) {
@@ -854,98 +1652,154 @@ fn handle_relay_coil_placement(
     }
 
     if mouse_button.just_pressed(MouseButton::Left) {
-        let mouse_grid_pos = convert_mouse_to_grid(mouse_position);
-        let Some(mouse_grid) = mouse_grid_pos else {
+        let Some((top, bottom)) =
+            mouse_grid_pos.and_then(|pos| orientation.terminals(pos, &grid_size))
+        else {
+            warn!("The component does not fit at the edge of the grid");
             return;
         };
 
-        let coil = cmd
-            .spawn((
-                Name::new(label.clone()),
-                RelayCoil {
-                    id,
-                    top: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y + 1,
-                    },
-                    bottom: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y - 1,
-                    },
-                    activated: false,
-                },
-                SpatialBundle::default(),
-            ))
-            .set_parent(grid_origin.single())
-            .id();
-
-        // Like other components, but with a rectangle instead of a square
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 30., y: 20. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.5,
-                )),
-                ..Default::default()
+        spawn_relay_coil(
+            &mut cmd,
+            &circuit_material,
+            grid_origin.single(),
+            RelayCoil {
+                id,
+                top,
+                bottom,
+                activated: false,
+                timer,
             },
-            Name::new("Relay Coil"),
-        ))
-        .set_parent(coil);
+            label,
+        );
 
-        // The two points
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) - 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Relay Coil Point1"),
+        *currently_placing = CurrentlyPlacing::Wire;
+    }
+}
+
+fn spawn_relay_coil(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    relay_coil: RelayCoil,
+    label: String,
+) -> Entity {
+    let e = cmd
+        .spawn((
+            Name::new(label.clone()),
+            relay_coil.clone(),
+            SpatialBundle::default(),
         ))
-        .set_parent(coil);
+        .set_parent(grid_origin)
+        .id();
+    spawn_relay_coil_visuals(cmd, circuit_material, e, &relay_coil, label);
+    e
+}
+
+// Everything that shows the relay coil, edits through reflection make these again
+fn spawn_relay_coil_visuals(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    parent: Entity,
+    relay_coil: &RelayCoil,
+    label: String,
+) {
+    // The middle of the three grid points the component spans
+    let middle = component_middle(relay_coil.top, relay_coil.bottom);
+
+    // Like other components, but with a rectangle instead of a square
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material
+                .relay_coil_mesh
+                .get(relay_coil.top, relay_coil.bottom),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Coil"),
+    ))
+    .set_parent(parent);
 
+    // Timer relays show their kind and delay on the other side than the label
+    if let Some(timer) = &relay_coil.timer {
+        let offset = oriented(relay_coil.top, relay_coil.bottom, Vec2::new(-45., 0.));
         cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) + 1.) + 10.,
-                    2.5,
-                )),
+            Text2dBundle {
+                text: Text::from_section(
+                    timer.describe(),
+                    TextStyle {
+                        font_size: 15.,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ),
+                transform: Transform::from_translation(
+                    (Vec2::new(20. * middle.x as f32 + 10., 20. * middle.y as f32 + 10.) + offset)
+                        .extend(5.),
+                ),
                 ..Default::default()
             },
-            Name::new("Relay Coil Point2"),
+            Name::new("Relay Coil Timer"),
+            BodyText,
         ))
-        .set_parent(coil);
-
-        // a wire all the way through
-        let wire = cmd
-            .spawn(MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 4., y: 40. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.,
-                )),
-                ..Default::default()
-            })
-            .set_parent(coil)
-            .id();
+        .set_parent(parent);
+    }
 
-        cmd.spawn(Text2dBundle {
+    // The two points
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * relay_coil.bottom.x as f32 + 10.,
+                20. * relay_coil.bottom.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Coil Point1"),
+    ))
+    .set_parent(parent);
+
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * relay_coil.top.x as f32 + 10.,
+                20. * relay_coil.top.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Coil Point2"),
+    ))
+    .set_parent(parent);
+
+    // a wire all the way through
+    let wire = cmd
+        .spawn(MaterialMesh2dBundle {
+            mesh: circuit_material
+                .component_wire_mesh
+                .get(relay_coil.top, relay_coil.bottom),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.,
+            )),
+            ..Default::default()
+        })
+        .set_parent(parent)
+        .id();
+
+    cmd.spawn((
+        Text2dBundle {
             text: Text::from_section(
                 label,
                 TextStyle {
@@ -960,11 +1814,10 @@ fn handle_relay_coil_placement(
                 z: 5.,
             }),
             ..Default::default()
-        })
-        .set_parent(wire);
-
-        *currently_placing = CurrentlyPlacing::Wire;
-    }
+        },
+        BodyText,
+    ))
+    .set_parent(wire);
 }
 
 // Exactly the same as buttons, but with the label -K{id} and the relayswitch component
@@ -973,10 +1826,11 @@ fn handle_relay_switch_placement(
     id: usize,
     label: String,
     typ: SwitchType,
-    mouse_position: Vec2,
+    mouse_grid_pos: Option<GridPosition>,
+    orientation: ComponentOrientation,
+    grid_size: GridSize,
     mouse_button: Res<Input<MouseButton>>,
     circuit_material: Res<CircuitHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     mut currently_placing: ResMut<CurrentlyPlacing>,
 ) {
@@ -986,139 +1840,165 @@ fn handle_relay_switch_placement(
     }
 
     if mouse_button.just_pressed(MouseButton::Left) {
-        let mouse_grid_pos = convert_mouse_to_grid(mouse_position);
-        let Some(mouse_grid) = mouse_grid_pos else {
+        let Some((top, bottom)) =
+            mouse_grid_pos.and_then(|pos| orientation.terminals(pos, &grid_size))
+        else {
+            warn!("The component does not fit at the edge of the grid");
             return;
         };
 
-        let relay = cmd
-            .spawn((
-                Name::new(label.clone()),
-                RelaySwitch {
-                    id,
-                    typ,
-                    top: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y + 1,
-                    },
-                    bottom: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y - 1,
-                    },
-                },
-                SpatialBundle::default(),
-            ))
-            .set_parent(grid_origin.single())
-            .id();
-
-        // Like button
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) - 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
+        spawn_relay_switch(
+            &mut cmd,
+            &circuit_material,
+            grid_origin.single(),
+            RelaySwitch {
+                id,
+                typ,
+                top,
+                bottom,
             },
-            Name::new("Relay Point1"),
-        ))
-        .set_parent(relay);
+            label,
+        );
 
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) + 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Relay Point2"),
-        ))
-        .set_parent(relay);
+        *currently_placing = CurrentlyPlacing::Wire;
+    }
+}
 
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 20., y: 20. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Relay Square"),
+fn spawn_relay_switch(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    relay_switch: RelaySwitch,
+    label: String,
+) -> Entity {
+    let e = cmd
+        .spawn((
+            Name::new(label.clone()),
+            relay_switch.clone(),
+            SpatialBundle::default(),
         ))
-        .set_parent(relay)
-        .with_children(|root| {
-            root.spawn((
-                Text2dBundle {
-                    text: Text::from_section(
-                        match typ {
-                            SwitchType::NormallyOpen => "NO",
-                            SwitchType::NormallyClosed => "NC",
-                        },
-                        TextStyle {
-                            font_size: 15.,
-                            color: Color::WHITE,
-                            ..Default::default()
-                        },
-                    ),
-                    transform: Transform::from_translation(Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: 5.,
-                    }),
-                    ..Default::default()
-                },
-                Name::new("Relay Text"),
-            ));
-        });
+        .set_parent(grid_origin)
+        .id();
+    spawn_relay_switch_visuals(cmd, circuit_material, e, &relay_switch, label);
+    e
+}
+
+// Everything that shows the relay switch, edits through reflection make these again
+fn spawn_relay_switch_visuals(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    parent: Entity,
+    relay_switch: &RelaySwitch,
+    label: String,
+) {
+    // The middle of the three grid points the component spans
+    let middle = component_middle(relay_switch.top, relay_switch.bottom);
+    let typ = relay_switch.typ;
+
+    // Like button
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * relay_switch.bottom.x as f32 + 10.,
+                20. * relay_switch.bottom.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Point1"),
+    ))
+    .set_parent(parent);
 
-        // a wire all the way through
-        let wire = cmd
-            .spawn(MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 4., y: 40. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.,
-                )),
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * relay_switch.top.x as f32 + 10.,
+                20. * relay_switch.top.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Point2"),
+    ))
+    .set_parent(parent);
+
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.component_body_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Relay Square"),
+    ))
+    .set_parent(parent)
+    .with_children(|root| {
+        root.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    match typ {
+                        SwitchType::NormallyOpen => "NO",
+                        SwitchType::NormallyClosed => "NC",
+                        SwitchType::Changeover => "CO",
+                    },
+                    TextStyle {
+                        font_size: 15.,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ),
+                transform: Transform::from_translation(Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 5.,
+                }),
                 ..Default::default()
-            })
-            .set_parent(relay)
-            .id();
+            },
+            Name::new("Relay Text"),
+            BodyText,
+        ));
+    });
 
-        cmd.spawn(Text2dBundle {
-            text: Text::from_section(
-                label,
-                TextStyle {
-                    font_size: 20.,
-                    color: Color::WHITE,
-                    ..Default::default()
-                },
-            ),
-            transform: Transform::from_translation(Vec3 {
-                x: 20.,
-                y: 0.,
-                z: 5.,
-            }),
+    // a wire all the way through
+    let wire = cmd
+        .spawn(MaterialMesh2dBundle {
+            mesh: circuit_material
+                .component_wire_mesh
+                .get(relay_switch.top, relay_switch.bottom),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.,
+            )),
             ..Default::default()
         })
-        .set_parent(wire);
-        *currently_placing = CurrentlyPlacing::Wire;
-    }
+        .set_parent(parent)
+        .id();
+
+    cmd.spawn(Text2dBundle {
+        text: Text::from_section(
+            label,
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        ),
+        transform: Transform::from_translation(
+            oriented(relay_switch.top, relay_switch.bottom, Vec2::new(20., 0.)).extend(5.),
+        ),
+        ..Default::default()
+    })
+    .set_parent(wire);
 }
 
 fn handle_button_placement(
@@ -1126,10 +2006,11 @@ fn handle_button_placement(
     id: usize,
     label: String,
     typ: SwitchType,
-    mouse_position: Vec2,
+    mouse_grid_pos: Option<GridPosition>,
+    orientation: ComponentOrientation,
+    grid_size: GridSize,
     mouse_button: Res<Input<MouseButton>>,
     circuit_material: Res<CircuitHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     mut currently_placing: ResMut<CurrentlyPlacing>,
 ) {
@@ -1139,149 +2020,176 @@ fn handle_button_placement(
     }
 
     if mouse_button.just_pressed(MouseButton::Left) {
-        let mouse_grid_pos = convert_mouse_to_grid(mouse_position);
-        let Some(mouse_grid) = mouse_grid_pos else {
+        let Some((top, bottom)) =
+            mouse_grid_pos.and_then(|pos| orientation.terminals(pos, &grid_size))
+        else {
+            warn!("The component does not fit at the edge of the grid");
             return;
         };
 
-        let button = cmd
-            .spawn((
-                Name::new(label.clone()),
-                ButtonSwitch {
-                    id,
-                    typ,
-                    top: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y + 1,
-                    },
-                    bottom: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y - 1,
-                    },
-                },
-                SpatialBundle::default(),
-            ))
-            .set_parent(grid_origin.single())
-            .id();
-
-        // Like wire, but with label in the middle on big circle
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) - 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
+        spawn_button(
+            &mut cmd,
+            &circuit_material,
+            grid_origin.single(),
+            ButtonSwitch {
+                id,
+                typ,
+                top,
+                bottom,
             },
-            Name::new("Button Point1"),
-        ))
-        .set_parent(button);
+            label,
+        );
 
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) + 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Button Point2"),
-        ))
-        .set_parent(button);
-        // The middle, for the button just a square with eiter NC or NO on it
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 20., y: 20. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Button Square"),
+        *currently_placing = CurrentlyPlacing::Wire;
+    }
+}
+
+fn spawn_button(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    button: ButtonSwitch,
+    label: String,
+) -> Entity {
+    let e = cmd
+        .spawn((
+            Name::new(label.clone()),
+            button.clone(),
+            SpatialBundle::default(),
         ))
-        .set_parent(button)
-        .with_children(|root| {
-            root.spawn((
-                Text2dBundle {
-                    text: Text::from_section(
-                        match typ {
-                            SwitchType::NormallyOpen => "NO",
-                            SwitchType::NormallyClosed => "NC",
-                        },
-                        TextStyle {
-                            font_size: 15.,
-                            color: Color::WHITE,
-                            ..Default::default()
-                        },
-                    ),
-                    transform: Transform::from_translation(Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: 5.,
-                    }),
-                    ..Default::default()
-                },
-                Name::new("Button Text"),
-            ));
-        });
+        .set_parent(grid_origin)
+        .id();
+    spawn_button_visuals(cmd, circuit_material, e, &button, label);
+    e
+}
+
+// Everything that shows the button, edits through reflection make these again
+fn spawn_button_visuals(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    parent: Entity,
+    button: &ButtonSwitch,
+    label: String,
+) {
+    // The middle of the three grid points the component spans
+    let middle = component_middle(button.top, button.bottom);
+    let typ = button.typ;
+
+    // Like wire, but with label in the middle on big circle
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * button.bottom.x as f32 + 10.,
+                20. * button.bottom.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Button Point1"),
+    ))
+    .set_parent(parent);
 
-        // a wire all the way through
-        let wire = cmd
-            .spawn(MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 4., y: 40. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.,
-                )),
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * button.top.x as f32 + 10.,
+                20. * button.top.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Button Point2"),
+    ))
+    .set_parent(parent);
+    // The middle, for the button just a square with eiter NC or NO on it
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.component_body_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Button Square"),
+    ))
+    .set_parent(parent)
+    .with_children(|root| {
+        root.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    match typ {
+                        SwitchType::NormallyOpen => "NO",
+                        SwitchType::NormallyClosed => "NC",
+                        SwitchType::Changeover => "CO",
+                    },
+                    TextStyle {
+                        font_size: 15.,
+                        color: Color::WHITE,
+                        ..Default::default()
+                    },
+                ),
+                transform: Transform::from_translation(Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 5.,
+                }),
                 ..Default::default()
-            })
-            .set_parent(button)
-            .id();
+            },
+            Name::new("Button Text"),
+            BodyText,
+        ));
+    });
 
-        cmd.spawn(Text2dBundle {
-            text: Text::from_section(
-                label,
-                TextStyle {
-                    font_size: 20.,
-                    color: Color::WHITE,
-                    ..Default::default()
-                },
-            ),
-            transform: Transform::from_translation(Vec3 {
-                x: 20.,
-                y: 0.,
-                z: 5.,
-            }),
+    // a wire all the way through
+    let wire = cmd
+        .spawn(MaterialMesh2dBundle {
+            mesh: circuit_material
+                .component_wire_mesh
+                .get(button.top, button.bottom),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.,
+            )),
             ..Default::default()
         })
-        .set_parent(wire);
-        *currently_placing = CurrentlyPlacing::Wire;
-    }
+        .set_parent(parent)
+        .id();
+
+    cmd.spawn(Text2dBundle {
+        text: Text::from_section(
+            label,
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        ),
+        transform: Transform::from_translation(
+            oriented(button.top, button.bottom, Vec2::new(20., 0.)).extend(5.),
+        ),
+        ..Default::default()
+    })
+    .set_parent(wire);
 }
 
 fn handle_light_placement(
     mut cmd: Commands,
     id: usize,
     label: String,
-    mouse_position: Vec2,
+    mouse_grid_pos: Option<GridPosition>,
+    orientation: ComponentOrientation,
+    grid_size: GridSize,
     mouse_button: Res<Input<MouseButton>>,
     circuit_material: Res<CircuitHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     mut currently_placing: ResMut<CurrentlyPlacing>,
 ) {
@@ -1291,114 +2199,135 @@ fn handle_light_placement(
     }
 
     if mouse_button.just_pressed(MouseButton::Left) {
-        let mouse_grid_pos = convert_mouse_to_grid(mouse_position);
-        let Some(mouse_grid) = mouse_grid_pos else {
+        let Some((top, bottom)) =
+            mouse_grid_pos.and_then(|pos| orientation.terminals(pos, &grid_size))
+        else {
+            warn!("The component does not fit at the edge of the grid");
             return;
         };
 
-        let light = cmd
-            .spawn((
-                Name::new(label.clone()),
-                Light {
-                    id,
-                    top: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y + 1,
-                    },
-                    bottom: GridPosition {
-                        x: mouse_grid.x,
-                        y: mouse_grid.y - 1,
-                    },
-                },
-                SpatialBundle::default(),
-            ))
-            .set_parent(grid_origin.single())
-            .id();
+        spawn_light(
+            &mut cmd,
+            &circuit_material,
+            grid_origin.single(),
+            Light { id, top, bottom },
+            label,
+        );
 
-        // Like wire, but with label in the middle on big circle
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * ((mouse_grid.y as f32) - 1.) + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Light Point1"),
-        ))
-        .set_parent(light);
+        *currently_placing = CurrentlyPlacing::Wire;
+    }
+}
 
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * (mouse_grid.y + 1) as f32 + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Light Point2"),
+fn spawn_light(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    light: Light,
+    label: String,
+) -> Entity {
+    let e = cmd
+        .spawn((
+            Name::new(label.clone()),
+            light.clone(),
+            SpatialBundle::default(),
         ))
-        .set_parent(light);
+        .set_parent(grid_origin)
+        .id();
+    spawn_light_visuals(cmd, circuit_material, e, &light, label);
+    e
+}
 
-        cmd.spawn((
-            MaterialMesh2dBundle {
-                mesh: circuit_material.wire_point_mesh.clone(),
-                material: circuit_material.light_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.5,
-                )),
-                ..Default::default()
-            },
-            Name::new("Light Point3"),
-        ))
-        .set_parent(light);
-
-        // a wire all the way through, this is always the same size, so not many calculations needes
-
-        let wire = cmd
-            .spawn(MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(shape::Quad::new(Vec2 { x: 4., y: 40. }).into())
-                    .into(),
-                material: circuit_material.wire_material.clone(),
-                transform: Transform::from_translation(Vec3::new(
-                    20. * mouse_grid.x as f32 + 10.,
-                    20. * mouse_grid.y as f32 + 10.,
-                    2.,
-                )),
-                ..Default::default()
-            })
-            .set_parent(light)
-            .id();
+// Everything that shows the light, edits through reflection make these again
+fn spawn_light_visuals(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    parent: Entity,
+    light: &Light,
+    label: String,
+) {
+    // The middle of the three grid points the component spans
+    let middle = component_middle(light.top, light.bottom);
 
-        cmd.spawn(Text2dBundle {
-            text: Text::from_section(
-                label,
-                TextStyle {
-                    font_size: 20.,
-                    color: Color::WHITE,
-                    ..Default::default()
-                },
-            ),
-            transform: Transform::from_translation(Vec3 {
-                x: 20.,
-                y: 0.,
-                z: 5.,
-            }),
+    // Like wire, but with label in the middle on big circle
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * light.bottom.x as f32 + 10.,
+                20. * light.bottom.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Light Point1"),
+    ))
+    .set_parent(parent);
+
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * light.top.x as f32 + 10.,
+                20. * light.top.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Light Point2"),
+    ))
+    .set_parent(parent);
+
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.light_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Light Point3"),
+        LightBulb,
+    ))
+    .set_parent(parent);
+
+    // a wire all the way through, this is always the same size, so not many calculations needes
+
+    let wire = cmd
+        .spawn(MaterialMesh2dBundle {
+            mesh: circuit_material
+                .component_wire_mesh
+                .get(light.top, light.bottom),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * middle.x as f32 + 10.,
+                20. * middle.y as f32 + 10.,
+                2.,
+            )),
             ..Default::default()
         })
-        .set_parent(wire);
+        .set_parent(parent)
+        .id();
 
-        *currently_placing = CurrentlyPlacing::Wire;
-    }
+    cmd.spawn(Text2dBundle {
+        text: Text::from_section(
+            label,
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        ),
+        transform: Transform::from_translation(
+            oriented(light.top, light.bottom, Vec2::new(20., 0.)).extend(5.),
+        ),
+        ..Default::default()
+    })
+    .set_parent(wire);
 }
 
 fn handle_light_button_press(
@@ -1420,14 +2349,33 @@ fn handle_light_button_press(
 }
 
 fn handle_button_button_press(
-    mut press_interaction: Query<(&Interaction, &mut UIButton)>,
+    mut press_interaction: Query<(Ref<Interaction>, &mut UIButton)>,
     mut place_interaction: Query<(&Interaction, &mut ButtonSelect)>,
+    mode_interaction: Query<(&Interaction, &ButtonModeSelect), Changed<Interaction>>,
     placed_buttons: Query<&ButtonSwitch>,
     mut currently_placing: ResMut<CurrentlyPlacing>,
 ) {
+    for (interaction, mode_select) in mode_interaction.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        for (_, mut ui_button) in press_interaction.iter_mut() {
+            if ui_button.id == mode_select.id {
+                ui_button.maintained = !ui_button.maintained;
+                ui_button.latched = false;
+            }
+        }
+    }
+
     for (interaction, mut ui_button) in press_interaction.iter_mut() {
-        if *interaction == Interaction::Pressed {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        // Momentary buttons are pressed for as long as they are held, maintained ones switch once per click
+        if !ui_button.maintained {
             ui_button.has_been_pressed = true;
+        } else if interaction.is_changed() {
+            ui_button.latched = !ui_button.latched;
         }
     }
 
@@ -1460,8 +2408,7 @@ fn handle_relay_switch_button_press(
                 relay_switch.id == relay_switch_select.id
                     && relay_switch.typ == relay_switch_select.typ
             })
-            .collect::<Vec<_>>()
-            .len()
+            .count()
             >= 5
         {
             continue;
@@ -1492,6 +2439,7 @@ fn handle_relay_coil_button_press(
             *currently_placing = CurrentlyPlacing::RelayCoil {
                 id: relay_coil_select.id,
                 label: format!("-K{}", relay_coil_select.id),
+                timer: None,
             };
         }
     }
@@ -1499,21 +2447,30 @@ fn handle_relay_coil_button_press(
 
 fn handle_wire_placement(
     mut cmd: Commands,
-    mouse_position: Vec2,
+    mouse_grid_pos: Option<GridPosition>,
     mouse_button: Res<Input<MouseButton>>,
     wires: Query<(Entity, &Wire)>,
     circuit_material: Res<CircuitHandles>,
-    mut meshes: ResMut<Assets<Mesh>>,
     grid_origin: Query<Entity, With<GridOrigin>>,
     mut wire_origin: Local<Option<GridPosition>>,
     lights: Query<(Entity, &Light)>,
     buttons: Query<(Entity, &ButtonSwitch)>,
     relay_switches: Query<(Entity, &RelaySwitch)>,
     relay_coils: Query<(Entity, &RelayCoil)>,
+    mut gizmos: Gizmos,
 ) {
-    let mouse_grid_pos = convert_mouse_to_grid(mouse_position);
     match mouse_grid_pos {
         Some(ref mouse_grid) => {
+            // Shows where the wire would go, red while it would not be placed because it is not straight
+            if let Some(origin) = *wire_origin {
+                let color = if mouse_grid.x == origin.x || mouse_grid.y == origin.y {
+                    Color::GRAY
+                } else {
+                    Color::RED
+                };
+                gizmos.line_2d(grid_to_world(origin), grid_to_world(*mouse_grid), color);
+            }
+
             if mouse_button.just_pressed(MouseButton::Left) {
                 let Some(ref wire_origin_position) = *wire_origin else {
                     *wire_origin = mouse_grid_pos;
@@ -1523,92 +2480,15 @@ fn handle_wire_placement(
                 // if the mouse is on the same x or y axis as the origin, create a wire
                 if mouse_grid.x == wire_origin_position.x || mouse_grid.y == wire_origin_position.y
                 {
-                    let wire = cmd
-                        .spawn((
-                            Name::new(format!(
-                                "Wire {}, {} to {}, {}",
-                                wire_origin_position.x,
-                                wire_origin_position.y,
-                                mouse_grid.x,
-                                mouse_grid.y
-                            )),
-                            // Wire that stores position for simulation
-                            Wire {
-                                first: *wire_origin_position,
-                                second: *mouse_grid,
-                            },
-                            SpatialBundle::default(),
-                        ))
-                        .set_parent(grid_origin.single())
-                        .id();
-
-                    // First Visual Point
-                    cmd.spawn((
-                        MaterialMesh2dBundle {
-                            mesh: circuit_material.wire_point_mesh.clone(),
-                            material: circuit_material.wire_material.clone(),
-                            transform: Transform::from_translation(Vec3::new(
-                                20. * mouse_grid.x as f32 + 10.,
-                                20. * mouse_grid.y as f32 + 10.,
-                                2.5,
-                            )),
-                            ..Default::default()
-                        },
-                        Name::new("Wire Point1"),
-                    ))
-                    .set_parent(wire);
-
-                    // Second Visual Point
-                    cmd.spawn((
-                        MaterialMesh2dBundle {
-                            mesh: circuit_material.wire_point_mesh.clone(),
-                            material: circuit_material.wire_material.clone(),
-                            transform: Transform::from_translation(Vec3::new(
-                                20. * wire_origin_position.x as f32 + 10.,
-                                20. * wire_origin_position.y as f32 + 10.,
-                                2.5,
-                            )),
-                            ..Default::default()
+                    spawn_wire(
+                        &mut cmd,
+                        &circuit_material,
+                        grid_origin.single(),
+                        Wire {
+                            first: *wire_origin_position,
+                            second: *mouse_grid,
                         },
-                        Name::new("Wire Point2"),
-                    ))
-                    .set_parent(wire);
-
-                    // Line in-between
-                    let (x_extent, y_extent, x_transform, y_transform): (f32, f32, f32, f32);
-                    if mouse_grid.x == wire_origin_position.x {
-                        x_extent = 4.;
-                        y_extent = (mouse_grid.y as f32 - wire_origin_position.y as f32) * 20.;
-                        x_transform = 20. * wire_origin_position.x as f32 + 10.;
-                        y_transform = 20. * wire_origin_position.y as f32 + 10. + y_extent / 2.;
-                    } else {
-                        x_extent = (mouse_grid.x as f32 - wire_origin_position.x as f32) * 20.;
-                        y_extent = 4.;
-                        x_transform = 20. * wire_origin_position.x as f32 + 10. + x_extent / 2.;
-                        y_transform = 20. * wire_origin_position.y as f32 + 10.;
-                    }
-                    cmd.spawn((
-                        MaterialMesh2dBundle {
-                            mesh: meshes
-                                .add(
-                                    shape::Quad::new(Vec2 {
-                                        x: x_extent,
-                                        y: y_extent,
-                                    })
-                                    .into(),
-                                )
-                                .into(),
-                            material: circuit_material.wire_material.clone(),
-                            transform: Transform::from_translation(Vec3::new(
-                                x_transform,
-                                y_transform,
-                                2.5,
-                            )),
-                            ..Default::default()
-                        },
-                        Name::new("Wire Line"),
-                    ))
-                    .set_parent(wire);
+                    );
                 }
                 *wire_origin = None;
             } else if mouse_button.just_pressed(MouseButton::Right) {
@@ -1618,67 +2498,31 @@ fn handle_wire_placement(
                 }
                 for (e, wire) in wires.iter() {
                     // if line between the two wire points intersects with the mouse position, remove it
-                    if wire.first.x == wire.second.x {
-                        if wire.first.x != mouse_grid.x {
-                            continue;
-                        }
-                        let min = wire.first.y.min(wire.second.y);
-                        let max = wire.first.y.max(wire.second.y);
-                        if (min..=max).contains(&mouse_grid.y) {
-                            cmd.entity(e).despawn_recursive();
-                        }
-                    } else if wire.first.y == wire.second.y {
-                        if wire.first.y != mouse_grid.y {
-                            continue;
-                        }
-                        let min = wire.first.x.min(wire.second.x);
-                        let max = wire.first.x.max(wire.second.x);
-                        if (min..=max).contains(&mouse_grid.x) {
-                            cmd.entity(e).despawn_recursive();
-                        }
+                    if wire_contains(wire, mouse_grid) {
+                        cmd.entity(e).despawn_recursive();
                     }
                 }
 
                 for (e, light) in lights.iter() {
-                    let mut middle = light.top;
-                    middle.y -= 1;
-                    if light.top == *mouse_grid
-                        || light.bottom == *mouse_grid
-                        || middle == *mouse_grid
-                    {
+                    if component_contains(light.top, light.bottom, *mouse_grid) {
                         cmd.entity(e).despawn_recursive();
                     }
                 }
 
                 for (e, button) in buttons.iter() {
-                    let mut middle = button.top;
-                    middle.y -= 1;
-                    if button.top == *mouse_grid
-                        || button.bottom == *mouse_grid
-                        || middle == *mouse_grid
-                    {
+                    if component_contains(button.top, button.bottom, *mouse_grid) {
                         cmd.entity(e).despawn_recursive();
                     }
                 }
 
                 for (e, relay_switch) in relay_switches.iter() {
-                    let mut middle = relay_switch.top;
-                    middle.y -= 1;
-                    if relay_switch.top == *mouse_grid
-                        || relay_switch.bottom == *mouse_grid
-                        || middle == *mouse_grid
-                    {
+                    if component_contains(relay_switch.top, relay_switch.bottom, *mouse_grid) {
                         cmd.entity(e).despawn_recursive();
                     }
                 }
 
                 for (e, relay_coil) in relay_coils.iter() {
-                    let mut middle = relay_coil.top;
-                    middle.y -= 1;
-                    if relay_coil.top == *mouse_grid
-                        || relay_coil.bottom == *mouse_grid
-                        || middle == *mouse_grid
-                    {
+                    if component_contains(relay_coil.top, relay_coil.bottom, *mouse_grid) {
                         cmd.entity(e).despawn_recursive();
                     }
                 }
@@ -1692,112 +2536,479 @@ fn handle_wire_placement(
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
-enum Visited {
-    Positive,
-    Negative,
-    Unvisited,
+fn spawn_wire(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    grid_origin: Entity,
+    wire: Wire,
+) -> Entity {
+    let e = cmd
+        .spawn((
+            Name::new(format!(
+                "Wire {}, {} to {}, {}",
+                wire.first.x, wire.first.y, wire.second.x, wire.second.y
+            )),
+            // Wire that stores position for simulation
+            wire.clone(),
+            SpatialBundle::default(),
+        ))
+        .set_parent(grid_origin)
+        .id();
+    spawn_wire_visuals(cmd, circuit_material, e, &wire);
+    e
+}
+
+// Everything that shows the wire, edits through reflection make these again
+fn spawn_wire_visuals(
+    cmd: &mut Commands,
+    circuit_material: &CircuitHandles,
+    parent: Entity,
+    wire: &Wire,
+) {
+    let (first, second) = (wire.first, wire.second);
+
+    // First Visual Point
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * second.x as f32 + 10.,
+                20. * second.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Wire Point1"),
+    ))
+    .set_parent(parent);
+
+    // Second Visual Point
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_point_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                20. * first.x as f32 + 10.,
+                20. * first.y as f32 + 10.,
+                2.5,
+            )),
+            ..Default::default()
+        },
+        Name::new("Wire Point2"),
+    ))
+    .set_parent(parent);
+
+    // Line in-between, the shared square stretched to the size of the line
+    let (x_extent, y_extent, x_transform, y_transform): (f32, f32, f32, f32);
+    if second.x == first.x {
+        x_extent = 4.;
+        y_extent = (second.y as f32 - first.y as f32) * 20.;
+        x_transform = 20. * first.x as f32 + 10.;
+        y_transform = 20. * first.y as f32 + 10. + y_extent / 2.;
+    } else {
+        x_extent = (second.x as f32 - first.x as f32) * 20.;
+        y_extent = 4.;
+        x_transform = 20. * first.x as f32 + 10. + x_extent / 2.;
+        y_transform = 20. * first.y as f32 + 10.;
+    }
+    cmd.spawn((
+        MaterialMesh2dBundle {
+            mesh: circuit_material.wire_line_mesh.clone(),
+            material: circuit_material.wire_material.clone(),
+            transform: Transform::from_translation(Vec3::new(x_transform, y_transform, 2.5))
+                .with_scale(Vec3::new(x_extent.abs(), y_extent.abs(), 1.)),
+            ..Default::default()
+        },
+        Name::new("Wire Line"),
+    ))
+    .set_parent(parent);
 }
 
+// The top and bottom grid point of whichever component an AnyOf query matched
+fn component_terminals(
+    (light, button, relay_coil, relay_switch): (
+        Option<&Light>,
+        Option<&ButtonSwitch>,
+        Option<&RelayCoil>,
+        Option<&RelaySwitch>,
+    ),
+) -> Option<(GridPosition, GridPosition)> {
+    light
+        .map(|light| (light.top, light.bottom))
+        .or(button.map(|button| (button.top, button.bottom)))
+        .or(relay_coil.map(|relay_coil| (relay_coil.top, relay_coil.bottom)))
+        .or(relay_switch.map(|relay_switch| (relay_switch.top, relay_switch.bottom)))
+}
+
+// The middle of the three grid points a component spans
+fn component_middle(top: GridPosition, bottom: GridPosition) -> GridPosition {
+    GridPosition {
+        x: (top.x + bottom.x) / 2,
+        y: (top.y + bottom.y) / 2,
+    }
+}
+
+// Whether the grid position is one of the three grid points of a component
+fn component_contains(top: GridPosition, bottom: GridPosition, pos: GridPosition) -> bool {
+    wire_contains(
+        &Wire {
+            first: top,
+            second: bottom,
+        },
+        &pos,
+    )
+}
+
+// Sizes and offsets are given for upright components, turned ones get x and y swapped
+fn oriented(top: GridPosition, bottom: GridPosition, upright: Vec2) -> Vec2 {
+    if top.x == bottom.x {
+        upright
+    } else {
+        Vec2::new(upright.y, upright.x)
+    }
+}
+
+// Whether the grid position lies on the line between the two wire points
+
+fn wire_contains(wire: &Wire, pos: &GridPosition) -> bool {
+    if wire.first.x == wire.second.x {
+        wire.first.x == pos.x
+            && (wire.first.y.min(wire.second.y)..=wire.first.y.max(wire.second.y)).contains(&pos.y)
+    } else if wire.first.y == wire.second.y {
+        wire.first.y == pos.y
+            && (wire.first.x.min(wire.second.x)..=wire.first.x.max(wire.second.x)).contains(&pos.x)
+    } else {
+        false
+    }
+}
+
+// What the simulation works on every tick, they only get cleared so the allocations stay around between ticks
+#[derive(Resource, Default)]
+struct SimulationScratch {
+    circuit: Circuit,
+    solver: Solver,
+    active_button_ids: Vec<usize>,
+    active_relay_ids: Vec<usize>,
+    // Relays with their coil on a sheet that is not shown that were pulled in after the last tick
+    other_sheet_relays: Vec<usize>,
+    // Whether the wires, links and diodes in the circuit still match the components, see detect_wiring_changes
+    wiring_up_to_date: bool,
+    // Closed time switches and intact fuses, they are wires as well but change while running
+    closed_extra_wires: Vec<(Point, Point)>,
+    extra_wires: Vec<(Point, Point)>,
+    // Every block flattened, only made again together with the wiring
+    block_parts: sheets::SheetParts,
+    settled_relay_ids: Vec<usize>,
+}
+
+// The wiring only changes when wires, diodes, net labels, blocks or broken wires are placed, changed or removed, or another sheet is shown
+// Runs every frame, removals would be missed by the fixed update that does not run every frame
+fn detect_wiring_changes(
+    changed: Query<
+        (),
+        Or<(
+            Changed<Wire>,
+            Changed<diode::Diode>,
+            Changed<net_labels::NetLabel>,
+            Changed<blocks::Block>,
+            Added<Faulty>,
+        )>,
+    >,
+    mut removed_wires: RemovedComponents<Wire>,
+    mut removed_diodes: RemovedComponents<diode::Diode>,
+    mut removed_net_labels: RemovedComponents<net_labels::NetLabel>,
+    mut removed_blocks: RemovedComponents<blocks::Block>,
+    mut removed_faulty: RemovedComponents<Faulty>,
+    sheets: Res<sheets::Sheets>,
+    mut scratch: ResMut<SimulationScratch>,
+) {
+    let removed = removed_wires.read().count()
+        + removed_diodes.read().count()
+        + removed_net_labels.read().count()
+        + removed_blocks.read().count()
+        + removed_faulty.read().count();
+    if !changed.is_empty() || removed > 0 || sheets.is_changed() {
+        scratch.wiring_up_to_date = false;
+    }
+}
+
+// Collects the circuit for relay_sim_core from the components, steps it and hands the result back to lights and relays
 fn simulate(
-    wires: Query<&Wire>,
+    wires: Query<&Wire, Without<Faulty>>,
     mut button_input: Query<&mut UIButton>,
-    button_switches: Query<&ButtonSwitch>,
-    mut relay_coils: Query<&mut RelayCoil>,
-    relay_switches: Query<&RelaySwitch>,
+    button_switches: Query<(&ButtonSwitch, Option<&Faulty>)>,
+    mut relay_coils: Query<(&mut RelayCoil, Has<Faulty>)>,
+    relay_switches: Query<(&RelaySwitch, Option<&Faulty>)>,
+    time_switches: Query<&time_switch::TimeSwitch>,
+    time_of_day: Res<time_switch::TimeOfDay>,
+    fuses: Query<&fuse::Fuse>,
+    (diodes, net_labels, sheets, blocks, breaker_tripped): (
+        Query<&diode::Diode>,
+        Query<&net_labels::NetLabel>,
+        Res<sheets::Sheets>,
+        Query<&blocks::Block>,
+        Res<load_meter::BreakerTripped>,
+    ),
     mut ui_lights: Query<&mut UILight>,
     lights: Query<&Light>,
     power_sources: Query<(&GridPosition, &Power)>,
+    mut scratch: ResMut<SimulationScratch>,
+    mut diagnostics: Diagnostics,
+    time: Res<Time>,
+    settle: Res<settle::SettleRelays>,
 ) {
-    // CAUTION! This does not cover when there are two consumers in series, for that, extra passes are needed, but it will work for now, if a consumer finds a not yet covered wire, that could be indicated as well
-
-    // Turn wires into 2 vectors. one with all Gridpositions, one with a tuple of indices for connections
-    let max_len = wires.iter().len() + button_switches.iter().len();
-    let mut wire_positions: Vec<(GridPosition, Visited)> = Vec::with_capacity(max_len);
-    let mut wire_connections: Vec<(usize, usize)> = Vec::with_capacity(max_len);
+    let SimulationScratch {
+        circuit,
+        solver,
+        active_button_ids,
+        active_relay_ids,
+        other_sheet_relays,
+        wiring_up_to_date,
+        closed_extra_wires,
+        extra_wires,
+        block_parts,
+        settled_relay_ids,
+    } = &mut *scratch;
+    // Blocks are flattened and simulated like a sheet that is not shown, they only change with the wiring
+    if !*wiring_up_to_date {
+        *block_parts = sheets::SheetParts::default();
+        for block in blocks.iter() {
+            block.add_parts(block_parts);
+        }
+    }
+    let others = sheets.others.iter().chain([&*block_parts]);
+    circuit.clear_keeping_wiring();
 
-    // Button prepass, resetting all ui buttons and transforming fitting buttons into wires
-    let mut active_button_ids = Vec::new();
+    // Button prepass, resetting all ui buttons
+    active_button_ids.clear();
     for mut button in button_input.iter_mut() {
-        if button.has_been_pressed {
+        if button.has_been_pressed || button.latched {
             active_button_ids.push(button.id);
         }
         button.has_been_pressed = false;
     }
 
-    let button_wires = button_switches
-        .iter()
-        .filter(|button| match button.typ {
-            SwitchType::NormallyOpen => active_button_ids.contains(&button.id),
-            SwitchType::NormallyClosed => !active_button_ids.contains(&button.id),
-        })
-        .map(Wire::from);
-
-    let mut active_relay_ids = Vec::new();
-    for mut relay_coil in relay_coils.iter_mut() {
+    active_relay_ids.clear();
+    for (mut relay_coil, _) in relay_coils.iter_mut() {
         if relay_coil.activated {
             active_relay_ids.push(relay_coil.id);
         }
         relay_coil.activated = false;
     }
+    active_relay_ids.append(other_sheet_relays);
 
-    let relay_wires = relay_switches
+    let time_switch_wires = time_switches
         .iter()
-        .filter(|relay_switch| match relay_switch.typ {
-            SwitchType::NormallyOpen => active_relay_ids.contains(&relay_switch.id),
-            SwitchType::NormallyClosed => !active_relay_ids.contains(&relay_switch.id),
-        })
+        .chain(others.clone().flat_map(|parts| &parts.time_switches))
+        .filter(|time_switch| time_switch.is_closed(&time_of_day))
         .map(Wire::from);
-
-    for wire in wires
+    let fuse_wires = fuses.iter().filter(|fuse| !fuse.blown).map(Wire::from);
+    extra_wires.clear();
+    extra_wires.extend(
+        time_switch_wires
+            .chain(fuse_wires)
+            .map(|wire| (wire.first.into(), wire.second.into())),
+    );
+    // The wiring is only collected again when it changed, the solver then goes through it again as well
+    if !*wiring_up_to_date || *extra_wires != *closed_extra_wires {
+        circuit.wires.clear();
+        circuit.links.clear();
+        circuit.diodes.clear();
+        circuit.wires.extend(
+            wires
+                .iter()
+                .chain(others.clone().flat_map(|parts| &parts.wires))
+                .map(|wire| (wire.first.into(), wire.second.into()))
+                .chain(extra_wires.iter().copied()),
+        );
+        circuit.diodes.extend(
+            diodes
+                .iter()
+                .chain(others.clone().flat_map(|parts| &parts.diodes))
+                .map(|diode| (diode.anode.into(), diode.cathode.into())),
+        );
+        circuit.links.extend(
+            net_labels::label_links(
+                net_labels
+                    .iter()
+                    .chain(others.clone().flat_map(|parts| &parts.net_labels)),
+            )
+            .into_iter()
+            .chain(others.clone().flat_map(|parts| parts.links.iter().copied()))
+            .map(|(first, second)| (first.into(), second.into())),
+        );
+        solver.invalidate_wiring();
+        std::mem::swap(closed_extra_wires, extra_wires);
+        *wiring_up_to_date = true;
+    }
+    circuit
+        .switches
+        .extend(button_switches.iter().filter_map(|(button, faulty)| {
+            Some(Switch {
+                top: button.top.into(),
+                bottom: button.bottom.into(),
+                typ: button.typ.into(),
+                actuated: Faulty::actuated(
+                    faulty,
+                    button.typ,
+                    active_button_ids.contains(&button.id),
+                )?,
+            })
+        }));
+    circuit.switches.extend(
+        others
+            .clone()
+            .flat_map(|parts| &parts.buttons)
+            .map(|button| Switch {
+                top: button.top.into(),
+                bottom: button.bottom.into(),
+                typ: button.typ.into(),
+                actuated: active_button_ids.contains(&button.id),
+            }),
+    );
+    // Lights first and then the working coils, the results come back in the same order
+    circuit.consumers.extend(
+        lights
+            .iter()
+            .map(|light| (light.top.into(), light.bottom.into())),
+    );
+    circuit.consumers.extend(
+        relay_coils
+            .iter()
+            .filter(|(_, faulty)| !faulty)
+            .map(|(relay_coil, _)| (relay_coil.top.into(), relay_coil.bottom.into())),
+    );
+    // Then the lights and coils of the other sheets
+    let other_lights = others.clone().flat_map(|parts| &parts.lights);
+    let other_coils = others.clone().flat_map(|parts| &parts.relay_coils);
+    circuit.consumers.extend(
+        other_lights
+            .clone()
+            .map(|light| (light.top.into(), light.bottom.into()))
+            .chain(
+                other_coils
+                    .clone()
+                    .map(|relay_coil| (relay_coil.top.into(), relay_coil.bottom.into())),
+            ),
+    );
+
+    // The fixed supply and every placed one, on every sheet, unless an overload tripped the breaker
+    let other_sources = others
+        .clone()
+        .flat_map(|parts| &parts.sources)
+        .map(|(pos, typ)| (pos, *typ));
+    for (pos, typ) in power_sources
         .iter()
-        .map(Clone::clone)
-        .chain(button_wires)
-        .chain(relay_wires)
+        .map(|(pos, power)| (pos, power.0))
+        .chain(other_sources)
+        .filter(|_| !breaker_tripped.0)
     {
-        let mut first_index = 0;
-        let mut second_index = 0;
-        for (pos, index) in &mut [
-            (wire.first, &mut first_index),
-            (wire.second, &mut second_index),
-        ] {
-            if let Some(idx) = wire_positions.iter().position(|p| &p.0 == pos) {
-                **index = idx;
-            } else {
-                **index = wire_positions.len();
-                wire_positions.push((*pos, Visited::Unvisited));
-            }
+        match typ {
+            PowerType::Positive => circuit.positive_sources.push((*pos).into()),
+            PowerType::Negative => circuit.negative_sources.push((*pos).into()),
         }
-        wire_connections.push((first_index, second_index));
     }
 
-    let power_sources = power_sources.iter().take(2).collect::<Vec<_>>();
-
-    let source_1 = power_sources[0];
-    let source_2 = power_sources[1];
-    let (positive_source, negative_source) = if source_1.1 .0 == PowerType::Positive {
-        (source_1.0, source_2.0)
+    // With settling on, relay contacts follow their coils within the same tick until no relay changes anymore
+    let button_switch_count = circuit.switches.len();
+    let light_count = lights.iter().len();
+    let coil_count = relay_coils.iter().filter(|(_, faulty)| !faulty).count();
+    let other_lights_start = light_count + coil_count;
+    let other_coils_start = other_lights_start + other_lights.clone().count();
+    // Timer relays on other sheets act like plain ones, their timers only run while their sheet is shown
+    let other_energized = |solver: &Solver, ids: &mut Vec<usize>| {
+        ids.extend(
+            other_coils
+                .clone()
+                .enumerate()
+                .filter(|(consumer, _)| solver.is_energized(other_coils_start + consumer))
+                .map(|(_, relay_coil)| relay_coil.id),
+        );
+    };
+    let passes = if settle.0 {
+        settle::MAX_SETTLE_PASSES
     } else {
-        (source_2.0, source_1.0)
+        1
     };
+    let mut evaluated_switches = 0;
+    for pass in 1..=passes {
+        circuit.switches.truncate(button_switch_count);
+        circuit
+            .switches
+            .extend(relay_switches.iter().filter_map(|(relay_switch, faulty)| {
+                Some(Switch {
+                    top: relay_switch.top.into(),
+                    bottom: relay_switch.bottom.into(),
+                    typ: relay_switch.typ.into(),
+                    actuated: Faulty::actuated(
+                        faulty,
+                        relay_switch.typ,
+                        active_relay_ids.contains(&relay_switch.id),
+                    )?,
+                })
+            }));
+        circuit
+            .switches
+            .extend(
+                others
+                    .clone()
+                    .flat_map(|parts| &parts.relay_switches)
+                    .map(|relay_switch| Switch {
+                        top: relay_switch.top.into(),
+                        bottom: relay_switch.bottom.into(),
+                        typ: relay_switch.typ.into(),
+                        actuated: active_relay_ids.contains(&relay_switch.id),
+                    }),
+            );
+        solver.step_keeping_wiring(circuit);
+        evaluated_switches += circuit.switches.len();
 
-    walk_wires(
-        positive_source,
-        Visited::Positive,
-        &mut wire_positions,
-        &wire_connections,
-    )
-    .unwrap();
+        if pass == passes || !circuit.has_sources() || solver.short_circuit() {
+            break;
+        }
+        // Timer relays only count their time once per tick, until then they stay as they were
+        settled_relay_ids.clear();
+        settled_relay_ids.extend(
+            relay_coils
+                .iter()
+                .filter(|(_, faulty)| !faulty)
+                .enumerate()
+                .filter(|(consumer, (relay_coil, _))| match relay_coil.timer {
+                    Some(_) => active_relay_ids.contains(&relay_coil.id),
+                    None => solver.is_energized(light_count + consumer),
+                })
+                .map(|(_, (relay_coil, _))| relay_coil.id),
+        );
+        other_energized(solver, settled_relay_ids);
+        settled_relay_ids.sort();
+        settled_relay_ids.dedup();
+        active_relay_ids.sort();
+        active_relay_ids.dedup();
+        if settled_relay_ids == active_relay_ids {
+            break;
+        }
+        std::mem::swap(active_relay_ids, settled_relay_ids);
+    }
+    diagnostics.add_measurement(perf_overlay::PerfOverlayPlugin::NET_COUNT, || {
+        solver.net_count() as f64
+    });
+    diagnostics.add_measurement(perf_overlay::PerfOverlayPlugin::SWITCH_COUNT, || {
+        evaluated_switches as f64
+    });
 
-    if walk_wires(
-        negative_source,
-        Visited::Negative,
-        &mut wire_positions,
-        &wire_connections,
-    )
-    .is_err()
-    {
-        // Short Circuit
+    // Without a supply everything goes dark, the coils were let go above already
+    if breaker_tripped.0 {
+        for mut ui_light in ui_lights.iter_mut() {
+            ui_light.is_lit = false;
+        }
+        return;
+    }
+    if !circuit.has_sources() {
+        return;
+    }
+    if solver.short_circuit() {
+        error!("Short Circuit");
         return;
     }
 
@@ -1805,93 +3016,40 @@ fn simulate(
         ui_light.is_lit = false;
     }
 
-    for light in lights.iter() {
-        let Some(top_index) = wire_positions.iter().position(|p| p.0 == light.top) else {
-            continue;
-        };
-        let Some(bottom_index) = wire_positions.iter().position(|p| p.0 == light.bottom) else {
-            continue;
-        };
-
-        if (wire_positions[top_index].1 == Visited::Positive
-            && wire_positions[bottom_index].1 == Visited::Negative)
-            || (wire_positions[top_index].1 == Visited::Negative
-                && wire_positions[bottom_index].1 == Visited::Positive)
-        {
-            ui_lights
+    for (consumer, light) in lights.iter().enumerate() {
+        if solver.is_energized(consumer) {
+            if let Some(mut ui_light) = ui_lights
                 .iter_mut()
                 .find(|ui_light| ui_light.id == light.id)
-                .unwrap()
-                .is_lit = true;
-        } else if wire_positions[top_index].1 == Visited::Unvisited
-            || wire_positions[bottom_index].1 == Visited::Unvisited
-        {
-            debug!("Unvisited Wire");
+            {
+                ui_light.is_lit = true;
+            }
         }
     }
-
-    for mut relay_coil in relay_coils.iter_mut() {
-        let Some(top_index) = wire_positions.iter().position(|p| p.0 == relay_coil.top) else {
-            continue;
-        };
-        let Some(bottom_index) = wire_positions.iter().position(|p| p.0 == relay_coil.bottom)
-        else {
-            continue;
-        };
-
-        if (wire_positions[top_index].1 == Visited::Positive
-            && wire_positions[bottom_index].1 == Visited::Negative)
-            || (wire_positions[top_index].1 == Visited::Negative
-                && wire_positions[bottom_index].1 == Visited::Positive)
-        {
-            relay_coil.activated = true;
-        } else if wire_positions[top_index].1 == Visited::Unvisited
-            || wire_positions[bottom_index].1 == Visited::Unvisited
-        {
-            debug!("Unvisited Wire");
+    // Lights on other sheets only show up in the light panel if it has their number
+    for (consumer, light) in other_lights.enumerate() {
+        if solver.is_energized(other_lights_start + consumer) {
+            if let Some(mut ui_light) = ui_lights
+                .iter_mut()
+                .find(|ui_light| ui_light.id == light.id)
+            {
+                ui_light.is_lit = true;
+            }
         }
     }
-}
+    other_energized(solver, other_sheet_relays);
 
-fn walk_wires(
-    source: &GridPosition,
-    mark: Visited,
-    wire_positions: &mut [(GridPosition, Visited)],
-    wire_connections: &[(usize, usize)],
-) -> Result<(), ()> {
-    let mut to_visit = vec![*source];
-
-    while let Some(pos) = to_visit.pop() {
-        let Some(index) = wire_positions.iter().position(|p| p.0 == pos) else {
-            continue;
+    // A burned coil never pulls in
+    for (consumer, (mut relay_coil, _)) in relay_coils
+        .iter_mut()
+        .filter(|(_, faulty)| !faulty)
+        .enumerate()
+    {
+        let energized = solver.is_energized(light_count + consumer);
+        let relay_coil = &mut *relay_coil;
+        relay_coil.activated = match &mut relay_coil.timer {
+            Some(timer) => timer.tick(energized, time.delta_seconds()),
+            None => energized,
         };
-
-        if wire_positions[index].1 == Visited::Unvisited {
-            wire_positions[index].1 = mark;
-        } else {
-            if wire_positions[index].1 != mark {
-                error!("Short Circuit");
-                return Err(());
-            }
-            continue;
-        }
-
-        // find all occurrences of index in wire_connections
-        let next_connections = wire_connections
-            .iter()
-            .filter_map(|(first, second)| {
-                if *first == index {
-                    Some(*second)
-                } else if *second == index {
-                    Some(*first)
-                } else {
-                    None
-                }
-            })
-            .filter(|idx| wire_positions[*idx].1 != mark)
-            .map(|idx| wire_positions[idx].0);
-
-        to_visit.extend(next_connections);
     }
-    Ok(())
 }
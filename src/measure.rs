@@ -0,0 +1,223 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    convert_mouse_to_grid,
+    grid::GridSize,
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    spawn_toolbar_button, CurrentlyPlacing, GridPosition, MainCamera, Toolbar, Wire,
+};
+
+const MEASURE_COLOR: Color = Color::rgb(0.6, 0.8, 1.);
+
+// In measure mode (M or the measure button) two clicks give the distance between the points
+// The rectangle between them is the selection, the total length of the wires inside it is shown as well
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Measurement>()
+            .add_systems(Startup, setup_measure_text)
+            .add_systems(PostStartup, setup_measure_button)
+            .add_systems(
+                Update,
+                (start_measuring, handle_measure_clicks, show_measurement).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Measurement {
+    start: Option<GridPosition>,
+    // Set once the second point was clicked, until then the cursor is the end
+    end: Option<GridPosition>,
+}
+
+impl Measurement {
+    // Both clicked corners of the selection, once there are two
+    pub fn selection(&self) -> Option<(GridPosition, GridPosition)> {
+        self.start.zip(self.end)
+    }
+
+    pub fn select(&mut self, start: GridPosition, end: GridPosition) {
+        self.start = Some(start);
+        self.end = Some(end);
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+}
+
+#[derive(Component)]
+struct MeasureButton;
+
+#[derive(Component)]
+struct MeasureText;
+
+fn setup_measure_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Measure", "Measure", MeasureButton);
+    });
+}
+
+fn setup_measure_text(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Measurement Text"),
+        MeasureText,
+    ));
+}
+
+fn start_measuring(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    measure_button: Query<&Interaction, (Changed<Interaction>, With<MeasureButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if (bindings.just_pressed(&keys, Action::Measure)
+        && matches!(*currently_placing, CurrentlyPlacing::Wire))
+        || measure_button
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Measure;
+    }
+}
+
+fn handle_measure_clicks(
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut measurement: ResMut<Measurement>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Measure) {
+        if measurement.start.is_some() {
+            *measurement = Measurement::default();
+        }
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        if measurement.start.is_none() {
+            *currently_placing = CurrentlyPlacing::Wire;
+        }
+        *measurement = Measurement::default();
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    // A third click starts over
+    if measurement.start.is_none() || measurement.end.is_some() {
+        *measurement = Measurement {
+            start: Some(mouse_grid),
+            end: None,
+        };
+    } else {
+        measurement.end = Some(mouse_grid);
+    }
+}
+
+fn show_measurement(
+    measurement: Res<Measurement>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    wires: Query<&Wire>,
+    mut measure_text: Query<(&mut Text, &mut Visibility), With<MeasureText>>,
+    mut gizmos: Gizmos,
+) {
+    let (mut text, mut visibility) = measure_text.single_mut();
+
+    let Some(start) = measurement.start else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let Some(end) = measurement.end.or_else(|| {
+        windows
+            .single()
+            .cursor_position()
+            .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    }) else {
+        return;
+    };
+
+    let (min_x, max_x) = (start.x.min(end.x), start.x.max(end.x));
+    let (min_y, max_y) = (start.y.min(end.y), start.y.max(end.y));
+    let dx = max_x - min_x;
+    let dy = max_y - min_y;
+
+    // Wires count when both of their ends lie inside the selection
+    let inside =
+        |pos: GridPosition| (min_x..=max_x).contains(&pos.x) && (min_y..=max_y).contains(&pos.y);
+    let (wire_count, wire_length) = wires
+        .iter()
+        .filter(|wire| inside(wire.first) && inside(wire.second))
+        .fold((0, 0), |(count, length), wire| {
+            (
+                count + 1,
+                length
+                    + wire.first.x.abs_diff(wire.second.x)
+                    + wire.first.y.abs_diff(wire.second.y),
+            )
+        });
+
+    let start_world = grid_to_world(start);
+    let end_world = grid_to_world(end);
+    gizmos.line_2d(start_world, end_world, MEASURE_COLOR);
+    gizmos.rect_2d(
+        (start_world + end_world) / 2.,
+        0.,
+        (end_world - start_world).abs() + Vec2::splat(20.),
+        MEASURE_COLOR.with_a(0.4),
+    );
+
+    *visibility = Visibility::Inherited;
+    text.sections[0].value = format!(
+        "dx: {dx}  dy: {dy}  along the grid: {}  straight: {:.1} cells   Wires in selection: {wire_count}, {wire_length} cells long",
+        dx + dy,
+        ((dx * dx + dy * dy) as f32).sqrt(),
+    );
+}
@@ -0,0 +1,253 @@
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{spawn_toolbar_button, Toolbar};
+
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+const MAX_FIELD_LENGTH: usize = 40;
+
+// Title, author, date and a description that are saved with the circuit, the info button shows and edits them
+pub struct MetadataPlugin;
+
+impl Plugin for MetadataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CircuitMetadata>()
+            .init_resource::<MetadataEditor>()
+            .add_systems(Startup, setup_info_panel)
+            .add_systems(PostStartup, setup_info_button)
+            // Typing has to happen before anything else looks at the keyboard, so shortcuts do not fire while editing
+            .add_systems(PreUpdate, type_field.after(InputSystem))
+            .add_systems(
+                Update,
+                (toggle_info_panel, start_field_edit, update_info_panel).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CircuitMetadata {
+    pub title: String,
+    pub author: String,
+    pub date: String,
+    pub description: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MetadataField {
+    Title,
+    Author,
+    Date,
+    Description,
+}
+
+impl MetadataField {
+    const ALL: [MetadataField; 4] = [
+        MetadataField::Title,
+        MetadataField::Author,
+        MetadataField::Date,
+        MetadataField::Description,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            MetadataField::Title => "Title",
+            MetadataField::Author => "Author",
+            MetadataField::Date => "Date",
+            MetadataField::Description => "Description",
+        }
+    }
+
+    fn max_length(self) -> usize {
+        match self {
+            MetadataField::Description => MAX_DESCRIPTION_LENGTH,
+            _ => MAX_FIELD_LENGTH,
+        }
+    }
+
+    fn value(self, metadata: &CircuitMetadata) -> &String {
+        match self {
+            MetadataField::Title => &metadata.title,
+            MetadataField::Author => &metadata.author,
+            MetadataField::Date => &metadata.date,
+            MetadataField::Description => &metadata.description,
+        }
+    }
+
+    fn value_mut(self, metadata: &mut CircuitMetadata) -> &mut String {
+        match self {
+            MetadataField::Title => &mut metadata.title,
+            MetadataField::Author => &mut metadata.author,
+            MetadataField::Date => &mut metadata.date,
+            MetadataField::Description => &mut metadata.description,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct MetadataEditor {
+    // The field that is being typed into, None when nothing is edited
+    field: Option<MetadataField>,
+    text: String,
+}
+
+#[derive(Component)]
+struct InfoButton;
+
+#[derive(Component)]
+struct InfoPanel;
+
+#[derive(Component)]
+struct InfoFieldButton(MetadataField);
+
+fn setup_info_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Info", "Circuit Info", InfoButton);
+    });
+}
+
+fn setup_info_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                bottom: Val::Px(80.),
+                width: Val::Px(320.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Info Panel"),
+        InfoPanel,
+    ))
+    .with_children(|root| {
+        for field in MetadataField::ALL {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(3.)),
+                        margin: UiRect::all(Val::Px(1.)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                },
+                Name::new(format!("Info {} Field", field.name())),
+                InfoFieldButton(field),
+            ))
+            .with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..Default::default()
+                        },
+                    ),
+                    Name::new(format!("Info {} Text", field.name())),
+                ));
+            });
+        }
+    });
+}
+
+fn toggle_info_panel(
+    info_button: Query<&Interaction, (Changed<Interaction>, With<InfoButton>)>,
+    mut panel: Query<&mut Visibility, With<InfoPanel>>,
+    mut editor: ResMut<MetadataEditor>,
+) {
+    if !info_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            // Closing the panel throws away what was not confirmed yet
+            editor.field = None;
+            Visibility::Hidden
+        };
+    }
+}
+
+fn start_field_edit(
+    field_buttons: Query<(&Interaction, &InfoFieldButton), Changed<Interaction>>,
+    metadata: Res<CircuitMetadata>,
+    mut editor: ResMut<MetadataEditor>,
+) {
+    for (interaction, field_button) in field_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            editor.field = Some(field_button.0);
+            editor.text = field_button.0.value(&metadata).clone();
+        }
+    }
+}
+
+fn type_field(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut metadata: ResMut<CircuitMetadata>,
+    mut editor: ResMut<MetadataEditor>,
+) {
+    let Some(field) = editor.field else {
+        characters.clear();
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.field = None;
+    } else if keys.just_pressed(KeyCode::Return) {
+        *field.value_mut(&mut metadata) = editor.text.clone();
+        editor.field = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            editor.text.pop();
+        }
+
+        for c in characters.read().map(|event| event.char) {
+            if editor.text.chars().count() < field.max_length() && !c.is_control() {
+                editor.text.push(c);
+            }
+        }
+    }
+
+    // The keys belong to the text field, nothing else should react to them this frame
+    characters.clear();
+    keys.reset_all();
+}
+
+fn update_info_panel(
+    metadata: Res<CircuitMetadata>,
+    editor: Res<MetadataEditor>,
+    field_buttons: Query<(&InfoFieldButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !metadata.is_changed() && !editor.is_changed() {
+        return;
+    }
+
+    for (field_button, children) in field_buttons.iter() {
+        let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) else {
+            continue;
+        };
+
+        let field = field_button.0;
+        text.sections[0].value = if editor.field == Some(field) {
+            format!("{}: {}_", field.name(), editor.text)
+        } else {
+            format!("{}: {}", field.name(), field.value(&metadata))
+        };
+    }
+}
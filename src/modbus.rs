@@ -0,0 +1,504 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    is_running, platform, simulate, spawn_toolbar_button, RelayCoil, SimulationScratch, Toolbar,
+    UIButton, UILight,
+};
+
+const MODBUS_PATH: &str = "modbus.ron";
+// Ids go up to 99, every kind of device gets a block of 100 addresses so address 7 is -S7 and 107 is -P7
+const BLOCK_SIZE: usize = 100;
+const LIGHT_BLOCK: usize = 1;
+const RELAY_BLOCK: usize = 2;
+const DISCRETE_INPUT_COUNT: usize = 3 * BLOCK_SIZE;
+// Largest counts a single request may ask for, from the Modbus specification
+const MAX_READ_BITS: usize = 2000;
+const MAX_WRITE_BITS: usize = 1968;
+// How long the server threads wait before they look again whether they should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const READ_COILS: u8 = 0x01;
+const READ_DISCRETE_INPUTS: u8 = 0x02;
+const WRITE_SINGLE_COIL: u8 = 0x05;
+const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+const GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+// The Modbus button starts a Modbus TCP server, so a PLC or SCADA system can press buttons and see what is lit and pulled in
+// Coil n presses -S{n} for as long as it is set, discrete input n is whether -S{n} is pressed, 100 + n whether -P{n} is lit
+// and 200 + n whether -K{n} is pulled in, port and unit id come from modbus.ron in the working directory, for example
+// (port: 5020, unit_id: 1)
+pub struct ModbusPlugin;
+
+impl Plugin for ModbusPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings())
+            .init_resource::<ModbusServer>()
+            .add_systems(PostStartup, setup_modbus_button)
+            .add_systems(Update, (toggle_modbus_server, update_modbus_button).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    press_coil_buttons.before(simulate),
+                    publish_outputs.after(simulate),
+                )
+                    .run_if(is_running),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Deserialize)]
+#[serde(default)]
+struct ModbusSettings {
+    port: u16,
+    unit_id: u8,
+}
+
+impl Default for ModbusSettings {
+    // 502 is the usual port, but only administrators may open ports below 1024
+    fn default() -> Self {
+        Self {
+            port: 5020,
+            unit_id: 1,
+        }
+    }
+}
+
+fn load_settings() -> ModbusSettings {
+    if !platform::file_exists(Path::new(MODBUS_PATH)) {
+        return ModbusSettings::default();
+    }
+
+    match platform::read_file(Path::new(MODBUS_PATH))
+        .and_then(|text| ron::from_str::<ModbusSettings>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Cannot read the Modbus settings from {MODBUS_PATH}, using the defaults: {e}");
+            ModbusSettings::default()
+        }
+    }
+}
+
+// What the server answers with and what clients wrote, shared between the app and the server threads
+struct ProcessImage {
+    coils: Vec<bool>,
+    discrete_inputs: Vec<bool>,
+}
+
+impl ProcessImage {
+    fn new() -> Self {
+        Self {
+            coils: vec![false; BLOCK_SIZE],
+            discrete_inputs: vec![false; DISCRETE_INPUT_COUNT],
+        }
+    }
+}
+
+struct RunningServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    image: Arc<Mutex<ProcessImage>>,
+}
+
+impl Drop for RunningServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Resource, Default)]
+struct ModbusServer(Option<RunningServer>);
+
+#[derive(Component)]
+struct ModbusButton;
+
+fn setup_modbus_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Modbus", "Modbus Server", ModbusButton);
+    });
+}
+
+fn toggle_modbus_server(
+    modbus_button: Query<&Interaction, (Changed<Interaction>, With<ModbusButton>)>,
+    settings: Res<ModbusSettings>,
+    mut server: ResMut<ModbusServer>,
+) {
+    if !modbus_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if server.0.take().is_some() {
+        info!("Stopped the Modbus server");
+        return;
+    }
+
+    match start_server(*settings) {
+        Ok(running) => {
+            info!(
+                "Modbus server listening on port {}, unit id {}",
+                settings.port, settings.unit_id
+            );
+            server.0 = Some(running);
+        }
+        Err(e) => error!(
+            "Cannot start the Modbus server on port {}: {e}",
+            settings.port
+        ),
+    }
+}
+
+fn update_modbus_button(
+    server: Res<ModbusServer>,
+    mut modbus_button: Query<(&Children, &mut BackgroundColor), With<ModbusButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !server.is_changed() {
+        return;
+    }
+
+    let (label, color) = match &server.0 {
+        Some(running) => (
+            format!("Modbus :{}", running.port),
+            Color::rgb(0.1, 0.5, 0.1),
+        ),
+        None => ("Modbus".to_string(), Color::rgb(0.25, 0.25, 0.25)),
+    };
+    for (children, mut background_color) in modbus_button.iter_mut() {
+        background_color.0 = color;
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+// A set coil holds its button down like the mouse does, the simulation lets go of it again every tick
+fn press_coil_buttons(server: Res<ModbusServer>, mut ui_buttons: Query<&mut UIButton>) {
+    let Some(image) = server
+        .0
+        .as_ref()
+        .and_then(|running| running.image.lock().ok())
+    else {
+        return;
+    };
+    for mut ui_button in ui_buttons.iter_mut() {
+        if image.coils.get(ui_button.id).copied().unwrap_or(false) {
+            ui_button.has_been_pressed = true;
+        }
+    }
+}
+
+fn publish_outputs(
+    server: Res<ModbusServer>,
+    scratch: Res<SimulationScratch>,
+    relay_coils: Query<&RelayCoil>,
+    ui_lights: Query<&UILight>,
+) {
+    let Some(mut image) = server
+        .0
+        .as_ref()
+        .and_then(|running| running.image.lock().ok())
+    else {
+        return;
+    };
+
+    let inputs = &mut image.discrete_inputs;
+    inputs.fill(false);
+    let mut set = |block: usize, id: usize| {
+        if id < BLOCK_SIZE {
+            inputs[block * BLOCK_SIZE + id] = true;
+        }
+    };
+    for id in scratch.active_button_ids.iter() {
+        set(0, *id);
+    }
+    for ui_light in ui_lights.iter().filter(|ui_light| ui_light.is_lit) {
+        set(LIGHT_BLOCK, ui_light.id);
+    }
+    for relay_coil in relay_coils.iter().filter(|relay_coil| relay_coil.activated) {
+        set(RELAY_BLOCK, relay_coil.id);
+    }
+}
+
+fn start_server(settings: ModbusSettings) -> std::io::Result<RunningServer> {
+    let listener = TcpListener::bind(("0.0.0.0", settings.port))?;
+    listener.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let image = Arc::new(Mutex::new(ProcessImage::new()));
+    let (thread_stop, thread_image) = (stop.clone(), image.clone());
+    thread::spawn(move || {
+        while !thread_stop.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    info!("Modbus client connected from {address}");
+                    let (stop, image) = (thread_stop.clone(), thread_image.clone());
+                    thread::spawn(move || serve_client(stream, settings.unit_id, &stop, &image));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => warn!("Cannot accept a Modbus client: {e}"),
+            }
+        }
+    });
+
+    Ok(RunningServer {
+        port: settings.port,
+        stop,
+        image,
+    })
+}
+
+// Requests come one after another on a connection, each starts with the 7 byte MBAP header
+fn serve_client(
+    mut stream: TcpStream,
+    unit_id: u8,
+    stop: &AtomicBool,
+    image: &Mutex<ProcessImage>,
+) {
+    if stream.set_nonblocking(false).is_err()
+        || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err()
+    {
+        return;
+    }
+
+    let mut request = Vec::new();
+    let mut buffer = [0; 260];
+    while !stop.load(Ordering::Relaxed) {
+        match stream.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(_) => return,
+        }
+
+        while request.len() >= 7 {
+            let length = u16::from_be_bytes([request[4], request[5]]) as usize;
+            // The length counts the unit id and the function code at least, a frame can not be longer than 260 bytes
+            if !(2..=254).contains(&length) || request[2..4] != [0, 0] {
+                return;
+            }
+            if request.len() < 6 + length {
+                break;
+            }
+
+            let frame = request.drain(..6 + length).collect::<Vec<_>>();
+            let pdu = match image.lock() {
+                Ok(mut image) if frame[6] == unit_id => respond(&frame[7..], &mut image),
+                Ok(_) => exception(frame[7], GATEWAY_TARGET_FAILED),
+                Err(_) => return,
+            };
+
+            let mut response = Vec::with_capacity(7 + pdu.len());
+            response.extend_from_slice(&frame[..4]);
+            response.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+            response.push(frame[6]);
+            response.extend_from_slice(&pdu);
+            if stream.write_all(&response).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn exception(function: u8, code: u8) -> Vec<u8> {
+    vec![function | 0x80, code]
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<usize> {
+    Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]) as usize)
+}
+
+// The answer to one request, without the header
+fn respond(pdu: &[u8], image: &mut ProcessImage) -> Vec<u8> {
+    let function = pdu[0];
+    if ![
+        READ_COILS,
+        READ_DISCRETE_INPUTS,
+        WRITE_SINGLE_COIL,
+        WRITE_MULTIPLE_COILS,
+    ]
+    .contains(&function)
+    {
+        return exception(function, ILLEGAL_FUNCTION);
+    }
+
+    let data = &pdu[1..];
+    let (Some(start), Some(value)) = (read_u16(data, 0), read_u16(data, 2)) else {
+        return exception(function, ILLEGAL_DATA_VALUE);
+    };
+
+    match function {
+        READ_COILS | READ_DISCRETE_INPUTS => {
+            let bits = match function {
+                READ_COILS => &image.coils,
+                _ => &image.discrete_inputs,
+            };
+            if !(1..=MAX_READ_BITS).contains(&value) {
+                return exception(function, ILLEGAL_DATA_VALUE);
+            }
+            let Some(bits) = bits.get(start..start + value) else {
+                return exception(function, ILLEGAL_DATA_ADDRESS);
+            };
+
+            let mut response = vec![function, value.div_ceil(8) as u8];
+            response.extend(bits.chunks(8).map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, bit)| byte | (u8::from(*bit) << i))
+            }));
+            response
+        }
+        WRITE_SINGLE_COIL => {
+            let on = match value {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return exception(function, ILLEGAL_DATA_VALUE),
+            };
+            let Some(coil) = image.coils.get_mut(start) else {
+                return exception(function, ILLEGAL_DATA_ADDRESS);
+            };
+            *coil = on;
+            pdu[..5].to_vec()
+        }
+        WRITE_MULTIPLE_COILS => {
+            let bytes = data.get(5..).unwrap_or_default();
+            if !(1..=MAX_WRITE_BITS).contains(&value)
+                || data.get(4).map(|count| *count as usize) != Some(value.div_ceil(8))
+                || bytes.len() != value.div_ceil(8)
+            {
+                return exception(function, ILLEGAL_DATA_VALUE);
+            }
+            let Some(coils) = image.coils.get_mut(start..start + value) else {
+                return exception(function, ILLEGAL_DATA_ADDRESS);
+            };
+            for (i, coil) in coils.iter_mut().enumerate() {
+                *coil = bytes[i / 8] & (1 << (i % 8)) != 0;
+            }
+            pdu[..5].to_vec()
+        }
+        _ => exception(function, ILLEGAL_FUNCTION),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_frame() {
+        let mut image = ProcessImage::new();
+        assert_eq!(
+            respond(&[READ_COILS], &mut image),
+            [READ_COILS | 0x80, ILLEGAL_DATA_VALUE]
+        );
+        assert_eq!(
+            respond(&[WRITE_SINGLE_COIL, 0, 7, 0xFF], &mut image),
+            [WRITE_SINGLE_COIL | 0x80, ILLEGAL_DATA_VALUE]
+        );
+        assert!(!image.coils[7]);
+    }
+
+    #[test]
+    fn unknown_function() {
+        let mut image = ProcessImage::new();
+        assert_eq!(
+            respond(&[0x03, 0, 0, 0, 1], &mut image),
+            [0x83, ILLEGAL_FUNCTION]
+        );
+    }
+
+    #[test]
+    fn read_discrete_inputs() {
+        let mut image = ProcessImage::new();
+        image.discrete_inputs[LIGHT_BLOCK * BLOCK_SIZE + 7] = true;
+        image.discrete_inputs[LIGHT_BLOCK * BLOCK_SIZE + 9] = true;
+        assert_eq!(
+            respond(&[READ_DISCRETE_INPUTS, 0, 100, 0, 10], &mut image),
+            [READ_DISCRETE_INPUTS, 2, 0b1000_0000, 0b10]
+        );
+    }
+
+    #[test]
+    fn address_out_of_range() {
+        let mut image = ProcessImage::new();
+        assert_eq!(
+            respond(&[READ_COILS, 0, 99, 0, 2], &mut image),
+            [READ_COILS | 0x80, ILLEGAL_DATA_ADDRESS]
+        );
+        assert_eq!(
+            respond(&[READ_DISCRETE_INPUTS, 1, 0x2C, 0, 1], &mut image),
+            [READ_DISCRETE_INPUTS | 0x80, ILLEGAL_DATA_ADDRESS]
+        );
+        assert_eq!(
+            respond(&[WRITE_SINGLE_COIL, 0, 100, 0xFF, 0], &mut image),
+            [WRITE_SINGLE_COIL | 0x80, ILLEGAL_DATA_ADDRESS]
+        );
+        assert_eq!(
+            respond(&[WRITE_MULTIPLE_COILS, 0, 98, 0, 3, 1, 0b111], &mut image),
+            [WRITE_MULTIPLE_COILS | 0x80, ILLEGAL_DATA_ADDRESS]
+        );
+        assert!(!image.coils.iter().any(|coil| *coil));
+    }
+
+    #[test]
+    fn write_coils() {
+        let mut image = ProcessImage::new();
+        assert_eq!(
+            respond(&[WRITE_SINGLE_COIL, 0, 3, 0xFF, 0], &mut image),
+            [WRITE_SINGLE_COIL, 0, 3, 0xFF, 0]
+        );
+        assert!(image.coils[3]);
+
+        assert_eq!(
+            respond(
+                &[WRITE_MULTIPLE_COILS, 0, 1, 0, 10, 2, 0b0000_0101, 0b10],
+                &mut image
+            ),
+            [WRITE_MULTIPLE_COILS, 0, 1, 0, 10]
+        );
+        let set = (0..BLOCK_SIZE)
+            .filter(|id| image.coils[*id])
+            .collect::<Vec<_>>();
+        assert_eq!(set, [1, 3, 10]);
+    }
+
+    #[test]
+    fn write_multiple_byte_count_mismatch() {
+        let mut image = ProcessImage::new();
+        let mismatched: [&[u8]; 3] = [
+            // 10 coils need 2 bytes, the count says 1
+            &[WRITE_MULTIPLE_COILS, 0, 0, 0, 10, 1, 0xFF, 0xFF],
+            // The count is right, but a byte is missing
+            &[WRITE_MULTIPLE_COILS, 0, 0, 0, 10, 2, 0xFF],
+            // No count at all
+            &[WRITE_MULTIPLE_COILS, 0, 0, 0, 10],
+        ];
+        for pdu in mismatched {
+            assert_eq!(
+                respond(pdu, &mut image),
+                [WRITE_MULTIPLE_COILS | 0x80, ILLEGAL_DATA_VALUE]
+            );
+        }
+        assert!(!image.coils.iter().any(|coil| *coil));
+    }
+}
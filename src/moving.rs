@@ -0,0 +1,360 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_contains, component_middle, component_terminals, convert_mouse_to_grid,
+    grid::GridSize, grid_to_world, oriented, spawn_toolbar_button, spawn_wire, ButtonSwitch,
+    CircuitHandles, CurrentlyPlacing, GridOrigin, GridPosition, Light, MainCamera, Power,
+    RelayCoil, RelaySwitch, Toolbar, Wire,
+};
+
+// In move mode (move button) components are dragged with the left mouse button and dropped where it is let go
+// The power squares can be dragged the same way, for example to lay the circuit out between a top and a bottom rail
+// Wires ending on a terminal of the moved component follow it, a wire that would end up diagonal gets a bend
+// Grabbing the end of a wire instead drags only that end, the wire stays straight along the axis closest to the cursor
+// The live edit plugin makes the visuals again once the positions changed
+pub struct MovePlugin;
+
+impl Plugin for MovePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_move_button).add_systems(
+            Update,
+            (start_move_mode, drag_components, drag_wire_ends).chain(),
+        );
+    }
+}
+
+#[derive(Component)]
+struct MoveButton;
+
+// The component being dragged, with its terminals and the grid point it was picked up at
+struct Held {
+    entity: Entity,
+    top: GridPosition,
+    bottom: GridPosition,
+    grabbed: GridPosition,
+}
+
+// The wire end being dragged, the other end stays where it is
+struct HeldWireEnd {
+    wire: Entity,
+    first: bool,
+    fixed: GridPosition,
+}
+
+fn setup_move_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Move", "Move", MoveButton);
+    });
+}
+
+fn start_move_mode(
+    move_button: Query<&Interaction, (Changed<Interaction>, With<MoveButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if move_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Move;
+    }
+}
+
+// Where the terminals end up when the component is dropped at the cursor, None if it does not fit there
+fn dropped_terminals(
+    held: &Held,
+    cursor: GridPosition,
+    grid_size: &GridSize,
+) -> Option<(GridPosition, GridPosition)> {
+    let shift = |pos: GridPosition| {
+        let x = (pos.x + cursor.x).checked_sub(held.grabbed.x)?;
+        let y = (pos.y + cursor.y).checked_sub(held.grabbed.y)?;
+        Some(GridPosition { x, y }).filter(|pos| grid_size.contains(*pos))
+    };
+    Some((shift(held.top)?, shift(held.bottom)?))
+}
+
+fn drag_components(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    (windows, cameras, grid_size): (
+        Query<&Window, With<PrimaryWindow>>,
+        Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+        Res<GridSize>,
+    ),
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+    mut lights: Query<(Entity, &mut Light)>,
+    mut buttons: Query<(Entity, &mut ButtonSwitch)>,
+    mut relay_coils: Query<(Entity, &mut RelayCoil)>,
+    mut relay_switches: Query<(Entity, &mut RelaySwitch)>,
+    mut wires: Query<(Entity, &mut Wire)>,
+    mut power_sources: Query<(Entity, &mut GridPosition), With<Power>>,
+    mut gizmos: Gizmos,
+    mut held: Local<Option<Held>>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Move) {
+        *held = None;
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    // Outline of where the component would land
+    if let Some((top, bottom)) = held
+        .as_ref()
+        .zip(mouse_grid)
+        .and_then(|(held, cursor)| dropped_terminals(held, cursor, &grid_size))
+    {
+        // A power square has one grid point
+        let size = if top == bottom {
+            Vec2::splat(24.)
+        } else {
+            oriented(top, bottom, Vec2::new(24., 60.))
+        };
+        gizmos.rect_2d(
+            grid_to_world(component_middle(top, bottom)),
+            0.,
+            size,
+            Color::WHITE,
+        );
+    }
+
+    if mouse_button.just_released(MouseButton::Left) {
+        let Some(held) = held.take() else {
+            return;
+        };
+        let Some((top, bottom)) =
+            mouse_grid.and_then(|cursor| dropped_terminals(&held, cursor, &grid_size))
+        else {
+            warn!("The component does not fit there");
+            return;
+        };
+        if top == held.top {
+            return;
+        }
+
+        if let Ok((_, mut light)) = lights.get_mut(held.entity) {
+            (light.top, light.bottom) = (top, bottom);
+        } else if let Ok((_, mut button)) = buttons.get_mut(held.entity) {
+            (button.top, button.bottom) = (top, bottom);
+        } else if let Ok((_, mut relay_coil)) = relay_coils.get_mut(held.entity) {
+            (relay_coil.top, relay_coil.bottom) = (top, bottom);
+        } else if let Ok((_, mut relay_switch)) = relay_switches.get_mut(held.entity) {
+            (relay_switch.top, relay_switch.bottom) = (top, bottom);
+        } else if power_sources.get(held.entity).is_ok() {
+            if power_sources.iter().any(|(_, pos)| *pos == top) {
+                warn!("There is a power source there already");
+                return;
+            }
+            if let Ok((_, mut pos)) = power_sources.get_mut(held.entity) {
+                *pos = top;
+            }
+        }
+
+        // The middle is a terminal as well for changeover contacts
+        let moved = |pos: GridPosition| {
+            if pos == held.top {
+                Some(top)
+            } else if pos == held.bottom {
+                Some(bottom)
+            } else if pos == component_middle(held.top, held.bottom) {
+                Some(component_middle(top, bottom))
+            } else {
+                None
+            }
+        };
+        for (e, mut wire) in wires.iter_mut() {
+            let (first, second) = match (moved(wire.first), moved(wire.second)) {
+                (None, None) => continue,
+                (Some(first), Some(second)) => (first, second),
+                (Some(first), None) => (first, wire.second),
+                (None, Some(second)) => (wire.first, second),
+            };
+
+            if first == second {
+                cmd.entity(e).despawn_recursive();
+            } else if first.x == second.x || first.y == second.y {
+                (wire.first, wire.second) = (first, second);
+            } else {
+                // Stays put along the end that did not move and turns towards the one that did
+                let (fixed, moving) = if moved(wire.first).is_some() {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                let corner = GridPosition {
+                    x: moving.x,
+                    y: fixed.y,
+                };
+                (wire.first, wire.second) = (fixed, corner);
+                spawn_wire(
+                    &mut cmd,
+                    &circuit_material,
+                    grid_origin.single(),
+                    Wire {
+                        first: corner,
+                        second: moving,
+                    },
+                );
+            }
+        }
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        *held = None;
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    }
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(cursor) = mouse_grid else {
+        return;
+    };
+
+    // Any of the three grid points of a component picks it up
+    *held = lights
+        .iter()
+        .map(|(e, light)| (e, light.top, light.bottom))
+        .chain(
+            buttons
+                .iter()
+                .map(|(e, button)| (e, button.top, button.bottom)),
+        )
+        .chain(
+            relay_coils
+                .iter()
+                .map(|(e, relay_coil)| (e, relay_coil.top, relay_coil.bottom)),
+        )
+        .chain(
+            relay_switches
+                .iter()
+                .map(|(e, relay_switch)| (e, relay_switch.top, relay_switch.bottom)),
+        )
+        .chain(power_sources.iter().map(|(e, pos)| (e, *pos, *pos)))
+        .find(|(_, top, bottom)| component_contains(*top, *bottom, cursor))
+        .map(|(entity, top, bottom)| Held {
+            entity,
+            top,
+            bottom,
+            grabbed: cursor,
+        });
+}
+
+// Where the dragged end lands, in line with the end that stays
+fn straightened(fixed: GridPosition, cursor: GridPosition) -> GridPosition {
+    if cursor.x.abs_diff(fixed.x) >= cursor.y.abs_diff(fixed.y) {
+        GridPosition {
+            x: cursor.x,
+            y: fixed.y,
+        }
+    } else {
+        GridPosition {
+            x: fixed.x,
+            y: cursor.y,
+        }
+    }
+}
+
+fn drag_wire_ends(
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    currently_placing: Res<CurrentlyPlacing>,
+    components: Query<AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>>,
+    power_sources: Query<&GridPosition, With<Power>>,
+    mut wires: Query<(Entity, &mut Wire)>,
+    mut gizmos: Gizmos,
+    mut held: Local<Option<HeldWireEnd>>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Move) {
+        *held = None;
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    if let Some((held, cursor)) = held.as_ref().zip(mouse_grid) {
+        gizmos.line_2d(
+            grid_to_world(held.fixed),
+            grid_to_world(straightened(held.fixed, cursor)),
+            Color::WHITE,
+        );
+    }
+
+    if mouse_button.just_released(MouseButton::Left) {
+        let (Some(held), Some(cursor)) = (held.take(), mouse_grid) else {
+            return;
+        };
+        let end = straightened(held.fixed, cursor);
+        if end == held.fixed {
+            warn!("A wire needs two different ends");
+            return;
+        }
+        if let Ok((_, mut wire)) = wires.get_mut(held.wire) {
+            if held.first {
+                wire.first = end;
+            } else {
+                wire.second = end;
+            }
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left)
+        || ui_interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(cursor) = mouse_grid else {
+        return;
+    };
+
+    // Components are picked up first, the wire ends on their terminals follow them anyway
+    let on_component = components
+        .iter()
+        .filter_map(component_terminals)
+        .any(|(top, bottom)| component_contains(top, bottom, cursor))
+        || power_sources.iter().any(|pos| *pos == cursor);
+    if on_component {
+        return;
+    }
+
+    *held = wires.iter().find_map(|(wire, ends)| {
+        if ends.first == cursor {
+            Some(HeldWireEnd {
+                wire,
+                first: true,
+                fixed: ends.second,
+            })
+        } else if ends.second == cursor {
+            Some(HeldWireEnd {
+                wire,
+                first: false,
+                fixed: ends.first,
+            })
+        } else {
+            None
+        }
+    });
+}
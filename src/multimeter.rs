@@ -0,0 +1,216 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, spawn_toolbar_button, CurrentlyPlacing,
+    GridPosition, MainCamera, SimulationScratch, Toolbar, Visited,
+};
+
+const PROBE_COLOR: Color = Color::rgb(1., 0.85, 0.2);
+// Relay control circuits usually run on 24 V, every consumer is the same load so voltages are shares of it
+pub const SUPPLY_VOLTAGE: f32 = 24.;
+
+// With the meter button two clicks place the red and the black probe, until the second click the cursor is the black one
+// A readout next to the black probe tells whether both are on the same net, which side each one is on and the voltage between them
+pub struct MultimeterPlugin;
+
+impl Plugin for MultimeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Probes>()
+            .add_systems(Startup, setup_readout)
+            .add_systems(PostStartup, setup_meter_button)
+            .add_systems(
+                Update,
+                (start_metering, handle_probe_clicks, show_readout).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct Probes {
+    red: Option<GridPosition>,
+    black: Option<GridPosition>,
+}
+
+#[derive(Component)]
+struct MeterButton;
+
+#[derive(Component)]
+struct Readout;
+
+fn setup_meter_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Meter", "Multimeter", MeterButton);
+    });
+}
+
+fn setup_readout(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(4.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.1, 0.1, 0.05, 0.9)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(15),
+            ..Default::default()
+        },
+        Name::new("Multimeter Readout"),
+        Readout,
+    ));
+}
+
+fn start_metering(
+    meter_button: Query<&Interaction, (Changed<Interaction>, With<MeterButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if meter_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Multimeter;
+    }
+}
+
+fn handle_probe_clicks(
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut probes: ResMut<Probes>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Multimeter) {
+        if probes.red.is_some() {
+            *probes = Probes::default();
+        }
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        if probes.red.is_none() {
+            *currently_placing = CurrentlyPlacing::Wire;
+        }
+        *probes = Probes::default();
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    // A third click starts over
+    if probes.red.is_none() || probes.black.is_some() {
+        *probes = Probes {
+            red: Some(mouse_grid),
+            black: None,
+        };
+    } else {
+        probes.black = Some(mouse_grid);
+    }
+}
+
+fn describe_polarity(scratch: &SimulationScratch, pos: GridPosition) -> &'static str {
+    if scratch.solver.is_shorted(pos) {
+        return "shorted";
+    }
+    match scratch.solver.mark(pos) {
+        Visited::Positive => "+",
+        Visited::Negative => "-",
+        Visited::Unvisited => "no potential",
+    }
+}
+
+fn show_readout(
+    probes: Res<Probes>,
+    scratch: Res<SimulationScratch>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    mut readout: Query<(&mut Text, &mut Style, &mut Visibility), With<Readout>>,
+    mut gizmos: Gizmos,
+) {
+    let (mut text, mut style, mut visibility) = readout.single_mut();
+
+    let Some(red) = probes.red else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let Some(black) = probes.black.or_else(|| {
+        windows
+            .single()
+            .cursor_position()
+            .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    }) else {
+        return;
+    };
+
+    let (red_world, black_world) = (grid_to_world(red), grid_to_world(black));
+    gizmos.line_2d(red_world, black_world, PROBE_COLOR);
+    gizmos.circle_2d(red_world, 6., Color::RED);
+    gizmos.circle_2d(black_world, 6., Color::BLACK);
+
+    // Follows the black probe on screen, also while the view moves
+    let (camera, camera_transform) = cameras.single();
+    let Some(screen) = camera.world_to_viewport(camera_transform, black_world.extend(0.)) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let (left, top) = (Val::Px(screen.x + 12.), Val::Px(screen.y + 12.));
+    if style.left != left || style.top != top {
+        style.left = left;
+        style.top = top;
+    }
+
+    let continuity = if scratch.solver.same_net(red, black) {
+        "continuity"
+    } else {
+        "open"
+    };
+    let voltage = match (
+        scratch.solver.potential(red),
+        scratch.solver.potential(black),
+    ) {
+        (Some(red), Some(black)) => format!("{:.1} V", (red - black) * SUPPLY_VOLTAGE),
+        _ => "-- V".to_string(),
+    };
+    let value = format!(
+        "{voltage}   {continuity}\nred {}, {}: {}   black {}, {}: {}",
+        red.x,
+        red.y,
+        describe_polarity(&scratch, red),
+        black.x,
+        black.y,
+        describe_polarity(&scratch, black),
+    );
+
+    *visibility = Visibility::Inherited;
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
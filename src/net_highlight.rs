@@ -0,0 +1,168 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_middle, component_terminals, convert_mouse_to_grid,
+    fuse::Fuse,
+    grid::GridSize,
+    grid_to_world,
+    hidden::HiddenRegion,
+    net_labels::{label_links, NetLabel},
+    time_switch, wire_contains, ButtonSwitch, CurrentlyPlacing, Faulty, GridPosition, IsRunning,
+    Light, MainCamera, RelayCoil, RelaySwitch, SimulationScratch, Solver, SwitchType, Wire,
+};
+use relay_sim_core::{Circuit, Switch};
+
+const NET_COLOR: Color = Color::rgb(0.3, 0.9, 1.);
+
+// Hovering a wire or terminal highlights every wire and terminal connected to it through wires and closed contacts
+// While running that is the graph of the last tick, while stopped the contacts are taken at rest
+// Not while troubleshooting, a broken wire would show up right away
+pub struct NetHighlightPlugin;
+
+impl Plugin for NetHighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, highlight_net);
+    }
+}
+
+// The same circuit simulate builds, with no button pressed and no relay pulled in
+fn build_rest_circuit<'a>(
+    circuit: &mut Circuit,
+    wires: &Query<(&Wire, Option<&Faulty>)>,
+    closed_wires: impl Iterator<Item = Wire>,
+    switches: impl Iterator<Item = (GridPosition, GridPosition, SwitchType, Option<&'a Faulty>)>,
+) {
+    circuit.clear();
+    circuit.wires.extend(
+        wires
+            .iter()
+            .filter(|(_, faulty)| faulty.is_none())
+            .map(|(wire, _)| wire.clone())
+            .chain(closed_wires)
+            .map(|wire| (wire.first.into(), wire.second.into())),
+    );
+    circuit
+        .switches
+        .extend(switches.filter_map(|(top, bottom, typ, faulty)| {
+            Some(Switch {
+                top: top.into(),
+                bottom: bottom.into(),
+                typ: typ.into(),
+                actuated: Faulty::actuated(faulty, typ, false)?,
+            })
+        }));
+}
+
+fn highlight_net(
+    running: Res<IsRunning>,
+    currently_placing: Res<CurrentlyPlacing>,
+    scratch: Res<SimulationScratch>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    hidden_regions: Query<&HiddenRegion>,
+    wires: Query<(&Wire, Option<&Faulty>)>,
+    time_switches: Query<&time_switch::TimeSwitch>,
+    time_of_day: Res<time_switch::TimeOfDay>,
+    fuses: Query<&Fuse>,
+    net_labels: Query<&NetLabel>,
+    components: Query<(
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&Faulty>,
+    )>,
+    mut circuit: Local<Circuit>,
+    mut rest: Local<Solver>,
+    mut gizmos: Gizmos,
+) {
+    if matches!(*currently_placing, CurrentlyPlacing::Troubleshoot) {
+        return;
+    }
+    let hidden = |pos: GridPosition| hidden_regions.iter().any(|region| region.hides(pos));
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .filter(|mouse_grid| !hidden(*mouse_grid))
+    else {
+        return;
+    };
+
+    // Every terminal, the common one of changeover contacts included
+    let terminals = components
+        .iter()
+        .filter_map(|((light, button, relay_coil, relay_switch), _)| {
+            let (top, bottom) = component_terminals((light, button, relay_coil, relay_switch))?;
+            let typ = button
+                .map(|button| button.typ)
+                .or(relay_switch.map(|relay_switch| relay_switch.typ));
+            let common =
+                (typ == Some(SwitchType::Changeover)).then(|| component_middle(top, bottom));
+            Some([Some(top), Some(bottom), common])
+        })
+        .flatten()
+        .flatten()
+        .filter(|terminal| !hidden(*terminal))
+        .collect::<Vec<_>>();
+
+    let Some(hovered) = wires
+        .iter()
+        .find(|(wire, _)| wire_contains(wire, &mouse_grid))
+        .map(|(wire, _)| wire.first)
+        .or(terminals
+            .iter()
+            .copied()
+            .find(|terminal| *terminal == mouse_grid))
+    else {
+        return;
+    };
+
+    let solver = if running.0 {
+        &scratch.solver
+    } else {
+        build_rest_circuit(
+            &mut circuit,
+            &wires,
+            time_switches
+                .iter()
+                .filter(|time_switch| time_switch.is_closed(&time_of_day))
+                .map(Wire::from)
+                .chain(fuses.iter().filter(|fuse| !fuse.blown).map(Wire::from)),
+            components
+                .iter()
+                .filter_map(|((_, button, _, relay_switch), faulty)| {
+                    button
+                        .map(|button| (button.top, button.bottom, button.typ))
+                        .or(relay_switch.map(|relay_switch| {
+                            (relay_switch.top, relay_switch.bottom, relay_switch.typ)
+                        }))
+                        .map(|(top, bottom, typ)| (top, bottom, typ, faulty))
+                }),
+        );
+        circuit.links.extend(
+            label_links(net_labels.iter())
+                .into_iter()
+                .map(|(first, second)| (first.into(), second.into())),
+        );
+        rest.step(&circuit);
+        &*rest
+    };
+
+    // Black boxes stay black, nothing inside them is highlighted
+    for (wire, _) in wires
+        .iter()
+        .filter(|(wire, _)| !hidden(wire.first) && !hidden(wire.second))
+    {
+        if solver.same_net(wire.first, hovered) && solver.same_net(wire.second, hovered) {
+            gizmos.line_2d(
+                grid_to_world(wire.first),
+                grid_to_world(wire.second),
+                NET_COLOR,
+            );
+        }
+    }
+    for terminal in terminals {
+        if solver.same_net(terminal, hovered) {
+            gizmos.circle_2d(grid_to_world(terminal), 5., NET_COLOR);
+        }
+    }
+}
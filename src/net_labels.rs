@@ -0,0 +1,264 @@
+use bevy::{input::InputSystem, prelude::*, sprite::Anchor, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, spawn_toolbar_button, CurrentlyPlacing,
+    GridPosition, MainCamera, Toolbar,
+};
+
+const MAX_NAME_LENGTH: usize = 8;
+const NET_LABEL_COLOR: Color = Color::rgb(0.5, 0.9, 0.5);
+
+// Net labels ("24V", "N", "X1") connect every grid point carrying the same name without a wire in between
+// With the net label button a click on a grid point opens an editor for its label, confirming an empty name removes it
+// Right clicking a label removes it as well
+pub struct NetLabelPlugin;
+
+impl Plugin for NetLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetLabelEditor>()
+            .add_systems(Startup, setup_net_label_editor)
+            .add_systems(PostStartup, setup_net_label_button)
+            // Typing runs before the shortcuts look at the keys, like the wire label editor
+            .add_systems(PreUpdate, type_net_label.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    start_placing_net_labels,
+                    handle_net_label_clicks,
+                    update_net_label_editor,
+                    update_net_label_text,
+                    draw_net_labels,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct NetLabel {
+    pub pos: GridPosition,
+    pub name: String,
+}
+
+#[derive(Resource, Default)]
+struct NetLabelEditor {
+    // The grid point whose label is being edited, None when the editor is closed
+    pos: Option<GridPosition>,
+    text: String,
+}
+
+#[derive(Component)]
+struct NetLabelButton;
+
+#[derive(Component)]
+struct NetLabelEditorText;
+
+// Every label joined to the first one with the same name
+pub fn label_links<'a>(
+    labels: impl Iterator<Item = &'a NetLabel>,
+) -> Vec<(GridPosition, GridPosition)> {
+    let mut labels = labels.collect::<Vec<_>>();
+    labels.sort_by(|a, b| a.name.cmp(&b.name));
+    labels
+        .windows(2)
+        .filter(|pair| pair[0].name == pair[1].name)
+        .map(|pair| (pair[0].pos, pair[1].pos))
+        .collect()
+}
+
+pub fn spawn_net_label(cmd: &mut Commands, label: NetLabel) -> Entity {
+    cmd.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.,
+                    color: NET_LABEL_COLOR,
+                    ..Default::default()
+                },
+            ),
+            text_anchor: Anchor::BottomLeft,
+            ..Default::default()
+        },
+        Name::new("Net Label"),
+        label,
+    ))
+    .id()
+}
+
+fn setup_net_label_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Net", "Net Label", NetLabelButton);
+    });
+}
+
+fn setup_net_label_editor(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Net Label Editor"),
+        NetLabelEditorText,
+    ));
+}
+
+fn start_placing_net_labels(
+    net_label_button: Query<&Interaction, (Changed<Interaction>, With<NetLabelButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if net_label_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::NetLabel;
+    }
+}
+
+fn handle_net_label_clicks(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    labels: Query<(Entity, &NetLabel)>,
+    mut editor: ResMut<NetLabelEditor>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::NetLabel) {
+        if editor.pos.is_some() {
+            editor.pos = None;
+        }
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+    let clicked = labels.iter().find(|(_, label)| label.pos == mouse_grid);
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        editor.pos = None;
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        editor.pos = Some(mouse_grid);
+        editor.text = clicked
+            .map(|(_, label)| label.name.clone())
+            .unwrap_or_default();
+    }
+}
+
+fn type_net_label(
+    mut cmd: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut editor: ResMut<NetLabelEditor>,
+    mut labels: Query<(Entity, &mut NetLabel)>,
+) {
+    let Some(pos) = editor.pos else {
+        characters.clear();
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.pos = None;
+    } else if keys.just_pressed(KeyCode::Return) {
+        let name = editor.text.trim().to_string();
+        match labels.iter_mut().find(|(_, label)| label.pos == pos) {
+            Some((e, _)) if name.is_empty() => cmd.entity(e).despawn_recursive(),
+            Some((_, mut label)) => label.name = name,
+            None if name.is_empty() => {}
+            None => {
+                spawn_net_label(&mut cmd, NetLabel { pos, name });
+            }
+        }
+        editor.pos = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            editor.text.pop();
+        }
+
+        for c in characters.read().map(|event| event.char) {
+            if editor.text.chars().count() < MAX_NAME_LENGTH && !c.is_control() {
+                editor.text.push(c);
+            }
+        }
+    }
+
+    characters.clear();
+    keys.reset_all();
+}
+
+fn update_net_label_editor(
+    editor: Res<NetLabelEditor>,
+    mut editor_text: Query<(&mut Text, &mut Visibility), With<NetLabelEditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in editor_text.iter_mut() {
+        let Some(pos) = editor.pos else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Inherited;
+        text.sections[0].value = format!(
+            "Net label at {}, {}: {}_   (Enter to confirm, Esc to cancel)",
+            pos.x, pos.y, editor.text
+        );
+    }
+}
+
+// Loaded labels only get their text here, so loading only has to spawn the label
+fn update_net_label_text(
+    mut labels: Query<(&NetLabel, &mut Text, &mut Transform), Changed<NetLabel>>,
+) {
+    for (label, mut text, mut transform) in labels.iter_mut() {
+        text.sections[0].value = label.name.clone();
+        transform.translation = (grid_to_world(label.pos) + Vec2::new(6., 4.)).extend(6.);
+    }
+}
+
+fn draw_net_labels(labels: Query<&NetLabel>, mut gizmos: Gizmos) {
+    for label in labels.iter() {
+        gizmos.circle_2d(grid_to_world(label.pos), 5., NET_LABEL_COLOR);
+    }
+}
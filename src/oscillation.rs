@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{is_running, simulate, IsRunning, RelayCoil};
+
+// Ticks that are looked back on, one second at 20 hz
+const OSCILLATION_WINDOW: usize = 20;
+// A relay that switched on at least this many of them never settles
+const OSCILLATION_CHANGES: usize = OSCILLATION_WINDOW / 2;
+
+// A relay that switches itself off through its own contact flaps every tick, a banner names the relays that do not settle
+// Timer relays are left out, blinking is what they are built for
+pub struct OscillationPlugin;
+
+impl Plugin for OscillationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Oscillation>()
+            .add_systems(Startup, setup_oscillation_banner)
+            .add_systems(
+                FixedUpdate,
+                detect_oscillation.after(simulate).run_if(is_running),
+            )
+            .add_systems(Update, update_oscillation_banner);
+    }
+}
+
+#[derive(Resource, Default)]
+struct Oscillation {
+    // State of every relay after the last tick
+    previous: Vec<(usize, bool)>,
+    // The relays that switched in each of the last ticks
+    changes: VecDeque<Vec<usize>>,
+    oscillating: Vec<usize>,
+}
+
+#[derive(Component)]
+struct OscillationBanner;
+
+fn setup_oscillation_banner(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                // Below the short circuit banner
+                top: Val::Px(85.),
+                left: Val::Percent(40.),
+                padding: UiRect::all(Val::Px(8.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.7, 0.4, 0., 0.85)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(20),
+            ..Default::default()
+        },
+        Name::new("Oscillation Banner"),
+        OscillationBanner,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 20.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            ),
+            Name::new("Oscillation Banner Text"),
+        ));
+    });
+}
+
+fn detect_oscillation(mut oscillation: ResMut<Oscillation>, relay_coils: Query<&RelayCoil>) {
+    // Only a different set of oscillating relays is a change, the banner does not need to hear about every tick
+    let state = oscillation.bypass_change_detection();
+
+    let mut changed = Vec::new();
+    let mut states = Vec::new();
+    for relay_coil in relay_coils
+        .iter()
+        .filter(|relay_coil| relay_coil.timer.is_none())
+    {
+        let was = state
+            .previous
+            .iter()
+            .find(|(id, _)| *id == relay_coil.id)
+            .map(|(_, state)| *state);
+        if was.is_some_and(|was| was != relay_coil.activated) && !changed.contains(&relay_coil.id) {
+            changed.push(relay_coil.id);
+        }
+        states.push((relay_coil.id, relay_coil.activated));
+    }
+    state.previous = states;
+
+    if state.changes.len() == OSCILLATION_WINDOW {
+        state.changes.pop_front();
+    }
+    state.changes.push_back(changed);
+
+    let mut oscillating = state
+        .changes
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|id| {
+            state
+                .changes
+                .iter()
+                .filter(|changes| changes.contains(id))
+                .count()
+                >= OSCILLATION_CHANGES
+        })
+        .collect::<Vec<_>>();
+    oscillating.sort();
+    oscillating.dedup();
+    if oscillation.oscillating != oscillating {
+        oscillation.oscillating = oscillating;
+    }
+}
+
+fn update_oscillation_banner(
+    running: Res<IsRunning>,
+    mut oscillation: ResMut<Oscillation>,
+    mut banner: Query<(&mut Visibility, &Children), With<OscillationBanner>>,
+    mut texts: Query<&mut Text>,
+) {
+    // Stopping puts every relay back to rest, what happened before does not count anymore
+    if running.is_changed() && !running.0 {
+        *oscillation = Oscillation::default();
+    }
+    if !oscillation.is_changed() {
+        return;
+    }
+
+    for (mut visibility, children) in banner.iter_mut() {
+        if oscillation.oscillating.is_empty() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+
+        let relays = oscillation
+            .oscillating
+            .iter()
+            .map(|id| format!("-K{id}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = format!("Circuit oscillates: {relays}");
+            }
+        }
+    }
+}
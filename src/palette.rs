@@ -0,0 +1,353 @@
+use bevy::{input::InputSystem, prelude::*, ui::UiSystem};
+
+use crate::keybindings::{key_name, Action, KeyBindings};
+
+const MAX_QUERY_LENGTH: usize = 40;
+const MAX_ROWS: usize = 12;
+
+// Ctrl+P opens a searchable list of every visible button and every keyboard action
+// Running an entry presses the button or the bound keys for one frame, so every feature stays reachable without new wiring
+pub struct CommandPalettePlugin;
+
+impl Plugin for CommandPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandPalette>()
+            .add_systems(Startup, setup_palette)
+            .add_systems(
+                PreUpdate,
+                (
+                    type_palette_query.after(InputSystem),
+                    // After the ui focus, which would otherwise overwrite the pressed button
+                    run_palette_command.after(UiSystem::Focus),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, (handle_palette_rows, update_palette).chain());
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PaletteCommand {
+    Button(Entity),
+    Key(Action),
+}
+
+#[derive(Resource, Default)]
+struct CommandPalette {
+    open: bool,
+    query: String,
+    // Collected when the palette opens, buttons of hidden panels are left out
+    commands: Vec<(String, PaletteCommand)>,
+    // Indices into the commands, best match first
+    matches: Vec<usize>,
+    selected: usize,
+    // Run at the start of the next frame
+    pending: Option<PaletteCommand>,
+}
+
+impl CommandPalette {
+    fn update_matches(&mut self) {
+        let mut scored = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (label, _))| fuzzy_score(&self.query, label).map(|score| (i, score)))
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| self.commands[a.0].0.cmp(&self.commands[b.0].0))
+        });
+
+        self.matches = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected = 0;
+    }
+
+    fn selected_command(&self) -> Option<PaletteCommand> {
+        self.matches.get(self.selected).map(|i| self.commands[*i].1)
+    }
+}
+
+#[derive(Component)]
+struct PalettePanel;
+
+#[derive(Component)]
+struct PaletteQueryText;
+
+// Position in the shown matches
+#[derive(Component)]
+struct PaletteRow(usize);
+
+// The letters of the query have to appear in order, consecutive letters and word starts count extra
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let label = label.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match = None;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + label[next..].iter().position(|l| *l == c)?;
+        score += 1;
+        if found > 0 && last_match == Some(found - 1) {
+            score += 5;
+        }
+        if found == 0 || label[found - 1] == ' ' {
+            score += 3;
+        }
+        last_match = Some(found);
+        next = found + 1;
+    }
+
+    // Shorter labels win among otherwise equal matches
+    Some(score * 100 - label.len() as i32)
+}
+
+fn setup_palette(mut cmd: Commands) {
+    let text_style = TextStyle {
+        font_size: 16.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                left: Val::Px(290.),
+                width: Val::Px(400.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.85)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(20),
+            ..Default::default()
+        },
+        Name::new("Command Palette"),
+        PalettePanel,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section("", text_style.clone()).with_style(Style {
+                margin: UiRect::bottom(Val::Px(4.)),
+                ..Default::default()
+            }),
+            Name::new("Command Palette Query"),
+            PaletteQueryText,
+        ));
+
+        for i in 0..MAX_ROWS {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(3.)),
+                        margin: UiRect::all(Val::Px(1.)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                },
+                Name::new("Command Palette Row"),
+                PaletteRow(i),
+            ))
+            .with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section("", text_style.clone()),
+                    Name::new("Command Palette Row Text"),
+                ));
+            });
+        }
+    });
+}
+
+fn type_palette_query(
+    mut keys: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut palette: ResMut<CommandPalette>,
+    buttons: Query<(Entity, &Name, &ViewVisibility), With<Button>>,
+) {
+    // Always read, otherwise the key that opened the palette would end up in the query
+    let typed = characters
+        .read()
+        .map(|event| event.char)
+        .collect::<Vec<_>>();
+
+    if !palette.open {
+        let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+        if !ctrl || !bindings.just_pressed(&keys, Action::CommandPalette) {
+            return;
+        }
+
+        // Every button of the app is named "<what it does> Button"
+        let mut commands = buttons
+            .iter()
+            .filter(|(_, _, visibility)| visibility.get())
+            .filter_map(|(e, name, _)| {
+                name.as_str()
+                    .strip_suffix(" Button")
+                    .map(|label| (label.to_string(), PaletteCommand::Button(e)))
+            })
+            .collect::<Vec<_>>();
+        commands.extend(
+            Action::ALL
+                .into_iter()
+                .filter(|action| *action != Action::CommandPalette)
+                .map(|action| {
+                    (
+                        format!("{} [{}]", action.name(), key_name(bindings.key(action))),
+                        PaletteCommand::Key(action),
+                    )
+                }),
+        );
+
+        *palette = CommandPalette {
+            open: true,
+            commands,
+            ..Default::default()
+        };
+        palette.update_matches();
+        keys.reset_all();
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        palette.open = false;
+    } else if keys.just_pressed(KeyCode::Return) {
+        palette.pending = palette.selected_command();
+        palette.open = false;
+    } else if keys.just_pressed(KeyCode::Up) {
+        palette.selected = palette.selected.saturating_sub(1);
+    } else if keys.just_pressed(KeyCode::Down) {
+        let last = palette.matches.len().min(MAX_ROWS).saturating_sub(1);
+        palette.selected = (palette.selected + 1).min(last);
+    } else {
+        let mut changed = false;
+        if keys.just_pressed(KeyCode::Back) {
+            changed = palette.query.pop().is_some();
+        }
+
+        for c in typed {
+            if palette.query.chars().count() < MAX_QUERY_LENGTH && !c.is_control() {
+                palette.query.push(c);
+                changed = true;
+            }
+        }
+
+        if changed {
+            palette.update_matches();
+        }
+    }
+
+    keys.reset_all();
+}
+
+fn handle_palette_rows(
+    rows: Query<(&Interaction, &PaletteRow), Changed<Interaction>>,
+    mut palette: ResMut<CommandPalette>,
+) {
+    for (interaction, PaletteRow(row)) in rows.iter() {
+        if *interaction != Interaction::Pressed || !palette.open {
+            continue;
+        }
+
+        palette.selected = *row;
+        palette.pending = palette.selected_command();
+        palette.open = false;
+    }
+}
+
+// The button or keys pressed for a command are let go again on the following frame
+fn run_palette_command(
+    mut palette: ResMut<CommandPalette>,
+    mut keys: ResMut<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut interactions: Query<&mut Interaction>,
+    mut pressed: Local<Option<PaletteCommand>>,
+) {
+    match pressed.take() {
+        Some(PaletteCommand::Button(e)) => {
+            if let Ok(mut interaction) = interactions.get_mut(e) {
+                interaction.set_if_neq(Interaction::None);
+            }
+        }
+        Some(PaletteCommand::Key(action)) => {
+            keys.release(bindings.key(action));
+            for modifier in action.modifiers() {
+                keys.release(*modifier);
+            }
+        }
+        None => {}
+    }
+
+    let Some(command) = palette.pending.take() else {
+        return;
+    };
+
+    match command {
+        PaletteCommand::Button(e) => {
+            let Ok(mut interaction) = interactions.get_mut(e) else {
+                warn!("Cannot run the command, its button no longer exists");
+                return;
+            };
+            *interaction = Interaction::Pressed;
+        }
+        PaletteCommand::Key(action) => {
+            for modifier in action.modifiers() {
+                keys.press(*modifier);
+            }
+            keys.press(bindings.key(action));
+        }
+    }
+    *pressed = Some(command);
+}
+
+fn update_palette(
+    palette: Res<CommandPalette>,
+    mut panel: Query<&mut Visibility, With<PalettePanel>>,
+    mut query_text: Query<&mut Text, With<PaletteQueryText>>,
+    mut rows: Query<(&PaletteRow, &mut Style, &mut BackgroundColor, &Children)>,
+    mut texts: Query<&mut Text, Without<PaletteQueryText>>,
+) {
+    if !palette.is_changed() {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if palette.open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !palette.open {
+        return;
+    }
+
+    for mut text in query_text.iter_mut() {
+        text.sections[0].value = format!(
+            "> {}_   ({} matches, Enter to run, Esc to close)",
+            palette.query,
+            palette.matches.len()
+        );
+    }
+
+    for (PaletteRow(row), mut style, mut color, children) in rows.iter_mut() {
+        let Some(command) = palette.matches.get(*row) else {
+            style.display = Display::None;
+            continue;
+        };
+
+        style.display = Display::Flex;
+        *color = if *row == palette.selected {
+            BackgroundColor(Color::rgb(0.3, 0.3, 0.45))
+        } else {
+            BackgroundColor(Color::rgb(0.15, 0.15, 0.15))
+        };
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = palette.commands[*command].0.clone();
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{
+    app::RunFixedUpdateLoop,
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin, RegisterDiagnostic,
+    },
+    prelude::*,
+    time::run_fixed_update_schedule,
+    utils::Instant,
+};
+
+use crate::{
+    keybindings::{Action, KeyBindings},
+    simulate,
+};
+
+// Share of the fixed timestep a tick may take before the overlay turns red
+const BUDGET_WARNING: f64 = 0.8;
+const NORMAL_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const OVER_BUDGET_COLOR: Color = Color::rgb(1., 0.4, 0.4);
+
+// Toggleable overlay (F3) in the top right corner with numbers that are useful when the simulation gets slow
+// A tick is one run of simulate, it is compared to the fixed timestep it has to fit into along with everything else that runs then
+pub struct PerfOverlayPlugin;
+
+impl PerfOverlayPlugin {
+    pub const FIXED_UPDATE_TIME: DiagnosticId =
+        DiagnosticId::from_u128(112794260354918123470953498612394837522);
+    pub const SIMULATE_TIME: DiagnosticId =
+        DiagnosticId::from_u128(261520437726390172856271042930187510661);
+    pub const NET_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(83150939276128741709826540167261452019);
+    // Every pass until the relays settle evaluates all switches again, so this counts each of them once per pass
+    pub const SWITCH_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(208391475226730018733591646281953140377);
+}
+
+#[derive(Component)]
+struct PerfOverlay;
+
+// Start of the currently measured section, one for the whole fixed update loop and one for simulate
+#[derive(Resource, Default)]
+struct PerfTimers {
+    fixed_update: Option<Instant>,
+    simulate: Option<Instant>,
+}
+
+impl Plugin for PerfOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
+            .register_diagnostic(
+                Diagnostic::new(Self::FIXED_UPDATE_TIME, "fixed_update_time", 20).with_suffix("ms"),
+            )
+            .register_diagnostic(
+                Diagnostic::new(Self::SIMULATE_TIME, "simulate_time", 20).with_suffix("ms"),
+            )
+            .register_diagnostic(Diagnostic::new(Self::NET_COUNT, "net_count", 1))
+            .register_diagnostic(Diagnostic::new(Self::SWITCH_COUNT, "switch_count", 1))
+            .init_resource::<PerfTimers>()
+            .add_systems(Startup, setup_perf_overlay)
+            .add_systems(
+                RunFixedUpdateLoop,
+                (
+                    start_fixed_update_timer.before(run_fixed_update_schedule),
+                    stop_fixed_update_timer.after(run_fixed_update_schedule),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    start_simulate_timer.before(simulate),
+                    stop_simulate_timer.after(simulate),
+                ),
+            )
+            .add_systems(Update, (toggle_perf_overlay, update_perf_overlay));
+    }
+}
+
+fn setup_perf_overlay(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: NORMAL_COLOR,
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                right: Val::Px(5.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Performance Overlay"),
+        PerfOverlay,
+    ));
+}
+
+fn toggle_perf_overlay(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut overlay: Query<&mut Visibility, With<PerfOverlay>>,
+) {
+    if !bindings.just_pressed(&keys, Action::TogglePerfOverlay) {
+        return;
+    }
+
+    for mut visibility in overlay.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_perf_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    fixed_time: Res<Time<Fixed>>,
+    mut overlay: Query<(&mut Text, &Visibility), With<PerfOverlay>>,
+) {
+    let value = |id: DiagnosticId| {
+        diagnostics
+            .get(id)
+            .and_then(|diagnostic| diagnostic.smoothed())
+            .unwrap_or(0.)
+    };
+
+    for (mut text, visibility) in overlay.iter_mut() {
+        if visibility == Visibility::Hidden {
+            continue;
+        }
+
+        let budget = fixed_time.timestep().as_secs_f64() * 1000.;
+        let tick = value(PerfOverlayPlugin::SIMULATE_TIME);
+        text.sections[0].value = format!(
+            "FPS: {:.0} ({:.2} ms)\nFixed update: {:.3} ms\nTick: {tick:.3} of {budget:.0} ms ({:.1}%)\nEntities: {:.0}\nNets: {:.0}\nSwitches evaluated: {:.0}",
+            value(FrameTimeDiagnosticsPlugin::FPS),
+            value(FrameTimeDiagnosticsPlugin::FRAME_TIME),
+            value(PerfOverlayPlugin::FIXED_UPDATE_TIME),
+            tick / budget * 100.,
+            value(EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+            value(PerfOverlayPlugin::NET_COUNT),
+            value(PerfOverlayPlugin::SWITCH_COUNT),
+        );
+        text.sections[0].style.color = if tick > budget * BUDGET_WARNING {
+            OVER_BUDGET_COLOR
+        } else {
+            NORMAL_COLOR
+        };
+    }
+}
+
+fn start_fixed_update_timer(mut timers: ResMut<PerfTimers>) {
+    timers.fixed_update = Some(Instant::now());
+}
+
+fn stop_fixed_update_timer(mut timers: ResMut<PerfTimers>, mut diagnostics: Diagnostics) {
+    if let Some(start) = timers.fixed_update.take() {
+        diagnostics.add_measurement(PerfOverlayPlugin::FIXED_UPDATE_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.
+        });
+    }
+}
+
+fn start_simulate_timer(mut timers: ResMut<PerfTimers>) {
+    timers.simulate = Some(Instant::now());
+}
+
+fn stop_simulate_timer(mut timers: ResMut<PerfTimers>, mut diagnostics: Diagnostics) {
+    if let Some(start) = timers.simulate.take() {
+        diagnostics.add_measurement(PerfOverlayPlugin::SIMULATE_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.
+        });
+    }
+}
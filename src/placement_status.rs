@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+
+use crate::{annotations::AnnotationShape, ComponentOrientation, CurrentlyPlacing, SwitchType};
+
+// Height of the strip, the left section leaves this much room below its last row
+pub const STATUS_STRIP_HEIGHT: f32 = 30.;
+
+// A strip at the bottom of the left section says what clicks on the grid currently do
+// Its cancel button goes back to placing wires, just like right clicking
+pub struct PlacementStatusPlugin;
+
+impl Plugin for PlacementStatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_status_strip).add_systems(
+            Update,
+            (handle_cancel_button_press, update_status_strip).chain(),
+        );
+    }
+}
+
+#[derive(Component)]
+struct PlacementStatusText;
+
+#[derive(Component)]
+struct CancelPlacementButton;
+
+fn setup_status_strip(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(0.),
+                bottom: Val::Px(0.),
+                width: Val::Px(280.),
+                height: Val::Px(STATUS_STRIP_HEIGHT),
+                padding: UiRect::horizontal(Val::Px(5.)),
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.05, 0.05, 0.05)),
+            z_index: ZIndex::Global(5),
+            ..Default::default()
+        },
+        Name::new("Placement Status"),
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("Placement Status Text"),
+            PlacementStatusText,
+        ));
+        root.spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::all(Val::Px(3.)),
+                    ..Default::default()
+                },
+                background_color: BackgroundColor(Color::rgb(0.25, 0.25, 0.25)),
+                ..Default::default()
+            },
+            Name::new("Cancel Placement Button"),
+            CancelPlacementButton,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                TextBundle::from_section(
+                    "Cancel",
+                    TextStyle {
+                        font_size: 16.,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                ),
+                Name::new("Cancel Placement Button Text"),
+            ));
+        });
+    });
+}
+
+fn handle_cancel_button_press(
+    interaction: Query<&Interaction, (Changed<Interaction>, With<CancelPlacementButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Wire;
+    }
+}
+
+fn contact_name(typ: SwitchType) -> &'static str {
+    match typ {
+        SwitchType::NormallyOpen => "NO",
+        SwitchType::NormallyClosed => "NC",
+        SwitchType::Changeover => "CO",
+    }
+}
+
+fn describe(placing: &CurrentlyPlacing, orientation: ComponentOrientation) -> String {
+    let turned = match orientation {
+        ComponentOrientation::Vertical => "",
+        ComponentOrientation::Horizontal => ", turned",
+    };
+    match placing {
+        CurrentlyPlacing::Wire => "Placing wires".to_string(),
+        CurrentlyPlacing::Light { label, .. } => format!("Placing {label} lamp{turned}"),
+        CurrentlyPlacing::Button { label, typ, .. } => {
+            format!("Placing {label} {} contact{turned}", contact_name(*typ))
+        }
+        CurrentlyPlacing::RelayCoil { label, timer, .. } => match timer {
+            Some(timer) => format!("Placing {label} coil, {}{turned}", timer.describe()),
+            None => format!("Placing {label} coil{turned}"),
+        },
+        CurrentlyPlacing::RelaySwitch { label, typ, .. } => {
+            format!("Placing {label} {} contact{turned}", contact_name(*typ))
+        }
+        CurrentlyPlacing::Annotation(AnnotationShape::Rectangle) => {
+            "Drawing rectangles".to_string()
+        }
+        CurrentlyPlacing::Annotation(AnnotationShape::Arrow) => "Drawing arrows".to_string(),
+        CurrentlyPlacing::Route => "Routing between terminals".to_string(),
+        CurrentlyPlacing::Measure => "Measuring".to_string(),
+        CurrentlyPlacing::Troubleshoot => "Naming the faulty element".to_string(),
+        CurrentlyPlacing::HiddenRegion => "Placing black boxes".to_string(),
+        CurrentlyPlacing::Macro(_) => "Playing a macro".to_string(),
+        CurrentlyPlacing::Paste => "Pasting the copied selection".to_string(),
+        CurrentlyPlacing::Clock => "Placing clocks".to_string(),
+        CurrentlyPlacing::TimeSwitch => "Placing time switches".to_string(),
+        CurrentlyPlacing::Move => "Moving components".to_string(),
+        CurrentlyPlacing::Delete => "Deleting".to_string(),
+        CurrentlyPlacing::InjectFault => "Injecting faults".to_string(),
+        CurrentlyPlacing::Multimeter => "Measuring with the multimeter".to_string(),
+        CurrentlyPlacing::Fuse => "Placing fuses".to_string(),
+        CurrentlyPlacing::Diode => "Placing diodes".to_string(),
+        CurrentlyPlacing::Supply => "Placing supplies".to_string(),
+        CurrentlyPlacing::NetLabel => "Placing net labels".to_string(),
+        CurrentlyPlacing::Block => "Placing blocks".to_string(),
+        CurrentlyPlacing::Template(_) => "Stamping a template".to_string(),
+    }
+}
+
+fn update_status_strip(
+    currently_placing: Res<CurrentlyPlacing>,
+    orientation: Res<ComponentOrientation>,
+    mut texts: Query<&mut Text, With<PlacementStatusText>>,
+    mut cancel_buttons: Query<&mut Style, With<CancelPlacementButton>>,
+) {
+    if !currently_placing.is_changed() && !orientation.is_changed() {
+        return;
+    }
+
+    let value = describe(&currently_placing, *orientation);
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+    // Wires are what clicks place anyway, there is nothing to cancel
+    let display = if matches!(*currently_placing, CurrentlyPlacing::Wire) {
+        Display::None
+    } else {
+        Display::Flex
+    };
+    for mut style in cancel_buttons.iter_mut() {
+        style.display = display;
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+// Files and the clock, on the desktop and in the browser
+// The browser has no file system, settings the app reads back are kept in the local storage of the page,
+// files that are saved or exported are downloaded and opening a file asks for one to upload
+// Uploading finishes some frames later, so opened files are picked up with take_opened_file instead of returned right away
+// Text is copied to and pasted from the system clipboard, in the browser it is shown to copy and asked for to paste
+
+// Files that were opened and are waiting for whoever asked for them
+static OPENED_FILES: Mutex<Vec<(PathBuf, Result<String, String>)>> = Mutex::new(Vec::new());
+
+pub fn take_opened_file(path: &Path) -> Option<Result<String, String>> {
+    let mut opened = OPENED_FILES.lock().ok()?;
+    let index = opened.iter().position(|(opened, _)| opened == path)?;
+    Some(opened.remove(index).1)
+}
+
+fn push_opened_file(path: PathBuf, result: Result<String, String>) {
+    if let Ok(mut opened) = OPENED_FILES.lock() {
+        opened.push((path, result));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        fs,
+        path::Path,
+        sync::Mutex,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    pub fn file_exists(path: &Path) -> bool {
+        path.exists()
+    }
+
+    pub fn read_file(path: &Path) -> Result<String, String> {
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+
+    pub fn write_file(path: &Path, text: &str) -> Result<(), String> {
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+
+    pub fn export_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+
+    pub fn open_file(path: &Path) {
+        super::push_opened_file(path.to_path_buf(), read_file(path));
+    }
+
+    // Kept for the whole session like the one for images, on linux the copied text is gone as soon as the clipboard is dropped
+    static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+    fn with_clipboard<T>(
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, String> {
+        let mut clipboard = CLIPBOARD.lock().map_err(|e| e.to_string())?;
+        let clipboard = match &mut *clipboard {
+            Some(clipboard) => clipboard,
+            None => clipboard.insert(arboard::Clipboard::new().map_err(|e| e.to_string())?),
+        };
+        f(clipboard).map_err(|e| e.to_string())
+    }
+
+    pub fn copy_text(text: &str) -> Result<(), String> {
+        with_clipboard(|clipboard| clipboard.set_text(text))
+    }
+
+    pub fn paste_text() -> Result<Option<String>, String> {
+        with_clipboard(|clipboard| clipboard.get_text()).map(Some)
+    }
+
+    // Only pages have links, there is nothing to put into one on the desktop
+    pub fn share_link(_fragment: &str) -> Option<String> {
+        None
+    }
+
+    pub fn link_fragment() -> Option<String> {
+        None
+    }
+
+    pub fn since_epoch() -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::{path::Path, time::Duration};
+
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::{Blob, FileReader, HtmlAnchorElement, HtmlInputElement, Storage, Url};
+
+    fn js_error(e: impl Into<JsValue>) -> String {
+        let e = e.into();
+        e.as_string().unwrap_or_else(|| format!("{e:?}"))
+    }
+
+    fn window() -> Result<web_sys::Window, String> {
+        web_sys::window().ok_or_else(|| "there is no browser window".to_string())
+    }
+
+    fn document() -> Result<web_sys::Document, String> {
+        window()?
+            .document()
+            .ok_or_else(|| "the page has no document".to_string())
+    }
+
+    fn storage() -> Result<Storage, String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| "the browser has no local storage".to_string())
+    }
+
+    fn key(path: &Path) -> String {
+        format!("relay-sim/{}", path.display())
+    }
+
+    fn file_name(path: &Path) -> String {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    }
+
+    pub fn file_exists(path: &Path) -> bool {
+        storage()
+            .ok()
+            .and_then(|storage| storage.get_item(&key(path)).ok().flatten())
+            .is_some()
+    }
+
+    pub fn read_file(path: &Path) -> Result<String, String> {
+        storage()?
+            .get_item(&key(path))
+            .map_err(js_error)?
+            .ok_or_else(|| format!("{} is not stored in this browser", path.display()))
+    }
+
+    pub fn write_file(path: &Path, text: &str) -> Result<(), String> {
+        storage()?.set_item(&key(path), text).map_err(js_error)
+    }
+
+    // Goes through a link to the file that is clicked right away, the browser then downloads it
+    pub fn export_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+        let blob = Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+        let url = Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+        let anchor = document()?
+            .create_element("a")
+            .map_err(js_error)?
+            .dyn_into::<HtmlAnchorElement>()
+            .map_err(js_error)?;
+        anchor.set_href(&url);
+        anchor.set_download(&file_name(path));
+        anchor.click();
+
+        Url::revoke_object_url(&url).map_err(js_error)
+    }
+
+    // A file input that is never added to the page, clicking it still brings up the file picker
+    pub fn open_file(path: &Path) {
+        if let Err(e) = pick_file(path) {
+            super::push_opened_file(path.to_path_buf(), Err(e));
+        }
+    }
+
+    fn pick_file(path: &Path) -> Result<(), String> {
+        let input = document()?
+            .create_element("input")
+            .map_err(js_error)?
+            .dyn_into::<HtmlInputElement>()
+            .map_err(js_error)?;
+        input.set_type("file");
+        input.set_accept(&format!(
+            ".{}",
+            path.extension().unwrap_or_default().to_string_lossy()
+        ));
+
+        let path = path.to_path_buf();
+        let picker = input.clone();
+        let on_change = Closure::once_into_js(move || {
+            let Some(file) = picker.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let reader = match FileReader::new() {
+                Ok(reader) => reader,
+                Err(e) => {
+                    super::push_opened_file(path, Err(js_error(e)));
+                    return;
+                }
+            };
+
+            let result_reader = reader.clone();
+            let result_path = path.clone();
+            let on_load = Closure::once_into_js(move || {
+                let result = result_reader.result().map_err(js_error).and_then(|text| {
+                    text.as_string()
+                        .ok_or_else(|| "the file is not text".to_string())
+                });
+                super::push_opened_file(result_path, result);
+            });
+            reader.set_onload(Some(on_load.unchecked_ref()));
+            if let Err(e) = reader.read_as_text(&file) {
+                super::push_opened_file(path, Err(js_error(e)));
+            }
+        });
+        input.set_onchange(Some(on_change.unchecked_ref()));
+        input.click();
+        Ok(())
+    }
+
+    // Pages cannot write to the clipboard without an await, the text is shown selected in a prompt to copy instead
+    pub fn copy_text(text: &str) -> Result<(), String> {
+        window()?
+            .prompt_with_message_and_default("Copy this with Ctrl+C", text)
+            .map(|_| ())
+            .map_err(js_error)
+    }
+
+    // None when the prompt was cancelled
+    pub fn paste_text() -> Result<Option<String>, String> {
+        window()?
+            .prompt_with_message("Paste a shared circuit")
+            .map_err(js_error)
+    }
+
+    // Puts the fragment behind the address of the page, so the address can be shared as it is
+    pub fn share_link(fragment: &str) -> Option<String> {
+        let location = window().ok()?.location();
+        location.set_hash(fragment).ok()?;
+        location.href().ok()
+    }
+
+    pub fn link_fragment() -> Option<String> {
+        let hash = window().ok()?.location().hash().ok()?;
+        let fragment = hash.trim_start_matches('#');
+        (!fragment.is_empty()).then(|| fragment.to_string())
+    }
+
+    pub fn since_epoch() -> Duration {
+        Duration::from_secs_f64(js_sys::Date::now() / 1000.)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+// Seconds since 1970, for file names and dates
+pub fn timestamp() -> u64 {
+    since_epoch().as_secs()
+}
+
+// Images are encoded first, so they are written or downloaded like every other file
+pub fn export_png(path: &Path, image: image::DynamicImage) -> Result<(), String> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    export_file(path, png.get_ref())
+}
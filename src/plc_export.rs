@@ -0,0 +1,479 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use bevy::prelude::*;
+
+use crate::{
+    component_middle,
+    platform::{self, timestamp},
+    print::today,
+    save::SavePath,
+    sheets::{SheetParts, Sheets},
+    spawn_toolbar_button,
+    spice_export::{Nodes, ShownSheet},
+    GridPosition, PowerType, SwitchType, Toolbar,
+};
+
+// Every way from the supply through a lamp or coil is a branch of its rung, beyond this many the rest is left out
+const MAX_PATHS: usize = 64;
+// Layout of the ladder in the editor of the PLC software
+const CONTACT_WIDTH: usize = 21;
+const ELEMENT_HEIGHT: usize = 20;
+const ELEMENT_SPACING: usize = 50;
+const BRANCH_HEIGHT: usize = 40;
+const RUNG_SPACING: usize = 20;
+const RAIL_X: usize = 20;
+
+// The PLC button writes the logic of the circuit as a ladder diagram in PLCopen XML next to the save file, circuit.xml for circuit.ron
+// Each lamp and relay coil becomes a rung, with a branch of contacts in series for every way the supply reaches it through the contacts
+// Buttons and time switches are inputs, lamps are outputs and relays are internal variables, so self-holding circuits stay self-holding
+// Consumers in series have no way of their own through the contacts and get no rung, timer relays lose their delay, both with a warning
+pub struct PlcExportPlugin;
+
+impl Plugin for PlcExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_plc_button)
+            .add_systems(Update, export_plc);
+    }
+}
+
+#[derive(Component)]
+struct PlcButton;
+
+fn setup_plc_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "PLC", "Export PLCopen", PlcButton);
+    });
+}
+
+// A contact in a branch, S1 is closed while -S1 is pressed and negated it is closed while -S1 is not
+#[derive(Clone, PartialEq)]
+pub struct Literal {
+    pub name: String,
+    pub negated: bool,
+}
+
+// Contacts in series, all of them have to be closed
+pub type Branch = Vec<Literal>;
+
+// A way between two nets, contacts conduct both ways and diodes only from anode to cathode
+struct Edge {
+    from: String,
+    to: String,
+    literal: Option<Literal>,
+    both_ways: bool,
+}
+
+struct Graph {
+    edges: Vec<Edge>,
+    positive: Vec<String>,
+}
+
+impl Graph {
+    fn new(parts: &[&SheetParts], nodes: &mut Nodes) -> Self {
+        let mut edges = Vec::new();
+        let mut contact = |nodes: &mut Nodes,
+                           (top, bottom): (GridPosition, GridPosition),
+                           typ: SwitchType,
+                           name: String| match typ {
+            SwitchType::NormallyOpen | SwitchType::NormallyClosed => edges.push(Edge {
+                from: nodes.node(top),
+                to: nodes.node(bottom),
+                literal: Some(Literal {
+                    name,
+                    negated: typ == SwitchType::NormallyClosed,
+                }),
+                both_ways: true,
+            }),
+            SwitchType::Changeover => {
+                let common = nodes.node(component_middle(top, bottom));
+                for (to, negated) in [(top, false), (bottom, true)] {
+                    edges.push(Edge {
+                        from: common.clone(),
+                        to: nodes.node(to),
+                        literal: Some(Literal {
+                            name: name.clone(),
+                            negated,
+                        }),
+                        both_ways: true,
+                    });
+                }
+            }
+        };
+
+        for parts in parts {
+            for button in parts.buttons.iter() {
+                let name = format!("S{}", button.id);
+                contact(nodes, (button.top, button.bottom), button.typ, name);
+            }
+            for relay_switch in parts.relay_switches.iter() {
+                let name = format!("K{}", relay_switch.id);
+                contact(
+                    nodes,
+                    (relay_switch.top, relay_switch.bottom),
+                    relay_switch.typ,
+                    name,
+                );
+            }
+        }
+        // Time switches have no ids, they are numbered in the order they were placed
+        let time_switches = parts.iter().flat_map(|parts| &parts.time_switches);
+        for (index, time_switch) in time_switches.enumerate() {
+            let name = format!("T{}", index + 1);
+            contact(
+                nodes,
+                (time_switch.top, time_switch.bottom),
+                SwitchType::NormallyOpen,
+                name,
+            );
+        }
+        for diode in parts.iter().flat_map(|parts| &parts.diodes) {
+            edges.push(Edge {
+                from: nodes.node(diode.anode),
+                to: nodes.node(diode.cathode),
+                literal: None,
+                both_ways: false,
+            });
+        }
+
+        let mut positive = parts
+            .iter()
+            .flat_map(|parts| &parts.sources)
+            .filter(|(_, typ)| *typ == PowerType::Positive)
+            .map(|(pos, _)| nodes.node(*pos))
+            .collect::<Vec<_>>();
+        positive.sort();
+        positive.dedup();
+        Self { edges, positive }
+    }
+
+    // Every way from one of the starts to the end without going through a net twice, in the direction current flows
+    fn branches(&self, starts: &[String], end: &str) -> Vec<Branch> {
+        let mut branches = Vec::new();
+        for start in starts {
+            let mut visited = vec![start.clone()];
+            self.walk(start, end, &mut visited, &mut Vec::new(), &mut branches);
+        }
+        branches
+    }
+
+    fn walk(
+        &self,
+        net: &str,
+        end: &str,
+        visited: &mut Vec<String>,
+        branch: &mut Branch,
+        branches: &mut Vec<Branch>,
+    ) {
+        if branches.len() >= MAX_PATHS {
+            return;
+        }
+        if net == end {
+            branches.push(branch.clone());
+            return;
+        }
+
+        for edge in self.edges.iter() {
+            let next = if edge.from == net {
+                &edge.to
+            } else if edge.both_ways && edge.to == net {
+                &edge.from
+            } else {
+                continue;
+            };
+            if visited.contains(next) {
+                continue;
+            }
+            visited.push(next.clone());
+            if let Some(literal) = &edge.literal {
+                branch.push(literal.clone());
+            }
+            self.walk(next, end, visited, branch, branches);
+            if edge.literal.is_some() {
+                branch.pop();
+            }
+            visited.pop();
+        }
+    }
+}
+
+// Every branch of the first followed by every branch of the second, without the ones that can never close
+// Contacts stay in the order the current goes through them, one that is passed twice only shows up once
+fn series(first: &[Branch], second: &[Branch]) -> Vec<Branch> {
+    let combined = first
+        .iter()
+        .flat_map(|a| second.iter().map(move |b| a.iter().chain(b)))
+        .map(|literals| {
+            let mut branch = Branch::new();
+            for literal in literals {
+                if !branch.contains(literal) {
+                    branch.push(literal.clone());
+                }
+            }
+            branch
+        })
+        .filter(|branch| {
+            !branch.iter().any(|literal| {
+                branch
+                    .iter()
+                    .any(|other| other.name == literal.name && other.negated != literal.negated)
+            })
+        })
+        .collect();
+    simplify(combined)
+}
+
+// Shorter branches first, a branch with all the contacts of a shorter one in it is left out, like a second copy of one
+fn simplify(mut branches: Vec<Branch>) -> Vec<Branch> {
+    branches.sort_by_key(|branch| branch.len());
+    let mut kept: Vec<Branch> = Vec::new();
+    for branch in branches {
+        if !kept
+            .iter()
+            .any(|other| other.iter().all(|literal| branch.contains(literal)))
+        {
+            kept.push(branch);
+        }
+    }
+    kept
+}
+
+// The branches of every lamp and coil, by the name of its variable, also shown by the expression panel
+pub fn rungs(parts: &[&SheetParts]) -> BTreeMap<String, Vec<Branch>> {
+    let mut nodes = Nodes::new(parts);
+    let graph = Graph::new(parts, &mut nodes);
+
+    let consumers = parts
+        .iter()
+        .flat_map(|parts| {
+            let relay_coils = parts.relay_coils.iter().map(|relay_coil| {
+                (
+                    format!("K{}", relay_coil.id),
+                    relay_coil.top,
+                    relay_coil.bottom,
+                )
+            });
+            let lights = parts
+                .lights
+                .iter()
+                .map(|light| (format!("P{}", light.id), light.top, light.bottom));
+            relay_coils.chain(lights)
+        })
+        .collect::<Vec<_>>();
+
+    let mut rungs = BTreeMap::<String, Vec<Branch>>::new();
+    for (name, top, bottom) in consumers {
+        let (top, bottom) = (nodes.node(top), nodes.node(bottom));
+        // Lamps and coils work either way around
+        let branches = [(&top, &bottom), (&bottom, &top)]
+            .into_iter()
+            .flat_map(|(from, to)| {
+                // Nodes names the negative side of the supply 0, like SPICE does
+                let to_negative = graph.branches(std::slice::from_ref(to), "0");
+                series(&graph.branches(&graph.positive, from), &to_negative)
+            });
+        let all = rungs.entry(name).or_default();
+        all.extend(branches);
+        *all = simplify(std::mem::take(all));
+    }
+    rungs
+}
+
+fn variables<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    names
+        .map(|name| {
+            format!("            <variable name=\"{name}\"><type><BOOL/></type></variable>\n")
+        })
+        .collect()
+}
+
+// The program as PLCopen TC6 XML, one rung below the other with a power rail on each side
+fn plcopen_xml(title: &str, rungs: &BTreeMap<String, Vec<Branch>>) -> String {
+    let mut inputs = rungs
+        .values()
+        .flatten()
+        .flatten()
+        .map(|literal| literal.name.clone())
+        .filter(|name| !name.starts_with('K'))
+        .collect::<Vec<_>>();
+    inputs.sort();
+    inputs.dedup();
+    let mut relays = rungs
+        .values()
+        .flatten()
+        .flatten()
+        .map(|literal| literal.name.clone())
+        .filter(|name| name.starts_with('K'))
+        .chain(rungs.keys().filter(|name| name.starts_with('K')).cloned())
+        .collect::<Vec<_>>();
+    relays.sort();
+    relays.dedup();
+    let outputs = rungs.keys().filter(|name| name.starts_with('P'));
+
+    let mut body = String::new();
+    let mut local_id = 0;
+    let mut next_id = || {
+        local_id += 1;
+        local_id
+    };
+    let mut y = RUNG_SPACING;
+    for (name, branches) in rungs.iter().filter(|(_, branches)| !branches.is_empty()) {
+        let longest = branches.iter().map(Vec::len).max().unwrap_or(0);
+        let height = branches.len() * BRANCH_HEIGHT;
+        let rail = next_id();
+        let _ = writeln!(
+            body,
+            "            <leftPowerRail localId=\"{rail}\" height=\"{height}\" width=\"2\"><position x=\"{RAIL_X}\" y=\"{y}\"/><connectionPointOut formalParameter=\"\"><relPosition x=\"2\" y=\"{}\"/></connectionPointOut></leftPowerRail>",
+            ELEMENT_HEIGHT / 2
+        );
+
+        // The last element of every branch, they all lead into the coil
+        let mut ends = Vec::new();
+        for (index, branch) in branches.iter().enumerate() {
+            let branch_y = y + index * BRANCH_HEIGHT;
+            let mut previous = rail;
+            for (column, literal) in branch.iter().enumerate() {
+                let id = next_id();
+                let _ = writeln!(
+                    body,
+                    "            <contact localId=\"{id}\" height=\"{ELEMENT_HEIGHT}\" width=\"{CONTACT_WIDTH}\" negated=\"{}\"><position x=\"{}\" y=\"{branch_y}\"/><connectionPointIn><relPosition x=\"0\" y=\"{}\"/><connection refLocalId=\"{previous}\"/></connectionPointIn><connectionPointOut><relPosition x=\"{CONTACT_WIDTH}\" y=\"{}\"/></connectionPointOut><variable>{}</variable></contact>",
+                    literal.negated,
+                    RAIL_X + (column + 1) * ELEMENT_SPACING,
+                    ELEMENT_HEIGHT / 2,
+                    ELEMENT_HEIGHT / 2,
+                    literal.name
+                );
+                previous = id;
+            }
+            ends.push(previous);
+        }
+
+        let coil = next_id();
+        let coil_x = RAIL_X + (longest + 1) * ELEMENT_SPACING;
+        let connections = ends
+            .iter()
+            .map(|end| format!("<connection refLocalId=\"{end}\"/>"))
+            .collect::<String>();
+        let _ = writeln!(
+            body,
+            "            <coil localId=\"{coil}\" height=\"{ELEMENT_HEIGHT}\" width=\"{CONTACT_WIDTH}\" negated=\"false\"><position x=\"{coil_x}\" y=\"{y}\"/><connectionPointIn><relPosition x=\"0\" y=\"{}\"/>{connections}</connectionPointIn><connectionPointOut><relPosition x=\"{CONTACT_WIDTH}\" y=\"{}\"/></connectionPointOut><variable>{name}</variable></coil>",
+            ELEMENT_HEIGHT / 2,
+            ELEMENT_HEIGHT / 2
+        );
+        let right_rail = next_id();
+        let _ = writeln!(
+            body,
+            "            <rightPowerRail localId=\"{right_rail}\" height=\"{height}\" width=\"2\"><position x=\"{}\" y=\"{y}\"/><connectionPointIn><relPosition x=\"0\" y=\"{}\"/><connection refLocalId=\"{coil}\"/></connectionPointIn></rightPowerRail>",
+            coil_x + ELEMENT_SPACING,
+            ELEMENT_HEIGHT / 2
+        );
+        y += height + RUNG_SPACING;
+    }
+
+    let seconds = timestamp() % 86400;
+    let created = format!(
+        "{}T{:02}:{:02}:{:02}",
+        today(),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<project xmlns="http://www.plcopen.org/xml/tc6_0201">
+  <fileHeader companyName="" productName="relay-sim" productVersion="{}" creationDateTime="{created}"/>
+  <contentHeader name="{title}">
+    <coordinateInfo>
+      <fbd><scaling x="1" y="1"/></fbd>
+      <ld><scaling x="1" y="1"/></ld>
+      <sfc><scaling x="1" y="1"/></sfc>
+    </coordinateInfo>
+  </contentHeader>
+  <types>
+    <dataTypes/>
+    <pous>
+      <pou name="{title}" pouType="program">
+        <interface>
+          <inputVars>
+{}          </inputVars>
+          <outputVars>
+{}          </outputVars>
+          <localVars>
+{}          </localVars>
+        </interface>
+        <body>
+          <LD>
+{body}          </LD>
+        </body>
+      </pou>
+    </pous>
+  </types>
+  <instances>
+    <configurations>
+      <configuration name="config">
+        <resource name="resource">
+          <task name="task" priority="0" interval="T#50ms">
+            <pouInstance name="instance" typeName="{title}"/>
+          </task>
+        </resource>
+      </configuration>
+    </configurations>
+  </instances>
+</project>
+"#,
+        env!("CARGO_PKG_VERSION"),
+        variables(inputs.iter()),
+        variables(outputs),
+        variables(relays.iter()),
+    )
+}
+
+fn export_plc(
+    interaction: Query<&Interaction, (Changed<Interaction>, With<PlcButton>)>,
+    save_path: Res<SavePath>,
+    shown: ShownSheet,
+    sheets: Res<Sheets>,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let (shown_parts, block_parts) = (shown.parts(), shown.block_parts());
+    let parts = [&shown_parts, &block_parts]
+        .into_iter()
+        .chain(sheets.others.iter())
+        .collect::<Vec<_>>();
+    let timer_relays = parts.iter().flat_map(|parts| &parts.relay_coils);
+    for relay_coil in timer_relays {
+        if let Some(timer) = &relay_coil.timer {
+            warn!(
+                "-K{} is a {} timer relay, the ladder leaves out its delay",
+                relay_coil.id,
+                timer.describe()
+            );
+        }
+    }
+    let rungs = rungs(&parts);
+    for (name, _) in rungs.iter().filter(|(_, branches)| branches.is_empty()) {
+        warn!("-{name} can never be switched on and gets no rung");
+    }
+
+    // IEC names start with a letter and only have letters, digits and underscores
+    let path = save_path.0.with_extension("xml");
+    let title = path
+        .file_stem()
+        .map(|stem| {
+            stem.to_string_lossy()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+        })
+        .filter(|title| title.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .unwrap_or_else(|| "circuit".to_string());
+    match platform::export_file(&path, plcopen_xml(&title, &rungs).as_bytes()) {
+        Ok(_) => info!("Exported the ladder to {}", path.display()),
+        Err(e) => error!("Cannot export the ladder to {}: {e}", path.display()),
+    }
+}
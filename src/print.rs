@@ -0,0 +1,314 @@
+use std::{fmt::Write, path::Path};
+
+use bevy::prelude::*;
+
+use crate::{
+    grid::GridSize,
+    metadata::CircuitMetadata,
+    platform::{self, timestamp},
+    save::SavePath,
+    spawn_toolbar_button,
+    svg_export::{escape, SchematicParts, CELL},
+    GridPosition, PlacedPositions, Toolbar,
+};
+
+// Every grid cell is printed at the same size, no matter how big the circuit is
+// The schematic is drawn with 2 pixel lines at 20 pixels per cell, which makes its lines 0.5 mm and its text 3.5 mm high
+const CELL_MM: f32 = 5.;
+const MARGIN_MM: f32 = 10.;
+// The frame and the border of the title block are one step heavier than the schematic
+const FRAME_LINE_MM: f32 = 0.7;
+const TITLE_BLOCK_SIZE: Vec2 = Vec2::new(65., 24.);
+// Longer descriptions are cut off, the title block only has room for one line
+const TITLE_BLOCK_DESCRIPTION_LENGTH: usize = 40;
+
+// The print button draws the schematic black on white onto A4 or Letter pages with a title block, big circuits are split over several pages
+// Pages are svg files sized in millimeters, drawn from the placed elements like the SVG export, so every printer gets the same line weights
+pub struct PrintPlugin;
+
+impl Plugin for PrintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrintJob>()
+            .add_systems(PostStartup, setup_print_buttons)
+            .add_systems(
+                Update,
+                (handle_print_buttons, print_pages, update_page_size_button).chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum PageSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    fn name(self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4",
+            PageSize::Letter => "Letter",
+        }
+    }
+
+    // Landscape, in millimeters
+    fn size(self) -> Vec2 {
+        match self {
+            PageSize::A4 => Vec2::new(297., 210.),
+            PageSize::Letter => Vec2::new(279.4, 215.9),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct PrintJob {
+    page_size: PageSize,
+    requested: bool,
+}
+
+#[derive(Component)]
+struct PrintButton;
+
+#[derive(Component)]
+struct PageSizeButton;
+
+fn setup_print_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Print", "Print", PrintButton);
+        spawn_toolbar_button(root, PageSize::A4.name(), "Page Size", PageSizeButton);
+    });
+}
+
+fn handle_print_buttons(
+    mut job: ResMut<PrintJob>,
+    print_button: Query<&Interaction, (Changed<Interaction>, With<PrintButton>)>,
+    page_size_button: Query<&Interaction, (Changed<Interaction>, With<PageSizeButton>)>,
+) {
+    if page_size_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        job.page_size = match job.page_size {
+            PageSize::A4 => PageSize::Letter,
+            PageSize::Letter => PageSize::A4,
+        };
+    }
+
+    if print_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        job.requested = true;
+    }
+}
+
+fn update_page_size_button(
+    job: Res<PrintJob>,
+    button: Query<&Children, With<PageSizeButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !job.is_changed() {
+        return;
+    }
+
+    for children in button.iter() {
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = job.page_size.name().to_string();
+        }
+    }
+}
+
+// Grid cells that contain anything placed, with one cell of room around them
+fn circuit_bounds(
+    positions: impl Iterator<Item = GridPosition>,
+    grid_size: &GridSize,
+) -> Option<URect> {
+    let mut bounds: Option<URect> = None;
+    for pos in positions {
+        let point = UVec2::new(pos.x as u32, pos.y as u32);
+        bounds = Some(match bounds {
+            Some(bounds) => bounds.union_point(point),
+            None => URect::from_corners(point, point),
+        });
+    }
+
+    bounds.map(|bounds| {
+        URect::new(
+            bounds.min.x.saturating_sub(1),
+            bounds.min.y.saturating_sub(1),
+            (bounds.max.x + 1).min(grid_size.width as u32 - 1),
+            (bounds.max.y + 1).min(grid_size.height as u32 - 1),
+        )
+    })
+}
+
+fn print_pages(
+    mut job: ResMut<PrintJob>,
+    save_path: Res<SavePath>,
+    metadata: Res<CircuitMetadata>,
+    placed: PlacedPositions,
+    schematic: SchematicParts,
+    grid_size: Res<GridSize>,
+) {
+    if !job.requested {
+        return;
+    }
+    job.requested = false;
+
+    let (Some(bounds), Some(drawing)) = (
+        circuit_bounds(placed.iter(), &grid_size),
+        schematic.drawing(),
+    ) else {
+        warn!("There is nothing to print");
+        return;
+    };
+
+    // How many grid cells fit on a page next to the margins and the title block
+    let page_size = job.page_size;
+    let page = page_size.size();
+    let cells_per_page = UVec2::new(
+        ((page.x - 2. * MARGIN_MM) / CELL_MM) as u32,
+        ((page.y - 3. * MARGIN_MM - TITLE_BLOCK_SIZE.y) / CELL_MM) as u32,
+    );
+
+    // Pages go top to bottom, then left to right
+    let size = bounds.size() + UVec2::ONE;
+    let page_count = (size + cells_per_page - UVec2::ONE) / cells_per_page;
+    let mut sheets = Vec::new();
+    for column in 0..page_count.x {
+        for row in 0..page_count.y {
+            let min_x = bounds.min.x + column * cells_per_page.x;
+            let max_y = bounds.max.y - row * cells_per_page.y;
+            let max_x = (min_x + cells_per_page.x - 1).min(bounds.max.x);
+            let min_y = max_y.saturating_sub(cells_per_page.y - 1).max(bounds.min.y);
+            sheets.push((UVec2::new(min_x, min_y), UVec2::new(max_x, max_y)));
+        }
+    }
+
+    // Whatever the metadata leaves empty is filled in from the file name, the user and the current date
+    let project = if metadata.title.is_empty() {
+        save_path
+            .0
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    } else {
+        metadata.title.clone()
+    };
+    let author = if metadata.author.is_empty() {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string())
+    } else {
+        metadata.author.clone()
+    };
+    let date = if metadata.date.is_empty() {
+        today()
+    } else {
+        metadata.date.clone()
+    };
+    let description = if metadata.description.chars().count() > TITLE_BLOCK_DESCRIPTION_LENGTH {
+        let cut = metadata
+            .description
+            .chars()
+            .take(TITLE_BLOCK_DESCRIPTION_LENGTH - 3)
+            .collect::<String>();
+        format!("{cut}...")
+    } else {
+        metadata.description.clone()
+    };
+
+    let path = format!("print_{}", timestamp());
+    for (index, (min, max)) in sheets.iter().enumerate() {
+        // Each cell is shown around its grid point, the part of the drawing outside of them is cut off by the inner svg
+        let top_left = drawing.point(GridPosition {
+            x: min.x as usize,
+            y: max.y as usize,
+        }) - Vec2::splat(CELL / 2.);
+        let cells = (*max - *min + UVec2::ONE).as_vec2();
+        let view = cells * CELL;
+        let area = cells * CELL_MM;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}mm\" height=\"{1}mm\" viewBox=\"0 0 {0} {1}\">\n  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>",
+            page.x, page.y
+        );
+        let _ = writeln!(
+            svg,
+            "  <svg x=\"{MARGIN_MM}\" y=\"{MARGIN_MM}\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n{}  </svg>",
+            area.x,
+            area.y,
+            top_left.x,
+            top_left.y,
+            view.x,
+            view.y,
+            drawing.body()
+        );
+        let _ = writeln!(
+            svg,
+            "  <rect x=\"{MARGIN_MM}\" y=\"{MARGIN_MM}\" width=\"{}\" height=\"{}\" stroke=\"black\" stroke-width=\"{FRAME_LINE_MM}\" fill=\"none\"/>",
+            area.x, area.y
+        );
+
+        let corner = page - Vec2::splat(MARGIN_MM) - TITLE_BLOCK_SIZE;
+        let _ = writeln!(
+            svg,
+            "  <g transform=\"translate({} {})\" font-family=\"sans-serif\">\n    <rect width=\"{}\" height=\"{}\" stroke=\"black\" stroke-width=\"{FRAME_LINE_MM}\" fill=\"white\"/>",
+            corner.x, corner.y, TITLE_BLOCK_SIZE.x, TITLE_BLOCK_SIZE.y
+        );
+        for (text, font_size, baseline) in [
+            (project.clone(), 5., 7.),
+            (format!("Author: {author}   Date: {date}"), 3.5, 12.5),
+            (
+                format!(
+                    "Sheet {} of {}   {}",
+                    index + 1,
+                    sheets.len(),
+                    page_size.name()
+                ),
+                3.5,
+                17.5,
+            ),
+            (description.clone(), 2.5, 22.),
+        ] {
+            let _ = writeln!(
+                svg,
+                "    <text x=\"2\" y=\"{baseline}\" font-size=\"{font_size}\">{}</text>",
+                escape(&text)
+            );
+        }
+        svg.push_str("  </g>\n</svg>\n");
+
+        let path = format!("{path}_{}.svg", index + 1);
+        match platform::export_file(Path::new(&path), svg.as_bytes()) {
+            Ok(_) => info!("Printed page saved to {path}"),
+            Err(e) => error!("Cannot save printed page: {e}"),
+        }
+    }
+}
+
+// The current date as yyyy-mm-dd, in UTC
+pub fn today() -> String {
+    let days = (timestamp() / 86400) as i64;
+
+    // Days since 1970-01-01 to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
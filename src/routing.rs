@@ -0,0 +1,281 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    component_terminals, convert_mouse_to_grid,
+    diode::Diode,
+    fuse::Fuse,
+    grid::GridSize,
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    spawn_toolbar_button, spawn_wire,
+    time_switch::TimeSwitch,
+    ButtonSwitch, CircuitHandles, CurrentlyPlacing, GridOrigin, GridPosition, Light, MainCamera,
+    Power, RelayCoil, RelaySwitch, Toolbar, Wire,
+};
+
+// Every turn costs as much as this many straight cells, so routes prefer few long segments
+const TURN_COST: usize = 5;
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+// In route mode (R or the route button) clicking two terminals places an orthogonal wire path between them around everything already placed
+pub struct RoutingPlugin;
+
+impl Plugin for RoutingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_route_button)
+            .add_systems(Update, (start_routing, handle_route_placement).chain());
+    }
+}
+
+#[derive(Component)]
+struct RouteButton;
+
+fn setup_route_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Route", "Auto Route", RouteButton);
+    });
+}
+
+fn start_routing(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    route_button: Query<&Interaction, (Changed<Interaction>, With<RouteButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if (bindings.just_pressed(&keys, Action::AutoRoute)
+        && matches!(*currently_placing, CurrentlyPlacing::Wire))
+        || route_button
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Route;
+    }
+}
+
+fn handle_route_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut route_start: Local<Option<GridPosition>>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+    obstacles: Query<AnyOf<(&Wire, &Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>>,
+    power_sources: Query<&GridPosition, With<Power>>,
+    parts: Query<AnyOf<(&Fuse, &Diode, &TimeSwitch)>>,
+    mut gizmos: Gizmos,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Route) {
+        *route_start = None;
+        return;
+    }
+
+    if let Some(start) = *route_start {
+        gizmos.circle_2d(grid_to_world(start), 8., Color::rgb(0.6, 0.8, 1.));
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        if route_start.take().is_none() {
+            *currently_placing = CurrentlyPlacing::Wire;
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    let Some(start) = route_start.take() else {
+        *route_start = Some(mouse_grid);
+        return;
+    };
+
+    // Cells covered by wires and components, the two picked terminals are always allowed
+    let mut blocked = HashSet::new();
+    for (wire, light, button, relay_coil, relay_switch) in obstacles.iter() {
+        if let Some(wire) = wire {
+            block_wire(&mut blocked, wire);
+        }
+        if let Some((top, bottom)) = component_terminals((light, button, relay_coil, relay_switch))
+        {
+            block_component(&mut blocked, top, bottom);
+        }
+    }
+    for pos in power_sources.iter() {
+        block_cell(&mut blocked, *pos);
+    }
+    for (fuse, diode, time_switch) in parts.iter() {
+        let terminals = fuse
+            .map(|fuse| (fuse.top, fuse.bottom))
+            .or(diode.map(|diode| (diode.anode, diode.cathode)))
+            .or(time_switch.map(|time_switch| (time_switch.top, time_switch.bottom)));
+        if let Some((top, bottom)) = terminals {
+            block_component(&mut blocked, top, bottom);
+        }
+    }
+
+    let Some(path) = find_route(start, mouse_grid, &blocked, &grid_size) else {
+        warn!(
+            "No free route from {}, {} to {}, {}",
+            start.x, start.y, mouse_grid.x, mouse_grid.y
+        );
+        return;
+    };
+
+    let grid_origin = grid_origin.single();
+    for segment in path.windows(2) {
+        spawn_wire(
+            &mut cmd,
+            &circuit_material,
+            grid_origin,
+            Wire {
+                first: segment[0],
+                second: segment[1],
+            },
+        );
+    }
+}
+
+pub fn block_cell(blocked: &mut HashSet<GridPosition>, pos: GridPosition) {
+    blocked.insert(pos);
+}
+
+pub fn block_wire(blocked: &mut HashSet<GridPosition>, wire: &Wire) {
+    for x in wire.first.x.min(wire.second.x)..=wire.first.x.max(wire.second.x) {
+        for y in wire.first.y.min(wire.second.y)..=wire.first.y.max(wire.second.y) {
+            block_cell(blocked, GridPosition { x, y });
+        }
+    }
+}
+
+// All components span three grid points in a line, upright or turned
+pub fn block_component(
+    blocked: &mut HashSet<GridPosition>,
+    top: GridPosition,
+    bottom: GridPosition,
+) {
+    block_wire(
+        blocked,
+        &Wire {
+            first: top,
+            second: bottom,
+        },
+    );
+}
+
+// Shortest orthogonal path with as few turns as possible, returned as the corner points including both ends
+// Only the cells the search reaches are kept, so large grids cost no more than the area around the route
+pub fn find_route(
+    start: GridPosition,
+    end: GridPosition,
+    blocked: &HashSet<GridPosition>,
+    grid_size: &GridSize,
+) -> Option<Vec<GridPosition>> {
+    if start == end {
+        return None;
+    }
+
+    let mut costs = HashMap::new();
+    let mut previous = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    for direction in 0..4 {
+        costs.insert((start, direction), 0);
+        queue.push(Reverse((0, start.x, start.y, direction)));
+    }
+
+    while let Some(Reverse((cost, x, y, direction))) = queue.pop() {
+        let pos = GridPosition { x, y };
+        if cost > costs[&(pos, direction)] {
+            continue;
+        }
+
+        if pos == end {
+            return Some(corners(trace_back(&previous, pos, direction)));
+        }
+
+        for (next_direction, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+            let (Some(next_x), Some(next_y)) =
+                (x.checked_add_signed(*dx), y.checked_add_signed(*dy))
+            else {
+                continue;
+            };
+            let next = GridPosition {
+                x: next_x,
+                y: next_y,
+            };
+            if !grid_size.contains(next) || (next != end && blocked.contains(&next)) {
+                continue;
+            }
+
+            let next_cost = cost
+                + 1
+                + if next_direction == direction {
+                    0
+                } else {
+                    TURN_COST
+                };
+            if costs
+                .get(&(next, next_direction))
+                .is_none_or(|known| next_cost < *known)
+            {
+                costs.insert((next, next_direction), next_cost);
+                previous.insert((next, next_direction), (pos, direction));
+                queue.push(Reverse((next_cost, next_x, next_y, next_direction)));
+            }
+        }
+    }
+
+    None
+}
+
+fn trace_back(
+    previous: &HashMap<(GridPosition, usize), (GridPosition, usize)>,
+    mut pos: GridPosition,
+    mut direction: usize,
+) -> Vec<GridPosition> {
+    let mut path = vec![pos];
+    while let Some(&(previous_pos, previous_direction)) = previous.get(&(pos, direction)) {
+        path.push(previous_pos);
+        (pos, direction) = (previous_pos, previous_direction);
+    }
+    path.reverse();
+    path
+}
+
+// Only keeps the points where the path changes direction
+fn corners(path: Vec<GridPosition>) -> Vec<GridPosition> {
+    let mut corners = vec![path[0]];
+    for window in path.windows(3) {
+        let straight = (window[0].x == window[1].x && window[1].x == window[2].x)
+            || (window[0].y == window[1].y && window[1].y == window[2].y);
+        if !straight {
+            corners.push(window[1]);
+        }
+    }
+    corners.extend(path.last().copied());
+    corners
+}
@@ -0,0 +1,726 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::{spawn_annotation, Annotation},
+    blocks::{spawn_block, Block},
+    clock::{spawn_clock, SimulationClock},
+    component_terminals,
+    device_counts::DeviceCounts,
+    diode::{spawn_diode, Diode},
+    fuse::{spawn_fuse, Fuse},
+    grid::{GridSize, MAX_GRIDSIZE},
+    hidden::{spawn_hidden_region, HiddenRegion},
+    keybindings::{Action, KeyBindings},
+    load_meter::SupplySettings,
+    metadata::CircuitMetadata,
+    net_labels::{spawn_net_label, NetLabel},
+    platform,
+    sheets::{SheetParts, Sheets},
+    spawn_button, spawn_light, spawn_relay_coil, spawn_relay_switch, spawn_toolbar_button,
+    spawn_wire,
+    supply::{spawn_supply, Supply},
+    time_switch::{spawn_time_switch, TimeSwitch},
+    undo::EditHistory,
+    ButtonSwitch, CircuitHandles, ComponentComment, GridOrigin, GridPosition, Light, MainSupply,
+    Power, PowerType, RelayCoil, RelaySwitch, Toolbar, Wire, WireLabel,
+};
+
+// Saving (Ctrl+S) and loading (Ctrl+O) of everything placed on the grid as a ron file
+// In the browser saving downloads the file and loading asks for one to upload
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavePath>()
+            .add_event::<SaveCircuit>()
+            .add_event::<LoadCircuit>()
+            .add_systems(PostStartup, setup_save_buttons)
+            .add_systems(
+                Update,
+                (send_save_events, save_circuit, load_circuit).chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct SavePath(pub PathBuf);
+
+impl Default for SavePath {
+    fn default() -> Self {
+        Self(PathBuf::from("circuit.ron"))
+    }
+}
+
+#[derive(Event)]
+struct SaveCircuit;
+
+#[derive(Event)]
+struct LoadCircuit;
+
+// Everything that is placed on the grid, this is what ends up in the save file
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct CircuitData {
+    #[serde(default)]
+    metadata: CircuitMetadata,
+    #[serde(default)]
+    supply: SupplySettings,
+    #[serde(default)]
+    grid_size: GridSize,
+    #[serde(default)]
+    wires: Vec<WireData>,
+    #[serde(default)]
+    lights: Vec<Light>,
+    #[serde(default)]
+    buttons: Vec<ButtonSwitch>,
+    #[serde(default)]
+    relay_coils: Vec<RelayCoil>,
+    #[serde(default)]
+    relay_switches: Vec<RelaySwitch>,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+    #[serde(default)]
+    comments: Vec<CommentData>,
+    #[serde(default)]
+    hidden_regions: Vec<HiddenRegion>,
+    #[serde(default)]
+    clocks: Vec<SimulationClock>,
+    #[serde(default)]
+    time_switches: Vec<TimeSwitch>,
+    #[serde(default)]
+    fuses: Vec<Fuse>,
+    #[serde(default)]
+    diodes: Vec<Diode>,
+    #[serde(default)]
+    supplies: Vec<Supply>,
+    #[serde(default)]
+    main_supply: Supply,
+    #[serde(default)]
+    net_labels: Vec<NetLabel>,
+    #[serde(default)]
+    blocks: Vec<Block>,
+    // The sheets after the first one, the file only has this on the first sheet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sheets: Vec<CircuitData>,
+}
+
+impl CircuitData {
+    pub fn title(&self) -> &str {
+        &self.metadata.title
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    // A circuit on the main supply made of nothing but these parts, like the generated ones of the stress test
+    pub fn from_parts(
+        grid_size: GridSize,
+        wires: Vec<Wire>,
+        lights: Vec<Light>,
+        buttons: Vec<ButtonSwitch>,
+        relay_coils: Vec<RelayCoil>,
+        relay_switches: Vec<RelaySwitch>,
+    ) -> Self {
+        Self {
+            grid_size,
+            wires: wires
+                .into_iter()
+                .map(|wire| WireData {
+                    first: wire.first,
+                    second: wire.second,
+                    label: String::new(),
+                })
+                .collect(),
+            lights,
+            buttons,
+            relay_coils,
+            relay_switches,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_net_labels(mut self, net_labels: Vec<NetLabel>) -> Self {
+        self.net_labels = net_labels;
+        self
+    }
+
+    // Only what the simulation looks at, without any visuals, for running circuits without a window
+    // Every sheet is spawned, each one the width of the largest grid further right than the one before
+    pub fn spawn_for_simulation(mut self, world: &mut World) {
+        let others = std::mem::take(&mut self.sheets);
+        for (sheet, data) in [self].into_iter().chain(others).enumerate() {
+            data.shifted(sheet * MAX_GRIDSIZE)
+                .spawn_sheet_for_simulation(world);
+        }
+    }
+
+    fn spawn_sheet_for_simulation(self, world: &mut World) {
+        for wire in self.wires {
+            world.spawn(Wire {
+                first: wire.first,
+                second: wire.second,
+            });
+        }
+        for light in self.lights {
+            world.spawn(light);
+        }
+        for button in self.buttons {
+            world.spawn(button);
+        }
+        for relay_coil in self.relay_coils {
+            world.spawn(relay_coil);
+        }
+        for relay_switch in self.relay_switches {
+            world.spawn(relay_switch);
+        }
+        for time_switch in self.time_switches {
+            world.spawn(time_switch);
+        }
+        for fuse in self.fuses {
+            world.spawn(fuse);
+        }
+        for diode in self.diodes {
+            world.spawn(diode);
+        }
+        for supply in self.supplies.into_iter().chain([self.main_supply]) {
+            world.spawn((Power(PowerType::Positive), supply.positive));
+            world.spawn((Power(PowerType::Negative), supply.negative));
+        }
+        for label in self.net_labels {
+            world.spawn(label);
+        }
+        for block in self.blocks {
+            world.spawn(block);
+        }
+    }
+
+    // Moves everything the simulation looks at right, so the points of different sheets never meet
+    fn shifted(mut self, offset: usize) -> Self {
+        let shift = |pos: &mut GridPosition| pos.x += offset;
+        for wire in &mut self.wires {
+            shift(&mut wire.first);
+            shift(&mut wire.second);
+        }
+        let terminals = self
+            .lights
+            .iter_mut()
+            .map(|light| (&mut light.top, &mut light.bottom))
+            .chain(
+                self.buttons
+                    .iter_mut()
+                    .map(|button| (&mut button.top, &mut button.bottom)),
+            )
+            .chain(
+                self.relay_coils
+                    .iter_mut()
+                    .map(|relay_coil| (&mut relay_coil.top, &mut relay_coil.bottom)),
+            )
+            .chain(
+                self.relay_switches
+                    .iter_mut()
+                    .map(|relay_switch| (&mut relay_switch.top, &mut relay_switch.bottom)),
+            )
+            .chain(
+                self.time_switches
+                    .iter_mut()
+                    .map(|time_switch| (&mut time_switch.top, &mut time_switch.bottom)),
+            )
+            .chain(
+                self.fuses
+                    .iter_mut()
+                    .map(|fuse| (&mut fuse.top, &mut fuse.bottom)),
+            )
+            .chain(
+                self.diodes
+                    .iter_mut()
+                    .map(|diode| (&mut diode.anode, &mut diode.cathode)),
+            )
+            .chain(
+                self.supplies
+                    .iter_mut()
+                    .chain([&mut self.main_supply])
+                    .map(|supply| (&mut supply.positive, &mut supply.negative)),
+            );
+        for (top, bottom) in terminals {
+            shift(top);
+            shift(bottom);
+        }
+        for label in &mut self.net_labels {
+            shift(&mut label.pos);
+        }
+        for block in &mut self.blocks {
+            shift(&mut block.origin);
+        }
+        self
+    }
+
+    // What a sheet that is not shown adds to the simulation of the shown one
+    pub fn sheet_parts(&self, sheet: usize) -> SheetParts {
+        let data = self.clone().shifted(sheet * MAX_GRIDSIZE);
+        let mut parts = SheetParts {
+            sheet,
+            wires: data
+                .wires
+                .iter()
+                .map(|wire| Wire {
+                    first: wire.first,
+                    second: wire.second,
+                })
+                // Fuses cannot blow while their sheet is not shown
+                .chain(data.fuses.iter().filter(|fuse| !fuse.blown).map(Wire::from))
+                .collect(),
+            time_switches: data.time_switches,
+            buttons: data.buttons,
+            relay_switches: data.relay_switches,
+            lights: data.lights,
+            relay_coils: data.relay_coils,
+            diodes: data.diodes,
+            sources: data
+                .supplies
+                .iter()
+                .chain([&data.main_supply])
+                .flat_map(|supply| {
+                    [
+                        (supply.positive, PowerType::Positive),
+                        (supply.negative, PowerType::Negative),
+                    ]
+                })
+                .collect(),
+            net_labels: data.net_labels,
+            links: Vec::new(),
+        };
+        for block in data.blocks.iter() {
+            block.add_parts(&mut parts);
+        }
+        parts
+    }
+}
+
+pub fn read_circuit(path: &Path) -> Result<CircuitData, String> {
+    platform::read_file(path).and_then(|text| parse_circuit(&text))
+}
+
+fn parse_circuit(text: &str) -> Result<CircuitData, String> {
+    ron::from_str::<CircuitData>(text).map_err(|e| e.to_string())
+}
+
+pub fn write_circuit(path: &Path, circuit: &CircuitData) -> Result<(), String> {
+    ron::ser::to_string_pretty(circuit, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| platform::export_file(path, text.as_bytes()))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct WireData {
+    first: GridPosition,
+    second: GridPosition,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    label: String,
+}
+
+// Comments belong to the component whose top is at that grid point
+#[derive(Serialize, Deserialize, Clone)]
+struct CommentData {
+    component: GridPosition,
+    text: String,
+}
+
+#[derive(Component)]
+struct SaveButton;
+
+#[derive(Component)]
+struct LoadButton;
+
+fn setup_save_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Save", "Save", SaveButton);
+        spawn_toolbar_button(root, "Load", "Load", LoadButton);
+    });
+}
+
+fn send_save_events(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    save_button: Query<&Interaction, (Changed<Interaction>, With<SaveButton>)>,
+    load_button: Query<&Interaction, (Changed<Interaction>, With<LoadButton>)>,
+    mut save_events: EventWriter<SaveCircuit>,
+    mut load_events: EventWriter<LoadCircuit>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if (ctrl && bindings.just_pressed(&keys, Action::Save))
+        || save_button
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        save_events.send(SaveCircuit);
+    }
+
+    if (ctrl && bindings.just_pressed(&keys, Action::Load))
+        || load_button
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        load_events.send(LoadCircuit);
+    }
+}
+
+// Everything placed on the grid, read into CircuitData and made again from it
+// Saving, loading and switching tabs all go through this
+#[derive(SystemParam)]
+pub struct CircuitAccess<'w, 's> {
+    cmd: Commands<'w, 's>,
+    metadata: ResMut<'w, CircuitMetadata>,
+    supply: ResMut<'w, SupplySettings>,
+    grid_size: ResMut<'w, GridSize>,
+    counts: Res<'w, DeviceCounts>,
+    circuit_material: Res<'w, CircuitHandles>,
+    edit_history: ResMut<'w, EditHistory>,
+    sheets: ResMut<'w, Sheets>,
+    grid_origin: Query<'w, 's, Entity, With<GridOrigin>>,
+    wires: Query<'w, 's, (&'static Wire, Option<&'static WireLabel>)>,
+    lights: Query<'w, 's, &'static Light>,
+    buttons: Query<'w, 's, &'static ButtonSwitch>,
+    relay_coils: Query<'w, 's, &'static RelayCoil>,
+    relay_switches: Query<'w, 's, &'static RelaySwitch>,
+    annotations: Query<'w, 's, &'static Annotation>,
+    hidden_regions: Query<'w, 's, &'static HiddenRegion>,
+    clocks: Query<'w, 's, &'static SimulationClock>,
+    time_switches: Query<'w, 's, &'static TimeSwitch>,
+    fuses: Query<'w, 's, &'static Fuse>,
+    diodes: Query<'w, 's, &'static Diode>,
+    supplies: Query<'w, 's, &'static Supply>,
+    main_supply: Query<'w, 's, (&'static Power, &'static mut GridPosition), With<MainSupply>>,
+    net_labels: Query<'w, 's, &'static NetLabel>,
+    blocks: Query<'w, 's, &'static Block>,
+    comments: Query<
+        'w,
+        's,
+        (
+            AnyOf<(
+                &'static Light,
+                &'static ButtonSwitch,
+                &'static RelayCoil,
+                &'static RelaySwitch,
+            )>,
+            &'static ComponentComment,
+        ),
+    >,
+    placed: Query<
+        'w,
+        's,
+        Entity,
+        Or<(
+            With<Wire>,
+            With<Light>,
+            With<ButtonSwitch>,
+            With<RelayCoil>,
+            With<RelaySwitch>,
+            With<Annotation>,
+            With<HiddenRegion>,
+            With<SimulationClock>,
+            With<TimeSwitch>,
+            With<Fuse>,
+            With<Diode>,
+            With<Supply>,
+            With<NetLabel>,
+            With<Block>,
+        )>,
+    >,
+}
+
+impl CircuitAccess<'_, '_> {
+    // The whole circuit, the first sheet carries the others
+    pub fn collect(&self) -> CircuitData {
+        let shown = self.collect_shown();
+        if self.sheets.sheets.len() == 1 {
+            return shown;
+        }
+
+        let mut sheets = self.sheets.sheets.clone();
+        sheets[self.sheets.active] = CircuitData {
+            metadata: CircuitMetadata::default(),
+            supply: SupplySettings::default(),
+            grid_size: GridSize::default(),
+            ..shown
+        };
+        let mut first = sheets.remove(0);
+        first.metadata = self.metadata.clone();
+        first.supply = self.supply.clone();
+        first.grid_size = *self.grid_size;
+        first.sheets = sheets;
+        first
+    }
+
+    fn collect_shown(&self) -> CircuitData {
+        CircuitData {
+            metadata: self.metadata.clone(),
+            supply: self.supply.clone(),
+            grid_size: *self.grid_size,
+            wires: self
+                .wires
+                .iter()
+                .map(|(wire, label)| WireData {
+                    first: wire.first,
+                    second: wire.second,
+                    label: label.map(|label| label.0.clone()).unwrap_or_default(),
+                })
+                .collect(),
+            lights: self.lights.iter().cloned().collect(),
+            buttons: self.buttons.iter().cloned().collect(),
+            relay_coils: self.relay_coils.iter().cloned().collect(),
+            relay_switches: self.relay_switches.iter().cloned().collect(),
+            annotations: self.annotations.iter().cloned().collect(),
+            comments: self
+                .comments
+                .iter()
+                .filter_map(|(component, comment)| {
+                    component_terminals(component).map(|(top, _)| CommentData {
+                        component: top,
+                        text: comment.0.clone(),
+                    })
+                })
+                .collect(),
+            hidden_regions: self.hidden_regions.iter().cloned().collect(),
+            clocks: self.clocks.iter().cloned().collect(),
+            time_switches: self.time_switches.iter().cloned().collect(),
+            fuses: self.fuses.iter().cloned().collect(),
+            diodes: self.diodes.iter().cloned().collect(),
+            supplies: self.supplies.iter().cloned().collect(),
+            main_supply: self
+                .main_supply
+                .iter()
+                .fold(Supply::default(), |supply, (power, pos)| match power.0 {
+                    PowerType::Positive => Supply {
+                        positive: *pos,
+                        ..supply
+                    },
+                    PowerType::Negative => Supply {
+                        negative: *pos,
+                        ..supply
+                    },
+                }),
+            net_labels: self.net_labels.iter().cloned().collect(),
+            blocks: self.blocks.iter().cloned().collect(),
+            sheets: Vec::new(),
+        }
+    }
+
+    // Removes everything that is placed right now and places the circuit instead
+    pub fn replace(&mut self, mut circuit: CircuitData) {
+        *self.sheets = Sheets::with_others(std::mem::take(&mut circuit.sheets));
+        *self.metadata = std::mem::take(&mut circuit.metadata);
+        *self.supply = std::mem::take(&mut circuit.supply);
+        *self.grid_size = circuit.grid_size.clamped();
+        self.show_sheet(circuit);
+    }
+
+    // Puts the shown sheet away and places another one of the same circuit instead
+    pub fn switch_sheet(&mut self, target: usize) {
+        let active = self.sheets.active;
+        self.sheets.sheets[active] = self.collect_shown();
+        self.show_stored_sheet(target);
+    }
+
+    pub fn add_sheet(&mut self) {
+        self.sheets.sheets.push(CircuitData::default());
+        self.switch_sheet(self.sheets.sheets.len() - 1);
+    }
+
+    // Everything on the shown sheet is gone with it
+    pub fn remove_sheet(&mut self) {
+        if self.sheets.sheets.len() == 1 {
+            warn!("The last sheet cannot be removed");
+            return;
+        }
+        let removed = self.sheets.active;
+        self.sheets.sheets.remove(removed);
+        self.sheets.active = removed.min(self.sheets.sheets.len() - 1);
+        self.show_stored_sheet(self.sheets.active);
+    }
+
+    fn show_stored_sheet(&mut self, target: usize) {
+        let sheet = std::mem::take(&mut self.sheets.sheets[target]);
+        self.sheets.active = target;
+        self.sheets.rebuild_parts();
+        self.show_sheet(sheet);
+    }
+
+    fn show_sheet(&mut self, mut circuit: CircuitData) {
+        self.edit_history.forget();
+
+        // Files, links and templates can come from a setup with more devices or a bigger grid than this one
+        // What the left section has no row for or what lies outside of the grid is left out, the simulation could not handle it
+        let counts = *self.counts;
+        let grid_size = *self.grid_size;
+        let on_grid =
+            |positions: &[GridPosition]| positions.iter().all(|pos| grid_size.contains(*pos));
+        let left_out = keep(&mut circuit.wires, |wire| {
+            on_grid(&[wire.first, wire.second])
+        }) + keep(&mut circuit.lights, |light| {
+            (1..=counts.lights).contains(&light.id) && on_grid(&[light.top, light.bottom])
+        }) + keep(&mut circuit.buttons, |button| {
+            (1..=counts.buttons).contains(&button.id) && on_grid(&[button.top, button.bottom])
+        }) + keep(&mut circuit.relay_coils, |relay_coil| {
+            (1..=counts.relays).contains(&relay_coil.id)
+                && on_grid(&[relay_coil.top, relay_coil.bottom])
+        }) + keep(&mut circuit.relay_switches, |relay_switch| {
+            (1..=counts.relays).contains(&relay_switch.id)
+                && on_grid(&[relay_switch.top, relay_switch.bottom])
+        }) + keep(&mut circuit.clocks, |clock| {
+            on_grid(&[clock.top, clock.bottom])
+        }) + keep(&mut circuit.time_switches, |time_switch| {
+            on_grid(&[time_switch.top, time_switch.bottom])
+        }) + keep(&mut circuit.fuses, |fuse| on_grid(&[fuse.top, fuse.bottom]))
+            + keep(&mut circuit.diodes, |diode| {
+                on_grid(&[diode.anode, diode.cathode])
+            })
+            + keep(&mut circuit.supplies, |supply| {
+                on_grid(&[supply.positive, supply.negative])
+            })
+            + keep(&mut circuit.net_labels, |label| on_grid(&[label.pos]));
+        if left_out > 0 {
+            warn!("Left out {left_out} parts that are outside of the grid or beyond the device counts");
+        }
+
+        for e in self.placed.iter() {
+            self.cmd.entity(e).despawn_recursive();
+        }
+
+        let mut comments = circuit
+            .comments
+            .into_iter()
+            .map(|comment| (comment.component, comment.text))
+            .collect::<HashMap<_, _>>();
+        let mut spawned_components = Vec::new();
+
+        let cmd = &mut self.cmd;
+        let circuit_material = &self.circuit_material;
+        let grid_origin = self.grid_origin.single();
+        for wire in circuit.wires {
+            let entity = spawn_wire(
+                cmd,
+                circuit_material,
+                grid_origin,
+                Wire {
+                    first: wire.first,
+                    second: wire.second,
+                },
+            );
+            if !wire.label.is_empty() {
+                cmd.entity(entity).insert(WireLabel(wire.label));
+            }
+        }
+        for light in circuit.lights {
+            let label = format!("-P{}", light.id);
+            let top = light.top;
+            let entity = spawn_light(cmd, circuit_material, grid_origin, light, label);
+            spawned_components.push((top, entity));
+        }
+        for button in circuit.buttons {
+            let label = format!("-S{}", button.id);
+            let top = button.top;
+            let entity = spawn_button(cmd, circuit_material, grid_origin, button, label);
+            spawned_components.push((top, entity));
+        }
+        for relay_coil in circuit.relay_coils {
+            let label = format!("-K{}", relay_coil.id);
+            let top = relay_coil.top;
+            let entity = spawn_relay_coil(cmd, circuit_material, grid_origin, relay_coil, label);
+            spawned_components.push((top, entity));
+        }
+        for relay_switch in circuit.relay_switches {
+            let label = format!("-K{}", relay_switch.id);
+            let top = relay_switch.top;
+            let entity =
+                spawn_relay_switch(cmd, circuit_material, grid_origin, relay_switch, label);
+            spawned_components.push((top, entity));
+        }
+
+        for (top, entity) in spawned_components {
+            if let Some(text) = comments.remove(&top) {
+                cmd.entity(entity).insert(ComponentComment(text));
+            }
+        }
+
+        for annotation in circuit.annotations {
+            spawn_annotation(cmd, annotation);
+        }
+        for region in circuit.hidden_regions {
+            spawn_hidden_region(cmd, region);
+        }
+        for clock in circuit.clocks {
+            spawn_clock(cmd, clock);
+        }
+        for time_switch in circuit.time_switches {
+            spawn_time_switch(cmd, time_switch);
+        }
+        for fuse in circuit.fuses {
+            spawn_fuse(cmd, fuse);
+        }
+        for diode in circuit.diodes {
+            spawn_diode(cmd, diode);
+        }
+        for supply in circuit.supplies {
+            spawn_supply(cmd, supply);
+        }
+        for label in circuit.net_labels {
+            spawn_net_label(cmd, label);
+        }
+        for block in circuit.blocks {
+            spawn_block(cmd, block);
+        }
+        for (power, mut pos) in self.main_supply.iter_mut() {
+            *pos = match power.0 {
+                PowerType::Positive => circuit.main_supply.positive,
+                PowerType::Negative => circuit.main_supply.negative,
+            };
+        }
+    }
+}
+
+// How many items were removed
+fn keep<T>(items: &mut Vec<T>, keep: impl Fn(&T) -> bool) -> usize {
+    let before = items.len();
+    items.retain(keep);
+    before - items.len()
+}
+
+fn save_circuit(mut events: EventReader<SaveCircuit>, path: Res<SavePath>, circuit: CircuitAccess) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    match write_circuit(&path.0, &circuit.collect()) {
+        Ok(_) => info!("Saved circuit to {}", path.0.display()),
+        Err(e) => error!("Cannot save circuit to {}: {e}", path.0.display()),
+    }
+}
+
+fn load_circuit(
+    mut events: EventReader<LoadCircuit>,
+    path: Res<SavePath>,
+    mut circuit: CircuitAccess,
+) {
+    // In the browser the file is picked and uploaded first, it only shows up some frames later
+    if events.read().count() > 0 {
+        platform::open_file(&path.0);
+    }
+    let Some(result) = platform::take_opened_file(&path.0) else {
+        return;
+    };
+
+    match result.and_then(|text| parse_circuit(&text)) {
+        Ok(data) => {
+            circuit.replace(data);
+            info!("Loaded circuit from {}", path.0.display());
+        }
+        Err(e) => error!("Cannot load circuit from {}: {e}", path.0.display()),
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{headless::Simulation, platform};
+
+// A scenario is a list of steps, each with the tick it happens at, for example
+// [(5, Press(1)), (20, ExpectLit(2)), (30, Release(1)), (31, ExpectReleased(1))]
+// presses -S1 before tick 5 and checks that -P2 is lit once tick 20 has run
+// Run with relay-sim --headless circuit.ron --scenario test.ron, every expectation prints a line with PASS or FAIL
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct Scenario(Vec<(usize, Step)>);
+
+#[derive(Deserialize, Clone, Copy)]
+enum Step {
+    Press(usize),
+    Release(usize),
+    ExpectLit(usize),
+    ExpectOff(usize),
+    ExpectPulledIn(usize),
+    ExpectReleased(usize),
+    ExpectShortCircuit,
+    ExpectNoShortCircuit,
+}
+
+impl Step {
+    fn is_input(self) -> bool {
+        matches!(self, Step::Press(_) | Step::Release(_))
+    }
+}
+
+pub fn read_scenario(path: &Path) -> Result<Scenario, String> {
+    platform::read_file(path)
+        .and_then(|text| ron::from_str::<Scenario>(&text).map_err(|e| e.to_string()))
+}
+
+fn lit(state: bool) -> &'static str {
+    if state {
+        "lit"
+    } else {
+        "off"
+    }
+}
+
+fn pulled_in(state: bool) -> &'static str {
+    if state {
+        "pulled in"
+    } else {
+        "released"
+    }
+}
+
+// The expectation, what was actually there and whether they match
+fn check(simulation: &mut Simulation, step: Step) -> (String, String, bool) {
+    match step {
+        Step::ExpectLit(id) | Step::ExpectOff(id) => {
+            let expected = matches!(step, Step::ExpectLit(_));
+            let expectation = format!("-P{id} {}", lit(expected));
+            match simulation.is_lit(id) {
+                Some(state) => (expectation, lit(state).to_string(), state == expected),
+                None => (expectation, "no such lamp".to_string(), false),
+            }
+        }
+        Step::ExpectPulledIn(id) | Step::ExpectReleased(id) => {
+            let expected = matches!(step, Step::ExpectPulledIn(_));
+            let expectation = format!("-K{id} {}", pulled_in(expected));
+            match simulation.is_pulled_in(id) {
+                Some(state) => (expectation, pulled_in(state).to_string(), state == expected),
+                None => (expectation, "no such relay".to_string(), false),
+            }
+        }
+        Step::ExpectShortCircuit | Step::ExpectNoShortCircuit => {
+            let expected = matches!(step, Step::ExpectShortCircuit);
+            let describe = |shorted: bool| {
+                if shorted {
+                    "short circuit"
+                } else {
+                    "no short circuit"
+                }
+            };
+            let state = simulation.short_circuit();
+            (
+                describe(expected).to_string(),
+                describe(state).to_string(),
+                state == expected,
+            )
+        }
+        Step::Press(_) | Step::Release(_) => unreachable!("inputs are not checked"),
+    }
+}
+
+// How one expectation turned out
+pub struct Outcome {
+    pub tick: usize,
+    pub expectation: String,
+    pub actual: String,
+    pub passed: bool,
+}
+
+// Ticks count from 1, presses and releases at a tick happen before it runs and expectations are checked after it ran
+pub fn evaluate_scenario(simulation: &mut Simulation, scenario: &Scenario) -> Vec<Outcome> {
+    let mut steps = scenario.0.clone();
+    steps.sort_by_key(|(tick, step)| (*tick, !step.is_input()));
+
+    let mut outcomes = Vec::new();
+    for (tick, step) in steps {
+        let run_until = if step.is_input() {
+            tick.saturating_sub(1)
+        } else {
+            tick
+        };
+        while simulation.ticks() < run_until {
+            simulation.tick();
+        }
+        match step {
+            Step::Press(id) => simulation.hold_button(id, true),
+            Step::Release(id) => simulation.hold_button(id, false),
+            _ => {
+                let (expectation, actual, passed) = check(simulation, step);
+                outcomes.push(Outcome {
+                    tick,
+                    expectation,
+                    actual,
+                    passed,
+                });
+            }
+        }
+    }
+    outcomes
+}
+
+// Returns whether every expectation held
+pub fn run_scenario(simulation: &mut Simulation, scenario: &Scenario) -> bool {
+    let outcomes = evaluate_scenario(simulation, scenario);
+    for outcome in outcomes.iter() {
+        if outcome.passed {
+            println!("tick {}: {} PASS", outcome.tick, outcome.expectation);
+        } else {
+            println!(
+                "tick {}: {} FAIL, was {}",
+                outcome.tick, outcome.expectation, outcome.actual
+            );
+        }
+    }
+
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed).count();
+    println!("{} passed, {failed} failed", outcomes.len() - failed);
+    failed == 0
+}
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+
+use crate::{
+    history::{SimulationHistory, HISTORY_LENGTH},
+    spawn_toolbar_button, ButtonSwitch, Light, RelayCoil, Toolbar,
+};
+
+const TRACK_HEIGHT: f32 = 12.;
+const HIGH_COLOR: Color = Color::rgb(0.2, 0.8, 0.2);
+
+// The scope button shows a timing diagram of the recorded history next to the schematic, one trace per button, relay and lamp on the grid
+// New ticks come in on the right while the simulation runs, so self-holding and interlocking can be followed as they happen
+// Traces that never switch are shown as well, a relay that stays dead is just as telling as one that pulls in
+pub struct ScopePlugin;
+
+impl Plugin for ScopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_scope_panel)
+            .add_systems(PostStartup, setup_scope_button)
+            .add_systems(Update, (toggle_scope_panel, draw_scope_traces).chain());
+    }
+}
+
+#[derive(Component)]
+struct ScopeButton;
+
+#[derive(Component)]
+struct ScopePanel;
+
+// Holds the traces, they are all rebuilt whenever the history changes
+#[derive(Component)]
+struct ScopeTraces;
+
+fn setup_scope_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Scope", "Scope", ScopeButton);
+    });
+}
+
+fn setup_scope_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(290.),
+                // Above the history scrubber, which shows up at the bottom while paused
+                bottom: Val::Px(70.),
+                width: Val::Px(560.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Scope Panel"),
+        ScopePanel,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(3.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Name::new("Scope Traces"),
+            ScopeTraces,
+        ));
+    });
+}
+
+fn toggle_scope_panel(
+    scope_button: Query<&Interaction, (Changed<Interaction>, With<ScopeButton>)>,
+    mut panel: Query<&mut Visibility, With<ScopePanel>>,
+) {
+    if !scope_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn spawn_block(root: &mut ChildBuilder, start: usize, length: usize, color: Color, name: &str) {
+    root.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(start as f32 / HISTORY_LENGTH as f32 * 100.),
+                width: Val::Percent(length as f32 / HISTORY_LENGTH as f32 * 100.),
+                height: Val::Percent(100.),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(color),
+            ..Default::default()
+        },
+        Name::new(name.to_string()),
+    ));
+}
+
+fn draw_scope_traces(
+    mut cmd: Commands,
+    history: Res<SimulationHistory>,
+    panel: Query<Ref<Visibility>, With<ScopePanel>>,
+    traces: Query<Entity, With<ScopeTraces>>,
+    buttons: Query<&ButtonSwitch>,
+    relay_coils: Query<&RelayCoil>,
+    lights: Query<&Light>,
+) {
+    let Ok(visibility) = panel.get_single() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden || (!history.is_changed() && !visibility.is_changed()) {
+        return;
+    }
+
+    let ids = |mut ids: Vec<usize>| {
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+    let mut rows: Vec<(String, Vec<bool>)> = Vec::new();
+    for id in ids(buttons.iter().map(|button| button.id).collect()) {
+        rows.push((
+            format!("-S{id}"),
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| snapshot.pressed_button_ids.contains(&id))
+                .collect(),
+        ));
+    }
+    for id in ids(relay_coils.iter().map(|relay_coil| relay_coil.id).collect()) {
+        rows.push((
+            format!("-K{id}"),
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| snapshot.activated_relay_ids.contains(&id))
+                .collect(),
+        ));
+    }
+    for id in ids(lights.iter().map(|light| light.id).collect()) {
+        rows.push((
+            format!("-P{id}"),
+            history
+                .snapshots
+                .iter()
+                .map(|snapshot| snapshot.lit_light_ids.contains(&id))
+                .collect(),
+        ));
+    }
+
+    let text_style = TextStyle {
+        font_size: 14.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+    // The newest tick is always at the right edge, a short history only fills the right part
+    let offset = HISTORY_LENGTH - history.snapshots.len();
+
+    for e in traces.iter() {
+        cmd.entity(e).despawn_descendants().with_children(|root| {
+            if rows.is_empty() {
+                root.spawn((
+                    TextBundle::from_section(
+                        "No buttons, relays or lamps on the grid",
+                        text_style.clone(),
+                    ),
+                    Name::new("Scope Text"),
+                ));
+                return;
+            }
+
+            for (label, states) in &rows {
+                root.spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::Flex,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    Name::new("Scope Trace"),
+                ))
+                .with_children(|root| {
+                    root.spawn((
+                        TextBundle::from_section(label.clone(), text_style.clone()).with_style(
+                            Style {
+                                width: Val::Px(40.),
+                                ..Default::default()
+                            },
+                        ),
+                        Name::new("Scope Trace Label"),
+                    ));
+                    root.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_grow: 1.,
+                                height: Val::Px(TRACK_HEIGHT),
+                                ..Default::default()
+                            },
+                            background_color: BackgroundColor(Color::rgb(0.2, 0.2, 0.2)),
+                            ..Default::default()
+                        },
+                        Name::new("Scope Track"),
+                    ))
+                    .with_children(|root| {
+                        // Runs of high ticks become one block each, low ticks are the bare track
+                        let mut tick = 0;
+                        while tick < states.len() {
+                            let run = states[tick..]
+                                .iter()
+                                .take_while(|other| **other == states[tick])
+                                .count();
+                            if states[tick] {
+                                spawn_block(root, offset + tick, run, HIGH_COLOR, "Scope High");
+                            }
+                            tick += run;
+                        }
+
+                        // The tick that is shown in the schematic while paused
+                        if let Some(cursor) = history.cursor {
+                            spawn_block(
+                                root,
+                                offset + cursor,
+                                1,
+                                Color::rgb(0.9, 0.9, 0.9),
+                                "Scope Cursor",
+                            );
+                        }
+                    });
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use bevy::prelude::*;
+use rhai::{Engine, FnPtr, AST, INT};
+
+use crate::{
+    is_running, platform, simulate, spawn_toolbar_button, RelayCoil, SimulationScratch, Toolbar,
+    UIButton, UILight,
+};
+
+const SCRIPT_PATH: &str = "script.rhai";
+// A script that loops forever should not freeze the app, this is per run of the script and per call of a tick handler
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// Only with cargo build --features scripting, the script button runs script.rhai from the working directory
+// Scripts press and release buttons, look at lamps, relays and buttons and can run a function after every tick, for example
+// on_tick(|t| if t == 10 { press("S1") } else if is_lit("P3") { release("S1") });
+// Pressing the button again stops the script and lets go of everything it pressed
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScriptRunner>()
+            .add_systems(PostStartup, setup_script_button)
+            .add_systems(Update, (toggle_script, update_script_button).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    press_script_buttons.before(simulate),
+                    run_tick_handlers.after(simulate),
+                )
+                    .run_if(is_running),
+            );
+    }
+}
+
+// What scripts can see of the circuit and which buttons they hold down, shared with the functions registered on the engine
+#[derive(Default)]
+struct ScriptIo {
+    pressed: HashSet<usize>,
+    active_buttons: HashSet<usize>,
+    lit: HashSet<usize>,
+    pulled_in: HashSet<usize>,
+}
+
+struct RunningScript {
+    engine: Engine,
+    ast: AST,
+    io: Arc<Mutex<ScriptIo>>,
+    tick_handlers: Arc<Mutex<Vec<FnPtr>>>,
+    tick: INT,
+}
+
+#[derive(Resource, Default)]
+struct ScriptRunner(Option<RunningScript>);
+
+// Names like in the schematic, with or without the dash, S1 is button 1
+fn parse_name(name: &str, kind: char) -> Result<usize, String> {
+    name.trim()
+        .trim_start_matches('-')
+        .strip_prefix(kind)
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| format!("{name} is not a name like -{kind}1"))
+}
+
+fn create_engine(io: &Arc<Mutex<ScriptIo>>, tick_handlers: &Arc<Mutex<Vec<FnPtr>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| info!("Script: {text}"));
+    engine.on_debug(|text, _, position| info!("Script at {position}: {text}"));
+
+    let with_io = |f: fn(&mut ScriptIo, usize) -> bool, kind: char| {
+        let io = io.clone();
+        move |name: &str| -> Result<bool, Box<rhai::EvalAltResult>> {
+            let id = parse_name(name, kind)?;
+            let mut io = io.lock().map_err(|e| e.to_string())?;
+            Ok(f(&mut io, id))
+        }
+    };
+    engine.register_fn("press", with_io(|io, id| io.pressed.insert(id), 'S'));
+    engine.register_fn("release", with_io(|io, id| io.pressed.remove(&id), 'S'));
+    engine.register_fn(
+        "is_pressed",
+        with_io(|io, id| io.active_buttons.contains(&id), 'S'),
+    );
+    engine.register_fn("is_lit", with_io(|io, id| io.lit.contains(&id), 'P'));
+    engine.register_fn(
+        "is_pulled_in",
+        with_io(|io, id| io.pulled_in.contains(&id), 'K'),
+    );
+
+    let handlers = tick_handlers.clone();
+    engine.register_fn("on_tick", move |handler: FnPtr| {
+        if let Ok(mut handlers) = handlers.lock() {
+            handlers.push(handler);
+        }
+    });
+
+    engine
+}
+
+fn start_script() -> Result<RunningScript, String> {
+    let source = platform::read_file(Path::new(SCRIPT_PATH))?;
+    let io = Arc::new(Mutex::new(ScriptIo::default()));
+    let tick_handlers = Arc::new(Mutex::new(Vec::new()));
+    let engine = create_engine(&io, &tick_handlers);
+    let ast = engine.compile(source).map_err(|e| e.to_string())?;
+    engine.run_ast(&ast).map_err(|e| e.to_string())?;
+
+    Ok(RunningScript {
+        engine,
+        ast,
+        io,
+        tick_handlers,
+        tick: 0,
+    })
+}
+
+#[derive(Component)]
+struct ScriptButton;
+
+fn setup_script_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Script", "Script", ScriptButton);
+    });
+}
+
+fn toggle_script(
+    script_button: Query<&Interaction, (Changed<Interaction>, With<ScriptButton>)>,
+    mut runner: ResMut<ScriptRunner>,
+) {
+    if !script_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if runner.0.take().is_some() {
+        info!("Stopped {SCRIPT_PATH}");
+        return;
+    }
+
+    match start_script() {
+        Ok(script) => {
+            info!("Running {SCRIPT_PATH}");
+            runner.0 = Some(script);
+        }
+        Err(e) => error!("Cannot run {SCRIPT_PATH}: {e}"),
+    }
+}
+
+fn update_script_button(
+    runner: Res<ScriptRunner>,
+    mut script_button: Query<&mut BackgroundColor, With<ScriptButton>>,
+) {
+    if !runner.is_changed() {
+        return;
+    }
+
+    for mut background_color in script_button.iter_mut() {
+        background_color.0 = match runner.0 {
+            Some(_) => Color::rgb(0.1, 0.5, 0.1),
+            None => Color::rgb(0.25, 0.25, 0.25),
+        };
+    }
+}
+
+// Buttons pressed by the script stay down like maintained ones until it releases them
+fn press_script_buttons(runner: Res<ScriptRunner>, mut ui_buttons: Query<&mut UIButton>) {
+    let Some(io) = runner.0.as_ref().and_then(|script| script.io.lock().ok()) else {
+        return;
+    };
+    for mut ui_button in ui_buttons.iter_mut() {
+        if io.pressed.contains(&ui_button.id) {
+            ui_button.has_been_pressed = true;
+        }
+    }
+}
+
+fn run_tick_handlers(
+    mut runner: ResMut<ScriptRunner>,
+    scratch: Res<SimulationScratch>,
+    relay_coils: Query<&RelayCoil>,
+    ui_lights: Query<&UILight>,
+) {
+    let Some(script) = runner.0.as_mut() else {
+        return;
+    };
+
+    if let Ok(mut io) = script.io.lock() {
+        io.active_buttons = scratch.active_button_ids.iter().copied().collect();
+        io.lit = ui_lights
+            .iter()
+            .filter(|ui_light| ui_light.is_lit)
+            .map(|ui_light| ui_light.id)
+            .collect();
+        io.pulled_in = relay_coils
+            .iter()
+            .filter(|relay_coil| relay_coil.activated)
+            .map(|relay_coil| relay_coil.id)
+            .collect();
+    }
+
+    // Handlers may add more handlers, so the list is not locked while they run
+    let handlers = match script.tick_handlers.lock() {
+        Ok(handlers) => handlers.clone(),
+        Err(_) => return,
+    };
+    let tick = script.tick;
+    script.tick += 1;
+    for handler in handlers {
+        if let Err(e) = handler.call::<rhai::Dynamic>(&script.engine, &script.ast, (tick,)) {
+            error!("{SCRIPT_PATH} stopped at tick {tick}: {e}");
+            runner.0 = None;
+            return;
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::Path,
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{is_running, platform, simulate, spawn_toolbar_button, Toolbar, UIButton, UILight};
+
+const SERIAL_PATH: &str = "serial.ron";
+// Ids go up to 99, so they fit into the low 7 bits of a byte
+const ID_COUNT: usize = 100;
+const LONGEST_LINE: usize = 32;
+
+// The serial button connects to a button box on a serial port, like an Arduino with a few pushbuttons and lamps
+// Buttons pressed on the box press -S{n} in the simulation, and -P{n} being lit or not is sent back to the box
+// Port, baud rate and protocol come from serial.ron in the working directory, for example
+// (port: "/dev/ttyUSB0", baud: 9600, protocol: Ascii)
+pub struct SerialPlugin;
+
+impl Plugin for SerialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings())
+            .init_resource::<SerialBridge>()
+            .add_systems(PostStartup, setup_serial_button)
+            .add_systems(Update, (toggle_serial_bridge, update_serial_button).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    press_serial_buttons.before(simulate),
+                    mirror_lamps.after(simulate),
+                )
+                    .run_if(is_running),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+enum SerialProtocol {
+    // A line per change, S3=1 presses -S3 and S3=0 lets go of it, lamps are sent as P2=1 and P2=0
+    Ascii,
+    // A byte per change, the low 7 bits are the id and the high bit is set for pressed or lit
+    Bytes,
+}
+
+#[derive(Resource, Clone, Deserialize)]
+#[serde(default)]
+struct SerialSettings {
+    port: String,
+    baud: u32,
+    protocol: SerialProtocol,
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        Self {
+            port: if cfg!(windows) {
+                "COM3".to_string()
+            } else {
+                "/dev/ttyUSB0".to_string()
+            },
+            baud: 9600,
+            protocol: SerialProtocol::Ascii,
+        }
+    }
+}
+
+fn load_settings() -> SerialSettings {
+    if !platform::file_exists(Path::new(SERIAL_PATH)) {
+        return SerialSettings::default();
+    }
+
+    match platform::read_file(Path::new(SERIAL_PATH))
+        .and_then(|text| ron::from_str::<SerialSettings>(&text).map_err(|e| e.to_string()))
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Cannot read the serial settings from {SERIAL_PATH}, using the defaults: {e}");
+            SerialSettings::default()
+        }
+    }
+}
+
+struct OpenBridge {
+    port: String,
+    protocol: SerialProtocol,
+    stop: Arc<AtomicBool>,
+    // Which buttons the box holds down right now
+    held: Arc<Mutex<Vec<bool>>>,
+    sender: Sender<Vec<u8>>,
+    // What the box was last told, only changes are sent
+    sent_lamps: Option<Vec<bool>>,
+}
+
+impl Drop for OpenBridge {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Resource, Default)]
+struct SerialBridge(Option<OpenBridge>);
+
+#[derive(Component)]
+struct SerialButton;
+
+fn setup_serial_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Serial", "Serial Bridge", SerialButton);
+    });
+}
+
+fn toggle_serial_bridge(
+    serial_button: Query<&Interaction, (Changed<Interaction>, With<SerialButton>)>,
+    settings: Res<SerialSettings>,
+    mut bridge: ResMut<SerialBridge>,
+) {
+    if !serial_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if let Some(open) = bridge.0.take() {
+        info!("Closed the serial port {}", open.port);
+        return;
+    }
+
+    match open_bridge(&settings) {
+        Ok(open) => {
+            info!(
+                "Opened the serial port {} at {} baud",
+                settings.port, settings.baud
+            );
+            bridge.0 = Some(open);
+        }
+        Err(e) => error!("Cannot open the serial port {}: {e}", settings.port),
+    }
+}
+
+fn update_serial_button(
+    bridge: Res<SerialBridge>,
+    mut serial_button: Query<(&Children, &mut BackgroundColor), With<SerialButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !bridge.is_changed() {
+        return;
+    }
+
+    let (label, color) = match &bridge.0 {
+        Some(open) => (format!("Serial {}", open.port), Color::rgb(0.1, 0.5, 0.1)),
+        None => ("Serial".to_string(), Color::rgb(0.25, 0.25, 0.25)),
+    };
+    for (children, mut background_color) in serial_button.iter_mut() {
+        background_color.0 = color;
+        if let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+// Held buttons of the box count like held mouse buttons, the simulation lets go of them again every tick
+fn press_serial_buttons(bridge: Res<SerialBridge>, mut ui_buttons: Query<&mut UIButton>) {
+    let Some(held) = bridge.0.as_ref().and_then(|open| open.held.lock().ok()) else {
+        return;
+    };
+    for mut ui_button in ui_buttons.iter_mut() {
+        if held.get(ui_button.id).copied().unwrap_or(false) {
+            ui_button.has_been_pressed = true;
+        }
+    }
+}
+
+fn mirror_lamps(mut bridge: ResMut<SerialBridge>, ui_lights: Query<&UILight>) {
+    let Some(open) = bridge.0.as_mut() else {
+        return;
+    };
+
+    let mut lamps = vec![false; ID_COUNT];
+    for ui_light in ui_lights.iter().filter(|ui_light| ui_light.id < ID_COUNT) {
+        lamps[ui_light.id] = ui_light.is_lit;
+    }
+    // Everything is sent once after connecting, so the box starts out showing the right lamps
+    let mut frames = Vec::new();
+    for ui_light in ui_lights.iter().filter(|ui_light| ui_light.id < ID_COUNT) {
+        let id = ui_light.id;
+        if open.sent_lamps.as_ref().map(|sent| sent[id]) != Some(lamps[id]) {
+            frames.extend(encode_lamp(open.protocol, id, lamps[id]));
+        }
+    }
+    open.sent_lamps = Some(lamps);
+
+    if !frames.is_empty() && open.sender.send(frames).is_err() {
+        error!("The serial port {} was closed", open.port);
+        bridge.0 = None;
+    }
+}
+
+fn encode_lamp(protocol: SerialProtocol, id: usize, lit: bool) -> Vec<u8> {
+    match protocol {
+        SerialProtocol::Ascii => format!("P{id}={}\n", u8::from(lit)).into_bytes(),
+        SerialProtocol::Bytes => vec![id as u8 | if lit { 0x80 } else { 0 }],
+    }
+}
+
+// A line like S3=1, with or without the dash in front of the S
+fn parse_line(line: &str) -> Option<(usize, bool)> {
+    let (name, state) = line.trim().split_once('=')?;
+    let id = name
+        .trim_start_matches('-')
+        .strip_prefix(['S', 's'])?
+        .parse::<usize>()
+        .ok()?;
+    let pressed = match state.trim() {
+        "1" => true,
+        "0" => false,
+        _ => return None,
+    };
+    (id < ID_COUNT).then_some((id, pressed))
+}
+
+// The port is set up with the tools every system comes with, so no serial library is needed
+fn configure_port(port: &str, baud: u32) -> Result<(), String> {
+    let output = if cfg!(windows) {
+        Command::new("mode")
+            .args([
+                port,
+                &format!("BAUD={baud}"),
+                "PARITY=N",
+                "DATA=8",
+                "STOP=1",
+            ])
+            .output()
+    } else {
+        // Reads come back after a tenth of a second without data, so the reading thread notices when it should stop
+        let device_flag = if cfg!(target_os = "macos") {
+            "-f"
+        } else {
+            "-F"
+        };
+        Command::new("stty")
+            .args([device_flag, port, &baud.to_string()])
+            .args(["raw", "-echo", "min", "0", "time", "1"])
+            .output()
+    }
+    .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+fn open_bridge(settings: &SerialSettings) -> Result<OpenBridge, String> {
+    configure_port(&settings.port, settings.baud)?;
+    let path = if cfg!(windows) && !settings.port.starts_with(r"\\.\") {
+        format!(r"\\.\{}", settings.port)
+    } else {
+        settings.port.clone()
+    };
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let writer = file.try_clone().map_err(|e| e.to_string())?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let held = Arc::new(Mutex::new(vec![false; ID_COUNT]));
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+    let (thread_stop, thread_held) = (stop.clone(), held.clone());
+    let protocol = settings.protocol;
+    thread::spawn(move || read_frames(file, protocol, &thread_stop, &thread_held));
+    thread::spawn(move || {
+        let mut writer = writer;
+        for frames in receiver {
+            if let Err(e) = writer.write_all(&frames) {
+                error!("Cannot write to the serial port: {e}");
+                return;
+            }
+        }
+    });
+
+    Ok(OpenBridge {
+        port: settings.port.clone(),
+        protocol: settings.protocol,
+        stop,
+        held,
+        sender,
+        sent_lamps: None,
+    })
+}
+
+fn read_frames(
+    mut file: File,
+    protocol: SerialProtocol,
+    stop: &AtomicBool,
+    held: &Mutex<Vec<bool>>,
+) {
+    let mut line = String::new();
+    let mut buffer = [0; 64];
+    while !stop.load(Ordering::Relaxed) {
+        let read = match file.read(&mut buffer) {
+            Ok(read) => read,
+            Err(e) if matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::TimedOut) => continue,
+            Err(e) => {
+                error!("Cannot read from the serial port: {e}");
+                return;
+            }
+        };
+
+        let mut changes = Vec::new();
+        for byte in &buffer[..read] {
+            match protocol {
+                SerialProtocol::Ascii if *byte == b'\n' || *byte == b'\r' => {
+                    if let Some(change) = parse_line(&line) {
+                        changes.push(change);
+                    } else if !line.trim().is_empty() {
+                        warn!("Unknown line from the serial port: {line}");
+                    }
+                    line.clear();
+                }
+                SerialProtocol::Ascii => {
+                    // Noise on the line should not pile up forever
+                    if line.len() < LONGEST_LINE {
+                        line.push(*byte as char);
+                    }
+                }
+                SerialProtocol::Bytes => {
+                    changes.push(((*byte & 0x7F) as usize, *byte & 0x80 != 0));
+                }
+            }
+        }
+
+        let Ok(mut held) = held.lock() else {
+            return;
+        };
+        for (id, pressed) in changes.into_iter().filter(|(id, _)| *id < ID_COUNT) {
+            held[id] = pressed;
+        }
+    }
+}
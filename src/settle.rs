@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+use crate::{spawn_toolbar_button, Toolbar};
+
+// Passes over the circuit per tick at most, a relay that switches itself off never settles and stops here
+pub const MAX_SETTLE_PASSES: usize = 50;
+
+// Normally a relay switches its contacts one tick after its coil, so a chain of relays takes one tick per stage
+// With the settle button on every tick is evaluated again and again until no relay changes anymore, cascades then respond at once
+pub struct SettlePlugin;
+
+impl Plugin for SettlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettleRelays>()
+            .add_systems(PostStartup, setup_settle_button)
+            .add_systems(Update, (handle_settle_button_press, show_settle).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SettleRelays(pub bool);
+
+#[derive(Component)]
+struct SettleButton;
+
+fn setup_settle_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Settle", "Settle", SettleButton);
+    });
+}
+
+fn handle_settle_button_press(
+    mut settle: ResMut<SettleRelays>,
+    interaction: Query<&Interaction, (Changed<Interaction>, With<SettleButton>)>,
+) {
+    if interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        settle.0 = !settle.0;
+    }
+}
+
+fn show_settle(
+    settle: Res<SettleRelays>,
+    mut settle_button: Query<&mut BackgroundColor, With<SettleButton>>,
+) {
+    if !settle.is_changed() {
+        return;
+    }
+
+    let color = if settle.0 {
+        Color::rgb(0.2, 0.5, 0.2)
+    } else {
+        Color::rgb(0.25, 0.25, 0.25)
+    };
+    for mut background in settle_button.iter_mut() {
+        background.0 = color;
+    }
+}
@@ -0,0 +1,211 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bevy::prelude::*;
+
+use crate::{
+    platform,
+    save::{CircuitAccess, CircuitData},
+    spawn_toolbar_button, Toolbar,
+};
+
+// Links start their fragment with this, pasted text may be a whole link or only the code after it
+const LINK_PREFIX: &str = "circuit=";
+// Far more than any circuit that fits on the grid, a pasted code cannot make the app run out of memory
+const MAX_DECODED_SIZE: usize = 64 * 1024 * 1024;
+
+// The share button copies the whole circuit as one short line of text, open shared places a pasted one
+// The circuit is the save file, deflated and in url safe base64, so it also fits behind the # of a link
+// In the browser sharing puts it in the address of the page, opening a link like that places the circuit right away
+pub struct SharePlugin;
+
+impl Plugin for SharePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_share_buttons)
+            .add_systems(
+                Update,
+                (
+                    load_linked_circuit.run_if(run_once()),
+                    share_circuit,
+                    open_shared_circuit,
+                ),
+            );
+    }
+}
+
+pub fn encode_circuit(circuit: &CircuitData) -> Result<String, String> {
+    let text = ron::to_string(circuit).map_err(|e| e.to_string())?;
+    let deflated = miniz_oxide::deflate::compress_to_vec(text.as_bytes(), 9);
+    Ok(URL_SAFE_NO_PAD.encode(deflated))
+}
+
+pub fn decode_circuit(shared: &str) -> Result<CircuitData, String> {
+    let code = match shared.rfind(LINK_PREFIX) {
+        Some(start) => &shared[start + LINK_PREFIX.len()..],
+        None => shared,
+    };
+    let deflated = URL_SAFE_NO_PAD
+        .decode(code.trim())
+        .map_err(|e| format!("this is not a shared circuit: {e}"))?;
+    let text = miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, MAX_DECODED_SIZE)
+        .map_err(|e| format!("this is not a shared circuit: {e}"))?;
+    let text = String::from_utf8(text).map_err(|e| e.to_string())?;
+    ron::from_str::<CircuitData>(&text).map_err(|e| e.to_string())
+}
+
+#[derive(Component)]
+struct ShareButton;
+
+#[derive(Component)]
+struct OpenSharedButton;
+
+fn setup_share_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Share", "Share", ShareButton);
+        spawn_toolbar_button(root, "Shared", "Open Shared", OpenSharedButton);
+    });
+}
+
+fn load_linked_circuit(mut circuit: CircuitAccess) {
+    let Some(fragment) = platform::link_fragment() else {
+        return;
+    };
+    if !fragment.starts_with(LINK_PREFIX) {
+        return;
+    }
+
+    match decode_circuit(&fragment) {
+        Ok(data) => {
+            circuit.replace(data);
+            info!("Opened the circuit from the link");
+        }
+        Err(e) => error!("Cannot open the circuit from the link: {e}"),
+    }
+}
+
+fn share_circuit(
+    share_button: Query<&Interaction, (Changed<Interaction>, With<ShareButton>)>,
+    circuit: CircuitAccess,
+) {
+    if !share_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let code = match encode_circuit(&circuit.collect()) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("Cannot share the circuit: {e}");
+            return;
+        }
+    };
+    let text = platform::share_link(&format!("{LINK_PREFIX}{code}")).unwrap_or(code);
+    match platform::copy_text(&text) {
+        Ok(_) => info!("Copied the circuit as {} characters", text.len()),
+        Err(e) => error!("Cannot copy the shared circuit: {e}"),
+    }
+}
+
+fn open_shared_circuit(
+    open_button: Query<&Interaction, (Changed<Interaction>, With<OpenSharedButton>)>,
+    mut circuit: CircuitAccess,
+) {
+    if !open_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let shared = match platform::paste_text() {
+        Ok(Some(shared)) => shared,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Cannot paste a shared circuit: {e}");
+            return;
+        }
+    };
+    match decode_circuit(&shared) {
+        Ok(data) => {
+            circuit.replace(data);
+            info!("Opened the shared circuit");
+        }
+        Err(e) => error!("Cannot open the shared circuit: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::GridSize, ButtonSwitch, GridPosition, Light, SwitchType, Wire};
+
+    fn pos(x: usize, y: usize) -> GridPosition {
+        GridPosition { x, y }
+    }
+
+    // A button switching a light on the main supply
+    fn circuit() -> CircuitData {
+        CircuitData::from_parts(
+            GridSize::default(),
+            vec![
+                Wire {
+                    first: pos(2, 10),
+                    second: pos(2, 8),
+                },
+                Wire {
+                    first: pos(2, 6),
+                    second: pos(4, 6),
+                },
+            ],
+            vec![Light {
+                id: 1,
+                top: pos(4, 6),
+                bottom: pos(4, 2),
+            }],
+            vec![ButtonSwitch {
+                id: 1,
+                typ: SwitchType::NormallyOpen,
+                top: pos(2, 8),
+                bottom: pos(2, 6),
+            }],
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+
+    // The circuit data cannot be compared directly, its text is what ends up in the file anyways
+    fn text(circuit: &CircuitData) -> String {
+        ron::to_string(circuit).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let code = encode_circuit(&circuit()).unwrap();
+        assert!(!code.contains(['+', '/', '=', '#']));
+        assert_eq!(text(&decode_circuit(&code).unwrap()), text(&circuit()));
+    }
+
+    #[test]
+    fn link_or_bare_code() {
+        let code = encode_circuit(&circuit()).unwrap();
+        let link = format!("https://example.com/relay-sim/#{LINK_PREFIX}{code}");
+        assert_eq!(text(&decode_circuit(&link).unwrap()), text(&circuit()));
+        let pasted = format!("  {code}\n");
+        assert_eq!(text(&decode_circuit(&pasted).unwrap()), text(&circuit()));
+    }
+
+    #[test]
+    fn truncated_code() {
+        let code = encode_circuit(&circuit()).unwrap();
+        assert!(decode_circuit(&code[..code.len() / 2]).is_err());
+        assert!(decode_circuit("").is_err());
+        assert!(decode_circuit("not a circuit!").is_err());
+    }
+
+    #[test]
+    fn oversized_code() {
+        let deflated = miniz_oxide::deflate::compress_to_vec(&vec![b' '; MAX_DECODED_SIZE + 1], 1);
+        let code = URL_SAFE_NO_PAD.encode(deflated);
+        assert!(decode_circuit(&code).is_err());
+    }
+}
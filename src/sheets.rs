@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+
+use crate::{
+    diode::Diode,
+    grid_to_world,
+    net_labels::NetLabel,
+    save::{CircuitAccess, CircuitData},
+    time_switch::TimeSwitch,
+    ButtonSwitch, CurrentlyPlacing, GridPosition, Light, PowerType, RelayCoil, RelaySwitch,
+    SimulationScratch, Wire,
+};
+
+const CROSS_REFERENCE_COLOR: Color = Color::rgb(0.7, 0.7, 0.9);
+
+// A circuit can be split over several sheets, like the power and control sections of a real schematic, the sheet bar switches between them
+// All sheets are simulated together, net labels connect across sheets and a relay switches its contacts on every sheet
+// Coils and contacts whose counterpart is on another sheet get a cross-reference with the sheet numbers
+pub struct SheetsPlugin;
+
+impl Plugin for SheetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sheets>()
+            .add_systems(Startup, setup_sheet_bar)
+            .add_systems(
+                Update,
+                (handle_sheets, update_sheet_bar, update_cross_references).chain(),
+            );
+    }
+}
+
+// What a sheet that is not shown adds to the simulation, moved a whole grid width right per sheet so no points meet
+#[derive(Default)]
+pub struct SheetParts {
+    // Numbered from 1 like in the sheet bar
+    pub sheet: usize,
+    pub wires: Vec<Wire>,
+    pub time_switches: Vec<TimeSwitch>,
+    pub buttons: Vec<ButtonSwitch>,
+    pub relay_switches: Vec<RelaySwitch>,
+    pub lights: Vec<Light>,
+    pub relay_coils: Vec<RelayCoil>,
+    pub diodes: Vec<Diode>,
+    pub sources: Vec<(GridPosition, PowerType)>,
+    pub net_labels: Vec<NetLabel>,
+    // The pins of blocks, joined to the inside of their block
+    pub links: Vec<(GridPosition, GridPosition)>,
+}
+
+#[derive(Resource)]
+pub struct Sheets {
+    // Every sheet of the circuit in order, the shown one is left empty here while it is placed
+    pub sheets: Vec<CircuitData>,
+    pub active: usize,
+    // Made again whenever the shown sheet changes
+    pub others: Vec<SheetParts>,
+}
+
+impl Default for Sheets {
+    fn default() -> Self {
+        Self::with_others(Vec::new())
+    }
+}
+
+impl Sheets {
+    // The first sheet is the shown one
+    pub fn with_others(others: Vec<CircuitData>) -> Self {
+        let mut sheets = Self {
+            sheets: [CircuitData::default()].into_iter().chain(others).collect(),
+            active: 0,
+            others: Vec::new(),
+        };
+        sheets.rebuild_parts();
+        sheets
+    }
+
+    pub fn rebuild_parts(&mut self) {
+        self.others = self
+            .sheets
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.active)
+            .map(|(i, sheet)| sheet.sheet_parts(i + 1))
+            .collect();
+    }
+}
+
+#[derive(Component)]
+struct SheetBar;
+
+#[derive(Component, Clone, Copy)]
+enum SheetRow {
+    Switch(usize),
+    New,
+    Remove,
+}
+
+// The text below a coil or contact, with the entity of that coil or contact
+#[derive(Component)]
+struct CrossReference(Entity);
+
+fn setup_sheet_bar(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                right: Val::Px(10.),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                ..Default::default()
+            },
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Sheet Bar"),
+        SheetBar,
+    ));
+}
+
+// Named like the toolbar buttons, so the command palette can switch sheets too
+fn spawn_sheet_row(
+    root: &mut ChildBuilder,
+    row: SheetRow,
+    label: String,
+    name: String,
+    active: bool,
+) {
+    root.spawn((
+        ButtonBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(3.)),
+                margin: UiRect::all(Val::Px(1.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(if active {
+                Color::rgb(0.3, 0.45, 0.3)
+            } else {
+                Color::rgb(0.15, 0.15, 0.15)
+            }),
+            ..Default::default()
+        },
+        Name::new(name),
+        row,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("Sheet Row Text"),
+        ));
+    });
+}
+
+fn handle_sheets(
+    rows: Query<(&Interaction, &SheetRow), Changed<Interaction>>,
+    relay_coils: Query<&RelayCoil>,
+    mut circuit: CircuitAccess,
+    mut scratch: ResMut<SimulationScratch>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    let Some(row) = rows
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, row)| *row)
+        .next()
+    else {
+        return;
+    };
+
+    // Relays on the sheet that is put away stay pulled in, the simulation goes on with them on the other sheet
+    scratch.other_sheet_relays.extend(
+        relay_coils
+            .iter()
+            .filter(|relay_coil| relay_coil.activated)
+            .map(|relay_coil| relay_coil.id),
+    );
+    match row {
+        SheetRow::Switch(target) => circuit.switch_sheet(target),
+        SheetRow::New => circuit.add_sheet(),
+        SheetRow::Remove => circuit.remove_sheet(),
+    }
+    // Whatever was about to be placed belongs to the sheet that was left
+    *currently_placing = CurrentlyPlacing::Wire;
+}
+
+fn update_sheet_bar(mut cmd: Commands, sheets: Res<Sheets>, bar: Query<Entity, With<SheetBar>>) {
+    if !sheets.is_changed() {
+        return;
+    }
+
+    for e in bar.iter() {
+        cmd.entity(e).despawn_descendants().with_children(|root| {
+            for i in 0..sheets.sheets.len() {
+                spawn_sheet_row(
+                    root,
+                    SheetRow::Switch(i),
+                    format!("Sheet {}", i + 1),
+                    format!("Switch To Sheet {} Button", i + 1),
+                    i == sheets.active,
+                );
+            }
+            spawn_sheet_row(
+                root,
+                SheetRow::New,
+                "+".to_string(),
+                "New Sheet Button".to_string(),
+                false,
+            );
+            spawn_sheet_row(
+                root,
+                SheetRow::Remove,
+                "-".to_string(),
+                "Remove Sheet Button".to_string(),
+                false,
+            );
+        });
+    }
+}
+
+fn sheet_list(mut sheets: Vec<usize>) -> String {
+    sheets.sort();
+    sheets.dedup();
+    sheets
+        .iter()
+        .map(|sheet| format!("/{sheet}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Like the reference below a coil in a printed schematic, only for what is on another sheet
+fn update_cross_references(
+    mut cmd: Commands,
+    sheets: Res<Sheets>,
+    relay_coils: Query<(Entity, &RelayCoil)>,
+    relay_switches: Query<(Entity, &RelaySwitch)>,
+    mut texts: Query<(Entity, &CrossReference, &mut Text, &mut Transform)>,
+) {
+    let shown = sheets.active + 1;
+    let mut wanted = Vec::new();
+    for (e, relay_coil) in relay_coils.iter() {
+        let contact_sheets = sheets
+            .others
+            .iter()
+            .filter(|parts| parts.relay_switches.iter().any(|s| s.id == relay_coil.id))
+            .map(|parts| parts.sheet)
+            .collect::<Vec<_>>();
+        if contact_sheets.is_empty() {
+            continue;
+        }
+        let on_shown = relay_switches
+            .iter()
+            .any(|(_, relay_switch)| relay_switch.id == relay_coil.id);
+        let all = contact_sheets
+            .into_iter()
+            .chain(on_shown.then_some(shown))
+            .collect();
+        wanted.push((
+            e,
+            relay_coil.bottom,
+            format!("contacts {}", sheet_list(all)),
+        ));
+    }
+    for (e, relay_switch) in relay_switches.iter() {
+        if relay_coils
+            .iter()
+            .any(|(_, relay_coil)| relay_coil.id == relay_switch.id)
+        {
+            continue;
+        }
+        let coil_sheets = sheets
+            .others
+            .iter()
+            .filter(|parts| parts.relay_coils.iter().any(|c| c.id == relay_switch.id))
+            .map(|parts| parts.sheet)
+            .collect::<Vec<_>>();
+        if !coil_sheets.is_empty() {
+            wanted.push((
+                e,
+                relay_switch.bottom,
+                format!("coil {}", sheet_list(coil_sheets)),
+            ));
+        }
+    }
+
+    for (e, reference, mut text, mut transform) in texts.iter_mut() {
+        let Some(index) = wanted
+            .iter()
+            .position(|(target, ..)| *target == reference.0)
+        else {
+            cmd.entity(e).despawn();
+            continue;
+        };
+        let (_, bottom, value) = wanted.swap_remove(index);
+        let translation = (grid_to_world(bottom) - Vec2::new(0., 12.)).extend(6.);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+    for (target, bottom, value) in wanted {
+        cmd.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    value,
+                    TextStyle {
+                        font_size: 12.,
+                        color: CROSS_REFERENCE_COLOR,
+                        ..Default::default()
+                    },
+                ),
+                text_anchor: bevy::sprite::Anchor::TopCenter,
+                transform: Transform::from_translation(
+                    (grid_to_world(bottom) - Vec2::new(0., 12.)).extend(6.),
+                ),
+                ..Default::default()
+            },
+            Name::new("Cross Reference"),
+            CrossReference(target),
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::SimulationScratch;
+
+// A banner at the top of the screen for as long as the positive and negative side are connected
+// The wires that make up the short are drawn red by the glow plugin, which picks the wire materials
+pub struct ShortCircuitPlugin;
+
+impl Plugin for ShortCircuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_short_circuit_banner)
+            .add_systems(Update, update_short_circuit_banner);
+    }
+}
+
+#[derive(Component)]
+struct ShortCircuitBanner;
+
+fn setup_short_circuit_banner(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                left: Val::Percent(40.),
+                padding: UiRect::all(Val::Px(8.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.6, 0., 0., 0.85)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(20),
+            ..Default::default()
+        },
+        Name::new("Short Circuit Banner"),
+        ShortCircuitBanner,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "Short circuit! Nothing is simulated until it is fixed",
+                TextStyle {
+                    font_size: 20.,
+                    color: Color::WHITE,
+                    ..Default::default()
+                },
+            ),
+            Name::new("Short Circuit Banner Text"),
+        ));
+    });
+}
+
+fn update_short_circuit_banner(
+    scratch: Res<SimulationScratch>,
+    mut banner: Query<&mut Visibility, With<ShortCircuitBanner>>,
+) {
+    let visibility = if scratch.solver.short_circuit() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut banner_visibility in banner.iter_mut() {
+        if *banner_visibility != visibility {
+            *banner_visibility = visibility;
+        }
+    }
+}
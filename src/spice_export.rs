@@ -0,0 +1,380 @@
+use std::fmt::Write;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use relay_sim_core::{Circuit, Solver, PULL_IN_SHARE};
+
+use crate::{
+    blocks::Block,
+    component_middle,
+    diode::Diode,
+    fuse::Fuse,
+    load_meter::SupplySettings,
+    multimeter::SUPPLY_VOLTAGE,
+    net_labels::{label_links, NetLabel},
+    platform,
+    save::SavePath,
+    sheets::{SheetParts, Sheets},
+    spawn_toolbar_button,
+    time_switch::{TimeOfDay, TimeSwitch},
+    ButtonSwitch, GridPosition, Light, Power, PowerType, RelayCoil, RelaySwitch, SwitchType,
+    Toolbar, Wire,
+};
+
+// Coils are given some inductance so ngspice shows them switching off like real ones, the simulation does not know any
+const COIL_INDUCTANCE: &str = "100m";
+
+// The SPICE button writes the circuit as a netlist for ngspice next to the save file, circuit.cir for circuit.ron
+// Every supply is a 24 V source, lamps and coils are resistors drawing the currents of the load panel and coils get an inductor on top
+// Contacts are voltage controlled switches, a coil switches its contacts through a behavioral source once it gets 75 % of the supply
+// Buttons and time switches get a control source each, set to how they are right now, DC 1 presses a button and DC 0 lets go of it
+pub struct SpiceExportPlugin;
+
+impl Plugin for SpiceExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_spice_button)
+            .add_systems(Update, export_spice);
+    }
+}
+
+#[derive(Component)]
+struct SpiceButton;
+
+fn setup_spice_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "SPICE", "Export SPICE", SpiceButton);
+    });
+}
+
+// The parts of the sheet that is shown, the other sheets and the blocks already come as SheetParts, also used by the ladder export
+#[derive(SystemParam)]
+pub struct ShownSheet<'w, 's> {
+    wires: Query<'w, 's, &'static Wire>,
+    fuses: Query<'w, 's, &'static Fuse>,
+    time_switches: Query<'w, 's, &'static TimeSwitch>,
+    buttons: Query<'w, 's, &'static ButtonSwitch>,
+    relay_switches: Query<'w, 's, &'static RelaySwitch>,
+    lights: Query<'w, 's, &'static Light>,
+    relay_coils: Query<'w, 's, &'static RelayCoil>,
+    diodes: Query<'w, 's, &'static Diode>,
+    power_sources: Query<'w, 's, (&'static GridPosition, &'static Power)>,
+    net_labels: Query<'w, 's, &'static NetLabel>,
+    blocks: Query<'w, 's, &'static Block>,
+}
+
+impl ShownSheet<'_, '_> {
+    // Fuses that are not blown are exported as wires, like the simulation treats them
+    pub fn parts(&self) -> SheetParts {
+        SheetParts {
+            wires: self
+                .wires
+                .iter()
+                .cloned()
+                .chain(self.fuses.iter().filter(|fuse| !fuse.blown).map(Wire::from))
+                .collect(),
+            time_switches: self.time_switches.iter().cloned().collect(),
+            buttons: self.buttons.iter().cloned().collect(),
+            relay_switches: self.relay_switches.iter().cloned().collect(),
+            lights: self.lights.iter().cloned().collect(),
+            relay_coils: self.relay_coils.iter().cloned().collect(),
+            diodes: self.diodes.iter().cloned().collect(),
+            sources: self
+                .power_sources
+                .iter()
+                .map(|(pos, power)| (*pos, power.0))
+                .collect(),
+            net_labels: self.net_labels.iter().cloned().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn block_parts(&self) -> SheetParts {
+        let mut parts = SheetParts::default();
+        for block in self.blocks.iter() {
+            block.add_parts(&mut parts);
+        }
+        parts
+    }
+}
+
+// Grid points joined into nodes the way the simulation joins them, through wires, net labels and block pins
+pub struct Nodes {
+    solver: Solver,
+    // A point of every node named so far, the negative side of the supply is the ground node 0
+    named: Vec<(GridPosition, String)>,
+}
+
+impl Nodes {
+    pub fn new(parts: &[&SheetParts]) -> Self {
+        let mut circuit = Circuit::default();
+        for parts in parts {
+            circuit.wires.extend(
+                parts
+                    .wires
+                    .iter()
+                    .map(|wire| (wire.first.into(), wire.second.into())),
+            );
+            circuit
+                .links
+                .extend(parts.links.iter().map(|(a, b)| ((*a).into(), (*b).into())));
+            for (pos, typ) in parts.sources.iter() {
+                match typ {
+                    PowerType::Positive => circuit.positive_sources.push((*pos).into()),
+                    PowerType::Negative => circuit.negative_sources.push((*pos).into()),
+                }
+            }
+        }
+        circuit.links.extend(
+            label_links(parts.iter().flat_map(|parts| &parts.net_labels))
+                .into_iter()
+                .map(|(a, b)| (a.into(), b.into())),
+        );
+
+        let mut solver = Solver::default();
+        solver.step(&circuit);
+        let named = parts
+            .iter()
+            .flat_map(|parts| &parts.sources)
+            .filter(|(_, typ)| *typ == PowerType::Negative)
+            .map(|(pos, _)| (*pos, "0".to_string()))
+            .collect();
+        Self { solver, named }
+    }
+
+    // Nodes are named after their first grid point, so they can be found in the schematic
+    pub fn node(&mut self, pos: GridPosition) -> String {
+        if let Some((_, name)) = self
+            .named
+            .iter()
+            .find(|(named, _)| self.solver.same_net(*named, pos))
+        {
+            return name.clone();
+        }
+        let name = format!("x{}y{}", pos.x, pos.y);
+        self.named.push((pos, name.clone()));
+        name
+    }
+}
+
+fn sorted_ids(ids: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut ids = ids.collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+// A contact between top and bottom, a changeover one is two switches sharing its middle
+// Normally closed switches are controlled the other way around, so they are on while the control is at 0
+fn write_contact(
+    netlist: &mut String,
+    nodes: &mut Nodes,
+    name: &str,
+    (top, bottom): (GridPosition, GridPosition),
+    typ: SwitchType,
+    (control, model): (&str, &str),
+) {
+    let (top_node, bottom_node) = (nodes.node(top), nodes.node(bottom));
+    let _ = match typ {
+        SwitchType::NormallyOpen => writeln!(
+            netlist,
+            "S{name} {top_node} {bottom_node} {control} 0 {model}"
+        ),
+        SwitchType::NormallyClosed => writeln!(
+            netlist,
+            "S{name} {top_node} {bottom_node} 0 {control} {model}_nc"
+        ),
+        SwitchType::Changeover => {
+            let common = nodes.node(component_middle(top, bottom));
+            writeln!(
+                netlist,
+                "S{name}a {common} {top_node} {control} 0 {model}\nS{name}b {common} {bottom_node} 0 {control} {model}_nc"
+            )
+        }
+    };
+}
+
+fn netlist(
+    title: &str,
+    parts: &[&SheetParts],
+    time_of_day: &TimeOfDay,
+    settings: &SupplySettings,
+) -> String {
+    let mut nodes = Nodes::new(parts);
+    if nodes.solver.short_circuit() {
+        warn!("The wiring shorts the supply, ngspice will not be able to solve the netlist");
+    }
+    let lamp_resistance = SUPPLY_VOLTAGE * 1000. / settings.lamp_current.max(1) as f32;
+    let coil_resistance = SUPPLY_VOLTAGE * 1000. / settings.coil_current.max(1) as f32;
+    let pull_in = PULL_IN_SHARE * SUPPLY_VOLTAGE;
+
+    let mut netlist = format!("* {title}, exported from relay-sim\n");
+    let _ = writeln!(
+        netlist,
+        ".model contact sw vt=0.5 vh=0.1 ron=0.01 roff=100meg\n.model contact_nc sw vt=-0.5 vh=0.1 ron=0.01 roff=100meg\n.model relay_diode d"
+    );
+    let _ = writeln!(
+        netlist,
+        ".model relay_contact sw vt={pull_in} vh=0.1 ron=0.01 roff=100meg\n.model relay_contact_nc sw vt=-{pull_in} vh=0.1 ron=0.01 roff=100meg\n"
+    );
+
+    let _ = writeln!(netlist, "* Supply");
+    let positive = parts
+        .iter()
+        .flat_map(|parts| &parts.sources)
+        .filter(|(_, typ)| *typ == PowerType::Positive);
+    for (index, (pos, _)) in positive.enumerate() {
+        let node = nodes.node(*pos);
+        let _ = writeln!(netlist, "V{} {node} 0 DC {SUPPLY_VOLTAGE}", index + 1);
+    }
+
+    let _ = writeln!(netlist, "\n* Lamps");
+    let lights = parts.iter().flat_map(|parts| &parts.lights);
+    for (index, light) in lights.enumerate() {
+        let (top, bottom) = (nodes.node(light.top), nodes.node(light.bottom));
+        let _ = writeln!(
+            netlist,
+            "RP{}_{} {top} {bottom} {lamp_resistance:.0}",
+            light.id,
+            index + 1
+        );
+    }
+
+    // Every coil of a relay drives the same control node, it follows the one with the most voltage across it
+    let _ = writeln!(netlist, "\n* Relay coils");
+    let relay_coils = parts
+        .iter()
+        .flat_map(|parts| &parts.relay_coils)
+        .collect::<Vec<_>>();
+    for (index, relay_coil) in relay_coils.iter().enumerate() {
+        let (top, bottom) = (nodes.node(relay_coil.top), nodes.node(relay_coil.bottom));
+        let inner = format!("k{}_{}", relay_coil.id, index + 1);
+        if let Some(timer) = &relay_coil.timer {
+            let _ = writeln!(
+                netlist,
+                "* -K{} is a {} timer relay, the delay is left out",
+                relay_coil.id,
+                timer.describe()
+            );
+        }
+        let _ = writeln!(
+            netlist,
+            "LK{0}_{1} {top} {inner} {COIL_INDUCTANCE}\nRK{0}_{1} {inner} {bottom} {coil_resistance:.0}",
+            relay_coil.id,
+            index + 1
+        );
+    }
+    for id in sorted_ids(relay_coils.iter().map(|relay_coil| relay_coil.id)) {
+        let voltages = relay_coils
+            .iter()
+            .filter(|relay_coil| relay_coil.id == id)
+            .map(|relay_coil| {
+                format!(
+                    "abs(V({})-V({}))",
+                    nodes.node(relay_coil.top),
+                    nodes.node(relay_coil.bottom)
+                )
+            })
+            .reduce(|all, voltage| format!("max({all},{voltage})"))
+            .unwrap_or_default();
+        let _ = writeln!(netlist, "BK{id} k{id} 0 V={voltages}");
+    }
+
+    // Relay contacts switch at the pull in voltage, contacts of relays without a coil stay at rest
+    let _ = writeln!(netlist, "\n* Relay contacts");
+    let relay_switches = parts.iter().flat_map(|parts| &parts.relay_switches);
+    for (index, relay_switch) in relay_switches.enumerate() {
+        let has_coil = relay_coils
+            .iter()
+            .any(|relay_coil| relay_coil.id == relay_switch.id);
+        let control = if has_coil {
+            format!("k{}", relay_switch.id)
+        } else {
+            "0".to_string()
+        };
+        let name = format!("K{}_{}", relay_switch.id, index + 1);
+        write_contact(
+            &mut netlist,
+            &mut nodes,
+            &name,
+            (relay_switch.top, relay_switch.bottom),
+            relay_switch.typ,
+            (&control, "relay_contact"),
+        );
+    }
+
+    let _ = writeln!(netlist, "\n* Buttons");
+    let buttons = parts
+        .iter()
+        .flat_map(|parts| &parts.buttons)
+        .collect::<Vec<_>>();
+    for id in sorted_ids(buttons.iter().map(|button| button.id)) {
+        let _ = writeln!(netlist, "VS{id} s{id} 0 DC 0");
+    }
+    for (index, button) in buttons.iter().enumerate() {
+        let name = format!("S{}_{}", button.id, index + 1);
+        write_contact(
+            &mut netlist,
+            &mut nodes,
+            &name,
+            (button.top, button.bottom),
+            button.typ,
+            (&format!("s{}", button.id), "contact"),
+        );
+    }
+
+    let _ = writeln!(netlist, "\n* Time switches");
+    let time_switches = parts.iter().flat_map(|parts| &parts.time_switches);
+    for (index, time_switch) in time_switches.enumerate() {
+        let number = index + 1;
+        let closed = u8::from(time_switch.is_closed(time_of_day));
+        let _ = writeln!(netlist, "VT{number} t{number} 0 DC {closed}");
+        write_contact(
+            &mut netlist,
+            &mut nodes,
+            &format!("T{number}"),
+            (time_switch.top, time_switch.bottom),
+            SwitchType::NormallyOpen,
+            (&format!("t{number}"), "contact"),
+        );
+    }
+
+    let _ = writeln!(netlist, "\n* Diodes");
+    let diodes = parts.iter().flat_map(|parts| &parts.diodes);
+    for (index, diode) in diodes.enumerate() {
+        let (anode, cathode) = (nodes.node(diode.anode), nodes.node(diode.cathode));
+        let _ = writeln!(netlist, "D{} {anode} {cathode} relay_diode", index + 1);
+    }
+
+    let _ = writeln!(netlist, "\n.tran 1m 200m\n.end");
+    netlist
+}
+
+fn export_spice(
+    interaction: Query<&Interaction, (Changed<Interaction>, With<SpiceButton>)>,
+    save_path: Res<SavePath>,
+    shown: ShownSheet,
+    sheets: Res<Sheets>,
+    time_of_day: Res<TimeOfDay>,
+    settings: Res<SupplySettings>,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let (shown_parts, block_parts) = (shown.parts(), shown.block_parts());
+    let parts = [&shown_parts, &block_parts]
+        .into_iter()
+        .chain(sheets.others.iter())
+        .collect::<Vec<_>>();
+    let path = save_path.0.with_extension("cir");
+    let title = path
+        .file_stem()
+        .map_or("circuit".into(), |stem| stem.to_string_lossy());
+    let netlist = netlist(&title, &parts, &time_of_day, &settings);
+    match platform::export_file(&path, netlist.as_bytes()) {
+        Ok(_) => info!("Exported the netlist to {}", path.display()),
+        Err(e) => error!("Cannot export the netlist to {}: {e}", path.display()),
+    }
+}
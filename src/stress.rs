@@ -0,0 +1,281 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    grid::{GridSize, MAX_GRIDSIZE},
+    headless::Simulation,
+    save::{write_circuit, CircuitData},
+    ButtonSwitch, GridPosition, Light, RelayCoil, RelaySwitch, SwitchType, Wire, NEGATIVE_SOURCE,
+    POSITIVE_SOURCE,
+};
+
+const EXIT_OK: i32 = 0;
+const EXIT_USAGE: i32 = 1;
+const EXIT_CRASHED: i32 = 3;
+
+// Chance per tick that one of the buttons is pressed or let go, like the fuzz test does it
+const TOGGLE_CHANCE: f64 = 0.1;
+// Components stand in columns two points apart and rows four points apart, right of the supply in the first column
+const FIRST_COLUMN: usize = 2;
+const COLUMN_DISTANCE: usize = 2;
+const ROW_DISTANCE: usize = 4;
+
+const USAGE: &str = "usage: relay-sim --stress [--relays <count>] [--wires <count>] [--ticks <count>] [--rounds <count>] [--seed <number>] [--save <circuit file>]";
+
+struct Options<'a> {
+    relays: usize,
+    wires: usize,
+    ticks: usize,
+    rounds: usize,
+    seed: u64,
+    save: Option<&'a str>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options<'_>, String> {
+    let mut options = Options {
+        relays: 100,
+        wires: 300,
+        ticks: 100,
+        rounds: 1,
+        seed: rand::random(),
+        save: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("{value} is not a number for {arg}"))
+        };
+        match arg.as_str() {
+            "--relays" => options.relays = number()?,
+            "--wires" => options.wires = number()?,
+            "--ticks" => options.ticks = number()?,
+            "--rounds" => options.rounds = number()?,
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("{value} is not a seed"))?;
+            }
+            "--save" => options.save = Some(value),
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+// relay-sim --stress --relays 1000 --wires 5000 generates a random circuit of that size and runs it without a window
+// Every relay has a coil and a contact, there are a quarter as many buttons and lamps, all of them wired at random to each other and the supply
+// Each round prints how long building and ticking took and how many nets there were, shorts are expected and only counted
+// Rounds go on with the next seed, a round that panics is reported with its seed so it can be generated again and exits with 3
+// --save writes the circuit of the first round that crashed, or the last one, so it can be opened in the window
+pub fn run(args: &[String]) -> i32 {
+    let options = match parse_options(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+
+    let mut crashed = None;
+    let mut last = None;
+    for round in 0..options.rounds as u64 {
+        let seed = options.seed.wrapping_add(round);
+        let circuit = match generate(&mut StdRng::seed_from_u64(seed), &options) {
+            Ok(circuit) => circuit,
+            Err(e) => {
+                eprintln!("{e}\n{USAGE}");
+                return EXIT_USAGE;
+            }
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_round(circuit.clone(), seed, &options)
+        }));
+        match result {
+            Ok(stats) => println!("seed {seed}: {stats}"),
+            Err(_) => {
+                println!("seed {seed}: crashed");
+                crashed.get_or_insert(circuit);
+                continue;
+            }
+        }
+        last = Some(circuit);
+    }
+
+    if let Some(path) = options.save {
+        if let Some(circuit) = crashed.as_ref().or(last.as_ref()) {
+            match write_circuit(Path::new(path), circuit) {
+                Ok(_) => println!("Wrote the circuit to {path}"),
+                Err(e) => eprintln!("Cannot write the circuit to {path}: {e}"),
+            }
+        }
+    }
+
+    if crashed.is_some() {
+        EXIT_CRASHED
+    } else {
+        EXIT_OK
+    }
+}
+
+fn run_round(circuit: CircuitData, seed: u64, options: &Options) -> String {
+    let ticks = options.ticks;
+    let button_ids = (1..=extra_count(options.relays)).collect::<Vec<_>>();
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let start = Instant::now();
+    let mut simulation = Simulation::new(circuit, &[]);
+    let build = start.elapsed();
+
+    let mut held = vec![false; button_ids.len()];
+    let mut shorted_ticks = 0;
+    let mut slowest = Duration::ZERO;
+    let start = Instant::now();
+    for _ in 0..ticks {
+        if !button_ids.is_empty() && rng.gen_bool(TOGGLE_CHANCE) {
+            let i = rng.gen_range(0..button_ids.len());
+            held[i] = !held[i];
+            simulation.hold_button(button_ids[i], held[i]);
+        }
+        let tick_start = Instant::now();
+        simulation.tick();
+        slowest = slowest.max(tick_start.elapsed());
+        if simulation.short_circuit() {
+            shorted_ticks += 1;
+        }
+    }
+    let run = start.elapsed();
+
+    format!(
+        "built in {:.2} ms, {ticks} ticks in {:.2} ms ({:.3} ms per tick, slowest {:.3} ms), {} nets, shorted for {shorted_ticks} ticks",
+        build.as_secs_f64() * 1000.,
+        run.as_secs_f64() * 1000.,
+        run.as_secs_f64() * 1000. / ticks.max(1) as f64,
+        slowest.as_secs_f64() * 1000.,
+        simulation.net_count(),
+    )
+}
+
+// How many lamps and how many buttons go with the relays
+fn extra_count(relays: usize) -> usize {
+    relays / 4 + 1
+}
+
+fn random_switch_type(rng: &mut StdRng) -> SwitchType {
+    if rng.gen_bool(0.5) {
+        SwitchType::NormallyOpen
+    } else {
+        SwitchType::NormallyClosed
+    }
+}
+
+// Components in a lattice so none of them overlap, wires run from terminal to terminal with one corner
+fn generate(rng: &mut StdRng, options: &Options) -> Result<CircuitData, String> {
+    let extras = extra_count(options.relays);
+    let component_count = 2 * options.relays + 2 * extras;
+    let columns = (component_count as f64).sqrt().ceil().max(1.) as usize;
+    let rows = component_count.div_ceil(columns);
+    let grid_size = GridSize {
+        width: FIRST_COLUMN + columns * COLUMN_DISTANCE,
+        height: (rows * ROW_DISTANCE).max(POSITIVE_SOURCE.y + 1),
+    };
+    if grid_size.width > MAX_GRIDSIZE || grid_size.height > MAX_GRIDSIZE {
+        return Err(format!(
+            "{} relays do not fit on the largest grid",
+            options.relays
+        ));
+    }
+
+    let mut cells = (0..component_count).map(|i| {
+        let bottom = GridPosition {
+            x: FIRST_COLUMN + (i % columns) * COLUMN_DISTANCE,
+            y: (i / columns) * ROW_DISTANCE + 1,
+        };
+        let top = GridPosition {
+            y: bottom.y + 2,
+            ..bottom
+        };
+        (top, bottom)
+    });
+
+    let mut relay_coils = Vec::new();
+    let mut relay_switches = Vec::new();
+    for id in 1..=options.relays {
+        let (top, bottom) = cells.next().unwrap_or_default();
+        relay_coils.push(RelayCoil {
+            id,
+            top,
+            bottom,
+            ..Default::default()
+        });
+        let (top, bottom) = cells.next().unwrap_or_default();
+        relay_switches.push(RelaySwitch {
+            id: rng.gen_range(1..=options.relays),
+            typ: random_switch_type(rng),
+            top,
+            bottom,
+        });
+    }
+    let mut lights = Vec::new();
+    let mut buttons = Vec::new();
+    for id in 1..=extras {
+        let (top, bottom) = cells.next().unwrap_or_default();
+        lights.push(Light { id, top, bottom });
+        let (top, bottom) = cells.next().unwrap_or_default();
+        buttons.push(ButtonSwitch {
+            id,
+            typ: random_switch_type(rng),
+            top,
+            bottom,
+        });
+    }
+
+    let terminals = relay_coils
+        .iter()
+        .flat_map(|relay_coil| [relay_coil.top, relay_coil.bottom])
+        .chain(
+            relay_switches
+                .iter()
+                .flat_map(|relay_switch| [relay_switch.top, relay_switch.bottom]),
+        )
+        .chain(lights.iter().flat_map(|light| [light.top, light.bottom]))
+        .chain(
+            buttons
+                .iter()
+                .flat_map(|button| [button.top, button.bottom]),
+        )
+        .chain([POSITIVE_SOURCE, NEGATIVE_SOURCE])
+        .collect::<Vec<_>>();
+    let mut wires = Vec::new();
+    while wires.len() < options.wires {
+        let first = terminals[rng.gen_range(0..terminals.len())];
+        let second = terminals[rng.gen_range(0..terminals.len())];
+        let corner = GridPosition {
+            x: second.x,
+            y: first.y,
+        };
+        for (first, second) in [(first, corner), (corner, second)] {
+            if first != second && wires.len() < options.wires {
+                wires.push(Wire { first, second });
+            }
+        }
+    }
+
+    Ok(CircuitData::from_parts(
+        grid_size,
+        wires,
+        lights,
+        buttons,
+        relay_coils,
+        relay_switches,
+    ))
+}
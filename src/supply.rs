@@ -0,0 +1,200 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, spawn_toolbar_button, CircuitHandles, CurrentlyPlacing,
+    GridPosition, MainCamera, Power, PowerType, Toolbar, GRIDORIGIN, NEGATIVE_SOURCE,
+    POSITIVE_SOURCE,
+};
+
+// The same distance as between the terminals of the fixed supply
+const TERMINAL_DISTANCE: usize = POSITIVE_SOURCE.y - NEGATIVE_SOURCE.y;
+
+// Next to the fixed supply more supplies can be placed with the supply button, a click puts the positive terminal there and the negative one below
+// Every supply feeds the same circuit, right clicking a terminal of a placed supply removes it again
+pub struct SupplyPlugin;
+
+impl Plugin for SupplyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_supply_button)
+            .add_systems(
+                Update,
+                (
+                    start_placing_supplies,
+                    handle_supply_placement,
+                    add_supply_terminals,
+                    follow_moved_terminals,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Supply {
+    pub positive: GridPosition,
+    pub negative: GridPosition,
+}
+
+// Where the main supply starts out
+impl Default for Supply {
+    fn default() -> Self {
+        Self {
+            positive: POSITIVE_SOURCE,
+            negative: NEGATIVE_SOURCE,
+        }
+    }
+}
+
+#[derive(Component)]
+struct SupplyButton;
+
+pub fn spawn_supply(cmd: &mut Commands, supply: Supply) -> Entity {
+    // Where the grid starts, the terminals are placed like the ones of the main supply
+    cmd.spawn((
+        SpatialBundle::from_transform(Transform::from_xyz(GRIDORIGIN.0, GRIDORIGIN.1, 0.)),
+        Name::new("Supply"),
+        supply,
+    ))
+    .id()
+}
+
+fn setup_supply_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Supply", "Supply", SupplyButton);
+    });
+}
+
+fn start_placing_supplies(
+    supply_button: Query<&Interaction, (Changed<Interaction>, With<SupplyButton>)>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if supply_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        *currently_placing = CurrentlyPlacing::Supply;
+    }
+}
+
+fn handle_supply_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    power_sources: Query<&GridPosition, With<Power>>,
+    supplies: Query<(Entity, &Supply)>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Supply) {
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        let clicked = supplies
+            .iter()
+            .find(|(_, supply)| supply.positive == mouse_grid || supply.negative == mouse_grid);
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(negative_y) = mouse_grid.y.checked_sub(TERMINAL_DISTANCE) else {
+        warn!("A supply does not fit at the edge of the grid");
+        return;
+    };
+    let supply = Supply {
+        positive: mouse_grid,
+        negative: GridPosition {
+            x: mouse_grid.x,
+            y: negative_y,
+        },
+    };
+    // Wires may end there, that is how the supply gets connected
+    if power_sources
+        .iter()
+        .any(|pos| *pos == supply.positive || *pos == supply.negative)
+    {
+        warn!("A supply cannot be placed on top of another one");
+        return;
+    }
+
+    spawn_supply(&mut cmd, supply);
+}
+
+// The terminals are power sources like the fixed ones, so everything that looks at power sources sees them
+fn add_supply_terminals(
+    mut cmd: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    circuit_material: Res<CircuitHandles>,
+    supplies: Query<(Entity, &Supply), Added<Supply>>,
+) {
+    for (e, supply) in supplies.iter() {
+        cmd.entity(e).with_children(|root| {
+            for (typ, pos, color, name) in [
+                (PowerType::Positive, supply.positive, Color::RED, "Positive"),
+                (
+                    PowerType::Negative,
+                    supply.negative,
+                    Color::BLUE,
+                    "Negative",
+                ),
+            ] {
+                root.spawn((
+                    Name::new(format!("Supply {name}")),
+                    Power(typ),
+                    pos,
+                    MaterialMesh2dBundle {
+                        material: materials.add(ColorMaterial::from(color)),
+                        mesh: circuit_material.component_body_mesh.clone(),
+                        transform: Transform::from_xyz(
+                            20. * pos.x as f32 + 10.,
+                            20. * pos.y as f32 + 10.,
+                            5.,
+                        ),
+                        ..Default::default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+// Terminals can be dragged in move mode, the supply remembers where they are for saving
+fn follow_moved_terminals(
+    terminals: Query<(&GridPosition, &Power, &Parent), Changed<GridPosition>>,
+    mut supplies: Query<&mut Supply>,
+) {
+    for (pos, power, parent) in terminals.iter() {
+        let Ok(mut supply) = supplies.get_mut(parent.get()) else {
+            continue;
+        };
+        match power.0 {
+            PowerType::Positive if supply.positive != *pos => supply.positive = *pos,
+            PowerType::Negative if supply.negative != *pos => supply.negative = *pos,
+            _ => {}
+        }
+    }
+}
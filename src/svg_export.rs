@@ -0,0 +1,336 @@
+use std::fmt::Write;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    component_middle, hidden::HiddenRegion, platform, save::SavePath, spawn_toolbar_button,
+    ButtonSwitch, GridPosition, Light, PlacedPositions, Power, PowerType, RelayCoil, RelaySwitch,
+    SwitchType, Toolbar, Wire,
+};
+
+// Pixels per grid cell, the same as on screen
+pub const CELL: f32 = 20.;
+const MARGIN: f32 = 40.;
+const STROKE: &str = "stroke=\"black\" stroke-width=\"2\" fill=\"none\" stroke-linecap=\"round\"";
+
+// The SVG button writes the circuit as a vector drawing next to the save file, circuit.svg for circuit.ron
+// It is drawn from the placed elements with the usual schematic symbols, not from what is rendered, so it stays sharp in worksheets
+// Black boxes are exported as filled rectangles, so exercises keep their secret
+pub struct SvgExportPlugin;
+
+impl Plugin for SvgExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_svg_button)
+            .add_systems(Update, export_svg);
+    }
+}
+
+#[derive(Component)]
+struct SvgButton;
+
+fn setup_svg_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "SVG", "Export SVG", SvgButton);
+    });
+}
+
+// Grid positions to drawing coordinates, with y pointing down like svg expects
+pub struct Drawing {
+    min: GridPosition,
+    max: GridPosition,
+    body: String,
+}
+
+impl Drawing {
+    // Also works for positions outside of what is placed, those just end up in the margin or outside the drawing
+    pub fn point(&self, pos: GridPosition) -> Vec2 {
+        Vec2::new(
+            MARGIN + CELL * (pos.x as f32 - self.min.x as f32),
+            MARGIN + CELL * (self.max.y as f32 - pos.y as f32),
+        )
+    }
+
+    // The svg elements without the surrounding svg tag
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    fn size(&self) -> Vec2 {
+        Vec2::new(
+            2. * MARGIN + CELL * (self.max.x - self.min.x) as f32,
+            2. * MARGIN + CELL * (self.max.y - self.min.y) as f32,
+        )
+    }
+
+    fn line(&mut self, a: Vec2, b: Vec2) {
+        let _ = writeln!(
+            self.body,
+            "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {STROKE}/>",
+            a.x, a.y, b.x, b.y
+        );
+    }
+
+    fn dot(&mut self, pos: GridPosition) {
+        let center = self.point(pos);
+        let _ = writeln!(
+            self.body,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"3\" fill=\"black\"/>",
+            center.x, center.y
+        );
+    }
+
+    fn text(&mut self, at: Vec2, anchor: &str, text: &str) {
+        let _ = writeln!(
+            self.body,
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"{anchor}\" font-family=\"sans-serif\" font-size=\"14\">{}</text>",
+            at.x,
+            at.y,
+            escape(text)
+        );
+    }
+
+    // The symbol is drawn upright around its middle, with the top terminal at y = -20 and the bottom one at y = 20
+    // Turned components get it rotated, the label goes to the right of upright ones and above turned ones
+    fn component(
+        &mut self,
+        top: GridPosition,
+        bottom: GridPosition,
+        common: bool,
+        symbol: &str,
+        label: &str,
+    ) {
+        let center = self.point(component_middle(top, bottom));
+        let turned = top.x != bottom.x;
+        let _ = writeln!(
+            self.body,
+            "  <g transform=\"translate({} {}){}\">\n{symbol}  </g>",
+            center.x,
+            center.y,
+            if turned { " rotate(90)" } else { "" }
+        );
+        if turned {
+            self.text(center + Vec2::new(0., -16.), "middle", label);
+        } else {
+            self.text(center + Vec2::new(16., 5.), "start", label);
+        }
+        self.dot(top);
+        self.dot(bottom);
+        if common {
+            self.dot(component_middle(top, bottom));
+        }
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn symbol_lines(lines: &[(f32, f32, f32, f32)]) -> String {
+    lines
+        .iter()
+        .map(|(x1, y1, x2, y2)| {
+            format!("    <line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" {STROKE}/>\n")
+        })
+        .collect()
+}
+
+// Lamp, a circle with a cross in it
+fn light_symbol() -> String {
+    let mut symbol = symbol_lines(&[
+        (0., -20., 0., -9.),
+        (0., 9., 0., 20.),
+        (-6.4, -6.4, 6.4, 6.4),
+        (-6.4, 6.4, 6.4, -6.4),
+    ]);
+    symbol.push_str(&format!(
+        "    <circle cx=\"0\" cy=\"0\" r=\"9\" {STROKE}/>\n"
+    ));
+    symbol
+}
+
+// Coil, a rectangle across the line
+fn coil_symbol() -> String {
+    let mut symbol = symbol_lines(&[(0., -20., 0., -10.), (0., 10., 0., 20.)]);
+    symbol.push_str(&format!(
+        "    <rect x=\"-15\" y=\"-10\" width=\"30\" height=\"20\" {STROKE}/>\n"
+    ));
+    symbol
+}
+
+// Contacts are drawn at rest, a changeover connects its middle terminal to the bottom one
+fn contact_symbol(typ: SwitchType, pushed: bool) -> String {
+    let mut lines = match typ {
+        SwitchType::NormallyOpen => {
+            vec![(0., -20., 0., -8.), (0., 20., 0., 8.), (0., 8., -9., -9.)]
+        }
+        SwitchType::NormallyClosed => vec![
+            (0., -20., 0., -8.),
+            (0., -8., 8., -8.),
+            (0., 20., 0., 8.),
+            (0., 8., 9., -11.),
+        ],
+        SwitchType::Changeover => vec![
+            (0., -20., 0., -10.),
+            (0., 20., 0., 12.),
+            (0., 12., 8., 12.),
+            (0., 0., 9., 14.),
+        ],
+    };
+    // Push buttons get the actuator, a dashed line from the blade with a bar at its end
+    let mut actuator = String::new();
+    if pushed {
+        lines.push((-18., -5., -18., 5.));
+        actuator = format!(
+            "    <line x1=\"-4\" y1=\"0\" x2=\"-18\" y2=\"0\" {STROKE} stroke-dasharray=\"3 3\"/>\n"
+        );
+    }
+    let mut symbol = symbol_lines(&lines);
+    symbol.push_str(&actuator);
+    symbol
+}
+
+// Everything that ends up in the drawing, also used for the thumbnails of grading reports
+#[derive(SystemParam)]
+pub struct SchematicParts<'w, 's> {
+    placed: PlacedPositions<'w, 's>,
+    wires: Query<'w, 's, &'static Wire>,
+    lights: Query<'w, 's, &'static Light>,
+    buttons: Query<'w, 's, &'static ButtonSwitch>,
+    relay_coils: Query<'w, 's, &'static RelayCoil>,
+    relay_switches: Query<'w, 's, &'static RelaySwitch>,
+    power_sources: Query<'w, 's, (&'static GridPosition, &'static Power)>,
+    hidden_regions: Query<'w, 's, &'static HiddenRegion>,
+}
+
+impl SchematicParts<'_, '_> {
+    // None if nothing is placed, scale shrinks the size the drawing asks for but keeps everything in it
+    pub fn svg(&self, scale: f32) -> Option<String> {
+        let drawing = self.drawing()?;
+        let size = drawing.size();
+        Some(format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{}</svg>\n",
+            size.x * scale,
+            size.y * scale,
+            size.x,
+            size.y,
+            drawing.body
+        ))
+    }
+
+    // None if nothing is placed
+    pub fn drawing(&self) -> Option<Drawing> {
+        let mut positions = self.placed.iter().peekable();
+        let first = positions.peek().copied()?;
+        let (min, max) = positions.fold((first, first), |(min, max), pos| {
+            (
+                GridPosition {
+                    x: min.x.min(pos.x),
+                    y: min.y.min(pos.y),
+                },
+                GridPosition {
+                    x: max.x.max(pos.x),
+                    y: max.y.max(pos.y),
+                },
+            )
+        });
+        let mut drawing = Drawing {
+            min,
+            max,
+            body: String::new(),
+        };
+
+        for wire in self.wires.iter() {
+            let (a, b) = (drawing.point(wire.first), drawing.point(wire.second));
+            drawing.line(a, b);
+            drawing.dot(wire.first);
+            drawing.dot(wire.second);
+        }
+        for light in self.lights.iter() {
+            let label = format!("-P{}", light.id);
+            drawing.component(light.top, light.bottom, false, &light_symbol(), &label);
+        }
+        for button in self.buttons.iter() {
+            let label = format!("-S{}", button.id);
+            drawing.component(
+                button.top,
+                button.bottom,
+                button.typ == SwitchType::Changeover,
+                &contact_symbol(button.typ, true),
+                &label,
+            );
+        }
+        for relay_coil in self.relay_coils.iter() {
+            let label = match &relay_coil.timer {
+                Some(timer) => format!("-K{} {}", relay_coil.id, timer.describe()),
+                None => format!("-K{}", relay_coil.id),
+            };
+            drawing.component(
+                relay_coil.top,
+                relay_coil.bottom,
+                false,
+                &coil_symbol(),
+                &label,
+            );
+        }
+        for relay_switch in self.relay_switches.iter() {
+            let label = format!("-K{}", relay_switch.id);
+            drawing.component(
+                relay_switch.top,
+                relay_switch.bottom,
+                relay_switch.typ == SwitchType::Changeover,
+                &contact_symbol(relay_switch.typ, false),
+                &label,
+            );
+        }
+        for (pos, power) in self.power_sources.iter() {
+            let sign = match power.0 {
+                PowerType::Positive => "+",
+                PowerType::Negative => "-",
+            };
+            let at = drawing.point(*pos);
+            drawing.dot(*pos);
+            drawing.text(at + Vec2::new(-8., 5.), "end", sign);
+        }
+        for region in self.hidden_regions.iter() {
+            let (min, max) = region.corners();
+            let (top_left, bottom_right) = (
+                drawing.point(GridPosition { x: min.x, y: max.y }),
+                drawing.point(GridPosition { x: max.x, y: min.y }),
+            );
+            let size = bottom_right - top_left;
+            let _ = writeln!(
+                drawing.body,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>",
+                top_left.x, top_left.y, size.x, size.y
+            );
+        }
+
+        Some(drawing)
+    }
+}
+
+fn export_svg(
+    interaction: Query<&Interaction, (Changed<Interaction>, With<SvgButton>)>,
+    save_path: Res<SavePath>,
+    schematic: SchematicParts,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let Some(svg) = schematic.svg(1.) else {
+        warn!("There is nothing to export");
+        return;
+    };
+
+    let path = save_path.0.with_extension("svg");
+    match platform::export_file(&path, svg.as_bytes()) {
+        Ok(_) => info!("Exported the schematic to {}", path.display()),
+        Err(e) => error!("Cannot export the schematic to {}: {e}", path.display()),
+    }
+}
@@ -0,0 +1,807 @@
+use std::collections::HashSet;
+
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    blocks::Block,
+    component_middle,
+    device_counts::DeviceCounts,
+    diode::Diode,
+    fuse::Fuse,
+    grid::GridSize,
+    macros::{spawn_step, MacroStep},
+    net_labels::{spawn_net_label, NetLabel},
+    spawn_toolbar_button,
+    time_switch::TimeSwitch,
+    ButtonSwitch, CircuitHandles, GridOrigin, GridPosition, Light, MainSupply, PlacedPositions,
+    Power, PowerType, RelayCoil, RelaySwitch, SwitchType, Toolbar, Wire,
+};
+
+const MAX_EXPRESSION_LENGTH: usize = 80;
+// Grid points between branches in parallel, so the labels of the contacts do not run into the next branch
+const BRANCH_SPACING: usize = 2;
+// The supply sits in the first column, nothing is placed next to it
+const FIRST_COLUMN: usize = 2;
+// Names of the net labels joining the arrangement to the supply, unless the supply is already labeled
+const POSITIVE_LABEL: &str = "L+";
+const NEGATIVE_LABEL: &str = "M";
+
+// The synth button opens an editor for an expression like P1 = (S1 & !K2) | S3, Enter places it wired up on a free area of the grid
+// Buttons and relay contacts in series and parallel switch the lamp or coil on the left, !S2 becomes a normally closed contact
+// AND, OR and NOT work as well, so what the logic panel shows can be typed back in
+// The arrangement is joined to the main supply with net labels, L+ above the contacts and M below the lamp or coil
+pub struct SynthesisPlugin;
+
+impl Plugin for SynthesisPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SynthesisEditor>()
+            .add_systems(Startup, setup_synthesis_editor)
+            .add_systems(PostStartup, setup_synthesis_button)
+            .add_systems(PreUpdate, type_expression.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    open_synthesis_editor,
+                    place_expression,
+                    update_synthesis_editor,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct SynthesisEditor {
+    open: bool,
+    text: String,
+    // Set by Enter, placing it happens with the rest of the circuit in Update
+    submitted: bool,
+    // Why the last expression could not be placed
+    error: Option<String>,
+}
+
+#[derive(Component)]
+struct SynthesisButton;
+
+#[derive(Component)]
+struct SynthesisEditorText;
+
+fn setup_synthesis_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Synth", "Synthesize", SynthesisButton);
+    });
+}
+
+fn setup_synthesis_editor(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                max_width: Val::Px(600.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Synthesis Editor"),
+        SynthesisEditorText,
+    ));
+}
+
+// A contact named like in the schematic, S for buttons and K for relays
+#[derive(Clone)]
+enum Expr {
+    Contact {
+        kind: char,
+        id: usize,
+        negated: bool,
+    },
+    Series(Vec<Expr>),
+    Parallel(Vec<Expr>),
+}
+
+impl Expr {
+    // NOT is pushed down to the contacts, where it turns a normally open one into a normally closed one
+    fn negate(self) -> Self {
+        match self {
+            Expr::Contact { kind, id, negated } => Expr::Contact {
+                kind,
+                id,
+                negated: !negated,
+            },
+            Expr::Series(items) => Expr::Parallel(items.into_iter().map(Expr::negate).collect()),
+            Expr::Parallel(items) => Expr::Series(items.into_iter().map(Expr::negate).collect()),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Name(char, usize),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+// Names like S1 or -K2, in upper or lower case
+fn parse_name(text: &str) -> Option<(char, usize)> {
+    let text = text.strip_prefix('-').unwrap_or(text);
+    let mut chars = text.chars();
+    let kind = chars.next()?.to_ascii_uppercase();
+    let id = chars.as_str().parse().ok()?;
+    Some((kind, id))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            ' ' => continue,
+            '&' | '*' => Token::And,
+            '|' | '+' => Token::Or,
+            '!' | '~' => Token::Not,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ if c == '-' || c.is_ascii_alphanumeric() => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(char::is_ascii_alphanumeric) {
+                    word.push(c);
+                }
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => parse_name(&word)
+                        .map(|(kind, id)| Token::Name(kind, id))
+                        .ok_or_else(|| format!("{word} is not a name like S1 or K2"))?,
+                }
+            }
+            _ => return Err(format!("{c} is not allowed here")),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// OR binds weakest, then AND, then NOT, like everywhere else
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, token: &Token) -> bool {
+        let matches = self.tokens.get(self.next) == Some(token);
+        if matches {
+            self.next += 1;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.and()?];
+        while self.eat(&Token::Or) {
+            items.push(self.and()?);
+        }
+        Ok(flatten(items, false))
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.not()?];
+        while self.eat(&Token::And) {
+            items.push(self.not()?);
+        }
+        Ok(flatten(items, true))
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(self.not()?.negate());
+        }
+        if self.eat(&Token::Open) {
+            let inner = self.or()?;
+            if !self.eat(&Token::Close) {
+                return Err("a ) is missing".to_string());
+            }
+            return Ok(inner);
+        }
+        match self.tokens.get(self.next) {
+            Some(Token::Name(kind, id)) if matches!(kind, 'S' | 'K') => {
+                self.next += 1;
+                Ok(Expr::Contact {
+                    kind: *kind,
+                    id: *id,
+                    negated: false,
+                })
+            }
+            Some(Token::Name(kind, id)) => Err(format!(
+                "{kind}{id} has no contacts, only buttons S and relays K do"
+            )),
+            _ => Err("a button or relay is missing".to_string()),
+        }
+    }
+}
+
+// Series in series and parallel in parallel are one arrangement
+fn flatten(items: Vec<Expr>, series: bool) -> Expr {
+    let mut flat = Vec::new();
+    for item in items {
+        match item {
+            Expr::Series(inner) if series => flat.extend(inner),
+            Expr::Parallel(inner) if !series => flat.extend(inner),
+            item => flat.push(item),
+        }
+    }
+    match flat.len() {
+        1 => flat.remove(0),
+        _ if series => Expr::Series(flat),
+        _ => Expr::Parallel(flat),
+    }
+}
+
+// The lamp or coil on the left of the = and the contacts switching it
+fn parse(text: &str) -> Result<((char, usize), Expr), String> {
+    let (target, expression) = text
+        .split_once('=')
+        .ok_or("the expression needs a lamp or coil on the left, like P1 = S1")?;
+    let target = parse_name(target.trim())
+        .filter(|(kind, _)| matches!(kind, 'P' | 'K'))
+        .ok_or_else(|| format!("{} is not a lamp P or relay K", target.trim()))?;
+
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        next: 0,
+    };
+    let expr = parser.or()?;
+    if parser.next < parser.tokens.len() {
+        return Err("there is something left after the expression".to_string());
+    }
+    Ok((target, expr))
+}
+
+// The parts of an arrangement relative to its output at 0, 0, its input is straight above at 0, height
+struct Layout {
+    width: usize,
+    height: usize,
+    steps: Vec<MacroStep>,
+}
+
+fn shifted(mut steps: Vec<MacroStep>, x: usize, y: usize) -> Vec<MacroStep> {
+    for step in steps.iter_mut() {
+        step.map_positions(|pos| GridPosition {
+            x: pos.x + x,
+            y: pos.y + y,
+        });
+    }
+    steps
+}
+
+fn wire(first: (usize, usize), second: (usize, usize)) -> MacroStep {
+    MacroStep::Wire(Wire {
+        first: GridPosition {
+            x: first.0,
+            y: first.1,
+        },
+        second: GridPosition {
+            x: second.0,
+            y: second.1,
+        },
+    })
+}
+
+// Contacts in series go below each other, branches in parallel next to each other
+// Parallel branches hang one point below a rail and end on a rail one point below the lowest of them, so no wires lie on top of each other
+fn layout(expr: &Expr) -> Layout {
+    match expr {
+        Expr::Contact { kind, id, negated } => {
+            let (top, bottom) = (GridPosition { x: 0, y: 2 }, GridPosition { x: 0, y: 0 });
+            let typ = if *negated {
+                SwitchType::NormallyClosed
+            } else {
+                SwitchType::NormallyOpen
+            };
+            let step = match kind {
+                'S' => MacroStep::Button(ButtonSwitch {
+                    id: *id,
+                    typ,
+                    top,
+                    bottom,
+                }),
+                _ => MacroStep::RelaySwitch(RelaySwitch {
+                    id: *id,
+                    typ,
+                    top,
+                    bottom,
+                }),
+            };
+            Layout {
+                width: 0,
+                height: 2,
+                steps: vec![step],
+            }
+        }
+        Expr::Series(items) => {
+            let mut series = Layout {
+                width: 0,
+                height: 0,
+                steps: Vec::new(),
+            };
+            for item in items.iter().rev() {
+                let item = layout(item);
+                series.width = series.width.max(item.width);
+                series.steps.extend(shifted(item.steps, 0, series.height));
+                series.height += item.height;
+            }
+            series
+        }
+        Expr::Parallel(items) => {
+            let items = items.iter().map(layout).collect::<Vec<_>>();
+            let height = items.iter().map(|item| item.height).max().unwrap_or(0) + 2;
+            let mut parallel = Layout {
+                width: 0,
+                height,
+                steps: Vec::new(),
+            };
+            let mut previous_x = None;
+            for item in items {
+                let x = previous_x.map_or(0, |_| parallel.width + BRANCH_SPACING);
+                let bottom = height - 1 - item.height;
+                parallel.steps.push(wire((x, height), (x, height - 1)));
+                if bottom > 0 {
+                    parallel.steps.push(wire((x, bottom), (x, 0)));
+                }
+                if let Some(previous_x) = previous_x {
+                    parallel.steps.push(wire((previous_x, height), (x, height)));
+                    parallel.steps.push(wire((previous_x, 0), (x, 0)));
+                }
+                parallel.steps.extend(shifted(item.steps, x, bottom));
+                parallel.width = x + item.width;
+                previous_x = Some(x);
+            }
+            parallel
+        }
+    }
+}
+
+fn open_synthesis_editor(
+    synthesis_button: Query<&Interaction, (Changed<Interaction>, With<SynthesisButton>)>,
+    mut editor: ResMut<SynthesisEditor>,
+) {
+    if synthesis_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        editor.open = !editor.open;
+        editor.error = None;
+    }
+}
+
+fn type_expression(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut editor: ResMut<SynthesisEditor>,
+) {
+    if !editor.open {
+        characters.clear();
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.open = false;
+    } else if keys.just_pressed(KeyCode::Return) {
+        editor.submitted = true;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            editor.text.pop();
+        }
+
+        for c in characters.read().map(|event| event.char) {
+            if editor.text.chars().count() < MAX_EXPRESSION_LENGTH && !c.is_control() {
+                editor.text.push(c);
+            }
+        }
+    }
+
+    characters.clear();
+    keys.reset_all();
+}
+
+// Every grid point something already covers, wires and components along their whole length
+fn occupied_points(
+    placed: &PlacedPositions,
+    diodes: &Query<&Diode>,
+    fuses: &Query<&Fuse>,
+    time_switches: &Query<&TimeSwitch>,
+    net_labels: &Query<&NetLabel>,
+) -> HashSet<GridPosition> {
+    let mut occupied = placed.iter().collect::<HashSet<_>>();
+    for wire in placed.wires.iter() {
+        let (min, max) = (
+            GridPosition {
+                x: wire.first.x.min(wire.second.x),
+                y: wire.first.y.min(wire.second.y),
+            },
+            GridPosition {
+                x: wire.first.x.max(wire.second.x),
+                y: wire.first.y.max(wire.second.y),
+            },
+        );
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                occupied.insert(GridPosition { x, y });
+            }
+        }
+    }
+    let terminals = placed
+        .lights
+        .iter()
+        .map(|light| (light.top, light.bottom))
+        .chain(
+            placed
+                .buttons
+                .iter()
+                .map(|button| (button.top, button.bottom)),
+        )
+        .chain(
+            placed
+                .relay_coils
+                .iter()
+                .map(|relay_coil| (relay_coil.top, relay_coil.bottom)),
+        )
+        .chain(
+            placed
+                .relay_switches
+                .iter()
+                .map(|relay_switch| (relay_switch.top, relay_switch.bottom)),
+        )
+        .chain(diodes.iter().map(|diode| (diode.anode, diode.cathode)))
+        .chain(fuses.iter().map(|fuse| (fuse.top, fuse.bottom)))
+        .chain(
+            time_switches
+                .iter()
+                .map(|time_switch| (time_switch.top, time_switch.bottom)),
+        );
+    for (top, bottom) in terminals {
+        occupied.extend([top, component_middle(top, bottom), bottom]);
+    }
+    occupied.extend(net_labels.iter().map(|label| label.pos));
+    occupied
+}
+
+// The lowest and then leftmost place with a free point all around, and room for the labels on the right
+fn free_area(
+    is_free: impl Fn(GridPosition) -> bool,
+    grid_size: &GridSize,
+    width: usize,
+    height: usize,
+) -> Option<GridPosition> {
+    let label_room = BRANCH_SPACING;
+    for y in 1..grid_size.height.saturating_sub(height + 1) {
+        for x in FIRST_COLUMN..grid_size.width.saturating_sub(width + label_room) {
+            let area_free = (x - 1..=x + width + label_room)
+                .all(|x| (y - 1..=y + height + 1).all(|y| is_free(GridPosition { x, y })));
+            if area_free {
+                return Some(GridPosition { x, y });
+            }
+        }
+    }
+    None
+}
+
+fn place_expression(
+    mut cmd: Commands,
+    mut editor: ResMut<SynthesisEditor>,
+    placed: PlacedPositions,
+    (diodes, fuses, time_switches, net_labels, blocks): (
+        Query<&Diode>,
+        Query<&Fuse>,
+        Query<&TimeSwitch>,
+        Query<&NetLabel>,
+        Query<&Block>,
+    ),
+    main_supply: Query<(&GridPosition, &Power), With<MainSupply>>,
+    counts: Res<DeviceCounts>,
+    grid_size: Res<GridSize>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+) {
+    if !editor.submitted {
+        return;
+    }
+    editor.submitted = false;
+
+    let result = parse(&editor.text).and_then(|((kind, id), expr)| {
+        let mut contacts = vec![expr.clone()];
+        while let Some(item) = contacts.pop() {
+            match item {
+                Expr::Contact { kind: 'S', id, .. } if id == 0 || id > counts.buttons => {
+                    return Err(format!("there is no button -S{id}"));
+                }
+                Expr::Contact { id, .. } if id == 0 || id > counts.relays => {
+                    return Err(format!("there is no relay -K{id}"));
+                }
+                Expr::Contact { .. } => {}
+                Expr::Series(items) | Expr::Parallel(items) => contacts.extend(items),
+            }
+        }
+        let (available, taken) = match kind {
+            'P' => (
+                counts.lights,
+                placed.lights.iter().any(|light| light.id == id),
+            ),
+            _ => (
+                counts.relays,
+                placed
+                    .relay_coils
+                    .iter()
+                    .any(|relay_coil| relay_coil.id == id),
+            ),
+        };
+        if id == 0 || id > available {
+            return Err(format!("there is no -{kind}{id}"));
+        }
+        if taken {
+            return Err(format!("-{kind}{id} is already placed"));
+        }
+
+        let network = layout(&expr);
+        let occupied = occupied_points(&placed, &diodes, &fuses, &time_switches, &net_labels);
+        let is_free =
+            |pos: GridPosition| !occupied.contains(&pos) && !blocks.iter().any(|b| b.contains(pos));
+        let origin = free_area(is_free, &grid_size, network.width, network.height + 2)
+            .ok_or("there is no free area large enough on the grid")?;
+        Ok((kind, id, network, origin))
+    });
+    let (kind, id, network, origin) = match result {
+        Ok(placement) => placement,
+        Err(e) => {
+            editor.error = Some(e);
+            return;
+        }
+    };
+
+    // The lamp or coil hangs below the contacts
+    let (top, bottom) = (GridPosition { x: 0, y: 2 }, GridPosition { x: 0, y: 0 });
+    let consumer = match kind {
+        'P' => MacroStep::Light(Light { id, top, bottom }),
+        _ => MacroStep::RelayCoil(RelayCoil {
+            id,
+            top,
+            bottom,
+            ..Default::default()
+        }),
+    };
+    let steps = shifted(network.steps, 0, 2).into_iter().chain([consumer]);
+    let grid_origin = grid_origin.single();
+    for step in shifted(steps.collect(), origin.x, origin.y) {
+        spawn_step(&mut cmd, &circuit_material, grid_origin, step);
+    }
+
+    // The supply keeps the labels it has, otherwise it gets the usual ones
+    let mut label_names = [POSITIVE_LABEL.to_string(), NEGATIVE_LABEL.to_string()];
+    for (pos, power) in main_supply.iter() {
+        let index = match power.0 {
+            PowerType::Positive => 0,
+            PowerType::Negative => 1,
+        };
+        match net_labels.iter().find(|label| label.pos == *pos) {
+            Some(label) => label_names[index] = label.name.clone(),
+            None => {
+                spawn_net_label(
+                    &mut cmd,
+                    NetLabel {
+                        pos: *pos,
+                        name: label_names[index].clone(),
+                    },
+                );
+            }
+        }
+    }
+    let [positive, negative] = label_names;
+    for (y, name) in [(network.height + 2, positive), (0, negative)] {
+        spawn_net_label(
+            &mut cmd,
+            NetLabel {
+                pos: GridPosition {
+                    x: origin.x,
+                    y: origin.y + y,
+                },
+                name,
+            },
+        );
+    }
+
+    info!("Placed {}", editor.text);
+    editor.open = false;
+    editor.error = None;
+}
+
+fn update_synthesis_editor(
+    editor: Res<SynthesisEditor>,
+    mut editor_text: Query<(&mut Text, &mut Visibility), With<SynthesisEditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in editor_text.iter_mut() {
+        if !editor.open {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        *visibility = Visibility::Inherited;
+        text.sections[0].value = format!(
+            "Expression: {}_   (like P1 = (S1 & !K2) | S3, Enter to place, Esc to cancel)",
+            editor.text
+        );
+        if let Some(error) = &editor.error {
+            text.sections[0].value += &format!("\nCannot place it: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use relay_sim_core::{Circuit, Solver, Switch};
+
+    use super::*;
+
+    // Written back like the logic panel would, with every series and parallel arrangement in brackets
+    fn show(expr: &Expr) -> String {
+        let join = |items: &[Expr], operator: &str| {
+            let items = items.iter().map(show).collect::<Vec<_>>();
+            format!("({})", items.join(operator))
+        };
+        match expr {
+            Expr::Contact { kind, id, negated } => {
+                format!("{}{kind}{id}", if *negated { "!" } else { "" })
+            }
+            Expr::Series(items) => join(items, " & "),
+            Expr::Parallel(items) => join(items, " | "),
+        }
+    }
+
+    fn parsed(text: &str) -> ((char, usize), String) {
+        let (target, expr) = parse(text).unwrap();
+        (target, show(&expr))
+    }
+
+    // Whether the expression holds with the buttons and relays in pressed actuated
+    fn holds(expr: &Expr, pressed: &[(char, usize)]) -> bool {
+        match expr {
+            Expr::Contact { kind, id, negated } => pressed.contains(&(*kind, *id)) != *negated,
+            Expr::Series(items) => items.iter().all(|item| holds(item, pressed)),
+            Expr::Parallel(items) => items.iter().any(|item| holds(item, pressed)),
+        }
+    }
+
+    // Whether the placed contacts join the input of the arrangement to its output
+    fn conducts(layout: &Layout, pressed: &[(char, usize)]) -> bool {
+        let mut circuit = Circuit::default();
+        for step in layout.steps.iter() {
+            let (kind, id, typ) = match step {
+                MacroStep::Wire(wire) => {
+                    circuit.wires.push((wire.first.into(), wire.second.into()));
+                    continue;
+                }
+                MacroStep::Button(button) => ('S', button.id, button.typ),
+                MacroStep::RelaySwitch(relay_switch) => ('K', relay_switch.id, relay_switch.typ),
+                _ => panic!("only wires and contacts are placed for the expression"),
+            };
+            let (top, bottom) = step.positions();
+            circuit.switches.push(Switch {
+                top: top.into(),
+                bottom: bottom.into(),
+                typ: typ.into(),
+                actuated: pressed.contains(&(kind, id)),
+            });
+        }
+        let mut solver = Solver::default();
+        solver.step(&circuit);
+        solver.same_net(
+            GridPosition {
+                x: 0,
+                y: layout.height,
+            },
+            GridPosition { x: 0, y: 0 },
+        )
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            parsed("P1 = (S1 & !K2) | S3"),
+            (('P', 1), "((S1 & !K2) | S3)".to_string())
+        );
+        assert_eq!(parsed("P1 = S1 & !K2 | S3"), parsed("P1 = (S1 & !K2) | S3"));
+        assert_eq!(
+            parsed("P2 = S1 | S2 & S3"),
+            (('P', 2), "(S1 | (S2 & S3))".to_string())
+        );
+        assert_eq!(
+            parsed("-k4 = s1 * (s2 + -K3) * S4"),
+            (('K', 4), "(S1 & (S2 | K3) & S4)".to_string())
+        );
+    }
+
+    #[test]
+    fn not_turns_contacts_around() {
+        assert_eq!(parsed("K1 = S1 AND NOT (S2 OR !K3)").1, "(S1 & !S2 & K3)");
+        assert_eq!(parsed("P1 = ~(S1 & S2)").1, "(!S1 | !S2)");
+        assert_eq!(parsed("P1 = !!S1").1, "S1");
+    }
+
+    #[test]
+    fn mistakes() {
+        for text in [
+            "S1",
+            "S1 = S2",
+            "P1 =",
+            "P1 = S1 &",
+            "P1 = (S1 | S2",
+            "P1 = S1 S2",
+            "P1 = S1 )",
+            "P1 = P2",
+            "P1 = S1 $ S2",
+            "P1 = Sx",
+        ] {
+            assert!(parse(text).is_err(), "{text} should not parse");
+        }
+    }
+
+    #[test]
+    fn layout_conducts_like_the_expression() {
+        let contacts = [('S', 1), ('S', 2), ('S', 3), ('K', 1)];
+        for text in [
+            "P1 = S1",
+            "P1 = !S1",
+            "P1 = S1 & S2 & !K1",
+            "P1 = S1 | S2 | K1",
+            "P1 = (S1 & !K1) | S3",
+            "P1 = (S1 | S2) & (!S3 | K1)",
+            "P1 = S1 & (S2 | (S3 & !K1) | !S1) | K1",
+            "P1 = !((S1 | S2) & S3)",
+        ] {
+            let (_, expr) = parse(text).unwrap();
+            let layout = layout(&expr);
+            for combination in 0..1 << contacts.len() {
+                let pressed = contacts
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| combination & (1 << i) != 0)
+                    .map(|(_, contact)| *contact)
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    conducts(&layout, &pressed),
+                    holds(&expr, &pressed),
+                    "{text} with {pressed:?} actuated"
+                );
+            }
+        }
+    }
+
+    // Three branches two points apart, the last one is the highest with S5 below its own parallel arrangement
+    #[test]
+    fn parallel_branches_side_by_side() {
+        let (_, expr) = parse("P1 = (S1 & S2) | K1 | (S3 | S4) & S5").unwrap();
+        let layout = layout(&expr);
+        assert_eq!((layout.width, layout.height), (6, 8));
+    }
+}
@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    history::SimulationHistory,
+    keybindings::{Action, KeyBindings},
+    metadata::CircuitMetadata,
+    save::{CircuitAccess, CircuitData, SavePath},
+    CurrentlyPlacing, IsRunning, SimulationScratch, UIButton,
+};
+
+// Several circuits can be open at once, the tab bar above the grid switches between them (also Ctrl+Tab)
+// Only the shown circuit exists as entities, the others are kept as circuit data together with their simulation history and state
+pub struct TabsPlugin;
+
+impl Plugin for TabsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Tabs>()
+            .add_systems(Startup, setup_tab_bar)
+            .add_systems(Update, (handle_tabs, update_tab_bar).chain());
+    }
+}
+
+#[derive(Default)]
+struct Tab {
+    path: PathBuf,
+    // All three only filled in while the tab is not shown
+    // Relay coils keep whether they are pulled in and how far their timer is in the circuit data, only files leave that out
+    circuit: CircuitData,
+    history: SimulationHistory,
+    simulation: TabSimulation,
+}
+
+// What the simulation keeps outside of the placed elements
+struct TabSimulation {
+    running: bool,
+    // Id, maintained and latched of every button in the left section
+    buttons: Vec<(usize, bool, bool)>,
+    other_sheet_relays: Vec<usize>,
+}
+
+impl Default for TabSimulation {
+    fn default() -> Self {
+        Self {
+            running: true,
+            buttons: Vec::new(),
+            other_sheet_relays: Vec::new(),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    // Counts every tab ever opened, so new tabs do not take the file of an existing one
+    opened: usize,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self {
+            tabs: vec![Tab {
+                path: SavePath::default().0,
+                ..Default::default()
+            }],
+            active: 0,
+            opened: 1,
+        }
+    }
+}
+
+#[derive(Component)]
+struct TabBar;
+
+#[derive(Component, Clone, Copy)]
+enum TabRow {
+    Switch(usize),
+    New,
+    Close,
+}
+
+fn setup_tab_bar(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                ..Default::default()
+            },
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Tab Bar"),
+        TabBar,
+    ));
+}
+
+// Named like the toolbar buttons, so the command palette can switch tabs too
+fn spawn_tab_row(root: &mut ChildBuilder, row: TabRow, label: String, name: String, active: bool) {
+    root.spawn((
+        ButtonBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(3.)),
+                margin: UiRect::all(Val::Px(1.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(if active {
+                Color::rgb(0.3, 0.3, 0.45)
+            } else {
+                Color::rgb(0.15, 0.15, 0.15)
+            }),
+            ..Default::default()
+        },
+        Name::new(name),
+        row,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("Tab Row Text"),
+        ));
+    });
+}
+
+// Everything that belongs to the shown tab
+#[derive(SystemParam)]
+struct ShownTab<'w, 's> {
+    circuit: CircuitAccess<'w, 's>,
+    path: ResMut<'w, SavePath>,
+    history: ResMut<'w, SimulationHistory>,
+    running: ResMut<'w, IsRunning>,
+    ui_buttons: Query<'w, 's, &'static mut UIButton>,
+    scratch: ResMut<'w, SimulationScratch>,
+}
+
+impl ShownTab<'_, '_> {
+    // The circuit that is shown right now is put away into its tab before the other one is placed
+    fn switch(&mut self, tabs: &mut Tabs, target: usize, keep_current: bool) {
+        if keep_current {
+            let current = &mut tabs.tabs[tabs.active];
+            current.circuit = self.circuit.collect();
+            current.history = std::mem::take(&mut *self.history);
+            current.path = self.path.0.clone();
+            current.simulation = TabSimulation {
+                running: self.running.0,
+                buttons: self
+                    .ui_buttons
+                    .iter()
+                    .map(|ui_button| (ui_button.id, ui_button.maintained, ui_button.latched))
+                    .collect(),
+                other_sheet_relays: std::mem::take(&mut self.scratch.other_sheet_relays),
+            };
+        }
+
+        let next = &mut tabs.tabs[target];
+        self.circuit.replace(std::mem::take(&mut next.circuit));
+        *self.history = std::mem::take(&mut next.history);
+        self.path.0 = next.path.clone();
+
+        let simulation = std::mem::take(&mut next.simulation);
+        self.running.0 = simulation.running;
+        for mut ui_button in self.ui_buttons.iter_mut() {
+            let (maintained, latched) = simulation
+                .buttons
+                .iter()
+                .find(|(id, ..)| *id == ui_button.id)
+                .map_or((false, false), |(_, maintained, latched)| {
+                    (*maintained, *latched)
+                });
+            ui_button.has_been_pressed = false;
+            ui_button.maintained = maintained;
+            ui_button.latched = latched;
+        }
+        self.scratch.other_sheet_relays = simulation.other_sheet_relays;
+        self.scratch.active_button_ids.clear();
+        tabs.active = target;
+    }
+}
+
+fn handle_tabs(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    rows: Query<(&Interaction, &TabRow), Changed<Interaction>>,
+    mut tabs: ResMut<Tabs>,
+    mut shown: ShownTab,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let mut pressed = rows
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, row)| *row)
+        .next();
+    if ctrl && bindings.just_pressed(&keys, Action::NextTab) {
+        pressed = Some(TabRow::Switch((tabs.active + 1) % tabs.tabs.len()));
+    }
+    let Some(row) = pressed else {
+        return;
+    };
+
+    match row {
+        TabRow::Switch(target) if target == tabs.active => return,
+        TabRow::Switch(target) => {
+            shown.switch(&mut tabs, target, true);
+        }
+        TabRow::New => {
+            tabs.opened += 1;
+            let path_buf = PathBuf::from(format!("circuit{}.ron", tabs.opened));
+            tabs.tabs.push(Tab {
+                path: path_buf,
+                ..Default::default()
+            });
+            let target = tabs.tabs.len() - 1;
+            shown.switch(&mut tabs, target, true);
+        }
+        TabRow::Close if tabs.tabs.len() == 1 => {
+            warn!("The last tab cannot be closed");
+            return;
+        }
+        // Anything that was not saved is gone with the tab
+        TabRow::Close => {
+            let closed = tabs.active;
+            tabs.tabs.remove(closed);
+            let target = closed.min(tabs.tabs.len() - 1);
+            shown.switch(&mut tabs, target, false);
+        }
+    }
+    // Whatever was about to be placed belongs to the circuit that was left
+    *currently_placing = CurrentlyPlacing::Wire;
+}
+
+fn update_tab_bar(
+    mut cmd: Commands,
+    tabs: Res<Tabs>,
+    metadata: Res<CircuitMetadata>,
+    path: Res<SavePath>,
+    bar: Query<Entity, With<TabBar>>,
+) {
+    if !tabs.is_changed() && !metadata.is_changed() && !path.is_changed() {
+        return;
+    }
+
+    // The title of a circuit names its tab, untitled ones go by their file
+    let label = |title: &str, path: &PathBuf| {
+        if title.is_empty() {
+            path.file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into_owned())
+        } else {
+            title.to_string()
+        }
+    };
+
+    for e in bar.iter() {
+        cmd.entity(e).despawn_descendants().with_children(|root| {
+            for (i, tab) in tabs.tabs.iter().enumerate() {
+                let active = i == tabs.active;
+                let text = if active {
+                    label(&metadata.title, &path.0)
+                } else {
+                    label(tab.circuit.title(), &tab.path)
+                };
+                let name = format!("Switch To {text} Button");
+                spawn_tab_row(root, TabRow::Switch(i), text, name, active);
+            }
+            spawn_tab_row(
+                root,
+                TabRow::New,
+                "+".to_string(),
+                "New Tab Button".to_string(),
+                false,
+            );
+            spawn_tab_row(
+                root,
+                TabRow::Close,
+                "x".to_string(),
+                "Close Tab Button".to_string(),
+                false,
+            );
+        });
+    }
+}
@@ -0,0 +1,318 @@
+use bevy::prelude::*;
+
+use crate::{
+    macros::{EditMacro, MacroStep},
+    spawn_toolbar_button,
+    timer_relay::{RelayTimer, TimerKind},
+    ButtonSwitch, CurrentlyPlacing, GridPosition, Light, RelayCoil, RelaySwitch, SwitchType,
+    Toolbar, Wire,
+};
+
+// Standard control circuits that come with the simulator, the templates button lists them
+// Choosing one stamps it wherever is clicked, placed like a macro so lights, buttons and relays get ids that are still free
+// Every template is fed from its top left corner (+) and its bottom left corner (-), wiring those to a supply is left to the user
+pub struct TemplatesPlugin;
+
+impl Plugin for TemplatesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Templates(builtin_templates()))
+            .add_systems(Startup, setup_template_panel)
+            .add_systems(PostStartup, setup_template_button)
+            .add_systems(
+                Update,
+                (toggle_template_panel, handle_template_rows).chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct Templates(pub Vec<EditMacro>);
+
+#[derive(Component)]
+struct TemplateButton;
+
+#[derive(Component)]
+struct TemplatePanel;
+
+#[derive(Component)]
+struct TemplateRow(usize);
+
+fn at(x: usize, y: usize) -> GridPosition {
+    GridPosition { x, y }
+}
+
+// Wires from point to point, every bend and every tap is the end of a wire
+fn wires(points: &[(usize, usize)]) -> Vec<MacroStep> {
+    points
+        .windows(2)
+        .map(|pair| {
+            MacroStep::Wire(Wire {
+                first: at(pair[0].0, pair[0].1),
+                second: at(pair[1].0, pair[1].1),
+            })
+        })
+        .collect()
+}
+
+// Components stand upright with their top terminal at x, y and the bottom one two points below
+fn light(id: usize, x: usize, y: usize) -> MacroStep {
+    MacroStep::Light(Light {
+        id,
+        top: at(x, y),
+        bottom: at(x, y - 2),
+    })
+}
+
+fn button(id: usize, typ: SwitchType, x: usize, y: usize) -> MacroStep {
+    MacroStep::Button(ButtonSwitch {
+        id,
+        typ,
+        top: at(x, y),
+        bottom: at(x, y - 2),
+    })
+}
+
+fn coil(id: usize, x: usize, y: usize) -> MacroStep {
+    MacroStep::RelayCoil(RelayCoil {
+        id,
+        top: at(x, y),
+        bottom: at(x, y - 2),
+        ..Default::default()
+    })
+}
+
+fn contact(id: usize, typ: SwitchType, x: usize, y: usize) -> MacroStep {
+    MacroStep::RelaySwitch(RelaySwitch {
+        id,
+        typ,
+        top: at(x, y),
+        bottom: at(x, y - 2),
+    })
+}
+
+// -S1 stops, -S2 starts, -K1 holds itself through its own contact and lights -P1
+fn self_holding() -> Vec<MacroStep> {
+    use SwitchType::*;
+    let mut steps = vec![
+        button(1, NormallyClosed, 1, 8),
+        button(2, NormallyOpen, 1, 6),
+        coil(1, 1, 4),
+        contact(1, NormallyOpen, 3, 6),
+        contact(1, NormallyOpen, 5, 8),
+        light(1, 5, 6),
+    ];
+    steps.extend(wires(&[(0, 8), (1, 8), (5, 8)]));
+    steps.extend(wires(&[(1, 6), (3, 6)]));
+    steps.extend(wires(&[(3, 4), (1, 4)]));
+    steps.extend(wires(&[(1, 2), (1, 0)]));
+    steps.extend(wires(&[(5, 4), (5, 0)]));
+    steps.extend(wires(&[(0, 0), (1, 0), (5, 0)]));
+    steps
+}
+
+// Two self-holding relays behind a common stop -S1, each one's normally closed contact keeps the other from pulling in
+// Like the forward and reverse contactors of a motor, -P1 and -P2 show which one is on
+fn interlock() -> Vec<MacroStep> {
+    use SwitchType::*;
+    let mut steps = vec![
+        button(1, NormallyClosed, 1, 12),
+        button(2, NormallyOpen, 1, 10),
+        contact(2, NormallyClosed, 1, 8),
+        coil(1, 1, 6),
+        contact(1, NormallyOpen, 3, 10),
+        button(3, NormallyOpen, 5, 10),
+        contact(1, NormallyClosed, 5, 8),
+        coil(2, 5, 6),
+        contact(2, NormallyOpen, 7, 10),
+        contact(1, NormallyOpen, 9, 12),
+        light(1, 9, 10),
+        contact(2, NormallyOpen, 11, 12),
+        light(2, 11, 10),
+    ];
+    steps.extend(wires(&[(0, 12), (1, 12), (9, 12), (11, 12)]));
+    steps.extend(wires(&[(1, 10), (3, 10), (5, 10), (7, 10)]));
+    steps.extend(wires(&[(3, 8), (1, 8)]));
+    steps.extend(wires(&[(7, 8), (5, 8)]));
+    steps.extend(wires(&[(1, 4), (1, 0)]));
+    steps.extend(wires(&[(5, 4), (5, 0)]));
+    steps.extend(wires(&[(9, 8), (9, 0)]));
+    steps.extend(wires(&[(11, 8), (11, 0)]));
+    steps.extend(wires(&[(0, 0), (1, 0), (5, 0), (9, 0), (11, 0)]));
+    steps
+}
+
+// -K1 is the main contactor, -K2 connects the motor in star and -K3 in delta
+// The on delay timer relay -K4 starts with -K1 and switches from star to delta, -K2 and -K3 lock each other out
+// -P1 shows star and -P2 delta
+fn star_delta() -> Vec<MacroStep> {
+    use SwitchType::*;
+    let mut steps = vec![
+        button(1, NormallyClosed, 1, 14),
+        button(2, NormallyOpen, 1, 12),
+        contact(1, NormallyOpen, 3, 12),
+        coil(1, 1, 10),
+        MacroStep::RelayCoil(RelayCoil {
+            id: 4,
+            top: at(3, 10),
+            bottom: at(3, 8),
+            timer: Some(RelayTimer {
+                kind: TimerKind::OnDelay,
+                delay: 3.,
+                elapsed: None,
+            }),
+            ..Default::default()
+        }),
+        contact(4, NormallyClosed, 5, 10),
+        contact(3, NormallyClosed, 5, 8),
+        coil(2, 5, 6),
+        contact(4, NormallyOpen, 7, 10),
+        contact(2, NormallyClosed, 7, 8),
+        coil(3, 7, 6),
+        contact(3, NormallyOpen, 9, 10),
+        contact(2, NormallyOpen, 11, 14),
+        light(1, 11, 12),
+        contact(3, NormallyOpen, 13, 14),
+        light(2, 13, 12),
+    ];
+    steps.extend(wires(&[(0, 14), (1, 14), (11, 14), (13, 14)]));
+    steps.extend(wires(&[(1, 12), (3, 12)]));
+    steps.extend(wires(&[(1, 10), (3, 10), (5, 10), (7, 10), (9, 10)]));
+    steps.extend(wires(&[(9, 8), (7, 8)]));
+    steps.extend(wires(&[(1, 8), (1, 0)]));
+    steps.extend(wires(&[(3, 8), (3, 0)]));
+    steps.extend(wires(&[(5, 4), (5, 0)]));
+    steps.extend(wires(&[(7, 4), (7, 0)]));
+    steps.extend(wires(&[(11, 10), (11, 0)]));
+    steps.extend(wires(&[(13, 10), (13, 0)]));
+    steps.extend(wires(&[
+        (0, 0),
+        (1, 0),
+        (3, 0),
+        (5, 0),
+        (7, 0),
+        (11, 0),
+        (13, 0),
+    ]));
+    steps
+}
+
+// Two changeover switches joined by their two travellers, either one turns the corridor light -P1 on or off
+fn corridor() -> Vec<MacroStep> {
+    use SwitchType::*;
+    let mut steps = vec![
+        button(1, Changeover, 1, 8),
+        button(2, Changeover, 3, 8),
+        light(1, 5, 7),
+    ];
+    steps.extend(wires(&[(0, 8), (0, 7), (1, 7)]));
+    steps.extend(wires(&[(1, 8), (3, 8)]));
+    steps.extend(wires(&[(1, 6), (3, 6)]));
+    steps.extend(wires(&[(3, 7), (5, 7)]));
+    steps.extend(wires(&[(5, 5), (5, 0), (0, 0)]));
+    steps
+}
+
+fn builtin_templates() -> Vec<EditMacro> {
+    [
+        ("Self-holding circuit", self_holding()),
+        ("Interlock", interlock()),
+        ("Star-delta starter", star_delta()),
+        ("Two-way corridor switching", corridor()),
+    ]
+    .into_iter()
+    .map(|(name, steps)| EditMacro {
+        name: name.to_string(),
+        steps,
+    })
+    .collect()
+}
+
+fn setup_template_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Templates", "Templates", TemplateButton);
+    });
+}
+
+fn setup_template_panel(mut cmd: Commands, templates: Res<Templates>) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                right: Val::Px(320.),
+                width: Val::Px(250.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Template Panel"),
+        TemplatePanel,
+    ))
+    .with_children(|root| {
+        for (i, template) in templates.0.iter().enumerate() {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(3.)),
+                        margin: UiRect::all(Val::Px(1.)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                },
+                Name::new(format!("{} Template", template.name)),
+                TemplateRow(i),
+            ))
+            .with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section(
+                        template.name.clone(),
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                            ..Default::default()
+                        },
+                    ),
+                    Name::new("Template Row Text"),
+                ));
+            });
+        }
+    });
+}
+
+fn toggle_template_panel(
+    template_button: Query<&Interaction, (Changed<Interaction>, With<TemplateButton>)>,
+    mut panel: Query<&mut Visibility, With<TemplatePanel>>,
+) {
+    if !template_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_template_rows(
+    rows: Query<(&Interaction, &TemplateRow), Changed<Interaction>>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction == Interaction::Pressed {
+            *currently_placing = CurrentlyPlacing::Template(row.0);
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::{
+    clock::SimulationClock,
+    component_middle,
+    diode::Diode,
+    fuse::Fuse,
+    grid::GridSize,
+    measure::Measurement,
+    net_labels::NetLabel,
+    routing::{block_cell, block_component, block_wire, find_route},
+    spawn_button, spawn_light, spawn_relay_coil, spawn_relay_switch, spawn_toolbar_button,
+    spawn_wire,
+    time_switch::TimeSwitch,
+    ButtonSwitch, Circuit, CircuitHandles, ComponentComment, GridOrigin, GridPosition, Light,
+    Power, RelayCoil, RelaySwitch, Solver, SwitchType, Toolbar, Wire, WireLabel,
+};
+
+// The tidy button cleans up the measured selection, or the whole circuit without one, without changing what is connected to what
+// Components one column off from a fuller column are moved over, chains of wires are replaced by the route with the fewest corners
+// and the rungs, the columns of components between the rails, are spread out evenly
+pub struct TidyPlugin;
+
+impl Plugin for TidyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_tidy_button)
+            .add_systems(Update, tidy_circuit);
+    }
+}
+
+#[derive(Component)]
+struct TidyButton;
+
+fn setup_tidy_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Tidy", "Tidy", TidyButton);
+    });
+}
+
+#[derive(Clone)]
+enum PlacedComponent {
+    Light(Light),
+    Button(ButtonSwitch),
+    RelayCoil(RelayCoil),
+    RelaySwitch(RelaySwitch),
+}
+
+impl PlacedComponent {
+    fn terminals(&self) -> (GridPosition, GridPosition) {
+        match self {
+            PlacedComponent::Light(light) => (light.top, light.bottom),
+            PlacedComponent::Button(button) => (button.top, button.bottom),
+            PlacedComponent::RelayCoil(relay_coil) => (relay_coil.top, relay_coil.bottom),
+            PlacedComponent::RelaySwitch(relay_switch) => (relay_switch.top, relay_switch.bottom),
+        }
+    }
+
+    // The middle terminal of changeover contacts
+    fn common(&self) -> Option<GridPosition> {
+        let typ = match self {
+            PlacedComponent::Button(button) => button.typ,
+            PlacedComponent::RelaySwitch(relay_switch) => relay_switch.typ,
+            PlacedComponent::Light(_) | PlacedComponent::RelayCoil(_) => return None,
+        };
+        let (top, bottom) = self.terminals();
+        (typ == SwitchType::Changeover).then(|| component_middle(top, bottom))
+    }
+
+    fn terminals_mut(&mut self) -> (&mut GridPosition, &mut GridPosition) {
+        match self {
+            PlacedComponent::Light(light) => (&mut light.top, &mut light.bottom),
+            PlacedComponent::Button(button) => (&mut button.top, &mut button.bottom),
+            PlacedComponent::RelayCoil(relay_coil) => (&mut relay_coil.top, &mut relay_coil.bottom),
+            PlacedComponent::RelaySwitch(relay_switch) => {
+                (&mut relay_switch.top, &mut relay_switch.bottom)
+            }
+        }
+    }
+
+    fn move_to_column(&mut self, x: usize) {
+        let (top, bottom) = self.terminals_mut();
+        top.x = x;
+        bottom.x = x;
+    }
+}
+
+// Everything that can move during tidying
+struct Layout {
+    wires: Vec<(Wire, String)>,
+    components: Vec<PlacedComponent>,
+    // Same order as the components, tidying never adds or removes any
+    comments: Vec<Option<ComponentComment>>,
+    // Power sources and the terminals of fuses, diodes, clocks, time switches and net labels, those stay where they are
+    fixed: Vec<GridPosition>,
+    // Lowest and highest corner of what gets tidied, nothing outside of it is touched
+    area: (GridPosition, GridPosition),
+    grid_size: GridSize,
+}
+
+impl Layout {
+    fn blocked(
+        &self,
+        skip_wires: &[usize],
+        skip_component: Option<usize>,
+    ) -> HashSet<GridPosition> {
+        let mut blocked = HashSet::new();
+        for (index, (wire, _)) in self.wires.iter().enumerate() {
+            if !skip_wires.contains(&index) {
+                block_wire(&mut blocked, wire);
+            }
+        }
+        for (index, component) in self.components.iter().enumerate() {
+            if skip_component != Some(index) {
+                let (top, bottom) = component.terminals();
+                block_component(&mut blocked, top, bottom);
+            }
+        }
+        for pos in &self.fixed {
+            block_cell(&mut blocked, *pos);
+        }
+        blocked
+    }
+
+    fn is_terminal(&self, pos: GridPosition) -> bool {
+        self.fixed.contains(&pos)
+            || self.components.iter().any(|component| {
+                let (top, bottom) = component.terminals();
+                top == pos || bottom == pos || component.common() == Some(pos)
+            })
+    }
+
+    fn inside(&self, pos: GridPosition) -> bool {
+        let (min, max) = self.area;
+        (min.x..=max.x).contains(&pos.x) && (min.y..=max.y).contains(&pos.y)
+    }
+}
+
+fn tidy_circuit(
+    mut cmd: Commands,
+    tidy_button: Query<&Interaction, (Changed<Interaction>, With<TidyButton>)>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+    wires: Query<(Entity, &Wire, Option<&WireLabel>)>,
+    components: Query<(
+        Entity,
+        AnyOf<(&Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&ComponentComment>,
+    )>,
+    power_sources: Query<&GridPosition, With<Power>>,
+    fixed: Query<AnyOf<(&Fuse, &Diode, &SimulationClock, &TimeSwitch, &NetLabel)>>,
+    measurement: Res<Measurement>,
+    grid_size: Res<GridSize>,
+) {
+    if !tidy_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let mut layout = Layout {
+        wires: wires
+            .iter()
+            .map(|(_, wire, label)| {
+                (
+                    wire.clone(),
+                    label.map(|label| label.0.clone()).unwrap_or_default(),
+                )
+            })
+            .collect(),
+        components: components
+            .iter()
+            .filter_map(|(_, (light, button, relay_coil, relay_switch), _)| {
+                light
+                    .cloned()
+                    .map(PlacedComponent::Light)
+                    .or(button.cloned().map(PlacedComponent::Button))
+                    .or(relay_coil.cloned().map(PlacedComponent::RelayCoil))
+                    .or(relay_switch.cloned().map(PlacedComponent::RelaySwitch))
+            })
+            .collect(),
+        comments: components
+            .iter()
+            .map(|(.., comment)| comment.cloned())
+            .collect(),
+        fixed: power_sources
+            .iter()
+            .copied()
+            .chain(
+                fixed
+                    .iter()
+                    .flat_map(|(fuse, diode, clock, time_switch, net_label)| {
+                        [
+                            fuse.map(|fuse| (fuse.top, fuse.bottom)),
+                            diode.map(|diode| (diode.anode, diode.cathode)),
+                            clock.map(|clock| (clock.top, clock.bottom)),
+                            time_switch.map(|time_switch| (time_switch.top, time_switch.bottom)),
+                            net_label.map(|net_label| (net_label.pos, net_label.pos)),
+                        ]
+                    })
+                    .flatten()
+                    .flat_map(|(first, second)| [first, second]),
+            )
+            .collect(),
+        area: match measurement.selection() {
+            Some((a, b)) => (
+                GridPosition {
+                    x: a.x.min(b.x),
+                    y: a.y.min(b.y),
+                },
+                GridPosition {
+                    x: a.x.max(b.x),
+                    y: a.y.max(b.y),
+                },
+            ),
+            None => (
+                GridPosition { x: 0, y: 0 },
+                GridPosition {
+                    x: grid_size.width.saturating_sub(1),
+                    y: grid_size.height.saturating_sub(1),
+                },
+            ),
+        },
+        grid_size: *grid_size,
+    };
+
+    let moved = align_components(&mut layout);
+    let straightened = straighten_wires(&mut layout);
+    let rungs = even_out_rungs(&mut layout);
+    if moved == 0 && straightened == 0 && rungs == 0 {
+        info!("Nothing to tidy");
+        return;
+    }
+
+    // Everything is placed again from the tidied layout, like loading a circuit
+    for e in wires
+        .iter()
+        .map(|(e, ..)| e)
+        .chain(components.iter().map(|(e, ..)| e))
+    {
+        cmd.entity(e).despawn_recursive();
+    }
+
+    let grid_origin = grid_origin.single();
+    for (wire, label) in layout.wires {
+        let entity = spawn_wire(&mut cmd, &circuit_material, grid_origin, wire);
+        if !label.is_empty() {
+            cmd.entity(entity).insert(WireLabel(label));
+        }
+    }
+    for (component, comment) in layout.components.into_iter().zip(layout.comments) {
+        let entity = match component {
+            PlacedComponent::Light(light) => {
+                let label = format!("-P{}", light.id);
+                spawn_light(&mut cmd, &circuit_material, grid_origin, light, label)
+            }
+            PlacedComponent::Button(button) => {
+                let label = format!("-S{}", button.id);
+                spawn_button(&mut cmd, &circuit_material, grid_origin, button, label)
+            }
+            PlacedComponent::RelayCoil(relay_coil) => {
+                let label = format!("-K{}", relay_coil.id);
+                spawn_relay_coil(&mut cmd, &circuit_material, grid_origin, relay_coil, label)
+            }
+            PlacedComponent::RelaySwitch(relay_switch) => {
+                let label = format!("-K{}", relay_switch.id);
+                spawn_relay_switch(
+                    &mut cmd,
+                    &circuit_material,
+                    grid_origin,
+                    relay_switch,
+                    label,
+                )
+            }
+        };
+        if let Some(comment) = comment {
+            cmd.entity(entity).insert(comment);
+        }
+    }
+
+    info!("Tidied circuit, moved {moved} components, straightened {straightened} wire runs and spaced out {rungs} rungs");
+}
+
+// Moves components that are one column next to a column with more components onto it
+// Only possible when every wire on their terminals is horizontal, those wires just get a bit longer or shorter
+fn align_components(layout: &mut Layout) -> usize {
+    let mut moved = 0;
+
+    for index in 0..layout.components.len() {
+        let (top, bottom) = layout.components[index].terminals();
+        // Turned components and changeover contacts, whose middle wire would be left behind, are never moved to another column
+        if top.x != bottom.x
+            || layout.components[index].common().is_some()
+            || !layout.inside(top)
+            || !layout.inside(bottom)
+        {
+            continue;
+        }
+        let column_count = |x: usize| {
+            layout
+                .components
+                .iter()
+                .enumerate()
+                .filter(|(other, component)| *other != index && component.terminals().0.x == x)
+                .count()
+        };
+
+        let own_count = column_count(top.x);
+        let Some(target) = [top.x.checked_sub(1), Some(top.x + 1)]
+            .into_iter()
+            .flatten()
+            .filter(|x| {
+                layout.inside(GridPosition { x: *x, y: top.y }) && column_count(*x) > own_count
+            })
+            .max_by_key(|x| column_count(*x))
+        else {
+            continue;
+        };
+
+        if layout.fixed.contains(&top) || layout.fixed.contains(&bottom) {
+            continue;
+        }
+
+        let attached = layout
+            .wires
+            .iter()
+            .enumerate()
+            .filter(|(_, (wire, _))| {
+                [top, bottom].contains(&wire.first) || [top, bottom].contains(&wire.second)
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        let stays_valid = attached.iter().all(|wire_index| {
+            let wire = &layout.wires[*wire_index].0;
+            let other_end = if [top, bottom].contains(&wire.first) {
+                wire.second
+            } else {
+                wire.first
+            };
+            wire.first.y == wire.second.y && other_end.x != target
+        });
+        let blocked = layout.blocked(&attached, Some(index));
+        let is_free = (bottom.y..=top.y).all(|y| !blocked.contains(&GridPosition { x: target, y }));
+        if !stays_valid || !is_free {
+            continue;
+        }
+
+        layout.components[index].move_to_column(target);
+        for wire_index in attached {
+            let wire = &mut layout.wires[wire_index].0;
+            for end in [&mut wire.first, &mut wire.second] {
+                if *end == top || *end == bottom {
+                    end.x = target;
+                }
+            }
+        }
+        moved += 1;
+    }
+
+    moved
+}
+
+// Replaces chains of wires whose inner points are not connected to anything else by a route with fewer segments
+fn straighten_wires(layout: &mut Layout) -> usize {
+    let mut straightened = 0;
+
+    // Every replacement can make other chains possible, so this starts over until nothing changes
+    'search: for _ in 0..layout.wires.len() {
+        let mut ends: HashMap<GridPosition, usize> = HashMap::new();
+        for (wire, _) in &layout.wires {
+            *ends.entry(wire.first).or_default() += 1;
+            *ends.entry(wire.second).or_default() += 1;
+        }
+        let is_inner = |pos: GridPosition| ends[&pos] == 2 && !layout.is_terminal(pos);
+
+        for start in 0..layout.wires.len() {
+            let chain = wire_chain(&layout.wires, start, is_inner);
+            if chain.wires.len() < 2 || chain.first == chain.last {
+                continue;
+            }
+            let chain_inside = chain.wires.iter().all(|index| {
+                let wire = &layout.wires[*index].0;
+                layout.inside(wire.first) && layout.inside(wire.second)
+            });
+            if !chain_inside {
+                continue;
+            }
+
+            let blocked = layout.blocked(&chain.wires, None);
+            let Some(route) = find_route(chain.first, chain.last, &blocked, &layout.grid_size)
+            else {
+                continue;
+            };
+            if route.len() > chain.wires.len() || !route.iter().all(|pos| layout.inside(*pos)) {
+                continue;
+            }
+
+            let label = chain
+                .wires
+                .iter()
+                .map(|index| layout.wires[*index].1.clone())
+                .find(|label| !label.is_empty())
+                .unwrap_or_default();
+            let mut removed = chain.wires;
+            removed.sort_unstable();
+            for index in removed.into_iter().rev() {
+                layout.wires.remove(index);
+            }
+            // The label ends up on the first segment of the new route
+            for (index, segment) in route.windows(2).enumerate() {
+                layout.wires.push((
+                    Wire {
+                        first: segment[0],
+                        second: segment[1],
+                    },
+                    if index == 0 {
+                        label.clone()
+                    } else {
+                        String::new()
+                    },
+                ));
+            }
+
+            straightened += 1;
+            continue 'search;
+        }
+
+        break;
+    }
+
+    straightened
+}
+
+// Spreads the rungs between the outermost ones evenly, the outermost ones stay where they are
+// Everything between two rungs keeps its distance to the one on its left, nothing is moved when that would change a connection
+fn even_out_rungs(layout: &mut Layout) -> usize {
+    let mut rungs = layout
+        .components
+        .iter()
+        .map(PlacedComponent::terminals)
+        .filter(|(top, bottom)| top.x == bottom.x && layout.inside(*top) && layout.inside(*bottom))
+        .map(|(top, _)| top.x)
+        .collect::<Vec<_>>();
+    rungs.sort_unstable();
+    rungs.dedup();
+    if rungs.len() < 3 {
+        return 0;
+    }
+
+    let (first, last) = (rungs[0], rungs[rungs.len() - 1]);
+    let gaps = rungs.len() - 1;
+    let spaced = (0..=gaps)
+        .map(|index| first + (last - first) * index / gaps)
+        .collect::<Vec<_>>();
+    let moved = rungs
+        .iter()
+        .zip(&spaced)
+        .filter(|(old, new)| old != new)
+        .count();
+    if moved == 0 {
+        return 0;
+    }
+
+    let move_point = |pos: GridPosition| {
+        if !layout.inside(pos) || pos.x < first || pos.x > last {
+            return Some(pos);
+        }
+        let rung = rungs.partition_point(|x| *x <= pos.x) - 1;
+        let x = spaced[rung] + (pos.x - rungs[rung]);
+        // It would end up on or past the next rung
+        if spaced.get(rung + 1).is_some_and(|next| x >= *next) {
+            return None;
+        }
+        Some(GridPosition { x, y: pos.y })
+    };
+
+    if layout
+        .fixed
+        .iter()
+        .any(|pos| move_point(*pos) != Some(*pos))
+    {
+        return 0;
+    }
+    let mut wires = Vec::with_capacity(layout.wires.len());
+    for (wire, label) in &layout.wires {
+        let (Some(first), Some(second)) = (move_point(wire.first), move_point(wire.second)) else {
+            return 0;
+        };
+        if first == second || (first.x != second.x && first.y != second.y) {
+            return 0;
+        }
+        wires.push((Wire { first, second }, label.clone()));
+    }
+    let mut components = layout.components.clone();
+    for component in &mut components {
+        let (top, bottom) = component.terminals_mut();
+        let (Some(new_top), Some(new_bottom)) = (move_point(*top), move_point(*bottom)) else {
+            return 0;
+        };
+        // Turned components can't be stretched
+        if new_top.x.abs_diff(new_bottom.x) != top.x.abs_diff(bottom.x) {
+            return 0;
+        }
+        (*top, *bottom) = (new_top, new_bottom);
+    }
+
+    let before = connections(layout);
+    let old_wires = std::mem::replace(&mut layout.wires, wires);
+    let old_components = std::mem::replace(&mut layout.components, components);
+    if connections(layout) != before {
+        layout.wires = old_wires;
+        layout.components = old_components;
+        return 0;
+    }
+
+    moved
+}
+
+// For every terminal the first terminal it shares a net with, in the order of the components and then the fixed points
+fn connections(layout: &Layout) -> Vec<usize> {
+    let terminals = layout
+        .components
+        .iter()
+        .flat_map(|component| {
+            let (top, bottom) = component.terminals();
+            [Some(top), Some(bottom), component.common()]
+        })
+        .flatten()
+        .chain(layout.fixed.iter().copied())
+        .collect::<Vec<_>>();
+    let circuit = Circuit {
+        wires: layout
+            .wires
+            .iter()
+            .map(|(wire, _)| (wire.first.into(), wire.second.into()))
+            .collect(),
+        ..default()
+    };
+    let mut solver = Solver::default();
+    solver.step(&circuit);
+
+    terminals
+        .iter()
+        .map(|terminal| {
+            terminals
+                .iter()
+                .position(|other| solver.same_net(*other, *terminal))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+struct WireChain {
+    wires: Vec<usize>,
+    first: GridPosition,
+    last: GridPosition,
+}
+
+// Follows the wire in both directions through inner points, those are only connected to the two wires meeting there
+fn wire_chain(
+    wires: &[(Wire, String)],
+    start: usize,
+    is_inner: impl Fn(GridPosition) -> bool,
+) -> WireChain {
+    let mut chain = vec![start];
+    let mut ends = [wires[start].0.first, wires[start].0.second];
+
+    for (side, end) in ends.iter_mut().enumerate() {
+        while is_inner(*end) {
+            let Some((next, (wire, _))) = wires.iter().enumerate().find(|(index, (wire, _))| {
+                !chain.contains(index) && (wire.first == *end || wire.second == *end)
+            }) else {
+                break;
+            };
+
+            *end = if wire.first == *end {
+                wire.second
+            } else {
+                wire.first
+            };
+            if side == 0 {
+                chain.insert(0, next);
+            } else {
+                chain.push(next);
+            }
+        }
+    }
+
+    WireChain {
+        wires: chain,
+        first: ends[0],
+        last: ends[1],
+    }
+}
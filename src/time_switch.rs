@@ -0,0 +1,502 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert_mouse_to_grid, grid::GridSize, grid_to_world, is_running, simulate,
+    spawn_toolbar_button, CircuitHandles, ComponentOrientation, CurrentlyPlacing, GridPosition,
+    MainCamera, Toolbar, Wire,
+};
+
+const MINUTES_PER_DAY: f32 = 24. * 60.;
+// Window edges move in steps of a quarter hour
+const WINDOW_STEP: u32 = 15;
+const MAX_SPEED: f32 = 3600.;
+const OPEN_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+const CLOSED_COLOR: Color = Color::rgb(0.2, 0.6, 0.2);
+
+// Time switches close their contact during the time windows they were placed with, like a timer for staircase or shop window lighting
+// The simulated time of day runs faster than the simulation by the speed factor, the time switch button opens a panel to set both
+// The panel also edits the windows for the next placed switch, right clicking a time switch removes it again
+pub struct TimeSwitchPlugin;
+
+impl Plugin for TimeSwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .init_resource::<DraftWindows>()
+            .add_systems(Startup, setup_time_switch_panel)
+            .add_systems(PostStartup, setup_time_switch_button)
+            .add_systems(
+                FixedUpdate,
+                advance_time_of_day.before(simulate).run_if(is_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_time_switch_panel,
+                    handle_time_switch_rows,
+                    handle_time_switch_placement,
+                    add_time_switch_visuals,
+                    update_time_switch_visuals,
+                    update_time_switch_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct TimeOfDay {
+    // Minutes since midnight
+    pub minutes: f32,
+    // Simulated seconds of the day per second of simulation
+    pub speed: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            minutes: 7. * 60.,
+            speed: 60.,
+        }
+    }
+}
+
+// Start and end in minutes since midnight, a window whose end lies before its start runs over midnight
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl TimeWindow {
+    fn contains(&self, minutes: f32) -> bool {
+        let (start, end) = (self.start as f32, self.end as f32);
+        if start <= end {
+            (start..end).contains(&minutes)
+        } else {
+            minutes >= start || minutes < end
+        }
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct TimeSwitch {
+    pub top: GridPosition,
+    pub bottom: GridPosition,
+    pub windows: Vec<TimeWindow>,
+}
+
+impl TimeSwitch {
+    pub fn is_closed(&self, time_of_day: &TimeOfDay) -> bool {
+        self.windows
+            .iter()
+            .any(|window| window.contains(time_of_day.minutes))
+    }
+}
+
+impl From<&TimeSwitch> for Wire {
+    fn from(time_switch: &TimeSwitch) -> Self {
+        Self {
+            first: time_switch.top,
+            second: time_switch.bottom,
+        }
+    }
+}
+
+// The windows the next placed time switch gets
+#[derive(Resource)]
+struct DraftWindows(Vec<TimeWindow>);
+
+impl Default for DraftWindows {
+    fn default() -> Self {
+        Self(vec![TimeWindow {
+            start: 8 * 60,
+            end: 17 * 60,
+        }])
+    }
+}
+
+#[derive(Component)]
+struct TimeSwitchButton;
+
+#[derive(Component)]
+struct TimeSwitchPanel;
+
+#[derive(Component)]
+struct TimeOfDayText;
+
+#[derive(Component)]
+struct TimeSwitchContact;
+
+#[derive(Component, Clone, Copy)]
+enum TimeSwitchRow {
+    HourBack,
+    HourForward,
+    Slower,
+    Faster,
+    StartEarlier(usize),
+    StartLater(usize),
+    EndEarlier(usize),
+    EndLater(usize),
+    RemoveWindow(usize),
+    AddWindow,
+    Place,
+}
+
+fn format_minutes(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
+
+pub fn spawn_time_switch(cmd: &mut Commands, time_switch: TimeSwitch) -> Entity {
+    cmd.spawn((
+        SpatialBundle::default(),
+        Name::new("Time Switch"),
+        time_switch,
+    ))
+    .id()
+}
+
+fn setup_time_switch_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Timer", "Time Switch", TimeSwitchButton);
+    });
+}
+
+fn setup_time_switch_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                right: Val::Px(10.),
+                width: Val::Px(300.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Time Switch Panel"),
+        TimeSwitchPanel,
+    ));
+}
+
+fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: 16.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    }
+}
+
+fn spawn_time_switch_row(root: &mut ChildBuilder, row: TimeSwitchRow, label: &str) {
+    root.spawn((
+        ButtonBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(3.)),
+                margin: UiRect::all(Val::Px(1.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+            ..Default::default()
+        },
+        Name::new("Time Switch Row"),
+        row,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(label, text_style()),
+            Name::new("Time Switch Row Text"),
+        ));
+    });
+}
+
+fn toggle_time_switch_panel(
+    time_switch_button: Query<&Interaction, (Changed<Interaction>, With<TimeSwitchButton>)>,
+    mut panel: Query<&mut Visibility, With<TimeSwitchPanel>>,
+) {
+    if !time_switch_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_time_switch_rows(
+    rows: Query<(&Interaction, &TimeSwitchRow), Changed<Interaction>>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut draft: ResMut<DraftWindows>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    let shift = |minutes: &mut u32, by: i32| {
+        let day = MINUTES_PER_DAY as i32;
+        *minutes = (*minutes as i32 + by).rem_euclid(day) as u32;
+    };
+
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match *row {
+            TimeSwitchRow::HourBack => {
+                time_of_day.minutes = (time_of_day.minutes - 60.).rem_euclid(MINUTES_PER_DAY);
+            }
+            TimeSwitchRow::HourForward => {
+                time_of_day.minutes = (time_of_day.minutes + 60.).rem_euclid(MINUTES_PER_DAY);
+            }
+            TimeSwitchRow::Slower => time_of_day.speed = (time_of_day.speed / 2.).max(1.),
+            TimeSwitchRow::Faster => time_of_day.speed = (time_of_day.speed * 2.).min(MAX_SPEED),
+            TimeSwitchRow::StartEarlier(i) => shift(&mut draft.0[i].start, -(WINDOW_STEP as i32)),
+            TimeSwitchRow::StartLater(i) => shift(&mut draft.0[i].start, WINDOW_STEP as i32),
+            TimeSwitchRow::EndEarlier(i) => shift(&mut draft.0[i].end, -(WINDOW_STEP as i32)),
+            TimeSwitchRow::EndLater(i) => shift(&mut draft.0[i].end, WINDOW_STEP as i32),
+            TimeSwitchRow::RemoveWindow(i) => {
+                draft.0.remove(i);
+            }
+            TimeSwitchRow::AddWindow => draft.0.push(TimeWindow {
+                start: 18 * 60,
+                end: 22 * 60,
+            }),
+            TimeSwitchRow::Place if draft.0.is_empty() => {
+                warn!("A time switch needs at least one time window");
+            }
+            TimeSwitchRow::Place => *currently_placing = CurrentlyPlacing::TimeSwitch,
+        }
+    }
+}
+
+fn handle_time_switch_placement(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    draft: Res<DraftWindows>,
+    time_switches: Query<(Entity, &TimeSwitch)>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::TimeSwitch) {
+        return;
+    }
+
+    if ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let mouse_grid = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size));
+
+    if mouse_button.just_pressed(MouseButton::Right) {
+        let clicked = mouse_grid.and_then(|pos| {
+            time_switches.iter().find(|(_, time_switch)| {
+                pos.x == time_switch.top.x
+                    && (time_switch.bottom.y..=time_switch.top.y).contains(&pos.y)
+            })
+        });
+        match clicked {
+            Some((e, _)) => cmd.entity(e).despawn_recursive(),
+            None => *currently_placing = CurrentlyPlacing::Wire,
+        }
+        return;
+    }
+
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(mouse_grid) = mouse_grid else {
+        return;
+    };
+    // Always upright, the same way as a component that was not turned
+    let Some((top, bottom)) = ComponentOrientation::Vertical.terminals(mouse_grid, &grid_size)
+    else {
+        warn!("A time switch does not fit at the edge of the grid");
+        return;
+    };
+
+    spawn_time_switch(
+        &mut cmd,
+        TimeSwitch {
+            top,
+            bottom,
+            windows: draft.0.clone(),
+        },
+    );
+}
+
+// Made from the switch itself, so loading only has to spawn the switch
+fn add_time_switch_visuals(
+    mut cmd: Commands,
+    circuit_material: Res<CircuitHandles>,
+    time_switches: Query<(Entity, &TimeSwitch), Added<TimeSwitch>>,
+) {
+    for (e, time_switch) in time_switches.iter() {
+        let top = grid_to_world(time_switch.top);
+        let bottom = grid_to_world(time_switch.bottom);
+        let windows = time_switch
+            .windows
+            .iter()
+            .map(|window| {
+                format!(
+                    "{}-{}",
+                    format_minutes(window.start),
+                    format_minutes(window.end)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        cmd.entity(e).with_children(|root| {
+            for (terminal, name) in [(top, "Time Switch Point1"), (bottom, "Time Switch Point2")] {
+                root.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: circuit_material.wire_point_mesh.clone(),
+                        material: circuit_material.wire_material.clone(),
+                        transform: Transform::from_translation(terminal.extend(2.5)),
+                        ..Default::default()
+                    },
+                    Name::new(name),
+                ));
+            }
+            root.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: OPEN_COLOR,
+                        custom_size: Some(Vec2::new(4., top.y - bottom.y)),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(((top + bottom) / 2.).extend(2.)),
+                    ..Default::default()
+                },
+                Name::new("Time Switch Contact"),
+                TimeSwitchContact,
+            ));
+            root.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        windows,
+                        TextStyle {
+                            font_size: 14.,
+                            color: Color::WHITE,
+                            ..Default::default()
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::CenterLeft,
+                    transform: Transform::from_translation(
+                        ((top + bottom) / 2. + Vec2::new(10., 0.)).extend(5.),
+                    ),
+                    ..Default::default()
+                },
+                Name::new("Time Switch Text"),
+            ));
+        });
+    }
+}
+
+fn update_time_switch_visuals(
+    time_of_day: Res<TimeOfDay>,
+    time_switches: Query<(&TimeSwitch, &Children)>,
+    mut contacts: Query<&mut Sprite, With<TimeSwitchContact>>,
+) {
+    for (time_switch, children) in time_switches.iter() {
+        let color = if time_switch.is_closed(&time_of_day) {
+            CLOSED_COLOR
+        } else {
+            OPEN_COLOR
+        };
+        let mut contacts = contacts.iter_many_mut(children);
+        while let Some(mut sprite) = contacts.fetch_next() {
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
+// Runs on every simulation tick, so pausing stops the time of day as well
+fn advance_time_of_day(time: Res<Time>, mut time_of_day: ResMut<TimeOfDay>) {
+    let minutes = time.delta_seconds() * time_of_day.speed / 60.;
+    time_of_day.minutes = (time_of_day.minutes + minutes).rem_euclid(MINUTES_PER_DAY);
+}
+
+fn update_time_switch_panel(
+    mut cmd: Commands,
+    time_of_day: Res<TimeOfDay>,
+    draft: Res<DraftWindows>,
+    panel: Query<Entity, With<TimeSwitchPanel>>,
+    mut time_text: Query<&mut Text, With<TimeOfDayText>>,
+) {
+    // The rows are made again whenever a window is changed, added or removed
+    if draft.is_changed() {
+        for e in panel.iter() {
+            cmd.entity(e).despawn_descendants().with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section("", text_style()),
+                    Name::new("Time Of Day Text"),
+                    TimeOfDayText,
+                ));
+                root.spawn((NodeBundle::default(), Name::new("Time Of Day Controls")))
+                    .with_children(|root| {
+                        spawn_time_switch_row(root, TimeSwitchRow::HourBack, "-1 h");
+                        spawn_time_switch_row(root, TimeSwitchRow::HourForward, "+1 h");
+                        spawn_time_switch_row(root, TimeSwitchRow::Slower, "Slower");
+                        spawn_time_switch_row(root, TimeSwitchRow::Faster, "Faster");
+                    });
+
+                for (i, window) in draft.0.iter().enumerate() {
+                    root.spawn((NodeBundle::default(), Name::new("Time Window")))
+                        .with_children(|root| {
+                            spawn_time_switch_row(root, TimeSwitchRow::StartEarlier(i), "<");
+                            spawn_time_switch_row(
+                                root,
+                                TimeSwitchRow::StartLater(i),
+                                &format!("{} >", format_minutes(window.start)),
+                            );
+                            spawn_time_switch_row(root, TimeSwitchRow::EndEarlier(i), "<");
+                            spawn_time_switch_row(
+                                root,
+                                TimeSwitchRow::EndLater(i),
+                                &format!("{} >", format_minutes(window.end)),
+                            );
+                            spawn_time_switch_row(root, TimeSwitchRow::RemoveWindow(i), "x");
+                        });
+                }
+
+                spawn_time_switch_row(root, TimeSwitchRow::AddWindow, "Add window");
+                spawn_time_switch_row(root, TimeSwitchRow::Place, "Place time switch");
+            });
+        }
+        // The time is filled in on the next frame, once the text exists
+        return;
+    }
+
+    let value = format!(
+        "Time of day {}, {}x",
+        format_minutes(time_of_day.minutes as u32),
+        time_of_day.speed
+    );
+    for mut text in time_text.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{spawn_toolbar_button, CurrentlyPlacing, RelayCoil, Toolbar};
+
+// In simulated seconds
+const DELAY_STEP: f32 = 0.5;
+const MAX_DELAY: f32 = 60.;
+
+// Timer relays are relay coils with a delay, their contacts switch the set time after the coil is powered (on delay) or let go (off delay)
+// The T button next to every relay places its coil as a timer relay, kind and delay come from the panel behind the timers button
+pub struct TimerRelayPlugin;
+
+impl Plugin for TimerRelayPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RelayTimer>()
+            .register_type::<TimerKind>()
+            .init_resource::<DraftTimer>()
+            .add_systems(Startup, setup_timer_panel)
+            .add_systems(PostStartup, setup_timer_button)
+            .add_systems(
+                Update,
+                (
+                    toggle_timer_panel,
+                    handle_timer_rows,
+                    handle_timer_relay_button_press,
+                    update_timer_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Reflect, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TimerKind {
+    #[default]
+    OnDelay,
+    OffDelay,
+}
+
+#[derive(Reflect, Clone, Default, Serialize, Deserialize)]
+pub struct RelayTimer {
+    pub kind: TimerKind,
+    // In simulated seconds
+    pub delay: f32,
+    // Since the coil was powered for on delay or let go for off delay, part of the simulation state
+    #[serde(skip)]
+    pub elapsed: Option<f32>,
+}
+
+impl RelayTimer {
+    // Whether the contacts are switched after this tick
+    pub fn tick(&mut self, powered: bool, delta: f32) -> bool {
+        match self.kind {
+            TimerKind::OnDelay => {
+                self.elapsed = powered.then(|| self.elapsed.unwrap_or(0.) + delta);
+                self.elapsed.is_some_and(|elapsed| elapsed >= self.delay)
+            }
+            TimerKind::OffDelay => {
+                self.elapsed = if powered {
+                    Some(0.)
+                } else {
+                    self.elapsed
+                        .map(|elapsed| elapsed + delta)
+                        .filter(|elapsed| *elapsed < self.delay)
+                };
+                self.elapsed.is_some()
+            }
+        }
+    }
+
+    // Short form shown next to the coil
+    pub fn describe(&self) -> String {
+        match self.kind {
+            TimerKind::OnDelay => format!("TON {:.1}s", self.delay),
+            TimerKind::OffDelay => format!("TOF {:.1}s", self.delay),
+        }
+    }
+}
+
+// Spawned next to the coil button of every relay in the ui section
+#[derive(Component)]
+pub struct TimerRelaySelect {
+    pub id: usize,
+}
+
+// Kind and delay for the next placed timer relay
+#[derive(Resource)]
+struct DraftTimer(RelayTimer);
+
+impl Default for DraftTimer {
+    fn default() -> Self {
+        Self(RelayTimer {
+            kind: TimerKind::OnDelay,
+            delay: 2.,
+            elapsed: None,
+        })
+    }
+}
+
+#[derive(Component)]
+struct TimerButton;
+
+#[derive(Component)]
+struct TimerPanel;
+
+#[derive(Component, Clone, Copy)]
+enum TimerRow {
+    Kind,
+    Shorter,
+    Longer,
+}
+
+fn setup_timer_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Timers", "Timer Relays", TimerButton);
+    });
+}
+
+fn setup_timer_panel(mut cmd: Commands) {
+    let text_style = TextStyle {
+        font_size: 16.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..Default::default()
+    };
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.),
+                right: Val::Px(10.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Timer Panel"),
+        TimerPanel,
+    ))
+    .with_children(|root| {
+        for row in [TimerRow::Kind, TimerRow::Shorter, TimerRow::Longer] {
+            root.spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(3.)),
+                        margin: UiRect::all(Val::Px(1.)),
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::rgb(0.15, 0.15, 0.15)),
+                    ..Default::default()
+                },
+                Name::new("Timer Row"),
+                row,
+            ))
+            .with_children(|root| {
+                root.spawn((
+                    TextBundle::from_section("", text_style.clone()),
+                    Name::new("Timer Row Text"),
+                ));
+            });
+        }
+    });
+}
+
+fn toggle_timer_panel(
+    timer_button: Query<&Interaction, (Changed<Interaction>, With<TimerButton>)>,
+    mut panel: Query<&mut Visibility, With<TimerPanel>>,
+) {
+    if !timer_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *visibility == Visibility::Hidden {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn handle_timer_rows(
+    rows: Query<(&Interaction, &TimerRow), Changed<Interaction>>,
+    mut draft: ResMut<DraftTimer>,
+) {
+    for (interaction, row) in rows.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let timer = &mut draft.0;
+        match row {
+            TimerRow::Kind => {
+                timer.kind = match timer.kind {
+                    TimerKind::OnDelay => TimerKind::OffDelay,
+                    TimerKind::OffDelay => TimerKind::OnDelay,
+                }
+            }
+            TimerRow::Shorter => timer.delay = (timer.delay - DELAY_STEP).max(DELAY_STEP),
+            TimerRow::Longer => timer.delay = (timer.delay + DELAY_STEP).min(MAX_DELAY),
+        }
+    }
+}
+
+// Like the coil buttons, every relay only has one coil
+fn handle_timer_relay_button_press(
+    interaction: Query<(&Interaction, &TimerRelaySelect), Changed<Interaction>>,
+    placed_relay_coils: Query<&RelayCoil>,
+    draft: Res<DraftTimer>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    for (interaction, timer_relay_select) in interaction.iter() {
+        if *interaction != Interaction::Pressed
+            || placed_relay_coils
+                .iter()
+                .any(|relay_coil| relay_coil.id == timer_relay_select.id)
+        {
+            continue;
+        }
+
+        *currently_placing = CurrentlyPlacing::RelayCoil {
+            id: timer_relay_select.id,
+            label: format!("-K{}", timer_relay_select.id),
+            timer: Some(draft.0.clone()),
+        };
+    }
+}
+
+fn update_timer_panel(
+    draft: Res<DraftTimer>,
+    rows: Query<(&TimerRow, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !draft.is_changed() {
+        return;
+    }
+
+    for (row, children) in rows.iter() {
+        let Some(mut text) = children.first().and_then(|e| texts.get_mut(*e).ok()) else {
+            continue;
+        };
+        text.sections[0].value = match row {
+            TimerRow::Kind => match draft.0.kind {
+                TimerKind::OnDelay => "On delay (click for off delay)".to_string(),
+                TimerKind::OffDelay => "Off delay (click for on delay)".to_string(),
+            },
+            TimerRow::Shorter => "-".to_string(),
+            TimerRow::Longer => format!("{:.1} s +", draft.0.delay),
+        };
+    }
+}
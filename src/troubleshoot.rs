@@ -0,0 +1,444 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::seq::SliceRandom;
+
+use crate::{
+    component_contains, component_middle, component_terminals, convert_mouse_to_grid,
+    grid::GridSize, grid_to_world, hidden::HiddenRegion, oriented, spawn_toolbar_button,
+    wire_contains, ButtonSwitch, CurrentlyPlacing, Faulty, GridPosition, Light, MainCamera,
+    RelayCoil, RelaySwitch, SimulationScratch, Toolbar, Visited, Wire,
+};
+
+const FAULT_COLOR: Color = Color::rgb(0.9, 0.3, 0.9);
+
+// The fault button secretly breaks one wire, contact or coil of the circuit, or welds a contact shut
+// With the instructor button clicks break elements by hand instead, clicking a contact again welds it and once more repairs it
+// Leaving the instructor mode hides the faults and starts the exercise with all of them
+// The probe button only turns on the probe, for measuring on circuits with hidden parts
+// While troubleshooting, the probe shows the potential of the grid point under the cursor and clicking an element names it as a fault
+pub struct TroubleshootPlugin;
+
+impl Plugin for TroubleshootPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Troubleshooting>()
+            .add_systems(Startup, setup_troubleshoot_text)
+            .add_systems(PostStartup, setup_fault_button)
+            .add_systems(
+                Update,
+                (
+                    handle_fault_button,
+                    inject_faults,
+                    handle_probe_button,
+                    handle_diagnosis,
+                    show_probe,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct Troubleshooting {
+    // The elements that are still broken and how, empty when there is no exercise running
+    faults: Vec<(Entity, String)>,
+    wrong_guesses: usize,
+}
+
+#[derive(Component)]
+struct FaultButton;
+
+#[derive(Component)]
+struct InstructorButton;
+
+#[derive(Component)]
+struct ProbeButton;
+
+#[derive(Component)]
+struct TroubleshootText;
+
+fn setup_fault_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Fault", "Inject Fault", FaultButton);
+        spawn_toolbar_button(root, "Instructor", "Instructor", InstructorButton);
+        spawn_toolbar_button(root, "Probe", "Probe", ProbeButton);
+    });
+}
+
+fn setup_troubleshoot_text(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Troubleshoot Text"),
+        TroubleshootText,
+    ));
+}
+
+// Lamps cannot break, None for them
+fn describe_fault(
+    (wire, _, button, relay_coil, relay_switch): (
+        Option<&Wire>,
+        Option<&Light>,
+        Option<&ButtonSwitch>,
+        Option<&RelayCoil>,
+        Option<&RelaySwitch>,
+    ),
+    faulty: Faulty,
+) -> Option<String> {
+    let contact = match faulty {
+        Faulty::Broken => "stuck open",
+        Faulty::Welded => "welded",
+    };
+    if let Some(wire) = wire {
+        Some(format!(
+            "broken wire from {}, {} to {}, {}",
+            wire.first.x, wire.first.y, wire.second.x, wire.second.y
+        ))
+    } else if let Some(button) = button {
+        Some(format!("{contact} contact on -S{}", button.id))
+    } else if let Some(relay_switch) = relay_switch {
+        Some(format!("{contact} contact on -K{}", relay_switch.id))
+    } else {
+        relay_coil.map(|relay_coil| format!("burned coil on -K{}", relay_coil.id))
+    }
+}
+
+// Components win over wires, their terminals are usually also the ends of wires
+fn element_at(
+    elements: &Query<(
+        Entity,
+        AnyOf<(&Wire, &Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&Faulty>,
+    )>,
+    mouse_grid: GridPosition,
+) -> Option<Entity> {
+    elements
+        .iter()
+        .find(|(_, (_, light, button, relay_coil, relay_switch), _)| {
+            component_terminals((*light, *button, *relay_coil, *relay_switch))
+                .is_some_and(|(top, bottom)| component_contains(top, bottom, mouse_grid))
+        })
+        .or_else(|| {
+            elements.iter().find(|(_, (wire, ..), _)| {
+                wire.is_some_and(|wire| wire_contains(wire, &mouse_grid))
+            })
+        })
+        .map(|(e, ..)| e)
+}
+
+// Pressing the button again while troubleshooting gives up and tells where the faults were
+fn handle_fault_button(
+    mut cmd: Commands,
+    fault_button: Query<&Interaction, (Changed<Interaction>, With<FaultButton>)>,
+    mut troubleshooting: ResMut<Troubleshooting>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    scratch: Res<SimulationScratch>,
+    elements: Query<(
+        Entity,
+        AnyOf<(&Wire, &Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&Faulty>,
+    )>,
+) {
+    if !fault_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+        || matches!(*currently_placing, CurrentlyPlacing::InjectFault)
+    {
+        return;
+    }
+
+    if !troubleshooting.faults.is_empty() {
+        for (e, description) in troubleshooting.faults.drain(..) {
+            if let Some(mut entity) = cmd.get_entity(e) {
+                entity.remove::<Faulty>();
+            }
+            info!("Gave up, there was a {description}");
+        }
+        *currently_placing = CurrentlyPlacing::Wire;
+        return;
+    }
+
+    // Only wires that currently carry a potential are broken, a fault on a dead branch could never be found
+    let is_live = |pos: GridPosition| scratch.solver.mark(pos) != Visited::Unvisited;
+    let mut rng = rand::thread_rng();
+    let faults = elements
+        .iter()
+        .filter_map(|(e, element, _)| {
+            let (wire, _, button, _, relay_switch) = element;
+            if wire.is_some_and(|wire| !is_live(wire.first) && !is_live(wire.second)) {
+                return None;
+            }
+            let faulty = if button.is_some() || relay_switch.is_some() {
+                *[Faulty::Broken, Faulty::Welded].choose(&mut rng)?
+            } else {
+                Faulty::Broken
+            };
+            Some((e, faulty, describe_fault(element, faulty)?))
+        })
+        .collect::<Vec<_>>();
+
+    let Some((e, faulty, description)) = faults.choose(&mut rng).cloned() else {
+        warn!("There is nothing in the circuit that could be broken");
+        return;
+    };
+
+    cmd.entity(e).insert(faulty);
+    *troubleshooting = Troubleshooting {
+        faults: vec![(e, description)],
+        wrong_guesses: 0,
+    };
+    *currently_placing = CurrentlyPlacing::Troubleshoot;
+}
+
+// The faults stay visible while placing them, pressing the button again or right clicking starts the exercise
+fn inject_faults(
+    mut cmd: Commands,
+    instructor_button: Query<&Interaction, (Changed<Interaction>, With<InstructorButton>)>,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut troubleshooting: ResMut<Troubleshooting>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    elements: Query<(
+        Entity,
+        AnyOf<(&Wire, &Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&Faulty>,
+    )>,
+    mut gizmos: Gizmos,
+) {
+    let pressed = instructor_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if !matches!(*currently_placing, CurrentlyPlacing::InjectFault) {
+        if pressed && troubleshooting.faults.is_empty() {
+            *currently_placing = CurrentlyPlacing::InjectFault;
+        }
+        return;
+    }
+
+    let over_ui = ui_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if pressed || (!over_ui && mouse_button.just_pressed(MouseButton::Right)) {
+        let faults = elements
+            .iter()
+            .filter_map(|(e, element, faulty)| Some((e, describe_fault(element, *faulty?)?)))
+            .collect::<Vec<_>>();
+        if faults.is_empty() {
+            *currently_placing = CurrentlyPlacing::Wire;
+            return;
+        }
+        info!("Started troubleshooting with {} faults", faults.len());
+        *troubleshooting = Troubleshooting {
+            faults,
+            wrong_guesses: 0,
+        };
+        *currently_placing = CurrentlyPlacing::Troubleshoot;
+        return;
+    }
+
+    for (_, (wire, light, button, relay_coil, relay_switch), faulty) in elements.iter() {
+        let Some(faulty) = faulty else {
+            continue;
+        };
+        if let Some(wire) = wire {
+            gizmos.line_2d(
+                grid_to_world(wire.first),
+                grid_to_world(wire.second),
+                FAULT_COLOR,
+            );
+        } else if let Some((top, bottom)) =
+            component_terminals((light, button, relay_coil, relay_switch))
+        {
+            let middle = grid_to_world(component_middle(top, bottom));
+            let size = oriented(top, bottom, Vec2::new(28., 64.));
+            gizmos.rect_2d(middle, 0., size, FAULT_COLOR);
+            // Welded contacts get a second outline
+            if *faulty == Faulty::Welded {
+                gizmos.rect_2d(middle, 0., size + Vec2::splat(8.), FAULT_COLOR);
+            }
+        }
+    }
+
+    if over_ui || !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(e) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .and_then(|mouse_grid| element_at(&elements, mouse_grid))
+    else {
+        return;
+    };
+    let Ok((_, element, faulty)) = elements.get(e) else {
+        return;
+    };
+    if describe_fault(element, Faulty::Broken).is_none() {
+        return;
+    }
+    let (_, _, button, _, relay_switch) = element;
+
+    // Only contacts can be welded
+    let is_contact = button.is_some() || relay_switch.is_some();
+    match faulty {
+        None => {
+            cmd.entity(e).insert(Faulty::Broken);
+        }
+        Some(Faulty::Broken) if is_contact => {
+            cmd.entity(e).insert(Faulty::Welded);
+        }
+        Some(_) => {
+            cmd.entity(e).remove::<Faulty>();
+        }
+    }
+}
+
+// Toggles the probe on its own, a running fault exercise keeps the probe on
+fn handle_probe_button(
+    probe_button: Query<&Interaction, (Changed<Interaction>, With<ProbeButton>)>,
+    troubleshooting: Res<Troubleshooting>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+) {
+    if !probe_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+        || !troubleshooting.faults.is_empty()
+        || matches!(*currently_placing, CurrentlyPlacing::InjectFault)
+    {
+        return;
+    }
+
+    *currently_placing = if matches!(*currently_placing, CurrentlyPlacing::Troubleshoot) {
+        CurrentlyPlacing::Wire
+    } else {
+        CurrentlyPlacing::Troubleshoot
+    };
+}
+
+fn handle_diagnosis(
+    mut cmd: Commands,
+    mouse_button: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    ui_interactions: Query<&Interaction>,
+    mut currently_placing: ResMut<CurrentlyPlacing>,
+    mut troubleshooting: ResMut<Troubleshooting>,
+    elements: Query<(
+        Entity,
+        AnyOf<(&Wire, &Light, &ButtonSwitch, &RelayCoil, &RelaySwitch)>,
+        Option<&Faulty>,
+    )>,
+) {
+    if !matches!(*currently_placing, CurrentlyPlacing::Troubleshoot)
+        || !mouse_button.just_pressed(MouseButton::Left)
+        || ui_interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+        || troubleshooting.faults.is_empty()
+    {
+        return;
+    }
+
+    let Some(guess) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .and_then(|mouse_grid| element_at(&elements, mouse_grid))
+    else {
+        return;
+    };
+
+    let Some(index) = troubleshooting
+        .faults
+        .iter()
+        .position(|(fault, _)| *fault == guess)
+    else {
+        troubleshooting.wrong_guesses += 1;
+        return;
+    };
+
+    let (fault, description) = troubleshooting.faults.remove(index);
+    if let Some(mut entity) = cmd.get_entity(fault) {
+        entity.remove::<Faulty>();
+    }
+    if !troubleshooting.faults.is_empty() {
+        info!(
+            "Found the {description}, {} faults are left",
+            troubleshooting.faults.len()
+        );
+        return;
+    }
+    info!(
+        "Found the {description}, all faults after {} wrong guesses",
+        troubleshooting.wrong_guesses
+    );
+    *currently_placing = CurrentlyPlacing::Wire;
+}
+
+fn show_probe(
+    troubleshooting: Res<Troubleshooting>,
+    currently_placing: Res<CurrentlyPlacing>,
+    scratch: Res<SimulationScratch>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    hidden_regions: Query<&HiddenRegion>,
+    mut troubleshoot_text: Query<(&mut Text, &mut Visibility), With<TroubleshootText>>,
+) {
+    let (mut text, mut visibility) = troubleshoot_text.single_mut();
+
+    if !matches!(*currently_placing, CurrentlyPlacing::Troubleshoot) {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    let reading = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+        .map(|mouse_grid| {
+            // Only the terminals on the border of a black box can be measured
+            if hidden_regions.iter().any(|region| region.hides(mouse_grid)) {
+                return format!("Probe {}, {}: hidden", mouse_grid.x, mouse_grid.y);
+            }
+            let value = match scratch.solver.mark(mouse_grid) {
+                Visited::Positive => "+",
+                Visited::Negative => "-",
+                Visited::Unvisited => "no potential",
+            };
+            format!("Probe {}, {}: {value}", mouse_grid.x, mouse_grid.y)
+        })
+        .unwrap_or_else(|| "Probe: off the grid".to_string());
+
+    *visibility = Visibility::Inherited;
+    text.sections[0].value = if !troubleshooting.faults.is_empty() {
+        format!(
+            "{reading}   Faults left: {}   Wrong guesses: {}   Click a faulty element, press Fault to give up",
+            troubleshooting.faults.len(),
+            troubleshooting.wrong_guesses
+        )
+    } else {
+        reading
+    };
+}
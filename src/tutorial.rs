@@ -0,0 +1,353 @@
+use bevy::prelude::*;
+use relay_sim_core::{Circuit, Solver};
+
+use crate::{
+    dock::PaletteHighlight, grid_to_world, spawn_toolbar_button, ButtonSelect, ButtonSwitch,
+    GridPosition, IsRunning, Light, MainSupply, Power, PowerType, RunButton, SwitchType, Toolbar,
+    UIButton, UILight, Wire,
+};
+
+const TUTORIAL_COLOR: Color = Color::rgb(1., 0.8, 0.1);
+const FRAME_WIDTH: f32 = 3.;
+// The button goes here, so its top ends up in the row of the positive terminal
+const BUTTON_CELL: GridPosition = GridPosition { x: 3, y: 18 };
+
+// The tutorial button walks through placing a button and a lamp and wiring them to the supply, then lighting the lamp
+// The palette button and the grid points for the current step are marked, a step is done once the circuit has what it asks for
+// Taking something away again goes back to the step that placed it, only lighting the lamp finishes the tutorial for good
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TutorialStep>()
+            .add_systems(Startup, setup_tutorial_panel)
+            .add_systems(PostStartup, setup_tutorial_button)
+            .add_systems(
+                Update,
+                (
+                    toggle_tutorial,
+                    advance_tutorial,
+                    update_tutorial_panel,
+                    mark_palette_button,
+                    mark_grid_points,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+enum TutorialStep {
+    #[default]
+    Off,
+    PlaceButton,
+    PlaceLamp,
+    ConnectLamp,
+    WirePositive,
+    WireNegative,
+    LightLamp,
+    Done,
+}
+
+impl TutorialStep {
+    fn text(self) -> &'static str {
+        match self {
+            TutorialStep::Off => "",
+            TutorialStep::PlaceButton => {
+                "Step 1: Pick the normally open -S1 (NO) in the left section, then click the marked grid point to place the button"
+            }
+            TutorialStep::PlaceLamp => {
+                "Step 2: Pick the lamp -P1 in the left section and click the marked point, right below the button"
+            }
+            TutorialStep::ConnectLamp => {
+                "The lamp is not connected to the button yet. Click one marked point and then the other to draw a wire between them"
+            }
+            TutorialStep::WirePositive => {
+                "Step 3: Draw a wire from the red + terminal to the top of the button, wires run straight between two clicks"
+            }
+            TutorialStep::WireNegative => {
+                "Step 4: Draw the second wire from the bottom of the lamp to the blue - terminal, add a bend with another wire if it is not in line"
+            }
+            TutorialStep::LightLamp => {
+                "Step 5: Start the simulation with the marked button, then hold the button in the left section down and watch the lamp"
+            }
+            TutorialStep::Done => "Done! The lamp lights while the button is held. End the tutorial whenever you like",
+        }
+    }
+}
+
+#[derive(Component)]
+struct TutorialButton;
+
+#[derive(Component)]
+struct EndTutorialButton;
+
+#[derive(Component)]
+struct TutorialPanel;
+
+#[derive(Component)]
+struct TutorialText;
+
+// Drawn around the palette button of the current step
+#[derive(Component)]
+struct TutorialFrame;
+
+fn setup_tutorial_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Tutorial", "Tutorial", TutorialButton);
+    });
+}
+
+fn setup_tutorial_panel(mut cmd: Commands) {
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(80.),
+                left: Val::Px(290.),
+                max_width: Val::Px(450.),
+                padding: UiRect::all(Val::Px(5.)),
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0.1, 0.08, 0., 0.85)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(15),
+            ..Default::default()
+        },
+        Name::new("Tutorial Panel"),
+        TutorialPanel,
+    ))
+    .with_children(|root| {
+        root.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.95, 0.95, 0.9),
+                    ..Default::default()
+                },
+            ),
+            Name::new("Tutorial Text"),
+            TutorialText,
+        ));
+        spawn_toolbar_button(root, "End tutorial", "End Tutorial", EndTutorialButton);
+    });
+
+    cmd.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                border: UiRect::all(Val::Px(FRAME_WIDTH)),
+                ..Default::default()
+            },
+            border_color: BorderColor(TUTORIAL_COLOR),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(20),
+            ..Default::default()
+        },
+        Name::new("Tutorial Frame"),
+        TutorialFrame,
+    ));
+}
+
+fn toggle_tutorial(
+    tutorial_button: Query<&Interaction, (Changed<Interaction>, With<TutorialButton>)>,
+    end_button: Query<&Interaction, (Changed<Interaction>, With<EndTutorialButton>)>,
+    mut step: ResMut<TutorialStep>,
+) {
+    let pressed = |interaction: &Interaction| *interaction == Interaction::Pressed;
+    if end_button.iter().any(pressed) {
+        *step = TutorialStep::Off;
+    } else if tutorial_button.iter().any(pressed) {
+        *step = if *step == TutorialStep::Off {
+            TutorialStep::PlaceButton
+        } else {
+            TutorialStep::Off
+        };
+    }
+}
+
+// The button and lamp the tutorial is about, the first ones that are placed
+fn placed(
+    buttons: &Query<&ButtonSwitch>,
+    lights: &Query<&Light>,
+) -> (Option<ButtonSwitch>, Option<Light>) {
+    (
+        buttons.iter().next().cloned(),
+        lights.iter().next().cloned(),
+    )
+}
+
+fn main_supply(supply: &Query<(&GridPosition, &Power), With<MainSupply>>) -> [GridPosition; 2] {
+    let terminal = |typ: PowerType| {
+        supply
+            .iter()
+            .find(|(_, power)| power.0 == typ)
+            .map(|(pos, _)| *pos)
+            .unwrap_or_default()
+    };
+    [terminal(PowerType::Positive), terminal(PowerType::Negative)]
+}
+
+// The first step whose goal is not met, lighting the lamp once is enough to be done
+fn advance_tutorial(
+    mut step: ResMut<TutorialStep>,
+    buttons: Query<&ButtonSwitch>,
+    lights: Query<&Light>,
+    wires: Query<&Wire>,
+    supply: Query<(&GridPosition, &Power), With<MainSupply>>,
+    ui_lights: Query<&UILight>,
+    mut circuit: Local<Circuit>,
+    mut solver: Local<Solver>,
+) {
+    if matches!(*step, TutorialStep::Off | TutorialStep::Done) {
+        return;
+    }
+
+    // Only the wires count, whether something is connected does not depend on the contacts
+    circuit.clear();
+    circuit.wires.extend(
+        wires
+            .iter()
+            .map(|wire| (wire.first.into(), wire.second.into())),
+    );
+    solver.step(&circuit);
+
+    let [positive, negative] = main_supply(&supply);
+    let reached = match placed(&buttons, &lights) {
+        (None, _) => TutorialStep::PlaceButton,
+        (Some(_), None) => TutorialStep::PlaceLamp,
+        (Some(button), Some(light)) => {
+            if !solver.same_net(button.bottom, light.top) {
+                TutorialStep::ConnectLamp
+            } else if !solver.same_net(positive, button.top) {
+                TutorialStep::WirePositive
+            } else if !solver.same_net(light.bottom, negative) {
+                TutorialStep::WireNegative
+            } else if !ui_lights
+                .iter()
+                .any(|ui_light| ui_light.id == light.id && ui_light.is_lit)
+            {
+                TutorialStep::LightLamp
+            } else {
+                TutorialStep::Done
+            }
+        }
+    };
+    if *step != reached {
+        *step = reached;
+    }
+}
+
+fn update_tutorial_panel(
+    step: Res<TutorialStep>,
+    mut panel: Query<&mut Visibility, With<TutorialPanel>>,
+    mut texts: Query<&mut Text, With<TutorialText>>,
+) {
+    if !step.is_changed() {
+        return;
+    }
+
+    for mut visibility in panel.iter_mut() {
+        *visibility = if *step == TutorialStep::Off {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = step.text().to_string();
+    }
+}
+
+// The frame follows the run button, also while the toolbar is scrolled, the dock frames the palette buttons itself
+fn mark_palette_button(
+    step: Res<TutorialStep>,
+    running: Res<IsRunning>,
+    buttons: Query<&ButtonSwitch>,
+    lights: Query<&Light>,
+    targets: Query<(
+        Entity,
+        &Node,
+        &GlobalTransform,
+        AnyOf<(&ButtonSelect, &UILight, &UIButton, &RunButton)>,
+    )>,
+    mut frame: Query<(&mut Style, &mut Visibility), With<TutorialFrame>>,
+    mut highlight: ResMut<PaletteHighlight>,
+) {
+    let (button, light) = placed(&buttons, &lights);
+    let target = targets.iter().find(|(.., target)| match (*step, target) {
+        (TutorialStep::PlaceButton, (Some(select), ..)) => {
+            select.id == 1 && select.typ == SwitchType::NormallyOpen
+        }
+        (TutorialStep::PlaceLamp, (_, Some(ui_light), ..)) => ui_light.id == 1,
+        (TutorialStep::LightLamp, (_, _, Some(ui_button), _)) => {
+            running.0
+                && button
+                    .as_ref()
+                    .is_some_and(|button| button.id == ui_button.id)
+        }
+        (TutorialStep::LightLamp, (.., Some(_))) => !running.0 && light.is_some(),
+        _ => false,
+    });
+
+    let (mut style, mut visibility) = frame.single_mut();
+    let Some((e, node, transform, target)) = target else {
+        highlight.0 = None;
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    if target.3.is_none() {
+        highlight.0 = Some(e);
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    highlight.0 = None;
+    let size = node.size() + Vec2::splat(2. * FRAME_WIDTH);
+    let corner = transform.translation().truncate() - size / 2.;
+    let (left, top) = (Val::Px(corner.x), Val::Px(corner.y));
+    if style.left != left || style.top != top {
+        style.left = left;
+        style.top = top;
+        style.width = Val::Px(size.x);
+        style.height = Val::Px(size.y);
+    }
+    *visibility = Visibility::Inherited;
+}
+
+fn mark_grid_points(
+    step: Res<TutorialStep>,
+    buttons: Query<&ButtonSwitch>,
+    lights: Query<&Light>,
+    supply: Query<(&GridPosition, &Power), With<MainSupply>>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+) {
+    let (button, light) = placed(&buttons, &lights);
+    let [positive, negative] = main_supply(&supply);
+    let points = match (*step, button, light) {
+        (TutorialStep::PlaceButton, ..) => vec![BUTTON_CELL],
+        (TutorialStep::PlaceLamp, Some(button), _) => button
+            .bottom
+            .y
+            .checked_sub(1)
+            .map(|y| GridPosition {
+                x: button.bottom.x,
+                y,
+            })
+            .into_iter()
+            .collect(),
+        (TutorialStep::ConnectLamp, Some(button), Some(light)) => vec![button.bottom, light.top],
+        (TutorialStep::WirePositive, Some(button), _) => vec![positive, button.top],
+        (TutorialStep::WireNegative, _, Some(light)) => vec![light.bottom, negative],
+        _ => Vec::new(),
+    };
+
+    // Pulses a little so it stands out from the junction dots and terminals
+    let radius = 9. + 2. * (time.elapsed_seconds() * 4.).sin();
+    for point in points {
+        gizmos.circle_2d(grid_to_world(point), radius, TUTORIAL_COLOR);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    keybindings::{Action, KeyBindings},
+    macros::{spawn_step, CircuitEdits, MacroStep},
+    CircuitHandles, GridOrigin,
+};
+
+// Undo (Ctrl+Z) and redo (Ctrl+Y) of placing, moving and deleting wires and components
+// Everything placed or deleted in the same frame is one step, so a whole macro or a right click that removed several wires goes back at once
+pub struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistory>()
+            .add_systems(Update, undo_and_redo)
+            // After every placement and deletion of the frame has been applied
+            .add_systems(PostUpdate, record_edits);
+    }
+}
+
+// Elements get a key when they are first placed, undo and redo spawn them as new entities but they keep their key
+enum Edit {
+    Placed(usize, MacroStep),
+    Deleted(usize, MacroStep),
+}
+
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    // Every element that is placed right now
+    elements: HashMap<Entity, (usize, MacroStep)>,
+    entities: HashMap<usize, Entity>,
+    next_key: usize,
+    // Set when the whole circuit was replaced
+    forget: bool,
+}
+
+impl EditHistory {
+    // Loading a circuit or switching tabs is nothing to undo, the steps from before would not fit anymore
+    pub fn forget(&mut self) {
+        self.forget = true;
+    }
+
+    fn present(
+        &mut self,
+        cmd: &mut Commands,
+        circuit_material: &CircuitHandles,
+        grid_origin: Entity,
+        key: usize,
+        step: &MacroStep,
+    ) {
+        let e = spawn_step(cmd, circuit_material, grid_origin, step.clone());
+        self.elements.insert(e, (key, step.clone()));
+        self.entities.insert(key, e);
+    }
+
+    fn absent(&mut self, cmd: &mut Commands, key: usize) {
+        let Some(e) = self.entities.remove(&key) else {
+            return;
+        };
+        self.elements.remove(&e);
+        if let Some(entity) = cmd.get_entity(e) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+fn record_edits(mut history: ResMut<EditHistory>, mut edits: CircuitEdits) {
+    let mut step = Vec::new();
+
+    // Whatever undo and redo removed themselves is not known anymore
+    for e in edits.removed() {
+        if let Some((key, element)) = history.elements.remove(&e) {
+            history.entities.remove(&key);
+            step.push(Edit::Deleted(key, element));
+        }
+    }
+
+    // Just as what they spawned is known already
+    for (e, element) in edits.added() {
+        if history.elements.contains_key(&e) {
+            continue;
+        }
+        let key = history.next_key;
+        history.next_key += 1;
+        history.elements.insert(e, (key, element.clone()));
+        history.entities.insert(key, e);
+        step.push(Edit::Placed(key, element));
+    }
+
+    // Moving something is deleting it where it was and placing it where it is now, it keeps its key
+    let moved = edits
+        .moved(|e| history.elements.get(&e).map(|(_, element)| element))
+        .collect::<Vec<_>>();
+    for (e, element) in moved {
+        if let Some((key, before)) = history.elements.get_mut(&e) {
+            let before = std::mem::replace(before, element.clone());
+            step.push(Edit::Deleted(*key, before));
+            step.push(Edit::Placed(*key, element));
+        }
+    }
+
+    if history.forget {
+        history.forget = false;
+        history.undo.clear();
+        history.redo.clear();
+    } else if !step.is_empty() {
+        history.undo.push(step);
+        history.redo.clear();
+    }
+}
+
+fn undo_and_redo(
+    mut cmd: Commands,
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut history: ResMut<EditHistory>,
+    circuit_material: Res<CircuitHandles>,
+    grid_origin: Query<Entity, With<GridOrigin>>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let undo = bindings.just_pressed(&keys, Action::Undo);
+    if !undo && !bindings.just_pressed(&keys, Action::Redo) {
+        return;
+    }
+
+    let step = if undo {
+        history.undo.pop()
+    } else {
+        history.redo.pop()
+    };
+    let Some(step) = step else {
+        info!("Nothing to {}", if undo { "undo" } else { "redo" });
+        return;
+    };
+
+    let grid_origin = grid_origin.single();
+    // Undone in the opposite order they were done in
+    let mut edits = step.iter().collect::<Vec<_>>();
+    if undo {
+        edits.reverse();
+    }
+    for edit in edits {
+        match (edit, undo) {
+            (Edit::Placed(key, _), true) | (Edit::Deleted(key, _), false) => {
+                history.absent(&mut cmd, *key);
+            }
+            (Edit::Placed(key, element), false) | (Edit::Deleted(key, element), true) => {
+                history.present(&mut cmd, &circuit_material, grid_origin, *key, element);
+            }
+        }
+    }
+
+    if undo {
+        history.redo.push(step);
+    } else {
+        history.undo.push(step);
+    }
+}
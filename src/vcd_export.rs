@@ -0,0 +1,200 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+use crate::{
+    history::{SimulationHistory, SimulationSnapshot},
+    platform,
+    save::SavePath,
+    spawn_toolbar_button, ButtonSwitch, Light, RelayCoil, RelaySwitch, SwitchType, Toolbar,
+};
+
+// Milliseconds per tick, the fixed update runs at 20 hz
+const TICK_MILLIS: usize = 50;
+
+// The VCD button writes the recorded history as a value change dump next to the save file, circuit.vcd for circuit.ron
+// There is one signal per button, relay coil, contact and lamp, so GTKWave and similar viewers can show them like any digital design
+// Contacts are 1 while closed, a changeover contact is 1 while it is switched over to its top terminal
+pub struct VcdExportPlugin;
+
+impl Plugin for VcdExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_vcd_button)
+            .add_systems(Update, export_vcd);
+    }
+}
+
+#[derive(Component)]
+struct VcdButton;
+
+fn setup_vcd_button(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "VCD", "Export VCD", VcdButton);
+    });
+}
+
+// Short identifiers made of the printable characters, like the ones simulators write
+fn identifier(mut index: usize) -> String {
+    let mut identifier = String::new();
+    loop {
+        identifier.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return identifier;
+        }
+        index -= 1;
+    }
+}
+
+fn contact_name(typ: SwitchType) -> &'static str {
+    match typ {
+        SwitchType::NormallyOpen => "NO",
+        SwitchType::NormallyClosed => "NC",
+        SwitchType::Changeover => "CO",
+    }
+}
+
+fn contact_state(typ: SwitchType, actuated: bool) -> bool {
+    match typ {
+        SwitchType::NormallyClosed => !actuated,
+        SwitchType::NormallyOpen | SwitchType::Changeover => actuated,
+    }
+}
+
+// A signal in its scope and how its value is read from a snapshot
+struct Signal {
+    scope: &'static str,
+    name: String,
+    value: Box<dyn Fn(&SimulationSnapshot) -> bool>,
+}
+
+fn sorted_ids(ids: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut ids = ids.collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+fn export_vcd(
+    interaction: Query<&Interaction, (Changed<Interaction>, With<VcdButton>)>,
+    save_path: Res<SavePath>,
+    history: Res<SimulationHistory>,
+    buttons: Query<&ButtonSwitch>,
+    relay_coils: Query<&RelayCoil>,
+    relay_switches: Query<&RelaySwitch>,
+    lights: Query<&Light>,
+) {
+    if !interaction
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if history.snapshots.is_empty() {
+        warn!("There is no recorded history to export, run the simulation first");
+        return;
+    }
+
+    let mut signals = Vec::new();
+    for id in sorted_ids(buttons.iter().map(|button| button.id)) {
+        signals.push(Signal {
+            scope: "buttons",
+            name: format!("S{id}"),
+            value: Box::new(move |snapshot| snapshot.pressed_button_ids.contains(&id)),
+        });
+    }
+    for id in sorted_ids(relay_coils.iter().map(|relay_coil| relay_coil.id)) {
+        signals.push(Signal {
+            scope: "coils",
+            name: format!("K{id}"),
+            value: Box::new(move |snapshot| snapshot.activated_relay_ids.contains(&id)),
+        });
+    }
+    // Contacts are told apart by where they are on the grid, a relay or button can have several of the same kind
+    let mut contacts = buttons
+        .iter()
+        .map(|button| ("S", button.id, button.typ, button.top))
+        .chain(
+            relay_switches
+                .iter()
+                .map(|relay_switch| ("K", relay_switch.id, relay_switch.typ, relay_switch.top)),
+        )
+        .collect::<Vec<_>>();
+    contacts.sort_by_key(|(prefix, id, _, top)| (*prefix, *id, top.x, top.y));
+    for (prefix, id, typ, top) in contacts {
+        signals.push(Signal {
+            scope: "contacts",
+            name: format!("{prefix}{id}_{}_{}_{}", contact_name(typ), top.x, top.y),
+            value: Box::new(move |snapshot| {
+                let actuated = if prefix == "S" {
+                    snapshot.pressed_button_ids.contains(&id)
+                } else {
+                    snapshot.activated_relay_ids.contains(&id)
+                };
+                contact_state(typ, actuated)
+            }),
+        });
+    }
+    for id in sorted_ids(lights.iter().map(|light| light.id)) {
+        signals.push(Signal {
+            scope: "lamps",
+            name: format!("P{id}"),
+            value: Box::new(move |snapshot| snapshot.lit_light_ids.contains(&id)),
+        });
+    }
+
+    let mut vcd = String::new();
+    let _ = writeln!(vcd, "$version relay-sim $end");
+    let _ = writeln!(vcd, "$timescale 1 ms $end");
+    let _ = writeln!(vcd, "$scope module circuit $end");
+    let mut scope = "";
+    for (index, signal) in signals.iter().enumerate() {
+        if signal.scope != scope {
+            if !scope.is_empty() {
+                let _ = writeln!(vcd, "$upscope $end");
+            }
+            scope = signal.scope;
+            let _ = writeln!(vcd, "$scope module {scope} $end");
+        }
+        let _ = writeln!(
+            vcd,
+            "$var wire 1 {} {} $end",
+            identifier(index),
+            signal.name
+        );
+    }
+    if !scope.is_empty() {
+        let _ = writeln!(vcd, "$upscope $end");
+    }
+    let _ = writeln!(vcd, "$upscope $end");
+    let _ = writeln!(vcd, "$enddefinitions $end");
+
+    // Only changes are written after the initial values
+    let mut previous: Vec<Option<bool>> = vec![None; signals.len()];
+    for (tick, snapshot) in history.snapshots.iter().enumerate() {
+        let mut changes = String::new();
+        for (index, signal) in signals.iter().enumerate() {
+            let value = (signal.value)(snapshot);
+            if previous[index] != Some(value) {
+                previous[index] = Some(value);
+                let _ = writeln!(changes, "{}{}", u8::from(value), identifier(index));
+            }
+        }
+        if tick == 0 {
+            let _ = writeln!(vcd, "#0\n$dumpvars\n{changes}$end");
+        } else if !changes.is_empty() {
+            let _ = write!(vcd, "#{}\n{changes}", tick * TICK_MILLIS);
+        }
+    }
+    let _ = writeln!(vcd, "#{}", history.snapshots.len() * TICK_MILLIS);
+
+    let path = save_path.0.with_extension("vcd");
+    match platform::export_file(&path, vcd.as_bytes()) {
+        Ok(_) => info!("Exported the recorded history to {}", path.display()),
+        Err(e) => error!(
+            "Cannot export the recorded history to {}: {e}",
+            path.display()
+        ),
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{
+    input::{
+        mouse::{MouseScrollUnit, MouseWheel},
+        touchpad::TouchpadMagnify,
+    },
+    prelude::*,
+    render::{primitives::Aabb, view::VisibilitySystems},
+    sprite::Anchor,
+    text::TextLayoutInfo,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    grid_to_world,
+    keybindings::{Action, KeyBindings},
+    spawn_toolbar_button, MainCamera, PlacedPositions, Toolbar,
+};
+
+const MIN_ZOOM: f32 = 0.25;
+// Far enough out to see the whole of the largest grid
+const MAX_ZOOM: f32 = 12.;
+// Room around the fitted circuit, in pixels on screen
+const FIT_MARGIN: f32 = 40.;
+// Zoom change per mouse wheel notch and per pixel of a ctrl + two finger scroll
+const WHEEL_ZOOM_STEP: f32 = 1.15;
+const PIXEL_ZOOM_SPEED: f32 = 0.005;
+
+// Moves and zooms the camera over the schematic, Home or the fit button frames everything that is placed
+// The mouse wheel zooms and middle drag pans, on trackpads two finger scrolling pans and pinching zooms
+// Meshes off screen are left out of drawing by bevy already, labels get their bounds here so they are left out too
+pub struct ViewPlugin;
+
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostStartup, setup_view_buttons)
+            .add_systems(Update, (zoom_to_fit, pan_and_zoom))
+            .add_systems(
+                PostUpdate,
+                add_text_bounds.in_set(VisibilitySystems::CalculateBounds),
+            );
+    }
+}
+
+#[derive(Component)]
+struct FitButton;
+
+fn setup_view_buttons(mut cmd: Commands, toolbar: Query<Entity, With<Toolbar>>) {
+    cmd.entity(toolbar.single()).with_children(|root| {
+        spawn_toolbar_button(root, "Fit", "Zoom To Fit", FitButton);
+    });
+}
+
+// Camera translation and zoom that show the given world rectangle in the schematic area right of the ui section
+// The window size is asked for, in the browser the canvas follows the size of the page
+fn view_for(rect: Rect, window: &Window) -> (Vec2, f32) {
+    let area = (Vec2::new(window.width() - 280., window.height()) - 2. * FIT_MARGIN).max(Vec2::ONE);
+    let scale = (rect.size() / area).max_element().clamp(MIN_ZOOM, MAX_ZOOM);
+
+    // The schematic area is 140 pixels right of the window center
+    (rect.center() - Vec2::new(140. * scale, 0.), scale)
+}
+
+fn zoom_to_fit(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    fit_button: Query<&Interaction, (Changed<Interaction>, With<FitButton>)>,
+    placed: PlacedPositions,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if !bindings.just_pressed(&keys, Action::ZoomToFit)
+        && !fit_button
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    let Some(rect) = placed
+        .iter()
+        .map(|pos| {
+            let center = grid_to_world(pos);
+            Rect::from_center_size(center, Vec2::splat(20.))
+        })
+        .reduce(|bounds, rect| bounds.union(rect))
+    else {
+        return;
+    };
+
+    let (translation, scale) = view_for(rect, windows.single());
+    for (mut transform, mut projection) in cameras.iter_mut() {
+        transform.translation = translation.extend(transform.translation.z);
+        projection.scale = scale;
+    }
+}
+
+// Moves the camera so the world point under the cursor stays where it is while the zoom changes
+fn zoom_around(
+    transform: &mut Transform,
+    projection: &mut OrthographicProjection,
+    cursor_offset: Vec2,
+    factor: f32,
+) {
+    let scale = (projection.scale * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    let world = transform.translation.truncate() + cursor_offset * projection.scale;
+    transform.translation = (world - cursor_offset * scale).extend(transform.translation.z);
+    projection.scale = scale;
+}
+
+fn pan_and_zoom(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut magnify_events: EventReader<TouchpadMagnify>,
+    mouse_button: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut last_drag_position: Local<Option<Vec2>>,
+) {
+    let (window_entity, window) = windows.single();
+    let (mut transform, mut projection) = cameras.single_mut();
+
+    // Scrolling over the ui section or the analysis window should not move the schematic
+    let Some(cursor) = window.cursor_position().filter(|cursor| cursor.x >= 280.) else {
+        wheel_events.clear();
+        magnify_events.clear();
+        *last_drag_position = None;
+        return;
+    };
+    // From the window center, with y pointing up like in the world
+    let cursor_offset =
+        (cursor - Vec2::new(window.width(), window.height()) / 2.) * Vec2::new(1., -1.);
+
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for event in wheel_events.read() {
+        if event.window != window_entity {
+            continue;
+        }
+
+        match event.unit {
+            MouseScrollUnit::Line => zoom_around(
+                &mut transform,
+                &mut projection,
+                cursor_offset,
+                WHEEL_ZOOM_STEP.powf(-event.y),
+            ),
+            // Most trackpads outside of macOS send pinching as a scroll with ctrl held
+            MouseScrollUnit::Pixel if ctrl => zoom_around(
+                &mut transform,
+                &mut projection,
+                cursor_offset,
+                (-event.y * PIXEL_ZOOM_SPEED).exp(),
+            ),
+            MouseScrollUnit::Pixel => {
+                transform.translation.x -= event.x * projection.scale;
+                transform.translation.y += event.y * projection.scale;
+            }
+        }
+    }
+
+    for event in magnify_events.read() {
+        zoom_around(
+            &mut transform,
+            &mut projection,
+            cursor_offset,
+            1. / (1. + event.0),
+        );
+    }
+
+    if mouse_button.pressed(MouseButton::Middle) {
+        if let Some(last) = *last_drag_position {
+            let delta = cursor - last;
+            transform.translation.x -= delta.x * projection.scale;
+            transform.translation.y += delta.y * projection.scale;
+        }
+        *last_drag_position = Some(cursor);
+    } else {
+        *last_drag_position = None;
+    }
+}
+
+// The text is drawn around its anchor, the bounds are made twice as large so scaled text is never cut off early
+fn add_text_bounds(
+    mut cmd: Commands,
+    texts: Query<(Entity, &TextLayoutInfo, &Anchor), (Changed<TextLayoutInfo>, Without<Node>)>,
+) {
+    for (e, layout, anchor) in texts.iter() {
+        let center = -anchor.as_vec() * layout.logical_size;
+        cmd.entity(e).insert(Aabb {
+            center: center.extend(0.).into(),
+            half_extents: layout.logical_size.extend(0.).into(),
+        });
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::{input::InputSystem, prelude::*, sprite::Anchor, window::PrimaryWindow};
+
+use crate::{
+    convert_mouse_to_grid,
+    grid::GridSize,
+    keybindings::{Action, KeyBindings},
+    wire_contains, CurrentlyPlacing, MainCamera, Wire, WireLabel,
+};
+
+const MAX_LABEL_LENGTH: usize = 8;
+
+// Pressing L while hovering a wire opens a small editor for the label of that wire
+pub struct WireLabelPlugin;
+
+impl Plugin for WireLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LabelEditor>()
+            .add_systems(Startup, setup_label_editor)
+            // Typing runs before the shortcuts look at the keys, a rebound shortcut may be any letter
+            .add_systems(PreUpdate, type_label.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    start_label_edit,
+                    update_label_editor,
+                    update_wire_label_text,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct LabelEditor {
+    // The wire whose label is being edited, None when the editor is closed
+    wire: Option<Entity>,
+    text: String,
+}
+
+#[derive(Component)]
+struct LabelEditorText;
+
+#[derive(Component)]
+struct WireLabelText;
+
+fn setup_label_editor(mut cmd: Commands) {
+    cmd.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..Default::default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.),
+                left: Val::Px(290.),
+                padding: UiRect::all(Val::Px(5.)),
+                ..Default::default()
+            },
+            background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..Default::default()
+        },
+        Name::new("Wire Label Editor"),
+        LabelEditorText,
+    ));
+}
+
+fn start_label_edit(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    grid_size: Res<GridSize>,
+    currently_placing: Res<CurrentlyPlacing>,
+    wires: Query<(Entity, &Wire, Option<&WireLabel>)>,
+    mut editor: ResMut<LabelEditor>,
+) {
+    if editor.wire.is_some()
+        || !bindings.just_pressed(&keys, Action::WireLabel)
+        || !matches!(*currently_placing, CurrentlyPlacing::Wire)
+    {
+        return;
+    }
+
+    let Some(mouse_grid) = windows
+        .single()
+        .cursor_position()
+        .and_then(|pos| convert_mouse_to_grid(pos, cameras.single(), &grid_size))
+    else {
+        return;
+    };
+
+    if let Some((e, _, label)) = wires
+        .iter()
+        .find(|(_, wire, _)| wire_contains(wire, &mouse_grid))
+    {
+        editor.wire = Some(e);
+        editor.text = label.map(|label| label.0.clone()).unwrap_or_default();
+    }
+}
+
+fn type_label(
+    mut cmd: Commands,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut editor: ResMut<LabelEditor>,
+) {
+    // Always read, otherwise the key that opened the editor would end up in the label
+    let typed = characters
+        .read()
+        .map(|event| event.char)
+        .collect::<Vec<_>>();
+
+    let Some(wire) = editor.wire else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        editor.wire = None;
+    } else if keys.just_pressed(KeyCode::Return) {
+        if let Some(mut entity) = cmd.get_entity(wire) {
+            entity.insert(WireLabel(editor.text.clone()));
+        }
+        editor.wire = None;
+    } else {
+        if keys.just_pressed(KeyCode::Back) {
+            editor.text.pop();
+        }
+
+        for c in typed {
+            if editor.text.chars().count() < MAX_LABEL_LENGTH
+                && (c.is_alphanumeric() || "-_.+/".contains(c))
+            {
+                editor.text.push(c);
+            }
+        }
+    }
+
+    keys.reset_all();
+}
+
+fn update_label_editor(
+    editor: Res<LabelEditor>,
+    mut editor_text: Query<(&mut Text, &mut Visibility), With<LabelEditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in editor_text.iter_mut() {
+        if editor.wire.is_none() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        *visibility = Visibility::Inherited;
+        text.sections[0].value = format!(
+            "Wire label: {}_   (Enter to confirm, Esc to cancel)",
+            editor.text
+        );
+    }
+}
+
+fn update_wire_label_text(
+    mut cmd: Commands,
+    wires: Query<(Entity, &Wire, &WireLabel, &Children), Changed<WireLabel>>,
+    label_texts: Query<(), With<WireLabelText>>,
+) {
+    for (e, wire, label, children) in wires.iter() {
+        for child in children
+            .iter()
+            .filter(|child| label_texts.contains(**child))
+        {
+            cmd.entity(*child).despawn_recursive();
+        }
+
+        if label.0.is_empty() {
+            continue;
+        }
+
+        let middle = Vec2::new(
+            (wire.first.x + wire.second.x) as f32 * 10. + 10.,
+            (wire.first.y + wire.second.y) as f32 * 10. + 10.,
+        );
+        // Next to vertical wires, above horizontal ones
+        let (offset, anchor) = if wire.first.x == wire.second.x {
+            (Vec2::new(6., 0.), Anchor::CenterLeft)
+        } else {
+            (Vec2::new(0., 4.), Anchor::BottomCenter)
+        };
+
+        cmd.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    label.0.clone(),
+                    TextStyle {
+                        font_size: 14.,
+                        color: Color::rgb(0.6, 0.8, 1.),
+                        ..Default::default()
+                    },
+                ),
+                text_anchor: anchor,
+                transform: Transform::from_translation((middle + offset).extend(5.)),
+                ..Default::default()
+            },
+            Name::new("Wire Label"),
+            WireLabelText,
+        ))
+        .set_parent(e);
+    }
+}